@@ -42,6 +42,7 @@ pub async fn run() -> Result<()> {
 
     // Run the chosen command
     match opt.command {
+        Commands::Billing(m) => m.run().await,
         Commands::Indexer(m) => m.run(db).await,
     }
 }
@@ -49,6 +50,9 @@ pub async fn run() -> Result<()> {
 /// Commands to be executed
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Run the billing command utilities
+    #[command(name = "billing")]
+    Billing(lightdotso_billing::config::BillingArgs),
     /// Run the indexer command utilities
     #[command(name = "indexer")]
     Indexer(lightdotso_indexer::config::IndexerArgs),
@@ -132,6 +136,9 @@ mod tests {
         // Test that the Cli struct can be parsed from command line arguments
         let cli = Cli::parse_from(["lightdotso-bin", "indexer"]);
         assert!(matches!(cli.command, Commands::Indexer(_)));
+
+        let cli = Cli::parse_from(["lightdotso-bin", "billing"]);
+        assert!(matches!(cli.command, Commands::Billing(_)));
         Ok(())
     }
 
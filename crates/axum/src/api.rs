@@ -64,6 +64,8 @@ use hyper::http::{
     header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
     HeaderValue, Method,
 };
+use lightdotso_constants::registry::spawn_chain_registry_watcher;
+use lightdotso_db::models::chain::spawn_chain_registry_sync;
 use lightdotso_opentelemetry::middleware::HttpMetricsLayerBuilder;
 use lightdotso_state::{create_client_state, ClientState};
 use lightdotso_tracing::tracing::info;
@@ -168,6 +170,7 @@ use utoipa_swagger_ui::SwaggerUi;
         schemas(signature::error::SignatureError),
         schemas(signature::types::Signature),
         schemas(simulation::create::SimulationCreateRequestParams),
+        schemas(simulation::create::SimulationStateOverrideParams),
         schemas(simulation::list::SimulationListCount),
         schemas(simulation::error::SimulationError),
         schemas(simulation::types::Simulation),
@@ -296,6 +299,10 @@ pub async fn start_api_server() -> Result<()> {
     // Create a shared client
     let state = create_client_state().await?;
 
+    // Keep the chain registry in sync w/ the file and the `Chain` table
+    spawn_chain_registry_watcher(Duration::from_secs(30));
+    spawn_chain_registry_sync(state.client.clone(), Duration::from_secs(60));
+
     // Allow CORS
     // From: https://github.com/MystenLabs/sui/blob/13df03f2fad0e80714b596f55b04e0b7cea37449/crates/sui-faucet/src/main.rs#L85
     // License: Apache-2.0
@@ -22,6 +22,7 @@ use axum::{
     extract::{Query, State},
     Json,
 };
+use lightdotso_db::models::chain::apply_chain_registry_overlay;
 use lightdotso_prisma::chain;
 use lightdotso_state::ClientState;
 use lightdotso_tracing::tracing::info;
@@ -116,6 +117,13 @@ pub(crate) async fn v1_chain_create_handler(
         .exec()
         .await?;
 
+    // -------------------------------------------------------------------------
+    // Registry
+    // -------------------------------------------------------------------------
+
+    // Apply the chain to the chain registry.
+    apply_chain_registry_overlay(&chain);
+
     // -------------------------------------------------------------------------
     // Return
     // -------------------------------------------------------------------------
@@ -22,6 +22,7 @@ use axum::{
     extract::{Query, State},
     Json,
 };
+use lightdotso_db::models::chain::apply_chain_registry_overlay;
 use lightdotso_prisma::chain;
 use lightdotso_state::ClientState;
 use lightdotso_tracing::tracing::info;
@@ -51,6 +52,8 @@ pub struct ChainUpdateRequestParams {
     /// The name of the chain.
     #[schema(example = "My Chain", default = "Chain")]
     pub name: Option<String>,
+    /// The chain registry config of the chain, overlaid on top of the registry file.
+    pub config: Option<serde_json::Value>,
 }
 
 // -----------------------------------------------------------------------------
@@ -115,13 +118,22 @@ pub(crate) async fn v1_chain_update_handler(
     // DB
     // -------------------------------------------------------------------------
 
+    // Construct the params to update.
+    let mut update_params = vec![chain::name::set(params.name)];
+    if let Some(config) = params.config {
+        update_params.push(chain::config::set(Some(config)));
+    }
+
     // Update the chain.
-    let chain = state
-        .client
-        .chain()
-        .update(chain::id::equals(chain_id), vec![chain::name::set(params.name)])
-        .exec()
-        .await?;
+    let chain =
+        state.client.chain().update(chain::id::equals(chain_id), update_params).exec().await?;
+
+    // -------------------------------------------------------------------------
+    // Registry
+    // -------------------------------------------------------------------------
+
+    // Apply the chain to the chain registry.
+    apply_chain_registry_overlay(&chain);
 
     // -------------------------------------------------------------------------
     // Return
@@ -22,6 +22,7 @@ use axum::{
 };
 use lightdotso_consumer::topics::routescan::RoutescanConsumer;
 use lightdotso_kafka::types::routescan::RoutescanMessage;
+use lightdotso_oracle::oracle::PriceOracle;
 use lightdotso_state::ClientState;
 use serde::Deserialize;
 use utoipa::IntoParams;
@@ -79,8 +80,14 @@ pub(crate) async fn v1_consumer_routescan_handler(
     // -------------------------------------------------------------------------
 
     let consumer = RoutescanConsumer;
+    let price_oracle = PriceOracle::with_clients(
+        state.redis.clone(),
+        state.client.clone(),
+        state.postgres_client.clone(),
+        (*state.pool).clone(),
+    );
     let msg = RoutescanMessage { chain_id: query.chain_id, address: parsed_query_address };
-    consumer.consume_with_message(&state, msg).await?;
+    consumer.consume_with_message(&state, &price_oracle, msg).await?;
 
     // -------------------------------------------------------------------------
     // Return
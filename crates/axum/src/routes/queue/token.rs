@@ -20,7 +20,7 @@ use axum::{
     extract::{Query, State},
     Json,
 };
-use lightdotso_constants::chains::{ALL_CHAIN_IDS, MAINNET_CHAIN_IDS, ROUTESCAN_CHAIN_IDS};
+use lightdotso_constants::registry::{get_chain_registry, ChainNetwork};
 use lightdotso_kafka::{
     topics::{covalent::produce_covalent_message, routescan::produce_routescan_message},
     types::{covalent::CovalentMessage, routescan::RoutescanMessage},
@@ -113,8 +113,15 @@ pub(crate) async fn v1_queue_token_handler(
         false
     };
 
+    // Get the chain registry.
+    let registry = get_chain_registry();
+
     // Define the chains.
-    let chains = if testnet_enabled { ALL_CHAIN_IDS.clone() } else { MAINNET_CHAIN_IDS.clone() };
+    let chains = if testnet_enabled {
+        registry.all_chain_ids()
+    } else {
+        registry.chain_ids(ChainNetwork::Mainnet)
+    };
 
     // For each chain, run the kafka producer.
     for chain in chains.iter() {
@@ -126,10 +133,10 @@ pub(crate) async fn v1_queue_token_handler(
     }
 
     // For each chain in routescan, run the kafka producer.
-    for chain in ROUTESCAN_CHAIN_IDS.iter() {
+    for chain_id in registry.chain_ids_where(|chain| chain.is_routescan()) {
         produce_routescan_message(
             state.producer.clone(),
-            &RoutescanMessage { address: parsed_query_address, chain_id: *chain.0 },
+            &RoutescanMessage { address: parsed_query_address, chain_id },
         )
         .await?;
     }
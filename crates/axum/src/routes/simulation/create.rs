@@ -14,6 +14,7 @@
 
 use super::{error::SimulationError, types::Simulation};
 use crate::{error::RouteError, result::AppJsonResult, tags::SIMULATION_TAG};
+use alloy::primitives::{B256, U256, U64};
 use autometrics::autometrics;
 use axum::{extract::State, Json};
 use clap::Parser;
@@ -29,13 +30,17 @@ use lightdotso_prisma::{
     asset_change, interpretation, interpretation_action, simulation, wallet, ActivityEntity,
     ActivityOperation,
 };
-use lightdotso_simulator::types::{SimulationRequest, SimulationUserOperationRequest};
+use lightdotso_simulator::{
+    state_override::{AccountOverride, StateOverride},
+    types::{SimulationUserOperationRequest, UserOperationRequest},
+};
 use lightdotso_state::ClientState;
 use lightdotso_tracing::tracing::info;
 use prisma_client_rust::or;
 // use lightdotso_tracing::tracing::info;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashMap;
 use utoipa::ToSchema;
 
 // -----------------------------------------------------------------------------
@@ -55,6 +60,24 @@ pub(crate) struct SimulationCreateRequestParams {
     pub init_code: String,
     /// The call data of the simulation to update for.
     pub call_data: String,
+    /// The state overrides of the simulation, keyed by address.
+    #[serde(default)]
+    pub state_overrides: Option<HashMap<String, SimulationStateOverrideParams>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SimulationStateOverrideParams {
+    /// The balance of the account to override.
+    pub balance: Option<String>,
+    /// The nonce of the account to override.
+    pub nonce: Option<String>,
+    /// The code of the account to override.
+    pub code: Option<String>,
+    /// The full storage of the account to override.
+    pub state: Option<HashMap<String, String>>,
+    /// The storage slots of the account to override.
+    pub state_diff: Option<HashMap<String, String>>,
 }
 
 // -----------------------------------------------------------------------------
@@ -71,6 +94,38 @@ impl TryFrom<SimulationCreateRequestParams> for SimulationUserOperationRequest {
             nonce: params.nonce,
             init_code: Some(hex_to_bytes(&params.init_code).unwrap_or_default().into()),
             call_data: Some(hex_to_bytes(&params.call_data).unwrap_or_default().into()),
+            state_overrides: params
+                .state_overrides
+                .map(|overrides| {
+                    overrides
+                        .into_iter()
+                        .map(|(address, account)| {
+                            Ok((address.parse()?, AccountOverride::try_from(account)?))
+                        })
+                        .collect::<Result<StateOverride, Self::Error>>()
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<SimulationStateOverrideParams> for AccountOverride {
+    type Error = eyre::Report;
+
+    fn try_from(params: SimulationStateOverrideParams) -> Result<Self, Self::Error> {
+        let parse_storage = |storage: HashMap<String, String>| {
+            storage
+                .into_iter()
+                .map(|(slot, value)| Ok((slot.parse::<B256>()?, value.parse::<B256>()?)))
+                .collect::<Result<HashMap<_, _>, Self::Error>>()
+        };
+
+        Ok(Self {
+            balance: params.balance.map(|balance| balance.parse::<U256>()).transpose()?,
+            nonce: params.nonce.map(|nonce| nonce.parse::<U64>()).transpose()?,
+            code: params.code.map(|code| hex_to_bytes(&code).map(Into::into)).transpose()?,
+            state: params.state.map(parse_storage).transpose()?,
+            state_diff: params.state_diff.map(parse_storage).transpose()?,
         })
     }
 }
@@ -104,9 +159,8 @@ pub(crate) async fn v1_simulation_create_handler(
     // Get the simulation from the post body.
     let simulation_request_op = SimulationUserOperationRequest::try_from(params.clone())?;
 
-    // Convert the simulation to a vector of simulation requests.
-    let simulation_requests: Vec<SimulationRequest> =
-        Vec::<SimulationRequest>::try_from(simulation_request_op.clone())?;
+    // Convert the simulation to the user operation simulated w/ the entrypoint.
+    let user_operation_request = UserOperationRequest::from(simulation_request_op.clone());
 
     // -------------------------------------------------------------------------
     // DB
@@ -116,7 +170,7 @@ pub(crate) async fn v1_simulation_create_handler(
     let args = InterpreterArgs::parse_from([""]);
 
     // Run the simulation.
-    let res = args.run(simulation_requests.clone()).await?;
+    let res = args.run_user_operation(user_operation_request).await?;
     info!("res: {:?}", res);
 
     // Upsert the interpretation
@@ -37,10 +37,13 @@ use axum::{
 use clap::Parser;
 use eyre::Result;
 use hyper::http::Method;
+use lightdotso_constants::registry::spawn_chain_registry_watcher;
 use lightdotso_hyper::get_hyper_client;
 use lightdotso_kafka::get_producer;
 use lightdotso_rpc::{
-    config::RpcArgs, internal_rpc_handler, protected_rpc_handler, public_rpc_handler,
+    config::RpcArgs, fixture::FIXTURE_STORE, hedge::init_hedger, internal_rpc_handler,
+    protected_rpc_handler, public_rpc_handler, subscription::SubscriptionManager,
+    ws::ws_rpc_handler,
 };
 use lightdotso_tracing::tracing::{info, Level};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
@@ -61,8 +64,20 @@ pub async fn start_rpc_server() -> Result<()> {
     let client = get_hyper_client()?;
     let producer = Arc::new(get_producer()?);
 
+    // Keep the chain registry in sync w/ the file
+    spawn_chain_registry_watcher(Duration::from_secs(30));
+
+    // Create the subscription manager shared by all websocket clients
+    let subscription_manager = Arc::new(SubscriptionManager::new());
+
     // Get the config
-    let _ = RpcArgs::try_parse().unwrap_or_else(|_| RpcArgs::parse_from(["".to_string()]));
+    let args = RpcArgs::try_parse().unwrap_or_else(|_| RpcArgs::parse_from(["".to_string()]));
+
+    // Build the hedger of the proxy from the config
+    init_hedger(&args);
+
+    // Build the fixture store of the proxy from the envs
+    lazy_static::initialize(&FIXTURE_STORE);
 
     // Allow CORS
     // From: https://github.com/MystenLabs/sui/blob/13df03f2fad0e80714b596f55b04e0b7cea37449/crates/sui-faucet/src/main.rs#L85
@@ -134,7 +149,15 @@ pub async fn start_rpc_server() -> Result<()> {
             on(MethodFilter::POST.or(MethodFilter::GET), internal_rpc_handler),
         )
         .layer(ServiceBuilder::new().layer(trace_layer.clone()).into_inner())
-        .with_state((client, producer));
+        .with_state((client.clone(), producer.clone()));
+
+    // The websocket routes w/ the subscription manager
+    let ws_app = Router::new()
+        .route("/ws/:chain_id", get(ws_rpc_handler))
+        .layer(ServiceBuilder::new().layer(trace_layer.clone()).into_inner())
+        .with_state((client, producer, subscription_manager));
+
+    let app = app.merge(ws_app);
 
     let socket_addr = "[::]:3000";
     let listener = TcpListener::bind(socket_addr).await?;
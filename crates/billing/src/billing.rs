@@ -14,33 +14,54 @@
 
 #![allow(clippy::unwrap_used)]
 
-use crate::config::BillingArgs;
+use crate::{
+    config::BillingArgs,
+    ledger::{
+        grant_journal, month_range, sponsorship_journal, top_up_journal, usd_from_wei,
+        MonthlyStatement, StatementEntry,
+    },
+};
 use alloy::{
-    primitives::{Address, U256},
+    eips::BlockNumberOrTag,
+    primitives::{Address, B256, U256},
     providers::{Provider, RootProvider},
     transports::BoxTransport,
 };
 use autometrics::autometrics;
 use backon::{ExponentialBuilder, Retryable};
 use eyre::{eyre, Result};
-use lightdotso_client::crypto::get_native_token_price;
-use lightdotso_common::traits::U256ToU64;
 use lightdotso_contracts::provider::get_provider;
 use lightdotso_db::{
     db::create_client,
-    models::{activity::CustomParams, billing_operation::create_billing_operation},
+    models::{
+        activity::CustomParams,
+        billing_ledger::{
+            billing_ledger_account, create_billing_ledger_journal,
+            get_billing_ledger_account_balance, get_billing_ledger_entries, BillingLedgerLine,
+        },
+        billing_operation::{
+            create_billing_operation, reconcile_billing_operation, BillingOperationReconciliation,
+        },
+    },
 };
 use lightdotso_kafka::{
     get_producer,
     rdkafka::producer::FutureProducer,
     topics::activity::produce_activity_message,
-    types::{activity::ActivityMessage, billing_operation::BillingOperationMessage},
+    types::{
+        activity::ActivityMessage,
+        billing_operation::{BillingOperationMessage, BillingOperationReconcileMessage},
+    },
+};
+use lightdotso_oracle::oracle::{PriceAt, PriceOracle};
+use lightdotso_prisma::{
+    billing_operation, ActivityEntity, ActivityOperation, BillingLedgerKind, PrismaClient,
 };
-use lightdotso_prisma::{billing_operation, ActivityEntity, ActivityOperation, PrismaClient};
 use lightdotso_redis::{get_redis_client, redis::Client};
 use lightdotso_tracing::tracing::info;
-use lightdotso_utils::{get_native_token_symbol, is_testnet};
-use std::sync::Arc;
+use lightdotso_utils::is_testnet;
+use sqlx::types::BigDecimal;
+use std::{path::Path, sync::Arc};
 
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -51,10 +72,12 @@ pub struct Billing {
     redis_client: Option<Arc<Client>>,
     /// The kafka client
     kafka_client: Option<Arc<FutureProducer>>,
+    /// The price oracle
+    price_oracle: Arc<PriceOracle>,
 }
 
 impl Billing {
-    pub async fn new(_args: &BillingArgs) -> Result<Self> {
+    pub async fn new(_args: &BillingArgs, price_oracle: Arc<PriceOracle>) -> Result<Self> {
         info!("Billing new, starting");
 
         // Create the db client
@@ -69,7 +92,7 @@ impl Billing {
             get_producer().map_or_else(|_e| None, |client| Some(Arc::new(client)));
 
         // Create the billing
-        Ok(Self { db_client, redis_client, kafka_client })
+        Ok(Self { db_client, redis_client, kafka_client, price_oracle })
     }
 
     /// Run the pending billing operation
@@ -82,7 +105,7 @@ impl Billing {
                 self.db_client.clone(),
                 msg.sender,
                 msg.paymaster_operation_id.clone(),
-                0.0,
+                BigDecimal::from(0),
             )
             .await?;
 
@@ -102,21 +125,22 @@ impl Billing {
         info!("gas_price: {}", gas_price);
 
         // Calculate the gas limit
-        let max_gas_limit =
-            msg.pre_verification_gas + msg.verification_gas_limit + msg.call_gas_limit;
+        let max_gas_limit = msg
+            .pre_verification_gas
+            .saturating_add(msg.verification_gas_limit)
+            .saturating_add(msg.call_gas_limit);
 
         // Log the gas limit
         info!("max_gas_limit: {}", max_gas_limit);
 
         // Multiply the gas price by the gas limit, denominated in ether 1e-18
-        let max_gas_consumed = U256::from(gas_price) * U256::from(max_gas_limit);
+        let max_gas_consumed = U256::from(gas_price).saturating_mul(U256::from(max_gas_limit));
 
         // Log the gas consumed
         info!("max_gas_consumed: {}", max_gas_consumed);
 
         // Calculate the total cost
-        let total_cost_usd =
-            (max_gas_consumed.to_u64()? as f64) / 10_u64.pow(18) as f64 * currency_price_usd;
+        let total_cost_usd = usd_from_wei(max_gas_consumed, currency_price_usd)?;
 
         // Log the total cost
         info!("total_cost_usd: {}", total_cost_usd);
@@ -133,6 +157,164 @@ impl Billing {
         Ok(())
     }
 
+    /// Reconcile the billing operation w/ the actual gas cost of the executed user operation
+    pub async fn run_reconcile(
+        &self,
+        msg: &BillingOperationReconcileMessage,
+    ) -> Result<BillingOperationReconciliation> {
+        info!("Run reconcile billing operation");
+
+        // If chain is testnet, the final amount is 0 USD
+        let total_cost_usd = if is_testnet(msg.chain_id) {
+            BigDecimal::from(0)
+        } else {
+            // Get the timestamp of the execution block
+            let timestamp = self
+                .get_block_timestamp(msg.chain_id, msg.block_number)
+                .await?
+                .ok_or(eyre!("Block not found"))?;
+
+            // Get the native token price at the execution block
+            let currency_price_usd =
+                self.get_native_currency_price_at(msg.chain_id, timestamp).await?;
+
+            // Log the currency
+            info!("currency_price_usd: {}", currency_price_usd);
+
+            // Log the actual gas
+            info!(
+                "actual_gas_used: {}, actual_gas_cost: {}",
+                msg.actual_gas_used, msg.actual_gas_cost
+            );
+
+            usd_from_wei(msg.actual_gas_cost, currency_price_usd)?
+        };
+
+        // Log the total cost
+        info!("total_cost_usd: {}", total_cost_usd);
+
+        // Update the billing operation w/ the final amount
+        let reconciliation = self
+            .db_reconcile_billing_operation(
+                self.db_client.clone(),
+                msg.user_operation_hash,
+                total_cost_usd.clone(),
+            )
+            .await?;
+
+        if let BillingOperationReconciliation::Reconciled(billing_operation) = &reconciliation {
+            // Debit the billing in the ledger w/ the exact amount
+            if total_cost_usd > BigDecimal::from(0) {
+                self.db_create_billing_ledger_journal(
+                    format!("sponsorship:{}", billing_operation.id),
+                    BillingLedgerKind::Sponsorship,
+                    sponsorship_journal(&billing_operation.billing_id, &total_cost_usd),
+                    Some(billing_operation.id.clone()),
+                    Some(format!("Sponsored user operation {:?}", msg.user_operation_hash)),
+                )
+                .await?;
+            }
+
+            // Send the activity of the update
+            if self.kafka_client.is_some() {
+                self.send_activity_queue(*billing_operation.clone()).await?;
+            }
+        }
+
+        Ok(reconciliation)
+    }
+
+    /// Credit the billing w/ a prepaid top-up, posted once per the external reference
+    pub async fn run_top_up(
+        &self,
+        billing_id: &str,
+        amount_usd: &BigDecimal,
+        reference: &str,
+        description: Option<String>,
+    ) -> Result<()> {
+        info!("Run top-up, billing_id: {} amount_usd: {}", billing_id, amount_usd);
+
+        self.db_create_billing_ledger_journal(
+            format!("top_up:{}", reference),
+            BillingLedgerKind::TopUp,
+            top_up_journal(billing_id, amount_usd),
+            None,
+            description,
+        )
+        .await
+    }
+
+    /// Credit the billing w/ a sponsorship grant, posted once per the external reference
+    pub async fn run_grant(
+        &self,
+        billing_id: &str,
+        amount_usd: &BigDecimal,
+        reference: &str,
+        description: Option<String>,
+    ) -> Result<()> {
+        info!("Run grant, billing_id: {} amount_usd: {}", billing_id, amount_usd);
+
+        self.db_create_billing_ledger_journal(
+            format!("grant:{}", reference),
+            BillingLedgerKind::Grant,
+            grant_journal(billing_id, amount_usd),
+            None,
+            description,
+        )
+        .await
+    }
+
+    /// Generate the month-end statement of the billing from the ledger
+    pub async fn generate_monthly_statement(
+        &self,
+        billing_id: &str,
+        year: i32,
+        month: u32,
+    ) -> Result<MonthlyStatement> {
+        let (from, to) = month_range(year, month)?;
+        let account = billing_ledger_account(billing_id);
+
+        let opening_balance =
+            get_billing_ledger_account_balance(self.db_client.clone(), account.clone(), Some(from))
+                .await?;
+        let entries = get_billing_ledger_entries(self.db_client.clone(), account, from, to).await?;
+
+        MonthlyStatement::new(
+            billing_id.to_string(),
+            year,
+            month,
+            opening_balance,
+            entries.into_iter().map(StatementEntry::from).collect(),
+        )
+    }
+
+    /// Generate the month-end statements of all of the billings in JSON and CSV
+    pub async fn write_monthly_statements(
+        &self,
+        year: i32,
+        month: u32,
+        output_dir: &Path,
+    ) -> Result<()> {
+        let billings = self.db_client.billing().find_many(vec![]).exec().await?;
+
+        std::fs::create_dir_all(output_dir)?;
+
+        for billing in billings {
+            let statement = self.generate_monthly_statement(&billing.id, year, month).await?;
+
+            // Skip the billings w/o any activity in the month
+            if statement.entries.is_empty() {
+                continue;
+            }
+
+            let name = format!("{}-{}", billing.id, statement.month);
+            std::fs::write(output_dir.join(format!("{}.json", name)), statement.to_json()?)?;
+            std::fs::write(output_dir.join(format!("{}.csv", name)), statement.to_csv())?;
+        }
+
+        Ok(())
+    }
+
     /// Get the provider
     pub async fn get_provider(
         &self,
@@ -151,8 +333,10 @@ impl Billing {
         db_client: Arc<PrismaClient>,
         wallet_address: Address,
         paymaster_operation_id: String,
-        pending_usd: f64,
+        pending_usd: BigDecimal,
     ) -> Result<()> {
+        let pending_usd = balance_usd_column(&pending_usd)?;
+
         {
             || {
                 create_billing_operation(
@@ -167,19 +351,93 @@ impl Billing {
         .await
     }
 
+    /// Reconciles the billing operation in the database
+    #[autometrics]
+    pub async fn db_reconcile_billing_operation(
+        &self,
+        db_client: Arc<PrismaClient>,
+        user_operation_hash: B256,
+        balance_usd: BigDecimal,
+    ) -> Result<BillingOperationReconciliation> {
+        let balance_usd = balance_usd_column(&balance_usd)?;
+
+        { || reconcile_billing_operation(db_client.clone(), user_operation_hash, balance_usd) }
+            .retry(ExponentialBuilder::default())
+            .await
+    }
+
+    /// Posts the balanced journal to the ledger in the database
+    #[autometrics]
+    pub async fn db_create_billing_ledger_journal(
+        &self,
+        journal_id: String,
+        kind: BillingLedgerKind,
+        lines: Vec<BillingLedgerLine>,
+        billing_operation_id: Option<String>,
+        description: Option<String>,
+    ) -> Result<()> {
+        {
+            || {
+                create_billing_ledger_journal(
+                    self.db_client.clone(),
+                    journal_id.clone(),
+                    kind,
+                    lines.clone(),
+                    billing_operation_id.clone(),
+                    description.clone(),
+                )
+            }
+        }
+        .retry(ExponentialBuilder::default())
+        .await?;
+
+        Ok(())
+    }
+
     /// Get the native currency balance for the chain
     #[autometrics]
     pub async fn get_native_currency_price(&self, chain_id: u64) -> Result<f64> {
-        // Get the native token symbol
-        let symbol = get_native_token_symbol(chain_id);
-
-        let res = { || get_native_token_price(symbol.to_string()) }
+        let res = { || self.price_oracle.get_native_price(chain_id, PriceAt::Latest) }
             .retry(ExponentialBuilder::default())
             .await?;
 
         Ok(res)
     }
 
+    /// Get the native currency price for the chain at the timestamp
+    #[autometrics]
+    pub async fn get_native_currency_price_at(&self, chain_id: u64, timestamp: u64) -> Result<f64> {
+        let res =
+            { || self.price_oracle.get_native_price(chain_id, PriceAt::Timestamp(timestamp)) }
+                .retry(ExponentialBuilder::default())
+                .await?;
+
+        Ok(res)
+    }
+
+    /// Get the timestamp of the block
+    #[autometrics]
+    pub async fn get_block_timestamp(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+    ) -> Result<Option<u64>> {
+        let client = self.get_provider(chain_id).await?;
+
+        info!("get_block, chain_id: {} block_number: {}", chain_id, block_number);
+
+        if let Some(client) = client {
+            let res =
+                { || client.get_block_by_number(BlockNumberOrTag::Number(block_number), false) }
+                    .retry(ExponentialBuilder::default())
+                    .await?;
+
+            return Ok(res.map(|block| block.header.timestamp));
+        }
+
+        Ok(None)
+    }
+
     /// Get the gas price for the chain
     #[autometrics]
     pub async fn get_gas_price(&self, chain_id: u64) -> Result<Option<u128>> {
@@ -222,6 +480,16 @@ impl Billing {
     }
 }
 
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the `balanceUSD` column of the billing operation from the exact amount in USD, as the
+/// column is the only float the amount is converted to w/ the ledger keeping the exact amount.
+pub fn balance_usd_column(amount_usd: &BigDecimal) -> Result<f64> {
+    Ok(amount_usd.to_string().parse::<f64>()?)
+}
+
 // -----------------------------------------------------------------------------
 // Tests
 // -----------------------------------------------------------------------------
@@ -240,11 +508,23 @@ mod tests {
 
         println!("max_gas_consumed: {}", max_gas_consumed);
 
-        let total_cost_usd =
-            (max_gas_consumed.to_u64()? as f64) / 10_u64.pow(18) as f64 * currency_price_usd;
+        let total_cost_usd = usd_from_wei(max_gas_consumed, currency_price_usd)?;
 
         println!("total_cost_usd: {}", total_cost_usd);
 
         Ok(())
     }
+
+    #[test]
+    fn test_balance_usd_column() -> Result<()> {
+        // 1 ether at 3000 USD
+        let total_cost_usd = usd_from_wei(U256::from(10).pow(U256::from(18)), 3000.0)?;
+        assert_eq!(balance_usd_column(&total_cost_usd)?, 3000.0);
+
+        // Does not overflow past u64
+        let total_cost_usd = usd_from_wei(U256::from(u64::MAX) * U256::from(1000), 1.0)?;
+        assert!(balance_usd_column(&total_cost_usd)? > 18_000.0);
+
+        Ok(())
+    }
 }
@@ -14,10 +14,13 @@
 
 #![allow(clippy::unwrap_used)]
 
-use crate::billing::Billing;
+use crate::{billing::Billing, ledger::parse_month};
 use clap::Parser;
-use eyre::Result;
+use eyre::{eyre, Result};
+use lightdotso_oracle::oracle::PriceOracle;
 use lightdotso_tracing::tracing::info;
+use sqlx::types::BigDecimal;
+use std::{path::Path, str::FromStr, sync::Arc};
 
 #[derive(Clone, Debug, Parser, Default)]
 pub struct BillingArgs {
@@ -32,14 +35,36 @@ pub struct BillingArgs {
     /// The covalent API key
     #[clap(long, env = "COVALENT_API_KEY")]
     pub covalent_api_key: Option<String>,
+    /// The month in `YYYY-MM` to generate the month-end statements of.
+    #[clap(long, env = "BILLING_STATEMENT_MONTH")]
+    pub statement_month: Option<String>,
+    /// The directory to write the statements to, in JSON and CSV.
+    #[arg(long, default_value_t = String::from("statements"))]
+    #[clap(long, env = "BILLING_STATEMENT_DIR")]
+    pub statement_dir: String,
+    /// The billing to credit w/ a prepaid top-up or a sponsorship grant.
+    #[clap(long, env = "BILLING_CREDIT_BILLING_ID")]
+    pub credit_billing_id: Option<String>,
+    /// The amount in USD to credit, as an exact decimal.
+    #[clap(long, env = "BILLING_CREDIT_AMOUNT_USD")]
+    pub credit_amount_usd: Option<String>,
+    /// The kind of the credit, either `top-up` or `grant`.
+    #[arg(long, default_value_t = String::from("top-up"))]
+    #[clap(long, env = "BILLING_CREDIT_KIND")]
+    pub credit_kind: String,
+    /// The external reference of the credit, e.g. the payment id, w/ the credit posted once.
+    #[clap(long, env = "BILLING_CREDIT_REFERENCE")]
+    pub credit_reference: Option<String>,
+    /// The description of the credit.
+    #[clap(long, env = "BILLING_CREDIT_DESCRIPTION")]
+    pub credit_description: Option<String>,
 }
 
 impl BillingArgs {
-    pub async fn create(&self) -> Result<Billing> {
-        Billing::new(&BillingArgs::default()).await
+    pub async fn create(&self, price_oracle: Arc<PriceOracle>) -> Result<Billing> {
+        Billing::new(&BillingArgs::default(), price_oracle).await
     }
 
-    #[tokio::main]
     pub async fn run(&self) -> Result<()> {
         // Add info
         info!("BillingArgs run, starting...");
@@ -47,6 +72,41 @@ impl BillingArgs {
         // Print the config
         info!("Config: {:?}", self);
 
+        // Generate the month-end statements if requested
+        if let Some(statement_month) = &self.statement_month {
+            let (year, month) = parse_month(statement_month)?;
+
+            let billing = self.create(Arc::new(PriceOracle::from_env().await)).await?;
+            billing.write_monthly_statements(year, month, Path::new(&self.statement_dir)).await?;
+
+            info!("Wrote the statements of {} to {}", statement_month, self.statement_dir);
+        }
+
+        // Credit the billing if requested
+        if let Some(billing_id) = &self.credit_billing_id {
+            let amount_usd = BigDecimal::from_str(
+                self.credit_amount_usd.as_deref().ok_or_else(|| eyre!("Credit amount not set"))?,
+            )?;
+            let reference = self
+                .credit_reference
+                .as_deref()
+                .ok_or_else(|| eyre!("Credit reference not set"))?;
+            let description = self.credit_description.clone();
+
+            let billing = self.create(Arc::new(PriceOracle::from_env().await)).await?;
+            match self.credit_kind.as_str() {
+                "top-up" => {
+                    billing.run_top_up(billing_id, &amount_usd, reference, description).await?
+                }
+                "grant" => {
+                    billing.run_grant(billing_id, &amount_usd, reference, description).await?
+                }
+                kind => return Err(eyre!("Invalid credit kind {}", kind)),
+            }
+
+            info!("Credited {} USD to the billing {}", amount_usd, billing_id);
+        }
+
         Ok(())
     }
 }
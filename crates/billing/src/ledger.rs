@@ -0,0 +1,369 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+use lightdotso_db::models::billing_ledger::{
+    billing_ledger_account, BillingLedgerEntry, BillingLedgerLine,
+};
+use lightdotso_prisma::{BillingLedgerDirection, BillingLedgerKind};
+use prisma_client_rust::chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::Serialize;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The platform account receiving the prepaid top-ups
+pub const PLATFORM_CASH_ACCOUNT: &str = "platform:cash";
+
+/// The platform account funding the sponsorship grants
+pub const PLATFORM_GRANTS_ACCOUNT: &str = "platform:grants";
+
+/// The platform account paying the gas of the sponsored operations
+pub const PLATFORM_GAS_ACCOUNT: &str = "platform:gas";
+
+/// The decimal places kept for the amounts in USD
+const USD_SCALE: i64 = 10;
+
+// -----------------------------------------------------------------------------
+// Journals
+// -----------------------------------------------------------------------------
+
+/// The balanced journal of a prepaid top-up, crediting the billing
+pub fn top_up_journal(billing_id: &str, amount: &BigDecimal) -> Vec<BillingLedgerLine> {
+    transfer(PLATFORM_CASH_ACCOUNT, None, billing_id, amount)
+}
+
+/// The balanced journal of a sponsorship grant, crediting the billing
+pub fn grant_journal(billing_id: &str, amount: &BigDecimal) -> Vec<BillingLedgerLine> {
+    transfer(PLATFORM_GRANTS_ACCOUNT, None, billing_id, amount)
+}
+
+/// The balanced journal of a sponsored operation, debiting the billing
+pub fn sponsorship_journal(billing_id: &str, amount: &BigDecimal) -> Vec<BillingLedgerLine> {
+    vec![
+        BillingLedgerLine {
+            account: billing_ledger_account(billing_id),
+            billing_id: Some(billing_id.to_string()),
+            direction: BillingLedgerDirection::Debit,
+            amount: amount.clone(),
+        },
+        BillingLedgerLine {
+            account: PLATFORM_GAS_ACCOUNT.to_string(),
+            billing_id: None,
+            direction: BillingLedgerDirection::Credit,
+            amount: amount.clone(),
+        },
+    ]
+}
+
+fn transfer(
+    from: &str,
+    from_billing_id: Option<&str>,
+    billing_id: &str,
+    amount: &BigDecimal,
+) -> Vec<BillingLedgerLine> {
+    vec![
+        BillingLedgerLine {
+            account: from.to_string(),
+            billing_id: from_billing_id.map(str::to_string),
+            direction: BillingLedgerDirection::Debit,
+            amount: amount.clone(),
+        },
+        BillingLedgerLine {
+            account: billing_ledger_account(billing_id),
+            billing_id: Some(billing_id.to_string()),
+            direction: BillingLedgerDirection::Credit,
+            amount: amount.clone(),
+        },
+    ]
+}
+
+/// Convert the amount in wei to exact USD w/ the native token price.
+pub fn usd_from_wei(wei: U256, currency_price_usd: f64) -> Result<BigDecimal> {
+    let wei = BigDecimal::from_str(&wei.to_string())?;
+    let price = BigDecimal::from_str(&currency_price_usd.to_string())?;
+    let ether = wei / BigDecimal::from(10_u64.pow(18));
+
+    Ok((ether * price).round(USD_SCALE))
+}
+
+// -----------------------------------------------------------------------------
+// Statements
+// -----------------------------------------------------------------------------
+
+/// The line of the monthly statement
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementEntry {
+    pub timestamp: DateTime<FixedOffset>,
+    pub journal_id: String,
+    pub kind: String,
+    pub direction: String,
+    pub amount: String,
+    pub balance: String,
+    pub description: Option<String>,
+}
+
+impl From<BillingLedgerEntry> for StatementEntry {
+    fn from(entry: BillingLedgerEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            journal_id: entry.journal_id,
+            kind: enum_name(&entry.kind),
+            direction: enum_name(&entry.direction),
+            amount: entry.amount.to_string(),
+            balance: entry.balance.to_string(),
+            description: entry.description,
+        }
+    }
+}
+
+/// The month-end statement of a billing
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyStatement {
+    pub billing_id: String,
+    /// The month in `YYYY-MM`
+    pub month: String,
+    pub opening_balance: String,
+    pub closing_balance: String,
+    pub total_debits: String,
+    pub total_credits: String,
+    pub entries: Vec<StatementEntry>,
+}
+
+impl MonthlyStatement {
+    /// Build the statement from the opening balance and the entries of the month
+    pub fn new(
+        billing_id: String,
+        year: i32,
+        month: u32,
+        opening_balance: BigDecimal,
+        entries: Vec<StatementEntry>,
+    ) -> Result<Self> {
+        let zero = BigDecimal::from(0);
+        let mut total_debits = zero.clone();
+        let mut total_credits = zero.clone();
+
+        for entry in &entries {
+            let amount = BigDecimal::from_str(&entry.amount)?;
+            match entry.direction.as_str() {
+                "DEBIT" => total_debits += amount,
+                _ => total_credits += amount,
+            }
+        }
+
+        // The balances are credit-normal, so the closing balance follows the totals
+        let closing_balance = &opening_balance + &total_credits - &total_debits;
+
+        // The running balance of the last entry must match, or the ledger does not reconcile
+        if let Some(last) = entries.last() {
+            if BigDecimal::from_str(&last.balance)? != closing_balance {
+                return Err(eyre!(
+                    "Statement of {} does not reconcile: {} != {}",
+                    billing_id,
+                    last.balance,
+                    closing_balance
+                ));
+            }
+        }
+
+        Ok(Self {
+            billing_id,
+            month: format!("{:04}-{:02}", year, month),
+            opening_balance: opening_balance.to_string(),
+            closing_balance: closing_balance.to_string(),
+            total_debits: total_debits.to_string(),
+            total_credits: total_credits.to_string(),
+            entries,
+        })
+    }
+
+    /// Render the statement in JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Render the entries of the statement in CSV
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("timestamp,journal_id,kind,direction,amount,balance,description\n");
+
+        for entry in &self.entries {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                entry.timestamp.to_rfc3339(),
+                entry.journal_id,
+                entry.kind,
+                entry.direction,
+                entry.amount,
+                entry.balance,
+                escape_csv(entry.description.as_deref().unwrap_or_default()),
+            ));
+        }
+
+        csv
+    }
+}
+
+/// The range of the month, from the first day inclusive to the next month exclusive
+pub fn month_range(
+    year: i32,
+    month: u32,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let start_of = |year: i32, month: u32| {
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc().fixed_offset())
+            .ok_or_else(|| eyre!("Invalid month {}-{}", year, month))
+    };
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+
+    Ok((start_of(year, month)?, start_of(next_year, next_month)?))
+}
+
+/// Parse the month in `YYYY-MM`
+pub fn parse_month(month: &str) -> Result<(i32, u32)> {
+    let (year, month) = month.split_once('-').ok_or_else(|| eyre!("Invalid month {}", month))?;
+
+    Ok((year.parse()?, month.parse()?))
+}
+
+/// The name of the prisma enum as stored, e.g. `TOP_UP`
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightdotso_db::models::billing_ledger::is_balanced;
+
+    fn entry(direction: &str, amount: &str, balance: &str) -> StatementEntry {
+        StatementEntry {
+            timestamp: month_range(2024, 6).unwrap().0,
+            journal_id: "journal".to_string(),
+            kind: "TOP_UP".to_string(),
+            direction: direction.to_string(),
+            amount: amount.to_string(),
+            balance: balance.to_string(),
+            description: Some("Top-up, card".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_journals_are_balanced() {
+        let amount = BigDecimal::from_str("12.34").unwrap();
+
+        assert!(is_balanced(&top_up_journal("billing", &amount)));
+        assert!(is_balanced(&grant_journal("billing", &amount)));
+        assert!(is_balanced(&sponsorship_journal("billing", &amount)));
+        assert!(!is_balanced(&sponsorship_journal("billing", &BigDecimal::from(0))));
+    }
+
+    #[test]
+    fn test_usd_from_wei() {
+        // 0.1 ether at 3000 USD, exactly
+        let usd = usd_from_wei(U256::from(10_u64.pow(17)), 3000.0).unwrap();
+        assert_eq!(usd, BigDecimal::from(300));
+
+        // Does not overflow past u64
+        let usd = usd_from_wei(U256::from(u64::MAX) * U256::from(1000), 1.0).unwrap();
+        assert!(usd > BigDecimal::from(18_000));
+    }
+
+    #[test]
+    fn test_monthly_statement() {
+        let statement = MonthlyStatement::new(
+            "billing".to_string(),
+            2024,
+            6,
+            BigDecimal::from(10),
+            vec![entry("CREDIT", "5.5", "15.5"), entry("DEBIT", "0.25", "15.25")],
+        )
+        .unwrap();
+
+        assert_eq!(statement.month, "2024-06");
+        assert_eq!(statement.closing_balance, "15.25");
+        assert_eq!(statement.total_credits, "5.5");
+        assert_eq!(statement.total_debits, "0.25");
+
+        let csv = statement.to_csv();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.ends_with(",DEBIT,0.25,15.25,\"Top-up, card\"\n"));
+
+        // Does not reconcile w/ the running balance
+        assert!(MonthlyStatement::new(
+            "billing".to_string(),
+            2024,
+            6,
+            BigDecimal::from(10),
+            vec![entry("CREDIT", "5.5", "16")],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_statement_entry_from_ledger_entry() {
+        let entry = StatementEntry::from(BillingLedgerEntry {
+            id: "id".to_string(),
+            timestamp: month_range(2024, 6).unwrap().0,
+            journal_id: "sponsorship:operation".to_string(),
+            account: billing_ledger_account("billing"),
+            sequence: 1,
+            direction: BillingLedgerDirection::Debit,
+            kind: BillingLedgerKind::Sponsorship,
+            amount: BigDecimal::from_str("0.1234567891").unwrap(),
+            balance: BigDecimal::from_str("-0.1234567891").unwrap(),
+            description: None,
+            billing_id: Some("billing".to_string()),
+            billing_operation_id: Some("operation".to_string()),
+        });
+
+        // The decimals are kept exact
+        assert_eq!(entry.amount, "0.1234567891");
+        assert_eq!(entry.balance, "-0.1234567891");
+        assert_eq!(entry.kind, "SPONSORSHIP");
+        assert_eq!(entry.direction, "DEBIT");
+    }
+
+    #[test]
+    fn test_month_range() {
+        let (from, to) = month_range(2024, 12).unwrap();
+        assert_eq!(from.to_rfc3339(), "2024-12-01T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2025-01-01T00:00:00+00:00");
+
+        assert_eq!(parse_month("2024-06").unwrap(), (2024, 6));
+        assert!(parse_month("2024").is_err());
+    }
+}
@@ -16,3 +16,4 @@
 
 pub mod billing;
 pub mod config;
+pub mod ledger;
@@ -12,10 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use eyre::Result;
+use eyre::{eyre, Result};
 use reqwest::Client;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{constants::MINI_CRYPTO_BASE_API_URL, get_api_json_response};
 
@@ -51,6 +51,20 @@ pub async fn get_native_token_price(symbol: String) -> Result<f64> {
     Ok(response.usd)
 }
 
+// Get the price of native token at the timestamp
+pub async fn get_native_token_price_at(symbol: String, timestamp: u64) -> Result<f64> {
+    // The path to the historical native token price
+    let path = format!("/data/pricehistorical?fsym={}&tsyms=USD&ts={}", symbol, timestamp);
+
+    // Get the response from the api, keyed by the symbol
+    let response = request_mini_crypto_api_text::<HashMap<String, CryptoResponse>>(path).await?;
+
+    response
+        .get(&symbol)
+        .map(|price| price.usd)
+        .ok_or_else(|| eyre!("Price not found for {}", symbol))
+}
+
 // Write the tests
 // -----------------------------------------------------------------------------
 // Tests
@@ -65,4 +79,10 @@ mod tests {
         let price = get_native_token_price("ETH".to_string()).await.unwrap();
         assert!(price > 0.0);
     }
+
+    #[tokio::test]
+    async fn test_get_native_token_price_at() {
+        let price = get_native_token_price_at("ETH".to_string(), 1_700_000_000).await.unwrap();
+        assert!(price > 0.0);
+    }
 }
@@ -0,0 +1,547 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use lightdotso_tracing::tracing::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The chain registry embedded at build time
+pub const DEFAULT_CHAIN_REGISTRY: &str = include_str!("../chains.toml");
+
+/// The env of the path to the chain registry file to load at runtime
+pub const CHAIN_REGISTRY_PATH_ENV: &str = "CHAIN_REGISTRY_PATH";
+
+/// The default native token symbol of a chain
+pub const DEFAULT_NATIVE_TOKEN_SYMBOL: &str = "ETH";
+
+/// The default block seconds of a mainnet chain
+pub const DEFAULT_CHAIN_BLOCK_SECONDS: u64 = 3;
+
+/// The default block seconds of a testnet chain
+pub const DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS: u64 = 12;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The network of a chain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainNetwork {
+    Mainnet,
+    Testnet,
+    Deprecated,
+    #[default]
+    Unlisted,
+}
+
+/// The rollup stack of a chain, which determines how the L1 data fee is priced
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainRollup {
+    /// The OP Stack w/ the `GasPriceOracle` predeploy
+    OpStack,
+    /// Arbitrum w/ the `NodeInterface` precompile
+    Arbitrum,
+    /// Scroll w/ the `L1GasPriceOracle` predeploy
+    Scroll,
+}
+
+/// The paymaster settings of a chain
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainPaymasterConfig {
+    /// The alchemy gas manager policy id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alchemy_policy_id: Option<String>,
+    /// The biconomy paymaster rpc url
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biconomy_rpc_url: Option<String>,
+    /// The biconomy paymaster policy id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biconomy_policy_id: Option<String>,
+    /// The sponsor providers in the order of priority, overriding the default order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sponsor_providers: Vec<String>,
+}
+
+/// The configuration of a single chain
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// The chain id
+    #[serde(default)]
+    pub id: u64,
+    /// The display name of the chain
+    #[serde(default)]
+    pub name: String,
+    /// The network of the chain
+    #[serde(default)]
+    pub network: ChainNetwork,
+    /// The native token symbol, defaults to `ETH`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_token_symbol: Option<String>,
+    /// The average seconds per block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_seconds: Option<u64>,
+    /// The seconds for the indexer to sleep per block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleep_seconds: Option<u64>,
+    /// Whether the token balances are indexed w/ routescan, `None` if unset in an overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routescan: Option<bool>,
+    /// Whether the chain is indexed by the runner, `None` if unset in an overlay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner: Option<bool>,
+    /// The rollup stack of the chain, if it pays an L1 data fee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollup: Option<ChainRollup>,
+    /// The rpc urls keyed by provider name
+    #[serde(default)]
+    pub rpc_urls: HashMap<String, String>,
+    /// The paymaster settings
+    #[serde(default)]
+    pub paymaster: ChainPaymasterConfig,
+    /// The subgraph ids keyed by polling service name
+    #[serde(default)]
+    pub subgraphs: HashMap<String, String>,
+    /// The overrides of the user operation gas overhead constants keyed by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub gas_overheads: HashMap<String, u64>,
+    /// The ids of the chain on the price sources keyed by source name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub price_ids: HashMap<String, String>,
+    /// The slots of the `balanceOf` mappings of the ERC20 tokens w/ a known layout keyed by token
+    /// address
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub erc20_balance_slots: HashMap<String, u64>,
+}
+
+impl ChainConfig {
+    /// Returns `true` if the chain is not a listed mainnet chain
+    pub fn is_testnet(&self) -> bool {
+        self.network != ChainNetwork::Mainnet
+    }
+
+    /// Returns `true` if the token balances are indexed w/ routescan
+    pub fn is_routescan(&self) -> bool {
+        self.routescan.unwrap_or(false)
+    }
+
+    /// Returns `true` if the chain is indexed by the runner
+    pub fn is_runner(&self) -> bool {
+        self.runner.unwrap_or(false)
+    }
+
+    /// Merges the overlay into the config, w/ the overlay taking precedence
+    pub fn merge(&mut self, overlay: ChainConfig) {
+        if !overlay.name.is_empty() {
+            self.name = overlay.name;
+        }
+        if overlay.network != ChainNetwork::Unlisted {
+            self.network = overlay.network;
+        }
+        if overlay.native_token_symbol.is_some() {
+            self.native_token_symbol = overlay.native_token_symbol;
+        }
+        if overlay.block_seconds.is_some() {
+            self.block_seconds = overlay.block_seconds;
+        }
+        if overlay.sleep_seconds.is_some() {
+            self.sleep_seconds = overlay.sleep_seconds;
+        }
+        if let Some(routescan) = overlay.routescan {
+            self.routescan = Some(routescan);
+        }
+        if let Some(runner) = overlay.runner {
+            self.runner = Some(runner);
+        }
+        if overlay.rollup.is_some() {
+            self.rollup = overlay.rollup;
+        }
+        self.rpc_urls.extend(overlay.rpc_urls);
+        if overlay.paymaster.alchemy_policy_id.is_some() {
+            self.paymaster.alchemy_policy_id = overlay.paymaster.alchemy_policy_id;
+        }
+        if overlay.paymaster.biconomy_rpc_url.is_some() {
+            self.paymaster.biconomy_rpc_url = overlay.paymaster.biconomy_rpc_url;
+        }
+        if overlay.paymaster.biconomy_policy_id.is_some() {
+            self.paymaster.biconomy_policy_id = overlay.paymaster.biconomy_policy_id;
+        }
+        if !overlay.paymaster.sponsor_providers.is_empty() {
+            self.paymaster.sponsor_providers = overlay.paymaster.sponsor_providers;
+        }
+        self.subgraphs.extend(overlay.subgraphs);
+        self.gas_overheads.extend(overlay.gas_overheads);
+        self.price_ids.extend(overlay.price_ids);
+        self.erc20_balance_slots.extend(overlay.erc20_balance_slots);
+    }
+}
+
+/// The on-disk format of the chain registry
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ChainRegistryFile {
+    #[serde(default)]
+    chains: Vec<ChainConfig>,
+}
+
+/// The registry of all of the chains
+#[derive(Clone, Debug, Default)]
+pub struct ChainRegistry {
+    chains: HashMap<u64, ChainConfig>,
+}
+
+impl ChainRegistry {
+    /// Parses the registry from a TOML string
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: ChainRegistryFile = toml::from_str(content)?;
+        Ok(Self::from_chains(file.chains))
+    }
+
+    /// Parses the registry from a JSON string
+    pub fn from_json(content: &str) -> Result<Self> {
+        let file: ChainRegistryFile = serde_json::from_str(content)?;
+        Ok(Self::from_chains(file.chains))
+    }
+
+    /// Loads the registry from a TOML or JSON file, depending on the extension
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("json") => Self::from_json(&content),
+            ext => Err(eyre!("Unsupported chain registry extension: {:?}", ext)),
+        }
+    }
+
+    /// Constructs the registry from a list of chains
+    pub fn from_chains(chains: Vec<ChainConfig>) -> Self {
+        let mut registry = Self::default();
+        registry.overlay(chains);
+        registry
+    }
+
+    /// Overlays the chains onto the registry, merging the chains that already exist
+    pub fn overlay(&mut self, chains: Vec<ChainConfig>) {
+        for chain in chains {
+            match self.chains.get_mut(&chain.id) {
+                Some(existing) => existing.merge(chain),
+                None => {
+                    self.chains.insert(chain.id, chain);
+                }
+            }
+        }
+    }
+
+    /// Get the config of the chain
+    pub fn get(&self, chain_id: u64) -> Option<&ChainConfig> {
+        self.chains.get(&chain_id)
+    }
+
+    /// Get all of the chains in the registry
+    pub fn chains(&self) -> impl Iterator<Item = &ChainConfig> {
+        self.chains.values()
+    }
+
+    /// Get the chain ids and names of the chains in the network
+    pub fn chain_ids(&self, network: ChainNetwork) -> HashMap<u64, String> {
+        self.chains
+            .values()
+            .filter(|chain| chain.network == network)
+            .map(|chain| (chain.id, chain.name.clone()))
+            .collect()
+    }
+
+    /// Get the chain ids and names of the mainnet and testnet chains
+    pub fn all_chain_ids(&self) -> HashMap<u64, String> {
+        let mut chain_ids = self.chain_ids(ChainNetwork::Mainnet);
+        chain_ids.extend(self.chain_ids(ChainNetwork::Testnet));
+        chain_ids
+    }
+
+    /// Get the chain ids flagged w/ the predicate
+    pub fn chain_ids_where(&self, predicate: impl Fn(&ChainConfig) -> bool) -> Vec<u64> {
+        let mut chain_ids: Vec<u64> =
+            self.chains.values().filter(|chain| predicate(chain)).map(|chain| chain.id).collect();
+        chain_ids.sort();
+        chain_ids
+    }
+
+    /// Returns `true` if the chain is a testnet chain
+    /// Falls back to `true` if the chain is not a listed mainnet chain
+    pub fn is_testnet(&self, chain_id: u64) -> bool {
+        self.get(chain_id).map_or(true, |chain| chain.is_testnet())
+    }
+
+    /// Get the native token symbol of the chain, falling back to `ETH`
+    pub fn native_token_symbol(&self, chain_id: u64) -> String {
+        self.get(chain_id)
+            .and_then(|chain| chain.native_token_symbol.clone())
+            .unwrap_or_else(|| DEFAULT_NATIVE_TOKEN_SYMBOL.to_string())
+    }
+
+    /// Get the block seconds of the chain, falling back to the network default
+    pub fn block_seconds(&self, chain_id: u64) -> u64 {
+        self.get(chain_id).and_then(|chain| chain.block_seconds).unwrap_or(
+            if self.is_testnet(chain_id) {
+                DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS
+            } else {
+                DEFAULT_CHAIN_BLOCK_SECONDS
+            },
+        )
+    }
+
+    /// Get the seconds for the indexer to sleep per block, if any
+    pub fn sleep_seconds(&self, chain_id: u64) -> Option<u64> {
+        self.get(chain_id).and_then(|chain| chain.sleep_seconds)
+    }
+
+    /// Get the rollup stack of the chain, `None` if the chain has no L1 data fee
+    pub fn rollup(&self, chain_id: u64) -> Option<ChainRollup> {
+        self.get(chain_id).and_then(|chain| chain.rollup)
+    }
+
+    /// Get the rpc url of the chain for the provider
+    pub fn rpc_url(&self, chain_id: u64, provider: &str) -> Option<String> {
+        self.get(chain_id).and_then(|chain| chain.rpc_urls.get(provider).cloned())
+    }
+
+    /// Get the rpc urls of all of the chains for the provider
+    pub fn rpc_urls(&self, provider: &str) -> HashMap<u64, String> {
+        self.chains
+            .values()
+            .filter_map(|chain| chain.rpc_urls.get(provider).map(|url| (chain.id, url.clone())))
+            .collect()
+    }
+
+    /// Get the gas overhead constant override of the chain
+    pub fn gas_overhead(&self, chain_id: u64, name: &str) -> Option<u64> {
+        self.get(chain_id).and_then(|chain| chain.gas_overheads.get(name).copied())
+    }
+
+    /// Get the id of the chain on the price source, e.g. the defillama chain slug
+    pub fn price_id(&self, chain_id: u64, source: &str) -> Option<String> {
+        self.get(chain_id).and_then(|chain| chain.price_ids.get(source).cloned())
+    }
+
+    /// Get the slot of the `balanceOf` mapping of the ERC20 token, if the layout is known
+    pub fn erc20_balance_slot(&self, chain_id: u64, token: &str) -> Option<u64> {
+        self.get(chain_id).and_then(|chain| {
+            chain
+                .erc20_balance_slots
+                .iter()
+                .find(|(address, _)| address.eq_ignore_ascii_case(token))
+                .map(|(_, slot)| *slot)
+        })
+    }
+
+    /// Get the subgraph ids of all of the chains for the polling service
+    pub fn subgraph_ids(&self, service: &str) -> HashMap<u64, String> {
+        self.chains
+            .values()
+            .filter_map(|chain| chain.subgraphs.get(service).map(|id| (chain.id, id.clone())))
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Global
+// -----------------------------------------------------------------------------
+
+/// The registry state w/ the base registry from file and the overlays on top
+#[derive(Default)]
+struct ChainRegistryState {
+    base: ChainRegistry,
+    overlays: Vec<ChainConfig>,
+    current: Arc<ChainRegistry>,
+}
+
+impl ChainRegistryState {
+    /// Rebuilds the current registry from the base and the overlays
+    fn rebuild(&mut self) {
+        let mut registry = self.base.clone();
+        registry.overlay(self.overlays.clone());
+        self.current = Arc::new(registry);
+    }
+}
+
+lazy_static! {
+    static ref CHAIN_REGISTRY: RwLock<ChainRegistryState> = {
+        let mut state = ChainRegistryState { base: load_base_registry(), ..Default::default() };
+        state.rebuild();
+        RwLock::new(state)
+    };
+}
+
+/// Get the path of the chain registry file from the env, if set
+fn get_chain_registry_path() -> Option<PathBuf> {
+    std::env::var(CHAIN_REGISTRY_PATH_ENV).ok().filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+/// Loads the base registry, overlaying the file at `CHAIN_REGISTRY_PATH` onto the embedded one
+fn load_base_registry() -> ChainRegistry {
+    let mut registry = ChainRegistry::from_toml(DEFAULT_CHAIN_REGISTRY).unwrap_or_else(|err| {
+        warn!("Failed to load the embedded chain registry: {:?}", err);
+        ChainRegistry::default()
+    });
+
+    if let Some(path) = get_chain_registry_path() {
+        match ChainRegistry::from_file(&path) {
+            Ok(file) => registry.overlay(file.chains.into_values().collect()),
+            Err(err) => warn!("Failed to load chain registry from {:?}: {:?}", path, err),
+        }
+    }
+
+    registry
+}
+
+/// Get a snapshot of the current chain registry
+pub fn get_chain_registry() -> Arc<ChainRegistry> {
+    CHAIN_REGISTRY.read().unwrap_or_else(PoisonError::into_inner).current.clone()
+}
+
+/// Reloads the base chain registry from the embedded registry and the file
+pub fn reload_chain_registry() {
+    let base = load_base_registry();
+
+    let mut state = CHAIN_REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    state.base = base;
+    state.rebuild();
+}
+
+/// Sets the overlay chains on top of the base registry, e.g. from the `Chain` table
+pub fn set_chain_registry_overlay(chains: Vec<ChainConfig>) {
+    let mut state = CHAIN_REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    state.overlays = chains;
+    state.rebuild();
+}
+
+/// Upserts a single overlay chain on top of the base registry
+pub fn upsert_chain_registry_overlay(chain: ChainConfig) {
+    let mut state = CHAIN_REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    state.overlays.retain(|overlay| overlay.id != chain.id);
+    state.overlays.push(chain);
+    state.rebuild();
+}
+
+/// Spawns a thread to reload the chain registry whenever the file at `CHAIN_REGISTRY_PATH` changes
+pub fn spawn_chain_registry_watcher(interval: Duration) -> Option<thread::JoinHandle<()>> {
+    let path = get_chain_registry_path()?;
+
+    Some(thread::spawn(move || {
+        let modified_at = |path: &Path| -> Option<SystemTime> {
+            std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+        };
+
+        let mut last_modified = modified_at(&path);
+        loop {
+            thread::sleep(interval);
+
+            let modified = modified_at(&path);
+            if modified != last_modified {
+                last_modified = modified;
+                reload_chain_registry();
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_chain_registry() {
+        let registry = ChainRegistry::from_toml(DEFAULT_CHAIN_REGISTRY).unwrap();
+
+        assert_eq!(registry.get(1).unwrap().name, "Ethereum Mainnet");
+        assert!(!registry.is_testnet(1));
+        assert!(registry.is_testnet(11155111));
+        assert!(registry.is_testnet(999_999_999_999));
+        assert_eq!(registry.native_token_symbol(137), "MATIC");
+        assert_eq!(registry.native_token_symbol(1), "ETH");
+        assert_eq!(registry.block_seconds(1), 12);
+        assert_eq!(registry.block_seconds(10), DEFAULT_CHAIN_BLOCK_SECONDS);
+        assert_eq!(registry.sleep_seconds(31337), Some(1));
+        assert_eq!(
+            registry.chain_ids_where(|chain| chain.is_runner()),
+            vec![1, 100, 31337, 11155111]
+        );
+        assert!(registry.rpc_url(1, "alchemy").is_some());
+        assert_eq!(registry.rollup(1), None);
+        assert_eq!(registry.rollup(10), Some(ChainRollup::OpStack));
+        assert_eq!(registry.rollup(42161), Some(ChainRollup::Arbitrum));
+        assert_eq!(registry.rollup(534352), Some(ChainRollup::Scroll));
+        assert_eq!(registry.price_id(10, "defillama").unwrap(), "optimism");
+        assert_eq!(registry.price_id(10, "coingecko").unwrap(), "optimistic-ethereum");
+        assert_eq!(registry.price_id(11155111, "defillama"), None);
+        assert_eq!(
+            registry.erc20_balance_slot(1, "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            Some(3)
+        );
+        assert_eq!(
+            registry.erc20_balance_slot(10, "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_chain_registry_overlay() {
+        let mut registry = ChainRegistry::from_toml(DEFAULT_CHAIN_REGISTRY).unwrap();
+
+        registry.overlay(vec![ChainConfig {
+            id: 1,
+            native_token_symbol: Some("TEST".to_string()),
+            runner: Some(false),
+            rpc_urls: HashMap::from([("custom".to_string(), "https://custom".to_string())]),
+            ..Default::default()
+        }]);
+        registry.overlay(vec![ChainConfig { id: 100, ..Default::default() }]);
+
+        let chain = registry.get(1).unwrap();
+        assert_eq!(chain.name, "Ethereum Mainnet");
+        assert_eq!(chain.network, ChainNetwork::Mainnet);
+        assert_eq!(registry.native_token_symbol(1), "TEST");
+        assert!(registry.rpc_url(1, "alchemy").is_some());
+        assert_eq!(registry.rpc_url(1, "custom").unwrap(), "https://custom");
+
+        // The overlay turns the flags off, and keeps them if unset
+        assert!(!chain.is_runner());
+        assert!(registry.get(100).unwrap().is_runner());
+    }
+
+    #[test]
+    fn test_chain_registry_from_json() {
+        let registry = ChainRegistry::from_json(
+            r#"{"chains": [{"id": 5, "name": "Goerli", "network": "deprecated"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.get(5).unwrap().network, ChainNetwork::Deprecated);
+        assert!(registry.is_testnet(5));
+    }
+}
@@ -77,7 +77,7 @@ impl Consumer {
         };
 
         // Create a consumer state
-        let consumer_state = match create_consumer_state().await {
+        let consumer_state = match create_consumer_state(&state).await {
             Ok(consumer_state) => Some(consumer_state),
             Err(e) => {
                 warn!("Consumer state creation failed: {:?}", e);
@@ -98,6 +98,11 @@ impl Consumer {
     pub async fn run(&self) -> Result<()> {
         info!("Consumer run, starting");
 
+        // Start bundling the mempool of the node
+        if let Some(consumer_state) = &self.consumer_state {
+            consumer_state.node.run().await;
+        }
+
         // Convert the topics to a vector of strings
         let topics: Vec<&str> = self.topics.iter().map(AsRef::as_ref).collect();
 
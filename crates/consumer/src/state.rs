@@ -19,7 +19,9 @@ use lightdotso_indexer::{config::IndexerArgs, indexer::Indexer};
 use lightdotso_interpreter::{config::InterpreterArgs, interpreter::Interpreter};
 use lightdotso_node::{config::NodeArgs, node::Node};
 use lightdotso_notifier::{config::NotifierArgs, notifier::Notifier};
+use lightdotso_oracle::oracle::PriceOracle;
 use lightdotso_polling::{config::PollingArgs, polling::Polling};
+use lightdotso_state::ClientState;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -36,17 +38,27 @@ pub struct ConsumerState {
     pub notifier: Arc<Notifier>,
     pub polling: Arc<Polling>,
     pub node: Arc<Node>,
+    // Shared clients
+    pub price_oracle: Arc<PriceOracle>,
 }
 
 /// -----------------------------------------------------------------------------
 // Utils
 // -----------------------------------------------------------------------------
 
-pub async fn create_consumer_state() -> Result<ConsumerState> {
+pub async fn create_consumer_state(state: &ClientState) -> Result<ConsumerState> {
+    // Create the price oracle w/ the clients of the state
+    let price_oracle = Arc::new(PriceOracle::with_clients(
+        state.redis.clone(),
+        state.client.clone(),
+        state.postgres_client.clone(),
+        (*state.pool).clone(),
+    ));
+
     // Parse the billing command line arguments
     let billing_args =
         BillingArgs::try_parse().unwrap_or_else(|_| BillingArgs::parse_from(["".to_string()]));
-    let billing = Arc::new(billing_args.create().await?);
+    let billing = Arc::new(billing_args.create(price_oracle.clone()).await?);
 
     // Parse the indexer command line arguments
     let indexer_args =
@@ -73,5 +85,5 @@ pub async fn create_consumer_state() -> Result<ConsumerState> {
         NotifierArgs::try_parse().unwrap_or_else(|_| NotifierArgs::parse_from(["".to_string()]));
     let notifier = Arc::new(notifier_args.create().await?);
 
-    Ok(ConsumerState { billing, indexer, interpreter, notifier, polling, node })
+    Ok(ConsumerState { billing, indexer, interpreter, notifier, polling, node, price_oracle })
 }
@@ -14,8 +14,10 @@
 
 use super::TopicConsumer;
 use crate::state::ConsumerState;
+use alloy::primitives::U256;
 use async_trait::async_trait;
 use eyre::{eyre, Result};
+use lightdotso_billing::ledger::usd_from_wei;
 use lightdotso_db::models::activity::create_activity_with_user_and_wallet;
 use lightdotso_kafka::{
     topics::notification::produce_notification_message,
@@ -24,11 +26,14 @@ use lightdotso_kafka::{
 use lightdotso_notifier::{
     types::NotificationOperation, utils::match_notification_operation_with_activity,
 };
+use lightdotso_oracle::oracle::{PriceAt, PriceOracle};
 use lightdotso_prisma::{configuration, owner, ActivityEntity, ActivityOperation};
 use lightdotso_state::ClientState;
-use lightdotso_tracing::tracing::info;
+use lightdotso_tracing::tracing::{info, warn};
+use lightdotso_utils::is_testnet;
 use prisma_client_rust::Direction;
 use rdkafka::{message::BorrowedMessage, Message};
+use serde_json::Value;
 
 // -----------------------------------------------------------------------------
 // Traits
@@ -155,8 +160,13 @@ impl ActivityConsumer {
         state: &ClientState,
         consumer_state: &ConsumerState,
         entity: ActivityEntity,
-        payload: ActivityMessage,
+        mut payload: ActivityMessage,
     ) -> Result<()> {
+        // Add the USD value of the gas cost to the executed user operation
+        if matches!(entity, ActivityEntity::UserOperation) {
+            add_gas_cost_usd(&consumer_state.price_oracle, &mut payload.log).await;
+        }
+
         // Create activity with user and wallet
         let activity = create_activity_with_user_and_wallet(
             state.client.clone(),
@@ -238,3 +248,37 @@ impl ActivityConsumer {
         Ok(())
     }
 }
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Add the actual gas cost in USD at the execution block to the log, if the log is of an executed
+/// user operation on a mainnet chain
+async fn add_gas_cost_usd(price_oracle: &PriceOracle, log: &mut Value) {
+    let (Some(chain_id), Some(actual_gas_cost), Some(block_number)) = (
+        log.get("chainId").and_then(Value::as_u64),
+        log.get("actualGasCost").and_then(Value::as_str).and_then(|cost| cost.parse::<U256>().ok()),
+        log.get("blockNumber").and_then(Value::as_u64),
+    ) else {
+        return;
+    };
+    if is_testnet(chain_id) {
+        return;
+    }
+
+    let gas_cost_usd =
+        match price_oracle.get_native_price(chain_id, PriceAt::Block(block_number)).await {
+            Ok(price) => usd_from_wei(actual_gas_cost, price),
+            Err(err) => Err(err),
+        };
+
+    match gas_cost_usd {
+        Ok(gas_cost_usd) => {
+            if let Some(log) = log.as_object_mut() {
+                log.insert("actualGasCostUSD".to_string(), Value::String(gas_cost_usd.to_string()));
+            }
+        }
+        Err(err) => warn!("Failed to price the gas cost of the user operation: {:?}", err),
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{state::ConsumerState, topics::TopicConsumer};
+use async_trait::async_trait;
+use backon::{ExponentialBuilder, Retryable};
+use eyre::{eyre, Result};
+use lightdotso_db::models::billing_operation::BillingOperationReconciliation;
+use lightdotso_kafka::{
+    topics::billing_operation::produce_billing_operation_reconcile_message,
+    types::billing_operation::BillingOperationReconcileMessage,
+};
+use lightdotso_state::ClientState;
+use lightdotso_tracing::tracing::{info, warn};
+use rdkafka::{message::BorrowedMessage, Message};
+use std::time::Duration;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The maximum number of times to requeue a reconcile w/ the user operation not yet indexed.
+const MAX_RECONCILE_ATTEMPTS: u32 = 10;
+
+/// The delay before requeueing a reconcile w/ the user operation not yet indexed.
+const RECONCILE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// -----------------------------------------------------------------------------
+// Consumer
+// -----------------------------------------------------------------------------
+
+pub struct BillingOperationReconcileConsumer;
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+#[async_trait]
+impl TopicConsumer for BillingOperationReconcileConsumer {
+    async fn consume(
+        &self,
+        state: &ClientState,
+        consumer_state: Option<&ConsumerState>,
+        msg: &BorrowedMessage<'_>,
+    ) -> Result<()> {
+        // Since we use consumer_state, we need to unwrap it
+        let consumer_state = consumer_state.ok_or_else(|| eyre!("Consumer state is None"))?;
+
+        // Convert the payload to a string
+        let payload_opt = msg.payload_view::<str>();
+        info!("payload_opt: {:?}", payload_opt);
+
+        // If the payload is valid
+        if let Some(Ok(payload)) = payload_opt {
+            // Parse the payload into a JSON object, `BillingOperationReconcileMessage`
+            let payload: BillingOperationReconcileMessage =
+                serde_json::from_slice(payload.as_bytes())?;
+            info!("payload: {:?}", payload);
+
+            // Consume the payload
+            self.consume_with_message(state, consumer_state, payload).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BillingOperationReconcileConsumer {
+    pub async fn consume_with_message(
+        &self,
+        state: &ClientState,
+        consumer_state: &ConsumerState,
+        payload: BillingOperationReconcileMessage,
+    ) -> Result<()> {
+        // Reconcile the billing operation
+        let reconciliation = consumer_state.billing.run_reconcile(&payload).await?;
+
+        // If the user operation is not indexed yet, requeue the reconcile w/ a delay
+        if let BillingOperationReconciliation::NotIndexed = reconciliation {
+            if payload.attempt >= MAX_RECONCILE_ATTEMPTS {
+                warn!(
+                    "User operation: {:?} not indexed after {} attempts, dropping the reconcile",
+                    payload.user_operation_hash, payload.attempt
+                );
+                return Ok(());
+            }
+
+            warn!(
+                "User operation: {:?} not indexed, requeueing the reconcile: {}",
+                payload.user_operation_hash, payload.attempt
+            );
+            tokio::time::sleep(RECONCILE_RETRY_DELAY).await;
+
+            let payload =
+                BillingOperationReconcileMessage { attempt: payload.attempt + 1, ..payload };
+            let client = state.producer.clone();
+            { || produce_billing_operation_reconcile_message(client.clone(), &payload) }
+                .retry(ExponentialBuilder::default())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -120,6 +120,7 @@ impl InterpretationConsumer {
                     .value
                     .and_then(|value| value.parse::<u64>().ok()),
                 traces: vec![],
+                erc20_balances: vec![],
                 logs: transaction_with_logs.logs.into_iter().map(|lx| lx.inner).collect(),
             };
             // Run the interpretation
@@ -162,6 +163,7 @@ impl InterpretationConsumer {
                 call_data: Some(user_operation_with_logs.user_operation.clone().call_data.into()),
                 value: None,
                 traces: vec![],
+                erc20_balances: vec![],
                 logs: user_operation_with_logs.logs.into_iter().map(|lx| lx.inner).collect(),
             };
 
@@ -16,6 +16,7 @@ use crate::{
     state::ConsumerState,
     topics::{
         activity::ActivityConsumer, billing_operation::BillingOperationConsumer,
+        billing_operation_reconcile::BillingOperationReconcileConsumer,
         error_transaction::ErrorTransactionConsumer, interpretation::InterpretationConsumer,
         node::NodeConsumer, notification::NotificationConsumer,
         paymaster_operation::PaymasterOperationConsumer, portfolio::PortfolioConsumer,
@@ -28,9 +29,10 @@ use covalent::CovalentConsumer;
 use eyre::Result;
 use lazy_static::lazy_static;
 use lightdotso_kafka::namespace::{
-    ACTIVITY, BILLING_OPERATION, COVALENT, ERROR_TRANSACTION, INTERPRETATION, NODE, NOTIFICATION,
-    PAYMASTER_OPERATION, PORTFOLIO, RETRY_TRANSACTION, RETRY_TRANSACTION_0, RETRY_TRANSACTION_1,
-    RETRY_TRANSACTION_2, ROUTESCAN, TRANSACTION, USER_OPERATION,
+    ACTIVITY, BILLING_OPERATION, BILLING_OPERATION_RECONCILE, COVALENT, ERROR_TRANSACTION,
+    INTERPRETATION, NODE, NOTIFICATION, PAYMASTER_OPERATION, PORTFOLIO, RETRY_TRANSACTION,
+    RETRY_TRANSACTION_0, RETRY_TRANSACTION_1, RETRY_TRANSACTION_2, ROUTESCAN, TRANSACTION,
+    USER_OPERATION,
 };
 use lightdotso_state::ClientState;
 use rdkafka::message::BorrowedMessage;
@@ -38,6 +40,7 @@ use std::{collections::HashMap, sync::Arc};
 
 pub mod activity;
 pub mod billing_operation;
+pub mod billing_operation_reconcile;
 pub mod covalent;
 pub mod error_transaction;
 pub mod interpretation;
@@ -62,6 +65,10 @@ lazy_static! {
             BILLING_OPERATION.to_string(),
             Arc::new(BillingOperationConsumer) as Arc<dyn TopicConsumer + Send + Sync>,
         );
+        m.insert(
+            BILLING_OPERATION_RECONCILE.to_string(),
+            Arc::new(BillingOperationReconcileConsumer) as Arc<dyn TopicConsumer + Send + Sync>,
+        );
         m.insert(
             COVALENT.to_string(),
             Arc::new(CovalentConsumer) as Arc<dyn TopicConsumer + Send + Sync>,
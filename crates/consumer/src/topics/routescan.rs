@@ -16,17 +16,19 @@
 
 use super::TopicConsumer;
 use crate::state::ConsumerState;
-use alloy::primitives::Address;
+use alloy::primitives::{utils::format_units, Address, U256};
 use async_trait::async_trait;
 use eyre::{eyre, Result};
 use lightdotso_kafka::types::routescan::RoutescanMessage;
+use lightdotso_oracle::oracle::{PriceAt, PriceOracle};
 use lightdotso_prisma::token;
 use lightdotso_prisma_postgres::wallet_balance;
 use lightdotso_routescan::{get_native_balance, get_token_balances, types::WalletBalanceItem};
 use lightdotso_state::ClientState;
-use lightdotso_tracing::tracing::info;
+use lightdotso_tracing::tracing::{info, warn};
 use lightdotso_utils::is_testnet;
 use rdkafka::{message::BorrowedMessage, Message};
+use std::sync::Arc;
 
 // -----------------------------------------------------------------------------
 // Consumer
@@ -43,7 +45,7 @@ impl TopicConsumer for RoutescanConsumer {
     async fn consume(
         &self,
         state: &ClientState,
-        _consumer_state: Option<&ConsumerState>,
+        consumer_state: Option<&ConsumerState>,
         msg: &BorrowedMessage<'_>,
     ) -> Result<()> {
         // Convert the payload to a string
@@ -55,8 +57,19 @@ impl TopicConsumer for RoutescanConsumer {
             // Parse the payload into a JSON object, `RoutescanMessage`
             let payload: RoutescanMessage = serde_json::from_slice(payload.as_bytes())?;
 
+            // Price the balances w/ the shared oracle, or w/ the clients of the state
+            let price_oracle = match consumer_state {
+                Some(consumer_state) => consumer_state.price_oracle.clone(),
+                None => Arc::new(PriceOracle::with_clients(
+                    state.redis.clone(),
+                    state.client.clone(),
+                    state.postgres_client.clone(),
+                    (*state.pool).clone(),
+                )),
+            };
+
             // Consume the payload
-            self.consume_with_message(state, payload).await?;
+            self.consume_with_message(state, &price_oracle, payload).await?;
         }
 
         Ok(())
@@ -71,6 +84,7 @@ impl RoutescanConsumer {
     pub async fn consume_with_message(
         &self,
         state: &ClientState,
+        price_oracle: &PriceOracle,
         payload: RoutescanMessage,
     ) -> Result<()> {
         // Log the payload
@@ -115,6 +129,21 @@ impl RoutescanConsumer {
             .collect::<Vec<_>>();
         info!(?new_items);
 
+        // Get the latest price of each token, w/ the native token at the zero address
+        let mut prices = vec![];
+        for item in &new_items {
+            let token = item.token_address.as_ref().unwrap().parse::<Address>()?;
+            let price = price_oracle
+                .get_price(payload.chain_id, token, PriceAt::Latest)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Failed to get the price of {:?}: {:?}", token, err);
+                    0.0
+                });
+            prices.push(price);
+        }
+        info!(?prices);
+
         // Create the tokens
         let res = state
             .client
@@ -168,7 +197,8 @@ impl RoutescanConsumer {
         // Create token data for each token
         let token_data_results = new_items
             .iter()
-            .map(|ite| {
+            .zip(prices.iter())
+            .map(|(ite, price)| {
                 // Find the item
                 let token = tokens.iter().find(|token| {
                     token.address.clone().to_lowercase() ==
@@ -179,10 +209,7 @@ impl RoutescanConsumer {
                 let token_result = token.ok_or(eyre!("Item not found for token: {:?}", ite));
 
                 // If valid item found, build data, else propagate error
-                // Temporary fix for quote rate
-                token_result.map(|token| (0.0, token.clone().id, vec![]))
-                // token_result.map(|token| (ite.quote_rate.unwrap_or(0.0), token.clone().id,
-                // vec![]))
+                token_result.map(|token| (*price, token.clone().id, vec![]))
             })
             .collect::<Vec<_>>();
 
@@ -218,7 +245,8 @@ impl RoutescanConsumer {
                     .create_many(
                         new_items
                             .iter()
-                            .map(|item| {
+                            .zip(prices.iter())
+                            .map(|(item, price)| {
                                 // Find the token
                                 let token = tokens
                                     .iter()
@@ -228,9 +256,7 @@ impl RoutescanConsumer {
                                     .unwrap();
 
                                 (
-                                    // Temporary fix for quote rate
-                                    // item.quote.unwrap_or(0.0),
-                                    0.0_f64,
+                                    balance_usd(item, *price),
                                     payload.chain_id as f64,
                                     payload.address.to_checksum(None),
                                     vec![
@@ -270,3 +296,22 @@ impl RoutescanConsumer {
         Ok(())
     }
 }
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// The balance of the item in USD at the price
+fn balance_usd(item: &WalletBalanceItem, price: f64) -> f64 {
+    let (Some(quantity), Some(decimals)) = (&item.token_quantity, item.token_decimals) else {
+        return 0.0;
+    };
+    let (Ok(quantity), Ok(decimals)) = (quantity.parse::<U256>(), u8::try_from(decimals)) else {
+        return 0.0;
+    };
+
+    format_units(quantity, decimals)
+        .ok()
+        .and_then(|amount| amount.parse::<f64>().ok())
+        .map_or(0.0, |amount| amount * price)
+}
@@ -216,6 +216,27 @@ lazy_static! {
       "0x4Fd9098af9ddcB41DA48A1d78F91F1398965addc".parse().unwrap();
 }
 
+// The OP Stack gas price oracle predeploy address
+lazy_static! {
+    #[derive(Debug)]
+    pub static ref OP_GAS_PRICE_ORACLE_ADDRESS: Address =
+      "0x420000000000000000000000000000000000000F".parse().unwrap();
+}
+
+// The Arbitrum node interface precompile address
+lazy_static! {
+    #[derive(Debug)]
+    pub static ref ARBITRUM_NODE_INTERFACE_ADDRESS: Address =
+      "0x00000000000000000000000000000000000000C8".parse().unwrap();
+}
+
+// The Scroll L1 gas price oracle predeploy address
+lazy_static! {
+    #[derive(Debug)]
+    pub static ref SCROLL_L1_GAS_PRICE_ORACLE_ADDRESS: Address =
+      "0x5300000000000000000000000000000000000002".parse().unwrap();
+}
+
 // -----------------------------------------------------------------------------
 // Tests
 // -----------------------------------------------------------------------------
@@ -48,20 +48,23 @@ pub async fn get_erc_1271_wallet(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::address::LIGHT_WALLET_EXAMPLE_ADDRESS;
+    use crate::{
+        address::LIGHT_WALLET_EXAMPLE_ADDRESS, fixture::get_replay_fixture_store,
+        provider::get_provider_with_fixtures,
+    };
 
-    #[ignore]
     #[tokio::test]
     async fn test_get_wallet() {
         let chain_id = 11155111;
         // Get the address
         let wallet_address = *LIGHT_WALLET_EXAMPLE_ADDRESS;
 
-        let res = get_erc_1271_wallet(chain_id, wallet_address).await;
+        let res = get_provider_with_fixtures(chain_id, get_replay_fixture_store()).await;
         assert!(res.is_ok());
 
         // If you want to test the details of the resulting contract:
-        let contract = res.unwrap();
+        let (provider, _) = res.unwrap();
+        let contract = ERC1271::new(wallet_address, provider);
         assert_eq!(contract.address().to_checksum(None), wallet_address.to_checksum(None));
     }
 }
@@ -0,0 +1,444 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::{
+    primitives::keccak256,
+    providers::{ProviderBuilder, RootProvider},
+    rpc::{
+        client::ClientBuilder,
+        json_rpc::{
+            ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload,
+            SerializedRequest,
+        },
+    },
+    transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut},
+};
+use eyre::Result;
+use lightdotso_tracing::tracing::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use std::{
+    path::PathBuf,
+    task::{Context, Poll},
+};
+use tokio::task::spawn_blocking;
+use tower::Service;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The read-only methods that are recorded to and replayed from the fixtures
+/// `eth_blockNumber` is included, so a replay pins the chain head to the block at recording time
+pub const FIXTURE_METHODS: [&str; 17] = [
+    "eth_blockNumber",
+    "eth_call",
+    "eth_chainId",
+    "eth_estimateGas",
+    "eth_feeHistory",
+    "eth_gasPrice",
+    "eth_getBalance",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getCode",
+    "eth_getLogs",
+    "eth_getStorageAt",
+    "eth_getTransactionByHash",
+    "eth_getTransactionCount",
+    "eth_getTransactionReceipt",
+    "eth_maxPriorityFeePerGas",
+    "net_version",
+];
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The mode of the rpc fixtures, set w/ the env `RPC_FIXTURE_MODE`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Requests are sent to the network as usual
+    #[default]
+    Off,
+    /// Requests are sent to the network and the responses are written to the fixtures
+    Record,
+    /// Requests are served from the fixtures
+    Replay,
+}
+
+impl FixtureMode {
+    /// Get the fixture mode from the env `RPC_FIXTURE_MODE`
+    pub fn from_env() -> Self {
+        match std::env::var("RPC_FIXTURE_MODE").unwrap_or_default().as_str() {
+            "record" => Self::Record,
+            "replay" => Self::Replay,
+            _ => Self::Off,
+        }
+    }
+}
+
+/// The key of a fixture, unique per chain, method and params
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixtureKey {
+    pub chain_id: u64,
+    pub method: String,
+    pub params_hash: String,
+}
+
+impl FixtureKey {
+    /// Constructs the key, hashing the params normalized w/ `serde_json`
+    pub fn new(chain_id: u64, method: &str, params: &Value) -> Self {
+        let params_hash = keccak256(params.to_string().as_bytes());
+
+        Self {
+            chain_id,
+            method: method.to_string(),
+            params_hash: format!("{:x}", params_hash)[..16].to_string(),
+        }
+    }
+}
+
+/// A recorded request and response pair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub chain_id: u64,
+    pub method: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+/// The store of the fixtures on disk
+#[derive(Clone, Debug)]
+pub struct FixtureStore {
+    pub mode: FixtureMode,
+    pub dir: PathBuf,
+    /// Whether a missing fixture in replay mode is an error, rather than a network fallback
+    pub strict: bool,
+}
+
+impl FixtureStore {
+    /// Constructs the store from the envs `RPC_FIXTURE_MODE`, `RPC_FIXTURE_DIR` and
+    /// `RPC_FIXTURE_STRICT`
+    pub fn from_env() -> Self {
+        Self {
+            mode: FixtureMode::from_env(),
+            dir: std::env::var("RPC_FIXTURE_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("fixtures/rpc")),
+            strict: std::env::var("RPC_FIXTURE_STRICT").map(|v| v != "false").unwrap_or(true),
+        }
+    }
+
+    /// Get the path of the fixture file
+    pub fn path(&self, key: &FixtureKey) -> PathBuf {
+        self.dir
+            .join(key.chain_id.to_string())
+            .join(&key.method)
+            .join(format!("{}.json", key.params_hash))
+    }
+
+    /// Reads the fixture off the runtime, returning `None` if it was never recorded
+    pub async fn read(&self, key: &FixtureKey) -> Result<Option<Fixture>> {
+        let path = self.path(key);
+        spawn_blocking(move || read_fixture_file(path)).await?
+    }
+
+    /// Writes the fixture off the runtime, overwriting any previous recording
+    pub async fn write(&self, key: &FixtureKey, fixture: &Fixture) -> Result<()> {
+        let path = self.path(key);
+        let content = serde_json::to_string_pretty(fixture)?;
+        spawn_blocking(move || write_fixture_file(path, content)).await?
+    }
+}
+
+/// Reads the fixture file, returning `None` if it does not exist
+fn read_fixture_file(path: PathBuf) -> Result<Option<Fixture>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+}
+
+/// Writes the fixture file, creating the parent directories
+fn write_fixture_file(path: PathBuf, content: String) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+// Transport
+// -----------------------------------------------------------------------------
+
+/// The transport recording to or replaying from the fixtures, wrapping the network transport
+#[derive(Clone)]
+pub struct FixtureTransport {
+    chain_id: u64,
+    store: FixtureStore,
+    inner: Option<BoxTransport>,
+}
+
+impl FixtureTransport {
+    /// Constructs the transport, w/ the inner transport used for recording and replay misses
+    pub fn new(chain_id: u64, store: FixtureStore, inner: Option<BoxTransport>) -> Self {
+        Self { chain_id, store, inner }
+    }
+
+    /// Handles a single request, sending the methods outside of `FIXTURE_METHODS` straight to the
+    /// inner transport w/o recording them
+    async fn handle_single(&self, req: SerializedRequest) -> Result<Response, TransportError> {
+        if !FIXTURE_METHODS.contains(&req.method()) {
+            return self.forward(req).await;
+        }
+
+        let params: Value = req
+            .params()
+            .map(|params| serde_json::from_str(params.get()))
+            .transpose()
+            .map_err(TransportError::ser_err)?
+            .unwrap_or(Value::Null);
+        let key = FixtureKey::new(self.chain_id, req.method(), &params);
+
+        if self.store.mode == FixtureMode::Replay {
+            if let Some(fixture) = self.store.read(&key).await.map_err(fixture_err)? {
+                return Ok(Response { id: req.id().clone(), payload: fixture_payload(fixture)? });
+            }
+
+            if self.store.strict {
+                return Err(TransportErrorKind::custom_str(&format!(
+                    "Missing fixture at {:?}",
+                    self.store.path(&key)
+                )));
+            }
+        }
+
+        let method = req.method().to_string();
+        let response = self.forward(req).await?;
+
+        // Record the response, including the misses of a lenient replay
+        let (result, error) = match &response.payload {
+            ResponsePayload::Success(result) => (serde_json::from_str(result.get()).ok(), None),
+            ResponsePayload::Failure(error) => (None, serde_json::to_value(error).ok()),
+        };
+        let fixture = Fixture { chain_id: self.chain_id, method, params, result, error };
+        if let Err(err) = self.store.write(&key, &fixture).await {
+            warn!("Failed to write fixture: {:?}", err);
+        }
+
+        Ok(response)
+    }
+
+    /// Sends the request to the inner transport, erroring if there is none
+    async fn forward(&self, req: SerializedRequest) -> Result<Response, TransportError> {
+        let mut inner = self.inner.clone().ok_or_else(|| {
+            TransportErrorKind::custom_str(&format!(
+                "No transport to send the method {} to",
+                req.method()
+            ))
+        })?;
+
+        match inner.call(RequestPacket::Single(req)).await? {
+            ResponsePacket::Single(response) => Ok(response),
+            ResponsePacket::Batch(_) => {
+                Err(TransportErrorKind::custom_str("Unexpected batch response"))
+            }
+        }
+    }
+
+    /// Handles a single or batch request packet
+    async fn handle(self, req: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        match req {
+            RequestPacket::Single(req) => {
+                Ok(ResponsePacket::Single(self.handle_single(req).await?))
+            }
+            RequestPacket::Batch(reqs) => {
+                let mut responses = Vec::with_capacity(reqs.len());
+                for req in reqs {
+                    responses.push(self.handle_single(req).await?);
+                }
+                Ok(ResponsePacket::Batch(responses))
+            }
+        }
+    }
+}
+
+impl Service<RequestPacket> for FixtureTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().handle(req))
+    }
+}
+
+/// Converts the recorded fixture into a response payload
+fn fixture_payload(fixture: Fixture) -> Result<ResponsePayload, TransportError> {
+    if let Some(error) = fixture.error {
+        let error: ErrorPayload =
+            serde_json::from_value(error).map_err(TransportError::deser_err)?;
+        return Ok(ResponsePayload::Failure(error));
+    }
+
+    let result: Box<RawValue> =
+        serde_json::value::to_raw_value(&fixture.result.unwrap_or_default())
+            .map_err(TransportError::ser_err)?;
+    Ok(ResponsePayload::Success(result))
+}
+
+/// Converts a fixture store error into a transport error
+fn fixture_err(err: eyre::Report) -> TransportError {
+    TransportErrorKind::custom_str(&format!("Fixture error: {:?}", err))
+}
+
+/// Wraps the provider's transport w/ the fixture transport, if the fixture mode is enabled
+pub fn get_fixture_provider(
+    chain_id: u64,
+    store: FixtureStore,
+    inner: Option<&RootProvider<BoxTransport>>,
+) -> Option<RootProvider<BoxTransport>> {
+    if store.mode == FixtureMode::Off {
+        return None;
+    }
+
+    let transport = FixtureTransport::new(
+        chain_id,
+        store,
+        inner.map(|provider| provider.client().transport().clone()),
+    );
+    let client = ClientBuilder::default().transport(BoxTransport::new(transport), false);
+
+    Some(ProviderBuilder::new().on_client(client))
+}
+
+/// The store serving the providers of the tests from the checked-in fixtures w/o network access
+#[cfg(test)]
+pub(crate) fn get_replay_fixture_store() -> FixtureStore {
+    FixtureStore {
+        mode: FixtureMode::Replay,
+        dir: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")),
+        strict: true,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::rpc::json_rpc::{Id, Request};
+    use serde_json::json;
+
+    #[test]
+    fn test_fixture_key() {
+        let key_1 = FixtureKey::new(1, "eth_getBalance", &json!(["0x0", "latest"]));
+        let key_2 = FixtureKey::new(1, "eth_getBalance", &json!(["0x0", "latest"]));
+        let key_3 = FixtureKey::new(10, "eth_getBalance", &json!(["0x0", "latest"]));
+
+        assert_eq!(key_1, key_2);
+        assert_eq!(key_1.params_hash.len(), 16);
+        let store = get_replay_fixture_store();
+        assert_ne!(store.path(&key_1), store.path(&key_3));
+    }
+
+    #[tokio::test]
+    async fn test_fixture_store_roundtrip() {
+        let store = FixtureStore {
+            mode: FixtureMode::Replay,
+            dir: get_temp_dir("roundtrip"),
+            strict: true,
+        };
+        let key = FixtureKey::new(1, "eth_blockNumber", &Value::Null);
+        let fixture = Fixture {
+            chain_id: 1,
+            method: "eth_blockNumber".to_string(),
+            params: Value::Null,
+            result: Some(json!("0x1")),
+            error: None,
+        };
+
+        store.write(&key, &fixture).await.unwrap();
+        let read = store.read(&key).await.unwrap().unwrap();
+        assert_eq!(read.result, Some(json!("0x1")));
+
+        let payload = fixture_payload(read).unwrap();
+        assert!(matches!(payload, ResponsePayload::Success(_)));
+    }
+
+    #[tokio::test]
+    async fn test_fixture_transport_replay() {
+        let store =
+            FixtureStore { mode: FixtureMode::Replay, dir: get_temp_dir("replay"), strict: true };
+        let fixture = Fixture {
+            chain_id: 1,
+            method: "eth_chainId".to_string(),
+            params: json!([]),
+            result: Some(json!("0x1")),
+            error: None,
+        };
+        store.write(&FixtureKey::new(1, "eth_chainId", &json!([])), &fixture).await.unwrap();
+
+        let transport = FixtureTransport::new(1, store, None);
+        let res = transport.handle_single(get_request("eth_chainId", json!([]))).await.unwrap();
+        assert!(
+            matches!(res.payload, ResponsePayload::Success(result) if result.get() == "\"0x1\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fixture_transport_write_method() {
+        let store =
+            FixtureStore { mode: FixtureMode::Replay, dir: get_temp_dir("write"), strict: true };
+        let params = json!(["0x00"]);
+        let fixture = Fixture {
+            chain_id: 1,
+            method: "eth_sendRawTransaction".to_string(),
+            params: params.clone(),
+            result: Some(json!("0x0")),
+            error: None,
+        };
+        store
+            .write(&FixtureKey::new(1, "eth_sendRawTransaction", &params), &fixture)
+            .await
+            .unwrap();
+
+        // The state changing methods are never replayed, even if a fixture exists
+        let transport = FixtureTransport::new(1, store, None);
+        assert!(transport
+            .handle_single(get_request("eth_sendRawTransaction", params))
+            .await
+            .is_err());
+    }
+
+    fn get_request(method: &'static str, params: Value) -> SerializedRequest {
+        Request::new(method, Id::Number(1), params).serialize().unwrap()
+    }
+
+    fn get_temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lightdotso-fixture-{}-{}", name, std::process::id()))
+    }
+}
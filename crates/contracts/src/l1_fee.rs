@@ -0,0 +1,235 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    address::{
+        ARBITRUM_NODE_INTERFACE_ADDRESS, ENTRYPOINT_V060_ADDRESS, ENTRYPOINT_V070_ADDRESS,
+        OP_GAS_PRICE_ORACLE_ADDRESS, SCROLL_L1_GAS_PRICE_ORACLE_ADDRESS,
+    },
+    entrypoint_v060::EntryPointV060,
+    entrypoint_v070::EntryPointV070,
+    provider::get_provider,
+    types::{PackedUserOperation, UserOperation},
+};
+use alloy::{
+    primitives::{keccak256, Address, Bytes, U256},
+    sol,
+    sol_types::SolCall,
+};
+use eyre::Result;
+use lightdotso_constants::registry::{get_chain_registry, ChainRollup};
+
+// -----------------------------------------------------------------------------
+// Contract
+// -----------------------------------------------------------------------------
+
+sol!(
+    #[sol(rpc)]
+    interface OpGasPriceOracle {
+        function getL1Fee(bytes memory _data) external view returns (uint256);
+    }
+);
+
+sol!(
+    #[sol(rpc)]
+    interface ArbitrumNodeInterface {
+        function gasEstimateL1Component(address to, bool contractCreation, bytes calldata data)
+            external
+            payable
+            returns (uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate);
+    }
+);
+
+sol!(
+    #[sol(rpc)]
+    interface ScrollL1GasPriceOracle {
+        function getL1Fee(bytes memory _data) external view returns (uint256);
+    }
+);
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The gas cost of a zero byte of calldata (EIP-2028)
+pub const CALLDATA_ZERO_BYTE_GAS: u64 = 4;
+
+/// The gas cost of a non-zero byte of calldata (EIP-2028)
+pub const CALLDATA_NON_ZERO_BYTE_GAS: u64 = 16;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The oracle of the L1 data fee of a rollup
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum L1FeeOracle {
+    /// The `GasPriceOracle` predeploy of the OP Stack
+    OpStack,
+    /// The `NodeInterface` precompile of Arbitrum
+    Arbitrum,
+    /// The `L1GasPriceOracle` predeploy of Scroll
+    Scroll,
+}
+
+impl L1FeeOracle {
+    /// Get the oracle of the chain from the rollup stack of the chain registry, `None` if the
+    /// chain has no L1 data fee
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        get_chain_registry().rollup(chain_id).map(Self::from)
+    }
+}
+
+impl From<ChainRollup> for L1FeeOracle {
+    fn from(rollup: ChainRollup) -> Self {
+        match rollup {
+            ChainRollup::OpStack => Self::OpStack,
+            ChainRollup::Arbitrum => Self::Arbitrum,
+            ChainRollup::Scroll => Self::Scroll,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Calldata
+// -----------------------------------------------------------------------------
+
+/// Get the gas cost of the calldata (EIP-2028)
+pub fn calldata_gas(data: &[u8]) -> u64 {
+    data.iter()
+        .map(|byte| if *byte == 0 { CALLDATA_ZERO_BYTE_GAS } else { CALLDATA_NON_ZERO_BYTE_GAS })
+        .sum()
+}
+
+/// Get the gas cost of calldata of the size, assuming no zero bytes
+pub fn calldata_gas_for_size(size: usize) -> u64 {
+    size as u64 * CALLDATA_NON_ZERO_BYTE_GAS
+}
+
+/// Get deterministic incompressible calldata of the size, to estimate the L1 data fee by size
+/// Rollups compressing the calldata would otherwise underestimate repetitive placeholder bytes
+pub fn sample_calldata(size: usize) -> Bytes {
+    let mut data = Vec::with_capacity(size + 32);
+    let mut seed = keccak256(size.to_be_bytes());
+    while data.len() < size {
+        data.extend_from_slice(seed.as_slice());
+        seed = keccak256(seed);
+    }
+    data.truncate(size);
+    data.into()
+}
+
+/// Get the calldata of a bundle of the user operation (v0.6)
+pub fn handle_ops_calldata(user_operation: &UserOperation, beneficiary: Address) -> Bytes {
+    EntryPointV060::handleOpsCall { ops: vec![user_operation.clone().into()], beneficiary }
+        .abi_encode()
+        .into()
+}
+
+/// Get the calldata of a bundle of the packed user operation (v0.7)
+pub fn handle_packed_ops_calldata(
+    user_operation: &PackedUserOperation,
+    beneficiary: Address,
+) -> Bytes {
+    EntryPointV070::handleOpsCall { ops: vec![user_operation.clone().into()], beneficiary }
+        .abi_encode()
+        .into()
+}
+
+// -----------------------------------------------------------------------------
+// Oracle
+// -----------------------------------------------------------------------------
+
+/// Get the L1 data fee in wei of the calldata sent to the entrypoint on the chain
+/// Returns `None` if the chain has no L1 data fee
+pub async fn get_l1_fee(chain_id: u64, data: Bytes, to: Option<Address>) -> Result<Option<U256>> {
+    let oracle = match L1FeeOracle::from_chain_id(chain_id) {
+        Some(oracle) => oracle,
+        None => return Ok(None),
+    };
+
+    // Get the provider.
+    let (provider, _) = get_provider(chain_id).await?;
+
+    let l1_fee = match oracle {
+        L1FeeOracle::OpStack => {
+            let contract = OpGasPriceOracle::new(*OP_GAS_PRICE_ORACLE_ADDRESS, provider);
+            contract.getL1Fee(data).call().await?._0
+        }
+        L1FeeOracle::Arbitrum => {
+            let contract = ArbitrumNodeInterface::new(*ARBITRUM_NODE_INTERFACE_ADDRESS, provider);
+            let to = to.unwrap_or(*ENTRYPOINT_V060_ADDRESS);
+            let res = contract.gasEstimateL1Component(to, false, data).call().await?;
+
+            // The L1 component is denominated in L2 gas
+            U256::from(res.gasEstimateForL1) * res.baseFee
+        }
+        L1FeeOracle::Scroll => {
+            let contract =
+                ScrollL1GasPriceOracle::new(*SCROLL_L1_GAS_PRICE_ORACLE_ADDRESS, provider);
+            contract.getL1Fee(data).call().await?._0
+        }
+    };
+
+    Ok(Some(l1_fee))
+}
+
+/// Get the L1 data fee in wei of a bundle of the user operation
+pub async fn get_user_operation_l1_fee(
+    chain_id: u64,
+    user_operation: &UserOperation,
+) -> Result<Option<U256>> {
+    let data = handle_ops_calldata(user_operation, Address::ZERO);
+    get_l1_fee(chain_id, data, Some(*ENTRYPOINT_V060_ADDRESS)).await
+}
+
+/// Get the L1 data fee in wei of a bundle of the packed user operation
+pub async fn get_packed_user_operation_l1_fee(
+    chain_id: u64,
+    user_operation: &PackedUserOperation,
+) -> Result<Option<U256>> {
+    let data = handle_packed_ops_calldata(user_operation, Address::ZERO);
+    get_l1_fee(chain_id, data, Some(*ENTRYPOINT_V070_ADDRESS)).await
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calldata_gas() {
+        assert_eq!(calldata_gas(&[]), 0);
+        assert_eq!(calldata_gas(&[0, 0, 1, 255]), 4 + 4 + 16 + 16);
+        assert_eq!(calldata_gas_for_size(4), 64);
+    }
+
+    #[test]
+    fn test_sample_calldata() {
+        assert_eq!(sample_calldata(0).len(), 0);
+        assert_eq!(sample_calldata(100).len(), 100);
+        assert_eq!(sample_calldata(100), sample_calldata(100));
+    }
+
+    #[test]
+    fn test_l1_fee_oracle_from_chain_id() {
+        assert_eq!(L1FeeOracle::from_chain_id(10), Some(L1FeeOracle::OpStack));
+        assert_eq!(L1FeeOracle::from_chain_id(42161), Some(L1FeeOracle::Arbitrum));
+        assert_eq!(L1FeeOracle::from_chain_id(534352), Some(L1FeeOracle::Scroll));
+        assert_eq!(L1FeeOracle::from_chain_id(1), None);
+    }
+}
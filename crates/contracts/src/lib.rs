@@ -18,6 +18,8 @@ pub mod dag;
 pub mod entrypoint_v060;
 pub mod entrypoint_v070;
 pub mod erc1271;
+pub mod fixture;
+pub mod l1_fee;
 pub mod light_wallet;
 pub mod merkle_tree;
 pub mod packed_user_operation;
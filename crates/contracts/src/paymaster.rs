@@ -126,22 +126,25 @@ pub fn decode_paymaster_and_data(msg: Vec<u8>) -> Result<(Address, u64, u64, Vec
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::address::LIGHT_PAYMASTER_ADDRESSES;
+    use crate::{
+        address::LIGHT_PAYMASTER_ADDRESSES, fixture::get_replay_fixture_store,
+        provider::get_provider_with_fixtures,
+    };
     use alloy::hex;
     use eyre::Result;
 
-    #[ignore]
     #[tokio::test]
     async fn test_get_paymaster() {
         let chain_id = 1;
         // Get the address
         let verifying_paymaster_address = LIGHT_PAYMASTER_ADDRESSES[0];
 
-        let res = get_paymaster(chain_id, verifying_paymaster_address).await;
+        let res = get_provider_with_fixtures(chain_id, get_replay_fixture_store()).await;
         assert!(res.is_ok());
 
         // If you want to test the details of the resulting contract:
-        let contract = res.unwrap();
+        let (provider, _) = res.unwrap();
+        let contract = LightPaymaster::new(verifying_paymaster_address, provider);
         assert_eq!(
             contract.address().to_checksum(None),
             verifying_paymaster_address.to_checksum(None)
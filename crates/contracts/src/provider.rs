@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::fixture::{get_fixture_provider, FixtureMode, FixtureStore};
 use alloy::{
     network::{Ethereum, EthereumWallet},
     providers::{
@@ -31,7 +32,40 @@ use reqwest::Client;
 // -----------------------------------------------------------------------------
 
 /// Returns a provider for the given chain ID w/ fallbacks
+/// If env `RPC_FIXTURE_MODE` is set, the calls are recorded to or replayed from the fixtures
 pub async fn get_provider(chain_id: u64) -> Result<(RootProvider<BoxTransport>, String)> {
+    get_provider_with_fixtures(chain_id, FixtureStore::from_env()).await
+}
+
+/// Returns a provider for the given chain ID w/ the calls recorded to or replayed from the store
+pub async fn get_provider_with_fixtures(
+    chain_id: u64,
+    store: FixtureStore,
+) -> Result<(RootProvider<BoxTransport>, String)> {
+    match store.mode {
+        FixtureMode::Off => get_network_provider(chain_id).await,
+        FixtureMode::Record => {
+            let (provider, rpc_url) = get_network_provider(chain_id).await?;
+            let provider = get_fixture_provider(chain_id, store, Some(&provider))
+                .ok_or_else(|| eyre!("Could not construct the fixture provider"))?;
+            Ok((provider, rpc_url))
+        }
+        FixtureMode::Replay => {
+            // Only connect to the network if the misses may fall back to it
+            let network_provider = if store.strict {
+                None
+            } else {
+                get_network_provider(chain_id).await.ok().map(|(provider, _)| provider)
+            };
+            let provider = get_fixture_provider(chain_id, store, network_provider.as_ref())
+                .ok_or_else(|| eyre!("Could not construct the fixture provider"))?;
+            Ok((provider, format!("fixture://{}", chain_id)))
+        }
+    }
+}
+
+/// Returns a provider for the given chain ID w/ fallbacks over the network
+async fn get_network_provider(chain_id: u64) -> Result<(RootProvider<BoxTransport>, String)> {
     // If env `ENVIRONMENT` is `development`, use the local anvil fork
     let internal_rpc_url = "http://localhost:8545".to_string();
     if std::env::var("ENVIRONMENT").unwrap_or_default() == "development" {
@@ -157,6 +157,89 @@ pub enum GasAndPaymasterAndDataVariant {
     Packed(PackedGasAndPaymasterAndData),
 }
 
+/// The quote of the gas paid in an ERC-20 token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenQuote {
+    /// The token paying the gas.
+    pub token: Address,
+    /// The ERC-20 paymaster to approve.
+    pub paymaster: Address,
+    /// The token amount per ether, in the token decimals.
+    pub exchange_rate: U256,
+    /// The max token amount charged for the user operation.
+    pub amount: U256,
+    /// The timestamp until which the quote is valid.
+    pub valid_until: u64,
+    /// The timestamp after which the quote is valid.
+    pub valid_after: u64,
+}
+
+/// The gas and paymaster and data response, w/ the token quote if paid in an ERC-20 token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasAndPaymasterAndDataResponse {
+    /// The gas and paymaster and data.
+    #[serde(flatten)]
+    pub gas_and_paymaster_and_data: GasAndPaymasterAndDataVariant,
+    /// The token quote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_quote: Option<TokenQuote>,
+}
+
+/// The sponsor info of the ERC-7677 paymaster stub data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterSponsor {
+    /// The name of the sponsor.
+    pub name: String,
+    /// The icon of the sponsor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+/// The ERC-7677 paymaster stub data, w/ `paymasterAndData` for v0.6 and the split fields for v0.7.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterStubData {
+    /// The paymaster (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    /// The paymaster data (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    /// The paymaster verification gas limit (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    /// The paymaster post operation gas limit (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    /// The paymaster and data (v0.6).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_and_data: Option<Bytes>,
+    /// The sponsor info.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sponsor: Option<PaymasterSponsor>,
+    /// Whether the stub data is final, so that `pm_getPaymasterData` is not needed.
+    #[serde(default)]
+    pub is_final: bool,
+}
+
+/// The ERC-7677 paymaster data, w/ `paymasterAndData` for v0.6 and the split fields for v0.7.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterData {
+    /// The paymaster (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster: Option<Address>,
+    /// The paymaster data (v0.7).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_data: Option<Bytes>,
+    /// The paymaster and data (v0.6).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_and_data: Option<Bytes>,
+}
+
 // -----------------------------------------------------------------------------
 // Implementations
 // -----------------------------------------------------------------------------
@@ -183,6 +266,40 @@ impl From<PackedGasAndPaymasterAndData> for GasAndPaymasterAndData {
     }
 }
 
+impl From<GasAndPaymasterAndDataVariant> for PaymasterStubData {
+    fn from(variant: GasAndPaymasterAndDataVariant) -> Self {
+        match variant {
+            GasAndPaymasterAndDataVariant::Default(data) => PaymasterStubData {
+                paymaster_and_data: Some(data.paymaster_and_data),
+                ..Default::default()
+            },
+            GasAndPaymasterAndDataVariant::Packed(data) => PaymasterStubData {
+                paymaster: Some(data.paymaster),
+                paymaster_data: Some(data.paymaster_data),
+                paymaster_verification_gas_limit: Some(data.paymaster_verification_gas_limit),
+                paymaster_post_op_gas_limit: Some(data.paymaster_post_op_gas_limit),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<GasAndPaymasterAndDataVariant> for PaymasterData {
+    fn from(variant: GasAndPaymasterAndDataVariant) -> Self {
+        match variant {
+            GasAndPaymasterAndDataVariant::Default(data) => PaymasterData {
+                paymaster_and_data: Some(data.paymaster_and_data),
+                ..Default::default()
+            },
+            GasAndPaymasterAndDataVariant::Packed(data) => PaymasterData {
+                paymaster: Some(data.paymaster),
+                paymaster_data: Some(data.paymaster_data),
+                ..Default::default()
+            },
+        }
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Structs
 // -----------------------------------------------------------------------------
@@ -0,0 +1,386 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::types::Database;
+use alloy::primitives::Address;
+use autometrics::autometrics;
+use eyre::{eyre, Result};
+use lightdotso_prisma::{wallet_billing, BillingLedgerDirection, BillingLedgerKind, PrismaClient};
+use lightdotso_tracing::tracing::info;
+use prisma_client_rust::{
+    chrono::{DateTime, FixedOffset, Utc},
+    PrismaValue, Raw,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The columns of the entry, w/ the decimals cast to strings to keep them exact
+const BILLING_LEDGER_ENTRY_COLUMNS: &str = "`id`, `timestamp`, `journalId`, `account`, \
+    `sequence`, `direction`, `kind`, CAST(`amount` AS CHAR) AS `amount`, \
+    CAST(`balance` AS CHAR) AS `balance`, `description`, `billingId`, `billingOperationId`";
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// A line of the journal posted to the ledger
+#[derive(Clone, Debug)]
+pub struct BillingLedgerLine {
+    /// The ledger account, either `billing:<id>` or one of the `platform:*` accounts
+    pub account: String,
+    /// The billing of the account, if a billing account
+    pub billing_id: Option<String>,
+    pub direction: BillingLedgerDirection,
+    pub amount: BigDecimal,
+}
+
+/// An entry of the ledger w/ the exact decimal amount and running balance
+#[derive(Clone, Debug)]
+pub struct BillingLedgerEntry {
+    pub id: String,
+    pub timestamp: DateTime<FixedOffset>,
+    pub journal_id: String,
+    pub account: String,
+    /// The per-account sequence, ordering the entries of the account
+    pub sequence: i64,
+    pub direction: BillingLedgerDirection,
+    pub kind: BillingLedgerKind,
+    pub amount: BigDecimal,
+    pub balance: BigDecimal,
+    pub description: Option<String>,
+    pub billing_id: Option<String>,
+    pub billing_operation_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BillingLedgerEntryRow {
+    id: String,
+    timestamp: DateTime<FixedOffset>,
+    journal_id: String,
+    account: String,
+    sequence: i64,
+    direction: BillingLedgerDirection,
+    kind: BillingLedgerKind,
+    amount: String,
+    balance: String,
+    description: Option<String>,
+    billing_id: Option<String>,
+    billing_operation_id: Option<String>,
+}
+
+impl TryFrom<BillingLedgerEntryRow> for BillingLedgerEntry {
+    type Error = eyre::Report;
+
+    fn try_from(row: BillingLedgerEntryRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            timestamp: row.timestamp,
+            journal_id: row.journal_id,
+            account: row.account,
+            sequence: row.sequence,
+            direction: row.direction,
+            kind: row.kind,
+            amount: BigDecimal::from_str(&row.amount)?,
+            balance: BigDecimal::from_str(&row.balance)?,
+            description: row.description,
+            billing_id: row.billing_id,
+            billing_operation_id: row.billing_operation_id,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Create
+// -----------------------------------------------------------------------------
+
+/// Post the balanced lines of the journal, w/ the running balance of each account.
+/// Posting the same journal again returns the entries already posted.
+#[autometrics]
+pub async fn create_billing_ledger_journal(
+    db: Database,
+    journal_id: String,
+    kind: BillingLedgerKind,
+    lines: Vec<BillingLedgerLine>,
+    billing_operation_id: Option<String>,
+    description: Option<String>,
+) -> Result<Vec<BillingLedgerEntry>> {
+    info!("Creating new billing ledger journal");
+
+    if !is_balanced(&lines) {
+        return Err(eyre!("Journal {} is not balanced", journal_id));
+    }
+
+    db._transaction()
+        .run(|client| async move {
+            let posted = get_billing_ledger_journal_with_client(&client, &journal_id).await?;
+            if !posted.is_empty() {
+                info!("Journal {} already posted", journal_id);
+                return Ok(posted);
+            }
+
+            let timestamp: DateTime<FixedOffset> = Utc::now().into();
+            let mut entries = vec![];
+
+            for line in lines {
+                // Lock the latest entry of the account, w/ the unique sequence rejecting any
+                // concurrent posting to the account w/o an entry yet
+                let previous =
+                    get_latest_billing_ledger_entry_with_client(&client, &line.account, None, true)
+                        .await?;
+                let (sequence, previous_balance) = previous
+                    .map(|entry| (entry.sequence + 1, entry.balance))
+                    .unwrap_or((0, BigDecimal::from(0)));
+                let balance = previous_balance + signed_amount(&line.direction, &line.amount);
+
+                let entry = BillingLedgerEntry {
+                    id: Uuid::new_v4().to_string(),
+                    timestamp,
+                    journal_id: journal_id.clone(),
+                    account: line.account.clone(),
+                    sequence,
+                    direction: line.direction,
+                    kind,
+                    amount: line.amount.clone(),
+                    balance,
+                    description: description.clone(),
+                    billing_id: line.billing_id.clone(),
+                    billing_operation_id: billing_operation_id.clone(),
+                };
+
+                client
+                    ._execute_raw(Raw::new(
+                        "INSERT INTO `BillingLedgerEntry` (`id`, `timestamp`, `journalId`, \
+                         `account`, `sequence`, `direction`, `kind`, `amount`, `balance`, \
+                         `description`, `billingId`, `billingOperationId`) \
+                         VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
+                        vec![
+                            PrismaValue::String(entry.id.clone()),
+                            PrismaValue::DateTime(entry.timestamp),
+                            PrismaValue::String(entry.journal_id.clone()),
+                            PrismaValue::String(entry.account.clone()),
+                            PrismaValue::Int(entry.sequence),
+                            PrismaValue::String(enum_value(&entry.direction)?),
+                            PrismaValue::String(enum_value(&entry.kind)?),
+                            PrismaValue::String(entry.amount.to_string()),
+                            PrismaValue::String(entry.balance.to_string()),
+                            optional_value(entry.description.clone()),
+                            optional_value(entry.billing_id.clone()),
+                            optional_value(entry.billing_operation_id.clone()),
+                        ],
+                    ))
+                    .exec()
+                    .await?;
+                info!(?entry);
+
+                // Keep the cached balance of the billing in sync w/ the ledger, w/ the exact amount
+                if let Some(billing_id) = &entry.billing_id {
+                    client
+                        ._execute_raw(Raw::new(
+                            "UPDATE `Billing` SET `balanceUSD` = {}, `updatedAt` = {} \
+                             WHERE `id` = {}",
+                            vec![
+                                PrismaValue::String(entry.balance.to_string()),
+                                PrismaValue::DateTime(entry.timestamp),
+                                PrismaValue::String(billing_id.clone()),
+                            ],
+                        ))
+                        .exec()
+                        .await?;
+                }
+
+                entries.push(entry);
+            }
+
+            Ok(entries)
+        })
+        .await
+}
+
+// -----------------------------------------------------------------------------
+// Get
+// -----------------------------------------------------------------------------
+
+/// Get the running balance of the account, before the timestamp if set
+#[autometrics]
+pub async fn get_billing_ledger_account_balance(
+    db: Database,
+    account: String,
+    before: Option<DateTime<FixedOffset>>,
+) -> Result<BigDecimal> {
+    let entry = get_latest_billing_ledger_entry_with_client(&db, &account, before, false).await?;
+
+    Ok(entry.map(|entry| entry.balance).unwrap_or(BigDecimal::from(0)))
+}
+
+/// Get the running balance of the billing account of the wallet, if the wallet has a billing
+#[autometrics]
+pub async fn get_billing_ledger_balance_with_wallet(
+    db: Database,
+    wallet_address: Address,
+) -> Result<Option<BigDecimal>> {
+    let wallet_billing = db
+        .wallet_billing()
+        .find_unique(wallet_billing::wallet_address::equals(wallet_address.to_checksum(None)))
+        .exec()
+        .await?;
+
+    let Some(wallet_billing) = wallet_billing else {
+        return Ok(None);
+    };
+
+    let balance = get_billing_ledger_account_balance(
+        db,
+        billing_ledger_account(&wallet_billing.billing_id),
+        None,
+    )
+    .await?;
+
+    Ok(Some(balance))
+}
+
+/// Whether the billing of the wallet holds at least the amount in its ledger balance
+#[autometrics]
+pub async fn has_billing_ledger_balance_with_wallet(
+    db: Database,
+    wallet_address: Address,
+    amount_usd: f64,
+) -> Result<bool> {
+    let amount = BigDecimal::from_str(&amount_usd.to_string())?;
+    let balance = get_billing_ledger_balance_with_wallet(db, wallet_address).await?;
+
+    Ok(balance.is_some_and(|balance| balance >= amount))
+}
+
+/// Get the entries of the account between the timestamps, in the order of posting
+#[autometrics]
+pub async fn get_billing_ledger_entries(
+    db: Database,
+    account: String,
+    from: DateTime<FixedOffset>,
+    to: DateTime<FixedOffset>,
+) -> Result<Vec<BillingLedgerEntry>> {
+    let rows: Vec<BillingLedgerEntryRow> = db
+        ._query_raw(Raw::new(
+            &format!(
+                "SELECT {BILLING_LEDGER_ENTRY_COLUMNS} FROM `BillingLedgerEntry` \
+                 WHERE `account` = {{}} AND `timestamp` >= {{}} AND `timestamp` < {{}} \
+                 ORDER BY `sequence` ASC"
+            ),
+            vec![
+                PrismaValue::String(account),
+                PrismaValue::DateTime(from),
+                PrismaValue::DateTime(to),
+            ],
+        ))
+        .exec()
+        .await?;
+
+    rows.into_iter().map(BillingLedgerEntry::try_from).collect()
+}
+
+async fn get_billing_ledger_journal_with_client(
+    client: &PrismaClient,
+    journal_id: &str,
+) -> Result<Vec<BillingLedgerEntry>> {
+    let rows: Vec<BillingLedgerEntryRow> = client
+        ._query_raw(Raw::new(
+            &format!(
+                "SELECT {BILLING_LEDGER_ENTRY_COLUMNS} FROM `BillingLedgerEntry` \
+                 WHERE `journalId` = {{}}"
+            ),
+            vec![PrismaValue::String(journal_id.to_string())],
+        ))
+        .exec()
+        .await?;
+
+    rows.into_iter().map(BillingLedgerEntry::try_from).collect()
+}
+
+async fn get_latest_billing_ledger_entry_with_client(
+    client: &PrismaClient,
+    account: &str,
+    before: Option<DateTime<FixedOffset>>,
+    for_update: bool,
+) -> Result<Option<BillingLedgerEntry>> {
+    let mut params = vec![PrismaValue::String(account.to_string())];
+    let before_clause = match before {
+        Some(before) => {
+            params.push(PrismaValue::DateTime(before));
+            "AND `timestamp` < {}"
+        }
+        None => "",
+    };
+    let lock_clause = if for_update { "FOR UPDATE" } else { "" };
+
+    let rows: Vec<BillingLedgerEntryRow> = client
+        ._query_raw(Raw::new(
+            &format!(
+                "SELECT {BILLING_LEDGER_ENTRY_COLUMNS} FROM `BillingLedgerEntry` \
+                 WHERE `account` = {{}} {before_clause} \
+                 ORDER BY `sequence` DESC LIMIT 1 {lock_clause}"
+            ),
+            params,
+        ))
+        .exec()
+        .await?;
+
+    rows.into_iter().next().map(BillingLedgerEntry::try_from).transpose()
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// The ledger account of the billing
+pub fn billing_ledger_account(billing_id: &str) -> String {
+    format!("billing:{}", billing_id)
+}
+
+/// The amount signed w/ the direction, as the balances are credit-normal
+pub fn signed_amount(direction: &BillingLedgerDirection, amount: &BigDecimal) -> BigDecimal {
+    match direction {
+        BillingLedgerDirection::Credit => amount.clone(),
+        BillingLedgerDirection::Debit => -amount.clone(),
+    }
+}
+
+/// The database value of the enum
+fn enum_value<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_value(value)?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| eyre!("Enum is not a string"))
+}
+
+fn optional_value(value: Option<String>) -> PrismaValue {
+    value.map(PrismaValue::String).unwrap_or(PrismaValue::Null)
+}
+
+/// Whether the debits and the credits of the lines are equal, w/ positive amounts
+pub fn is_balanced(lines: &[BillingLedgerLine]) -> bool {
+    let zero = BigDecimal::from(0);
+    if lines.is_empty() || lines.iter().any(|line| line.amount <= zero) {
+        return false;
+    }
+
+    lines.iter().map(|line| signed_amount(&line.direction, &line.amount)).sum::<BigDecimal>() ==
+        zero
+}
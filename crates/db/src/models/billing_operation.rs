@@ -15,11 +15,12 @@
 #![allow(clippy::unwrap_used)]
 
 use crate::types::Database;
-use alloy::primitives::Address;
+use alloy::primitives::{Address, B256};
 use autometrics::autometrics;
 use eyre::{eyre, Result};
 use lightdotso_prisma::{
-    billing, paymaster_operation, wallet, wallet_billing, BillingOperationStatus,
+    billing, billing_operation, paymaster_operation, user_operation, wallet, wallet_billing,
+    BillingOperationStatus,
 };
 use lightdotso_tracing::tracing::info;
 
@@ -69,3 +70,80 @@ pub async fn create_billing_operation(
 
     Ok(())
 }
+
+// -----------------------------------------------------------------------------
+// Update
+// -----------------------------------------------------------------------------
+
+/// The outcome of the reconciliation of a billing operation
+#[derive(Debug)]
+pub enum BillingOperationReconciliation {
+    /// The billing operation was reconciled w/ the actual cost
+    Reconciled(Box<billing_operation::Data>),
+    /// The user operation or its billing operation is not indexed yet
+    NotIndexed,
+    /// The user operation was not sponsored by our paymaster, or is already reconciled
+    Skipped,
+}
+
+/// Reconcile the billing operation of the sponsored user operation w/ the actual cost
+#[autometrics]
+pub async fn reconcile_billing_operation(
+    db: Database,
+    user_operation_hash: B256,
+    balance_usd: f64,
+) -> Result<BillingOperationReconciliation> {
+    info!("Reconciling billing operation");
+
+    let user_operation = db
+        .user_operation()
+        .find_unique(user_operation::hash::equals(format!("{:?}", user_operation_hash)))
+        .exec()
+        .await?;
+    info!(?user_operation);
+
+    // Retry later if the user operation is not indexed yet
+    let Some(user_operation) = user_operation else {
+        return Ok(BillingOperationReconciliation::NotIndexed);
+    };
+
+    // Skip if the user operation was not sponsored by our paymaster
+    let Some(paymaster_operation_id) = user_operation.paymaster_operation_id else {
+        return Ok(BillingOperationReconciliation::Skipped);
+    };
+
+    let billing_operation = db
+        .billing_operation()
+        .find_unique(billing_operation::paymaster_operation_id::equals(
+            paymaster_operation_id.clone(),
+        ))
+        .exec()
+        .await?;
+
+    // Retry later if the billing operation is not created yet, and skip if already reconciled
+    match billing_operation {
+        None => return Ok(BillingOperationReconciliation::NotIndexed),
+        Some(op) if op.status != BillingOperationStatus::Pending => {
+            return Ok(BillingOperationReconciliation::Skipped)
+        }
+        Some(_) => {}
+    }
+
+    let billing_operation =
+        db.billing_operation()
+            .update(
+                billing_operation::paymaster_operation_id::equals(paymaster_operation_id),
+                vec![
+                    billing_operation::balance_usd::set(balance_usd),
+                    billing_operation::status::set(BillingOperationStatus::Sponsored),
+                    billing_operation::user_operation::connect(user_operation::hash::equals(
+                        format!("{:?}", user_operation_hash),
+                    )),
+                ],
+            )
+            .exec()
+            .await?;
+    info!(?billing_operation);
+
+    Ok(BillingOperationReconciliation::Reconciled(Box::new(billing_operation)))
+}
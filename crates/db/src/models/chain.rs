@@ -0,0 +1,79 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::types::{AppResult, Database};
+use autometrics::autometrics;
+use lightdotso_constants::registry::{
+    set_chain_registry_overlay, upsert_chain_registry_overlay, ChainConfig, ChainNetwork,
+};
+use lightdotso_prisma::chain;
+use lightdotso_tracing::tracing::{error, info, warn};
+use std::time::Duration;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// Converts the `Chain` row into a registry overlay.
+/// The optional `config` column is merged on top of the name and network.
+pub fn chain_to_chain_config(chain: &chain::Data) -> ChainConfig {
+    let mut chain_config = ChainConfig {
+        id: chain.id as u64,
+        name: chain.name.clone().unwrap_or_default(),
+        network: if chain.is_testnet { ChainNetwork::Testnet } else { ChainNetwork::Mainnet },
+        ..Default::default()
+    };
+
+    if let Some(config) = chain.config.clone() {
+        match serde_json::from_value::<ChainConfig>(config) {
+            Ok(overlay) => chain_config.merge(ChainConfig { id: chain_config.id, ..overlay }),
+            Err(err) => warn!("Invalid config for chain {}: {:?}", chain.id, err),
+        }
+    }
+
+    chain_config
+}
+
+// -----------------------------------------------------------------------------
+// Sync
+// -----------------------------------------------------------------------------
+
+/// Loads all of the chains from the `Chain` table as the chain registry overlay.
+#[autometrics]
+pub async fn sync_chain_registry_overlay(db: Database) -> AppResult<usize> {
+    let chains = db.chain().find_many(vec![]).exec().await?;
+    info!("Syncing chain registry overlay w/ {} chains", chains.len());
+
+    let count = chains.len();
+    set_chain_registry_overlay(chains.iter().map(chain_to_chain_config).collect());
+
+    Ok(count)
+}
+
+/// Applies a single `Chain` row to the chain registry overlay.
+pub fn apply_chain_registry_overlay(chain: &chain::Data) {
+    upsert_chain_registry_overlay(chain_to_chain_config(chain));
+}
+
+/// Spawns a task to periodically resync the chain registry overlay from the `Chain` table.
+pub fn spawn_chain_registry_sync(db: Database, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = sync_chain_registry_overlay(db.clone()).await {
+                error!("Failed to sync chain registry overlay: {:?}", err);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
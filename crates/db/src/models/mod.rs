@@ -13,7 +13,9 @@
 // limitations under the License.
 
 pub mod activity;
+pub mod billing_ledger;
 pub mod billing_operation;
+pub mod chain;
 pub mod interpretation;
 pub mod log;
 pub mod paymaster_operation;
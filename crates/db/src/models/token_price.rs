@@ -14,11 +14,12 @@
 
 use autometrics::autometrics;
 use eyre::Result;
+use lightdotso_prisma_postgres::{token_price, PrismaClient as PrismaPostgresClient};
 use lightdotso_sqlx::{
     sqlx::{query_as, Error as SqlxError, FromRow},
     PostgresPool,
 };
-use prisma_client_rust::chrono::{DateTime, Utc};
+use prisma_client_rust::chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::types::BigDecimal;
 
@@ -36,10 +37,58 @@ pub struct TokenPriceAggregate {
     pub date: DateTime<Utc>,
 }
 
+// -----------------------------------------------------------------------------
+// Create
+// -----------------------------------------------------------------------------
+
+/// Create the token price at the timestamp, skipping if already recorded
+#[autometrics]
+pub async fn create_token_price(
+    db: &PrismaPostgresClient,
+    token_id: String,
+    price: f64,
+    timestamp: DateTime<Utc>,
+) -> Result<()> {
+    db.token_price()
+        .create_many(vec![(
+            price,
+            token_id,
+            vec![token_price::timestamp::set(timestamp.fixed_offset())],
+        )])
+        .skip_duplicates()
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
 // -----------------------------------------------------------------------------
 // Get
 // -----------------------------------------------------------------------------
 
+/// Get the recorded token price nearest to the timestamp, within the tolerance
+#[autometrics]
+pub async fn get_token_price_at(
+    pool: &PostgresPool,
+    token_id: String,
+    timestamp: DateTime<Utc>,
+    tolerance: Duration,
+) -> Result<Option<TokenPriceAggregate>, SqlxError> {
+    query_as::<_, TokenPriceAggregate>(
+        r#"SELECT price, timestamp as date
+           FROM "TokenPrice"
+           WHERE "tokenId" = $1 AND timestamp BETWEEN $2 AND $3
+           ORDER BY ABS(EXTRACT(EPOCH FROM (timestamp - $4))) ASC
+           LIMIT 1"#,
+    )
+    .bind(token_id)
+    .bind(timestamp - tolerance)
+    .bind(timestamp + tolerance)
+    .bind(timestamp)
+    .fetch_optional(pool)
+    .await
+}
+
 #[autometrics]
 pub async fn get_token_prices(
     pool: &PostgresPool,
@@ -36,6 +36,7 @@ impl From<ApiResponseData> for GasEstimationParams {
             GasEstimationParams {
                 max_priority_fee_per_gas: U256::from(value),
                 max_fee_per_gas: U256::from(value),
+                ..Default::default()
             }
         };
 
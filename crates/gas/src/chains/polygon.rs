@@ -45,6 +45,7 @@ impl From<ApiResponse> for GasEstimationParams {
             GasEstimationParams {
                 max_priority_fee_per_gas: from_gwei_f64(gas_data.max_priority_fee),
                 max_fee_per_gas: from_gwei_f64(gas_data.max_fee),
+                ..Default::default()
             }
         };
 
@@ -12,20 +12,107 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{gas::GasApi, gas_api::GasApiServer};
+use crate::{
+    gas::GasApi,
+    gas_api::GasApiServer,
+    sampler::{spawn_gas_sampler, GasState},
+};
 use clap::Parser;
 use eyre::{eyre, Result};
+use lightdotso_constants::registry::{get_chain_registry, ChainNetwork};
 use lightdotso_jsonrpsee::rpc::{JsonRpcServer, JsonRpcServerType};
+use lightdotso_redis::{get_redis_client, redis::Client};
 use lightdotso_tracing::tracing::info;
 use std::{
+    collections::HashMap,
     future::pending,
     net::{IpAddr, Ipv6Addr},
+    sync::Arc,
 };
 
 #[derive(Clone, Debug, Parser)]
-pub struct GasArgs {}
+pub struct GasArgs {
+    /// The number of blocks of fee history to derive the tiers from
+    #[arg(long, default_value_t = 20)]
+    #[clap(long, env = "GAS_FEE_HISTORY_BLOCKS")]
+    pub fee_history_blocks: u64,
+    /// The reward percentiles of the low, medium, high and instant tiers
+    #[arg(long, value_delimiter = ',', default_values_t = vec![10.0, 30.0, 60.0, 90.0])]
+    #[clap(long, env = "GAS_REWARD_PERCENTILES")]
+    pub reward_percentiles: Vec<f64>,
+    /// The number of blocks the base fee is projected ahead
+    #[arg(long, default_value_t = 3)]
+    #[clap(long, env = "GAS_BASE_FEE_PROJECTION_BLOCKS")]
+    pub base_fee_projection_blocks: u64,
+    /// The multipliers in percent of the chains priced from `eth_gasPrice` w/ chain_id
+    /// Example: 42220=150,42161=125
+    // Celo (3/2): https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L73-L79
+    // Arbitrum (5/4): https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L81
+    // License: GPL-3.0
+    #[arg(long, value_delimiter = ',', default_values_t = vec!["42220=150".to_string(), "42161=125".to_string()])]
+    #[clap(long, env = "GAS_PRICE_MULTIPLIERS")]
+    pub gas_price_multipliers: Vec<String>,
+    /// The multipliers in percent of the max fee per gas w/ chain_id
+    /// Example: 53935=200
+    // DFK (2): https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L107-L109
+    // License: GPL-3.0
+    #[arg(long, value_delimiter = ',', default_values_t = vec!["53935=200".to_string()])]
+    #[clap(long, env = "GAS_MAX_FEE_MULTIPLIERS")]
+    pub max_fee_multipliers: Vec<String>,
+    /// Whether to sample the gas estimations in the background
+    #[arg(long, default_value_t = true)]
+    #[clap(long, env = "GAS_SAMPLER_ENABLED")]
+    pub sampler_enabled: bool,
+    /// The chain ids to sample, defaulting to all of the mainnets
+    #[arg(long, value_delimiter = ',')]
+    #[clap(long, env = "GAS_SAMPLER_CHAIN_IDS")]
+    pub sampler_chain_ids: Vec<u64>,
+}
+
+/// The configuration of the gas estimation
+#[derive(Clone, Debug)]
+pub struct GasConfig {
+    pub fee_history_blocks: u64,
+    pub reward_percentiles: Vec<f64>,
+    pub base_fee_projection_blocks: u64,
+    pub gas_price_multipliers: HashMap<u64, u64>,
+    pub max_fee_multipliers: HashMap<u64, u64>,
+}
+
+/// Parses the multipliers w/ chain_id in the format of `chain_id=percent`
+pub fn parse_chain_multipliers(values: &[String]) -> Result<HashMap<u64, u64>> {
+    values
+        .iter()
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| {
+            let (chain_id, percent) = value
+                .trim()
+                .split_once('=')
+                .ok_or_else(|| eyre!("Invalid multiplier: {}", value))?;
+            Ok((chain_id.parse()?, percent.parse()?))
+        })
+        .collect()
+}
 
 impl GasArgs {
+    /// Get the gas config from the args
+    pub fn config(&self) -> Result<GasConfig> {
+        if self.reward_percentiles.len() != 4 {
+            return Err(eyre!(
+                "Expected 4 reward percentiles, got {}",
+                self.reward_percentiles.len()
+            ));
+        }
+
+        Ok(GasConfig {
+            fee_history_blocks: self.fee_history_blocks,
+            reward_percentiles: self.reward_percentiles.clone(),
+            base_fee_projection_blocks: self.base_fee_projection_blocks,
+            gas_price_multipliers: parse_chain_multipliers(&self.gas_price_multipliers)?,
+            max_fee_multipliers: parse_chain_multipliers(&self.max_fee_multipliers)?,
+        })
+    }
+
     pub async fn run(self) -> Result<()> {
         // Add info
         info!("GasArgs run, starting...");
@@ -33,6 +120,30 @@ impl GasArgs {
         // Print the config
         info!("Config: {:?}", self);
 
+        // Get the gas config
+        let config = self.config()?;
+
+        // Create the shared state w/ the redis client for the history
+        let redis_client: Option<Arc<Client>> =
+            get_redis_client().map_or_else(|_e| None, |client| Some(Arc::new(client)));
+        let api = GasApi { config, state: Arc::new(GasState::new(redis_client)) };
+
+        // Spawn the samplers of the chains
+        if self.sampler_enabled {
+            let chain_ids = if self.sampler_chain_ids.is_empty() {
+                let mut chain_ids: Vec<u64> =
+                    get_chain_registry().chain_ids(ChainNetwork::Mainnet).into_keys().collect();
+                chain_ids.sort();
+                chain_ids
+            } else {
+                self.sampler_chain_ids.clone()
+            };
+
+            for chain_id in chain_ids {
+                spawn_gas_sampler(api.clone(), chain_id);
+            }
+        }
+
         tokio::spawn({
             async move {
                 // Create the server
@@ -45,12 +156,12 @@ impl GasArgs {
                     3001,
                 );
 
-                // Add the gas server
-                server.add_methods(GasApi {}.into_rpc(), JsonRpcServerType::Http)?;
+                // Add the gas server w/ the subscriptions over ws
+                server.add_methods(api.into_rpc(), JsonRpcServerType::Both)?;
 
                 // Start the server
                 let _handle = server.start().await.map_err(|e| eyre!("Error in handle: {:?}", e));
-                info!("Started gas JSON-RPC server at [::]:3000");
+                info!("Started gas JSON-RPC server at [::]:3000 and [::]:3001");
 
                 pending::<Result<()>>().await
             }
@@ -0,0 +1,180 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::types::{GasEstimation, GasEstimationParams};
+use alloy::{primitives::U256, rpc::types::FeeHistory};
+
+/// The denominator of the maximum base fee change per block in EIP-1559 (12.5%)
+/// From: https://eips.ethereum.org/EIPS/eip-1559
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+
+/// Projects the base fee `blocks` ahead, assuming every block is full
+/// The result is the upper bound of the base fee allowed by EIP-1559
+pub fn project_base_fee(next_base_fee: u128, blocks: u64) -> u128 {
+    let mut base_fee = next_base_fee;
+    for _ in 0..blocks {
+        base_fee = base_fee.saturating_add(base_fee / BASE_FEE_MAX_CHANGE_DENOMINATOR);
+    }
+    base_fee
+}
+
+/// Get the median of the values, rounding down
+fn median(values: &mut [u128]) -> u128 {
+    if values.is_empty() {
+        return 0;
+    }
+
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2
+    } else {
+        values[mid]
+    }
+}
+
+/// Get the confidence of the inclusion in the next block, as the average over the blocks of the
+/// highest requested reward percentile the priority fee meets or beats
+/// The reward at percentile `p` of a block is the fee that `p`% of the block's gas paid at most
+fn inclusion_confidence(
+    rewards: &[Vec<u128>],
+    reward_percentiles: &[f64],
+    priority_fee: u128,
+) -> f64 {
+    if rewards.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = rewards
+        .iter()
+        .map(|block| {
+            block
+                .iter()
+                .zip(reward_percentiles)
+                .filter(|(reward, _)| **reward <= priority_fee)
+                .map(|(_, percentile)| *percentile)
+                .fold(0.0, f64::max)
+        })
+        .sum();
+
+    (total / rewards.len() as f64 / 100.0).clamp(0.0, 1.0)
+}
+
+/// Create a gas estimation w/ a tier per reward percentile of the fee history
+/// The percentiles of the fee history are expected to be the low, medium, high and instant tiers
+/// Returns `None` if the fee history does not contain the rewards of all four tiers
+pub fn create_fee_history_estimation(
+    fee_history: &FeeHistory,
+    reward_percentiles: &[f64],
+    projection_blocks: u64,
+    block_seconds: u64,
+) -> Option<GasEstimation> {
+    let rewards = fee_history.reward.as_ref()?;
+    if rewards.is_empty() ||
+        reward_percentiles.len() < 4 ||
+        rewards.iter().any(|block| block.len() < 4)
+    {
+        return None;
+    }
+
+    // The last base fee is the base fee of the next block
+    let next_base_fee = *fee_history.base_fee_per_gas.last()?;
+    let base_fee = project_base_fee(next_base_fee, projection_blocks);
+
+    let mut tiers: Vec<GasEstimationParams> = Vec::with_capacity(4);
+    let mut previous_priority_fee = 0;
+
+    for tier in 0..4 {
+        // Take the median across the window so that a single outlier block does not skew the tier
+        let mut tier_rewards: Vec<u128> = rewards.iter().map(|block| block[tier]).collect();
+        let priority_fee = median(&mut tier_rewards).max(previous_priority_fee);
+        previous_priority_fee = priority_fee;
+
+        // Expect a geometric number of blocks until inclusion
+        let confidence = inclusion_confidence(rewards, reward_percentiles, priority_fee);
+        let inclusion_blocks = if confidence > 0.0 { (1.0 / confidence).ceil() as u64 } else { 0 };
+
+        tiers.push(GasEstimationParams {
+            max_priority_fee_per_gas: U256::from(priority_fee),
+            max_fee_per_gas: U256::from(base_fee.saturating_add(priority_fee)),
+            confidence: Some(confidence),
+            inclusion_seconds: (inclusion_blocks > 0).then_some(inclusion_blocks * block_seconds),
+        });
+    }
+
+    let instant = tiers.pop()?;
+    let high = tiers.pop()?;
+    let medium = tiers.pop()?;
+    let low = tiers.pop()?;
+
+    Some(GasEstimation { low, medium, high, instant, l1_fee: None })
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_base_fee() {
+        assert_eq!(project_base_fee(800, 0), 800);
+        assert_eq!(project_base_fee(800, 1), 900);
+        assert_eq!(project_base_fee(800, 2), 1012);
+    }
+
+    #[test]
+    fn test_create_fee_history_estimation() {
+        let fee_history = FeeHistory {
+            base_fee_per_gas: vec![100, 100, 100, 800],
+            gas_used_ratio: vec![0.5, 0.5, 0.5],
+            reward: Some(vec![vec![1, 2, 3, 4], vec![1, 2, 3, 40], vec![1, 2, 3, 4]]),
+            ..Default::default()
+        };
+
+        let estimation =
+            create_fee_history_estimation(&fee_history, &[10.0, 30.0, 60.0, 90.0], 1, 12).unwrap();
+
+        assert_eq!(estimation.low.max_priority_fee_per_gas, U256::from(1));
+        assert_eq!(estimation.low.confidence, Some(0.1));
+        assert_eq!(estimation.low.inclusion_seconds, Some(120));
+        assert_eq!(estimation.low.max_fee_per_gas, U256::from(901));
+        // The outlier of the second block is ignored by the median
+        assert_eq!(estimation.instant.max_priority_fee_per_gas, U256::from(4));
+        assert!(estimation.low.confidence < estimation.instant.confidence);
+        assert!(estimation.low.inclusion_seconds >= estimation.instant.inclusion_seconds);
+    }
+
+    #[test]
+    fn test_create_fee_history_estimation_without_rewards() {
+        let fee_history = FeeHistory { base_fee_per_gas: vec![100], ..Default::default() };
+        assert!(
+            create_fee_history_estimation(&fee_history, &[10.0, 30.0, 60.0, 90.0], 1, 12).is_none()
+        );
+    }
+
+    #[test]
+    fn test_inclusion_confidence() {
+        let rewards = vec![vec![1, 2, 3, 4], vec![1, 2, 3, 40]];
+        let percentiles = [10.0, 30.0, 60.0, 90.0];
+
+        assert_eq!(inclusion_confidence(&rewards, &percentiles, 0), 0.0);
+        assert_eq!(inclusion_confidence(&rewards, &percentiles, 2), 0.3);
+        // The outlier block only meets the 60th percentile
+        assert_eq!(inclusion_confidence(&rewards, &percentiles, 4), 0.75);
+        assert_eq!(inclusion_confidence(&[], &percentiles, 4), 0.0);
+    }
+}
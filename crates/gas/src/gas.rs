@@ -16,19 +16,88 @@
 
 use crate::{
     chains::polygon::polygon_gas_estimation,
-    types::{GasEstimation, GasEstimationParams},
+    config::GasConfig,
+    fee_history::create_fee_history_estimation,
+    sampler::{GasSample, GasState},
+    types::{GasEstimation, GasEstimationParams, L1FeeParams},
 };
 use alloy::{eips::BlockNumberOrTag, primitives::U256, providers::Provider};
+use eyre::Result;
 use jsonrpsee::core::RpcResult;
-use lightdotso_contracts::provider::get_provider;
+use lightdotso_constants::registry::get_chain_registry;
+use lightdotso_contracts::{
+    l1_fee::{
+        get_l1_fee, get_packed_user_operation_l1_fee, get_user_operation_l1_fee, sample_calldata,
+        L1FeeOracle,
+    },
+    provider::get_provider,
+};
 use lightdotso_jsonrpsee::error::JsonRpcError;
-use lightdotso_tracing::tracing::info;
-use std::ops::{Div, Mul};
+use lightdotso_tracing::tracing::{info, warn};
+use std::{
+    ops::{Div, Mul},
+    sync::Arc,
+    time::Duration,
+};
+
+/// The calldata size of a typical bundle of a single user operation
+const DEFAULT_L1_FEE_CALLDATA_SIZE: u64 = 1024;
 
-pub(crate) struct GasApi {}
+/// The maximum window of the gas history in seconds (7 days)
+const MAX_GAS_HISTORY_WINDOW: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone)]
+pub(crate) struct GasApi {
+    pub(crate) config: GasConfig,
+    pub(crate) state: Arc<GasState>,
+}
 
 impl GasApi {
-    pub(crate) async fn request_gas_estimation(&self, chain_id: u64) -> RpcResult<GasEstimation> {
+    pub(crate) async fn request_gas_estimation(
+        &self,
+        chain_id: u64,
+        l1_fee_params: Option<L1FeeParams>,
+    ) -> RpcResult<GasEstimation> {
+        // Serve the estimation from the sampler if it is recent enough
+        if l1_fee_params.is_none() {
+            let max_age = Duration::from_secs(get_chain_registry().block_seconds(chain_id) * 2);
+            if let Some(estimation) = self.state.latest_estimation(chain_id, max_age) {
+                return Ok(estimation);
+            }
+        }
+
+        self.estimate_gas(chain_id, l1_fee_params).await
+    }
+
+    pub(crate) async fn get_history(
+        &self,
+        chain_id: u64,
+        window: u64,
+    ) -> RpcResult<Vec<GasSample>> {
+        Ok(self
+            .state
+            .history(chain_id, window.min(MAX_GAS_HISTORY_WINDOW))
+            .map_err(JsonRpcError::from)?)
+    }
+
+    /// Estimates the gas from the network, bypassing the sampled estimations
+    pub(crate) async fn estimate_gas(
+        &self,
+        chain_id: u64,
+        l1_fee_params: Option<L1FeeParams>,
+    ) -> RpcResult<GasEstimation> {
+        let mut estimation = self.request_execution_gas_estimation(chain_id).await?;
+
+        // Add the L1 data fee on rollups, w/o failing the estimation if the oracle is unavailable
+        match get_estimation_l1_fee(chain_id, l1_fee_params.unwrap_or_default()).await {
+            Ok(l1_fee) => estimation.l1_fee = l1_fee,
+            Err(err) => warn!("Failed to get the L1 fee for chain {}: {:?}", chain_id, err),
+        }
+
+        Ok(estimation)
+    }
+
+    async fn request_execution_gas_estimation(&self, chain_id: u64) -> RpcResult<GasEstimation> {
         // Get the estimation from pre-configured APIs
         let estimation = get_estimation(chain_id).await;
 
@@ -42,73 +111,63 @@ impl GasApi {
         let (client, _) = get_provider(chain_id).await.map_err(JsonRpcError::from)?;
 
         // Get the gas price from the client
-        let mut gas_price = client.get_gas_price().await.map_err(JsonRpcError::from)?;
+        let gas_price = client.get_gas_price().await.map_err(JsonRpcError::from)?;
         info!("Gas price for chain {} is {:?}", chain_id, gas_price);
 
-        // For Celo, we need to multiply the gas price by 3/2
-        // https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L73-L79
-        // License: GPL-3.0
-        if chain_id == 42220 {
-            let gas_price = gas_price * 3 / 2;
-            let params = GasEstimationParams {
-                max_fee_per_gas: U256::from(gas_price),
-                max_priority_fee_per_gas: U256::from(gas_price),
-            };
-            return Ok(create_gas_estimation(&params));
-        }
-
-        // For Arbitrum, we need to multiply the gas price by 5/4
-        // From: https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L81
-        // License: GPL-3.0
-        if chain_id == 42161 {
-            let gas_price = gas_price * 5 / 4;
+        // For the chains priced from `eth_gasPrice`, multiply the gas price by the configured
+        // percent
+        if let Some(multiplier) = self.config.gas_price_multipliers.get(&chain_id) {
+            let gas_price = gas_price * *multiplier as u128 / 100;
             let params = GasEstimationParams {
                 max_fee_per_gas: U256::from(gas_price),
                 max_priority_fee_per_gas: U256::from(gas_price),
+                ..Default::default()
             };
             return Ok(create_gas_estimation(&params));
         }
 
-        // Get the fee history
+        // Get the fee history w/ the reward percentiles of the tiers
         let fee_history = client
-            .get_fee_history(10, BlockNumberOrTag::Latest, &[20.0])
+            .get_fee_history(
+                self.config.fee_history_blocks,
+                BlockNumberOrTag::Latest,
+                &self.config.reward_percentiles,
+            )
             .await
             .map_err(JsonRpcError::from)?;
 
-        if fee_history.reward.is_none() {
-            let gas_price = gas_price * 3 / 2;
-            // Use the gas price to create the params
-            let params = GasEstimationParams {
-                max_fee_per_gas: U256::from(gas_price),
-                max_priority_fee_per_gas: U256::from(gas_price),
-            };
-            return Ok(create_gas_estimation(&params));
+        let block_seconds = get_chain_registry().block_seconds(chain_id);
+        let mut estimation = match create_fee_history_estimation(
+            &fee_history,
+            &self.config.reward_percentiles,
+            self.config.base_fee_projection_blocks,
+            block_seconds,
+        ) {
+            Some(estimation) => estimation,
+            None => {
+                // Fallback to the gas price if the fee history has no rewards
+                let gas_price = gas_price * 3 / 2;
+                let params = GasEstimationParams {
+                    max_fee_per_gas: U256::from(gas_price),
+                    max_priority_fee_per_gas: U256::from(gas_price),
+                    ..Default::default()
+                };
+                create_gas_estimation(&params)
+            }
         };
-        // Get the average gas price
-        let original_fee_average =
-            fee_history.reward.as_ref().unwrap().iter().flatten().sum::<u128>() / 10;
-        let fee_average = U256::from(original_fee_average);
-
-        // Compare the average gas price with the current gas price
-        if fee_average > U256::from(gas_price) {
-            gas_price = original_fee_average;
-        }
-        let max_priority_fee_per_gas = gas_price;
 
-        // If chain is DFK, multiply the gas price by 2
-        // From: https://github.com/pimlicolabs/alto/blob/58bcc4e75a214f9074c7d4c73626960527fa43ce/packages/utils/src/gasPrice.ts#L107-L109
-        // License: GPL-3.0
-        if chain_id == 53935 {
-            gas_price *= 2;
-        }
+        let max_fee_multiplier = self.config.max_fee_multipliers.get(&chain_id).copied();
+        for tier in estimation.tiers_mut() {
+            // The max fee should never be below the current gas price
+            tier.max_fee_per_gas = tier.max_fee_per_gas.max(U256::from(gas_price));
 
-        // Use the gas price to create the params
-        let params = GasEstimationParams {
-            max_fee_per_gas: U256::from(gas_price),
-            max_priority_fee_per_gas: U256::from(max_priority_fee_per_gas),
-        };
+            if let Some(multiplier) = max_fee_multiplier {
+                tier.max_fee_per_gas =
+                    tier.max_fee_per_gas.mul(U256::from(multiplier)).div(U256::from(100));
+            }
+        }
 
-        Ok(create_gas_estimation(&params))
+        Ok(estimation)
     }
 }
 
@@ -122,6 +181,7 @@ fn create_gas_estimation(gas_price: &GasEstimationParams) -> GasEstimation {
             .max_priority_fee_per_gas
             .mul(U256::from(110))
             .div(U256::from(100)),
+        ..Default::default()
     };
 
     let medium_params = GasEstimationParams {
@@ -130,6 +190,7 @@ fn create_gas_estimation(gas_price: &GasEstimationParams) -> GasEstimation {
             .max_priority_fee_per_gas
             .mul(U256::from(115))
             .div(U256::from(100)),
+        ..Default::default()
     };
 
     let high_params = GasEstimationParams {
@@ -138,6 +199,7 @@ fn create_gas_estimation(gas_price: &GasEstimationParams) -> GasEstimation {
             .max_priority_fee_per_gas
             .mul(U256::from(120))
             .div(U256::from(100)),
+        ..Default::default()
     };
 
     let instant_params = GasEstimationParams {
@@ -146,6 +208,7 @@ fn create_gas_estimation(gas_price: &GasEstimationParams) -> GasEstimation {
             .max_priority_fee_per_gas
             .mul(U256::from(125))
             .div(U256::from(100)),
+        ..Default::default()
     };
 
     GasEstimation {
@@ -153,7 +216,28 @@ fn create_gas_estimation(gas_price: &GasEstimationParams) -> GasEstimation {
         medium: medium_params,
         high: high_params,
         instant: instant_params,
+        l1_fee: None,
+    }
+}
+
+/// Get the L1 data fee of the params, defaulting to the calldata of a typical user operation
+async fn get_estimation_l1_fee(chain_id: u64, params: L1FeeParams) -> Result<Option<U256>> {
+    if L1FeeOracle::from_chain_id(chain_id).is_none() {
+        return Ok(None);
     }
+
+    if let Some(calldata) = params.calldata {
+        return get_l1_fee(chain_id, calldata, None).await;
+    }
+    if let Some(user_operation) = params.user_operation {
+        return get_user_operation_l1_fee(chain_id, &user_operation).await;
+    }
+    if let Some(packed_user_operation) = params.packed_user_operation {
+        return get_packed_user_operation_l1_fee(chain_id, &packed_user_operation).await;
+    }
+
+    let size = params.calldata_size.unwrap_or(DEFAULT_L1_FEE_CALLDATA_SIZE) as usize;
+    get_l1_fee(chain_id, sample_calldata(size), None).await
 }
 
 /// Get the gas estimation from pre-configured APIs
@@ -12,11 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::types::GasEstimation;
-use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use crate::{
+    sampler::GasSample,
+    types::{GasEstimation, L1FeeParams},
+};
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    proc_macros::rpc,
+};
 
 #[rpc(server, namespace = "gas")]
 pub trait GasApi {
     #[method(name = "requestGasEstimation")]
-    async fn request_gas_estimation(&self, chain_id: u64) -> RpcResult<GasEstimation>;
+    async fn request_gas_estimation(
+        &self,
+        chain_id: u64,
+        l1_fee_params: Option<L1FeeParams>,
+    ) -> RpcResult<GasEstimation>;
+
+    #[method(name = "getHistory")]
+    async fn get_history(&self, chain_id: u64, window: u64) -> RpcResult<Vec<GasSample>>;
+
+    #[subscription(
+        name = "subscribeGasEstimation" => "gasEstimation",
+        unsubscribe = "unsubscribeGasEstimation",
+        item = GasSample
+    )]
+    async fn subscribe_gas_estimation(&self, chain_id: u64) -> SubscriptionResult;
 }
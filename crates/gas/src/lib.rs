@@ -14,7 +14,9 @@
 
 pub mod chains;
 pub mod config;
+pub mod fee_history;
 pub mod gas;
 pub mod gas_api;
+pub mod sampler;
 pub mod server;
 pub mod types;
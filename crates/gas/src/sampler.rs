@@ -0,0 +1,266 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{gas::GasApi, types::GasEstimation};
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::U256,
+    providers::{Provider, RootProvider},
+    transports::BoxTransport,
+};
+use eyre::{eyre, Result};
+use lightdotso_constants::registry::get_chain_registry;
+use lightdotso_contracts::provider::get_provider;
+use lightdotso_redis::{
+    query::gas::{add_gas_history, get_gas_history},
+    redis::Client,
+};
+use lightdotso_tracing::tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::broadcast, task::JoinHandle, time::sleep};
+
+/// The number of samples kept in the history of each chain
+const GAS_HISTORY_MAX_LEN: isize = 10_000;
+
+/// The capacity of the channel of the sample updates
+const GAS_SAMPLE_CHANNEL_CAPACITY: usize = 1024;
+
+/// A gas estimation sampled at a block
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GasSample {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub estimation: GasEstimation,
+    /// The base fee of the next block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<U256>,
+    /// The gas used ratio of the sampled block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used_ratio: Option<f64>,
+}
+
+/// The state of the sampled gas estimations shared by the api and the samplers
+#[derive(Debug)]
+pub struct GasState {
+    latest: RwLock<HashMap<u64, GasSample>>,
+    sender: broadcast::Sender<GasSample>,
+    redis_client: Option<Arc<Client>>,
+}
+
+impl GasState {
+    /// Constructs the state w/ the optional redis client for the history
+    pub fn new(redis_client: Option<Arc<Client>>) -> Self {
+        let (sender, _) = broadcast::channel(GAS_SAMPLE_CHANNEL_CAPACITY);
+        Self { latest: RwLock::new(HashMap::new()), sender, redis_client }
+    }
+
+    /// Get the latest sampled estimation of the chain, if sampled within the max age
+    pub fn latest_estimation(&self, chain_id: u64, max_age: Duration) -> Option<GasEstimation> {
+        let latest = self.latest.read().ok()?;
+        let sample = latest.get(&chain_id)?;
+
+        (now_seconds().saturating_sub(sample.timestamp) <= max_age.as_secs())
+            .then(|| sample.estimation.clone())
+    }
+
+    /// Get the block number of the latest sample of the chain
+    fn latest_block_number(&self, chain_id: u64) -> Option<u64> {
+        self.latest.read().ok()?.get(&chain_id).map(|sample| sample.block_number)
+    }
+
+    /// Subscribes to the sample updates of all chains
+    pub fn subscribe(&self) -> broadcast::Receiver<GasSample> {
+        self.sender.subscribe()
+    }
+
+    /// Stores the sample as the latest, adds it to the history and notifies the subscribers
+    pub fn publish(&self, sample: GasSample) -> Result<()> {
+        self.latest
+            .write()
+            .map_err(|_| eyre!("Gas state lock poisoned"))?
+            .insert(sample.chain_id, sample.clone());
+
+        if let Some(client) = &self.redis_client {
+            let mut con = client.get_connection()?;
+            add_gas_history(
+                &mut con,
+                sample.chain_id,
+                sample.timestamp,
+                &serde_json::to_string(&sample)?,
+                GAS_HISTORY_MAX_LEN,
+            )?;
+        }
+
+        // Ignore the error if there are no subscribers
+        let _ = self.sender.send(sample);
+
+        Ok(())
+    }
+
+    /// Get the samples of the chain within the window in seconds, from the oldest to the latest
+    pub fn history(&self, chain_id: u64, window: u64) -> Result<Vec<GasSample>> {
+        let client = self.redis_client.as_ref().ok_or_else(|| eyre!("Gas history not enabled"))?;
+        let mut con = client.get_connection()?;
+
+        let samples = get_gas_history(&mut con, chain_id, now_seconds().saturating_sub(window))?;
+        Ok(samples.iter().filter_map(|sample| serde_json::from_str(sample).ok()).collect())
+    }
+}
+
+/// Get the current unix timestamp in seconds
+fn now_seconds() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or_default()
+}
+
+/// Spawns the sampler of the chain, sampling the gas estimation once per block
+pub(crate) fn spawn_gas_sampler(api: GasApi, chain_id: u64) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("Starting gas sampler for chain {}", chain_id);
+
+        // Reuse the provider across the samples until it fails
+        let mut provider = None;
+
+        loop {
+            if let Err(err) = sample_gas(&api, chain_id, &mut provider).await {
+                warn!("Failed to sample gas for chain {}: {:?}", chain_id, err);
+                provider = None;
+            }
+
+            // Poll once per block
+            sleep(Duration::from_secs(get_chain_registry().block_seconds(chain_id).max(1))).await;
+        }
+    })
+}
+
+/// Samples the gas estimation of the chain if a new block was produced
+async fn sample_gas(
+    api: &GasApi,
+    chain_id: u64,
+    provider: &mut Option<RootProvider<BoxTransport>>,
+) -> Result<()> {
+    let client = match provider.take() {
+        Some(client) => client,
+        None => get_provider(chain_id).await?.0,
+    };
+    let client = provider.get_or_insert(client);
+
+    // Skip the sample if the block has not changed
+    let block_number = client.get_block_number().await?;
+    if api.state.latest_block_number(chain_id) == Some(block_number) {
+        return Ok(());
+    }
+
+    let estimation = api.estimate_gas(chain_id, None).await.map_err(|err| eyre!("{:?}", err))?;
+
+    // Get the base fee and the gas used ratio of the latest block
+    let fee_history = client.get_fee_history(1, BlockNumberOrTag::Latest, &[]).await.ok();
+    let base_fee_per_gas = fee_history
+        .as_ref()
+        .and_then(|fee_history| fee_history.base_fee_per_gas.last().copied())
+        .map(U256::from);
+    let gas_used_ratio =
+        fee_history.as_ref().and_then(|fee_history| fee_history.gas_used_ratio.last().copied());
+
+    let sample = GasSample {
+        chain_id,
+        block_number,
+        timestamp: now_seconds(),
+        estimation,
+        base_fee_per_gas,
+        gas_used_ratio,
+    };
+
+    api.state.publish(sample)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GasEstimationParams;
+
+    fn sample(chain_id: u64, block_number: u64, timestamp: u64) -> GasSample {
+        let params = GasEstimationParams::default();
+        GasSample {
+            chain_id,
+            block_number,
+            timestamp,
+            estimation: GasEstimation {
+                low: params.clone(),
+                medium: params.clone(),
+                high: params.clone(),
+                instant: params,
+                l1_fee: None,
+            },
+            base_fee_per_gas: None,
+            gas_used_ratio: None,
+        }
+    }
+
+    #[test]
+    fn test_gas_state_latest() {
+        let state = GasState::new(None);
+        assert!(state.latest_estimation(1, Duration::from_secs(60)).is_none());
+        assert_eq!(state.latest_block_number(1), None);
+
+        state.publish(sample(1, 100, now_seconds())).unwrap();
+        assert!(state.latest_estimation(1, Duration::from_secs(60)).is_some());
+        assert_eq!(state.latest_block_number(1), Some(100));
+        assert_eq!(state.latest_block_number(10), None);
+
+        // The stale samples are not served as the latest estimation
+        state.publish(sample(1, 101, now_seconds() - 120)).unwrap();
+        assert!(state.latest_estimation(1, Duration::from_secs(60)).is_none());
+        assert_eq!(state.latest_block_number(1), Some(101));
+    }
+
+    #[tokio::test]
+    async fn test_gas_state_subscribe() {
+        let state = GasState::new(None);
+        let mut receiver = state.subscribe();
+
+        state.publish(sample(10, 1, now_seconds())).unwrap();
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(received.chain_id, 10);
+        assert_eq!(received.block_number, 1);
+    }
+
+    #[test]
+    fn test_gas_state_history_without_redis() {
+        let state = GasState::new(None);
+        assert!(state.history(1, 60).is_err());
+    }
+
+    #[test]
+    fn test_gas_sample_serde() {
+        let json = serde_json::to_value(sample(1, 2, 3)).unwrap();
+        assert_eq!(json["chainId"], 1);
+        assert_eq!(json["blockNumber"], 2);
+        assert!(json.get("baseFeePerGas").is_none());
+
+        let decoded: GasSample = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.timestamp, 3);
+    }
+}
@@ -12,13 +12,55 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{gas::GasApi, gas_api::GasApiServer, types::GasEstimation};
+use crate::{
+    gas::GasApi,
+    gas_api::GasApiServer,
+    sampler::GasSample,
+    types::{GasEstimation, L1FeeParams},
+};
 use async_trait::async_trait;
-use jsonrpsee::core::RpcResult;
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    PendingSubscriptionSink, SubscriptionMessage,
+};
+use tokio::sync::broadcast::error::RecvError;
 
 #[async_trait]
 impl GasApiServer for GasApi {
-    async fn request_gas_estimation(&self, chain_id: u64) -> RpcResult<GasEstimation> {
-        Ok(GasApi::request_gas_estimation(self, chain_id).await?)
+    async fn request_gas_estimation(
+        &self,
+        chain_id: u64,
+        l1_fee_params: Option<L1FeeParams>,
+    ) -> RpcResult<GasEstimation> {
+        Ok(GasApi::request_gas_estimation(self, chain_id, l1_fee_params).await?)
+    }
+
+    async fn get_history(&self, chain_id: u64, window: u64) -> RpcResult<Vec<GasSample>> {
+        Ok(GasApi::get_history(self, chain_id, window).await?)
+    }
+
+    async fn subscribe_gas_estimation(
+        &self,
+        pending: PendingSubscriptionSink,
+        chain_id: u64,
+    ) -> SubscriptionResult {
+        let mut receiver = self.state.subscribe();
+        let sink = pending.accept().await?;
+
+        // Push the samples of the chain until the client unsubscribes
+        loop {
+            tokio::select! {
+                _ = sink.closed() => break,
+                sample = receiver.recv() => match sample {
+                    Ok(sample) if sample.chain_id == chain_id => {
+                        sink.send(SubscriptionMessage::from_json(&sample)?).await?;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+
+        Ok(())
     }
 }
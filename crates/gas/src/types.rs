@@ -12,14 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloy::primitives::U256;
+use alloy::primitives::{Bytes, U256};
+use lightdotso_contracts::types::{PackedUserOperation, UserOperation};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct GasEstimationParams {
     pub max_priority_fee_per_gas: U256,
     pub max_fee_per_gas: U256,
+    /// The share of the recent priority fees the tier meets or beats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// The expected time until inclusion of the tier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inclusion_seconds: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,4 +36,25 @@ pub struct GasEstimation {
     pub medium: GasEstimationParams,
     pub high: GasEstimationParams,
     pub instant: GasEstimationParams,
+    /// The L1 data fee in wei on rollups, added on top of the execution fee
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_fee: Option<U256>,
+}
+
+/// The params of the L1 data fee of the gas estimation
+/// The fee is estimated from the first of the calldata, the user operations or the calldata size
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct L1FeeParams {
+    pub calldata: Option<Bytes>,
+    pub user_operation: Option<UserOperation>,
+    pub packed_user_operation: Option<PackedUserOperation>,
+    pub calldata_size: Option<u64>,
+}
+
+impl GasEstimation {
+    /// Get the mutable tiers of the estimation from low to instant
+    pub fn tiers_mut(&mut self) -> [&mut GasEstimationParams; 4] {
+        [&mut self.low, &mut self.medium, &mut self.high, &mut self.instant]
+    }
 }
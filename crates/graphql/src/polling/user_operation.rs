@@ -129,6 +129,9 @@ pub struct UserOperationEvent {
     pub user_op_hash: Bytes,
     pub paymaster: Bytes,
     pub nonce: BigInt,
+    pub success: bool,
+    pub actual_gas_cost: BigInt,
+    pub actual_gas_used: BigInt,
     pub transaction_hash: Bytes,
 }
 
@@ -284,6 +284,9 @@ mod tests {
               user_op_hash: Bytes("0x1a8d7c5989225f7ef86fd7844c64b74e04d361734664fa6d2bf307414327875a".to_string()),
               paymaster: Bytes("0x000000000018d32df916ff115a25fbefc70baf8b".to_string()),
               nonce: BigInt("0".to_string()),
+              success: true,
+              actual_gas_cost: BigInt("0".to_string()),
+              actual_gas_used: BigInt("0".to_string()),
               transaction_hash: Bytes("0x87efb66c2b17af424b7fd2584d268eb1c301b9337eaad3137be5c4c7bbd574bf".to_string())
             }),
           user_operation_revert_reason: None,
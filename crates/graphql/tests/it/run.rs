@@ -15,8 +15,9 @@
 use alloy::primitives::B256;
 use dotenvy::dotenv;
 use eyre::Result;
+use lightdotso_constants::registry::get_chain_registry;
 use lightdotso_graphql::{
-    constants::{THE_GRAPH_STUDIO_BASE_URL, THE_GRAPH_STUDIO_SERVICE_IDS},
+    constants::THE_GRAPH_STUDIO_BASE_URL,
     polling::user_operations::{run_user_operation_query, GetUserOperationQueryVariables},
 };
 
@@ -29,7 +30,7 @@ async fn test_integration_run_query() -> Result<()> {
     let hash: B256 =
         "0x35bef2d3da16e9f4621a6e4852afcc939c64e949def198d4c542c4d9f3f0ee21".parse()?;
 
-    let id = THE_GRAPH_STUDIO_SERVICE_IDS.get(&137).unwrap();
+    let id = get_chain_registry().subgraph_ids("studio").remove(&137).unwrap();
 
     let url = format!(
         "{}/{}/{}/{}",
@@ -15,9 +15,11 @@
 use crate::indexer::Indexer;
 use clap::Parser;
 use eyre::{eyre, Result};
+use lightdotso_constants::registry::spawn_chain_registry_watcher;
+use lightdotso_db::models::chain::spawn_chain_registry_sync;
 use lightdotso_prisma::PrismaClient;
 use lightdotso_tracing::tracing::info;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 #[derive(Clone, Debug, Parser, Default)]
 pub struct IndexerArgs {
@@ -70,6 +72,10 @@ impl IndexerArgs {
             return Err(eyre!("Chain id is 0"));
         }
 
+        // Keep the chain registry in sync w/ the file and the `Chain` table
+        spawn_chain_registry_watcher(Duration::from_secs(30));
+        spawn_chain_registry_sync(db.clone(), Duration::from_secs(60));
+
         // Construct the indexer
         let indexer = Indexer::new(self).await;
 
@@ -49,7 +49,7 @@ use axum::Json;
 use backon::{BlockingRetryable, ExponentialBuilder, Retryable};
 use eyre::{eyre, Result};
 use futures::StreamExt;
-use lightdotso_constants::chains::{RUNNER_CHAIN_IDS, SLEEP_CHAIN_IDS};
+use lightdotso_constants::registry::get_chain_registry;
 use lightdotso_contracts::address::LIGHT_WALLET_FACTORY_ADDRESSES;
 use lightdotso_db::{error::DbError, models::transaction::upsert_transaction_with_log_receipt};
 use lightdotso_kafka::{
@@ -130,14 +130,18 @@ impl Indexer {
             // Get the block number
             info!("New block: {:?}", block.clone().header.number);
 
-            // Check if the block is in the sleep chain ids
-            if SLEEP_CHAIN_IDS.contains_key(&self.chain_id) {
+            // Get the chain config from the registry
+            let registry = get_chain_registry();
+
+            // Check if the chain has sleep seconds
+            if let Some(sleep_seconds) = registry.sleep_seconds(self.chain_id) {
                 // Sleep for the duration
-                sleep(Duration::from_secs(SLEEP_CHAIN_IDS[&self.chain_id] as u64)).await;
+                sleep(Duration::from_secs(sleep_seconds)).await;
             }
 
             // Send the transaction to the queue for indexing if not runner
-            if self.kafka_client.is_some() && !RUNNER_CHAIN_IDS.contains(&self.chain_id) {
+            let is_runner = registry.get(self.chain_id).map_or(false, |chain| chain.is_runner());
+            if self.kafka_client.is_some() && !is_runner {
                 let queue_res = self.send_tx_queue(block.clone()).await;
                 if queue_res.is_err() {
                     error!("send_tx_queue error: {:?}", queue_res);
@@ -21,7 +21,7 @@ use crate::{
     },
 };
 use alloy::{
-    primitives::Bytes,
+    primitives::{Bytes, Log},
     sol,
     sol_types::{SolCall, SolEvent},
 };
@@ -93,35 +93,59 @@ impl Adapter for ERC20Adapter {
         let mut asset_changes = Vec::new();
 
         // Iterate over all of the logs
-        for log in logs {
+        for log in &logs {
             // Get the `from` and `to` addresses from the log
-            let res = ERC20::Transfer::decode_log(log, true)?;
+            let res = ERC20::Transfer::decode_log(*log, true)?;
             let (from, to, value) = (res.from, res.to, res.value);
 
             // Get the token address from the log
             let token_address = log.address;
 
-            // Get the token balance of the `from` address
-            let mut before_from_balance =
-                self.get_erc20_balance(evm, from, token_address).await.ok();
-
-            // Get the token balance of the `to` address
-            let mut before_to_balance = self.get_erc20_balance(evm, to, token_address).await.ok();
-
-            // Check if the value does not overflow
-            let (after_from_balance, after_to_balance) = before_from_balance
-                .and_then(|before_balance| {
-                    if value <= before_balance {
-                        Some((Some(before_balance - value), before_to_balance.map(|b| b + value)))
-                    } else {
-                        // If the value overflows, set the before balances to `None`
-                        before_from_balance = None;
-                        before_to_balance = None;
+            // Get the token balances from the simulated state diff, if the layout is known and the
+            // transfer is the only one of the owners, as the diff spans the whole transaction
+            let (mut before_from_balance, mut before_to_balance, after_balances) = match (
+                request.erc20_balance(token_address, from),
+                request.erc20_balance(token_address, to),
+            ) {
+                (Some(from_diff), Some(to_diff))
+                    if transfer_count(&logs, token_address, from) == 1 &&
+                        transfer_count(&logs, token_address, to) == 1 =>
+                {
+                    (
+                        Some(from_diff.before),
+                        Some(to_diff.before),
+                        Some((Some(from_diff.after), Some(to_diff.after))),
+                    )
+                }
+                _ => (
+                    // Get the token balance of the `from` address
+                    self.get_erc20_balance(evm, from, token_address).await.ok(),
+                    // Get the token balance of the `to` address
+                    self.get_erc20_balance(evm, to, token_address).await.ok(),
+                    None,
+                ),
+            };
 
-                        None
-                    }
-                })
-                .unwrap_or((None, None));
+            // Check if the value does not overflow, if the after balances are not mapped
+            let (after_from_balance, after_to_balance) = match after_balances {
+                Some(after_balances) => after_balances,
+                None => before_from_balance
+                    .and_then(|before_balance| {
+                        if value <= before_balance {
+                            Some((
+                                Some(before_balance - value),
+                                before_to_balance.map(|b| b + value),
+                            ))
+                        } else {
+                            // If the value overflows, set the before balances to `None`
+                            before_from_balance = None;
+                            before_to_balance = None;
+
+                            None
+                        }
+                    })
+                    .unwrap_or((None, None)),
+            };
 
             // Get the after balance of the `to` address
 
@@ -177,3 +201,46 @@ impl Adapter for ERC20Adapter {
         Ok(AdapterResponse { actions, asset_changes })
     }
 }
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Counts the `Transfer` logs of the token from or to the owner
+fn transfer_count(logs: &[&Log], token: Address, owner: Address) -> usize {
+    logs.iter()
+        .filter(|log| {
+            log.address == token &&
+                log.topics()[1..].iter().any(|topic| Address::from_word(*topic) == owner)
+        })
+        .count()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_count() {
+        let token = Address::repeat_byte(1);
+        let (a, b, c) = (Address::repeat_byte(2), Address::repeat_byte(3), Address::repeat_byte(4));
+        let transfer = |address: Address, from: Address, to: Address| {
+            Log::new_unchecked(
+                address,
+                vec![*TRANSFER_EVENT_TOPIC, from.into_word(), to.into_word()],
+                U256::from(1).to_be_bytes_vec().into(),
+            )
+        };
+
+        let logs = [transfer(token, a, b), transfer(token, b, c), transfer(c, a, b)];
+        let logs = logs.iter().collect::<Vec<_>>();
+
+        assert_eq!(transfer_count(&logs, token, a), 1);
+        assert_eq!(transfer_count(&logs, token, b), 2);
+        assert_eq!(transfer_count(&logs, token, c), 1);
+    }
+}
@@ -18,7 +18,7 @@ use crate::{
 };
 use clap::Parser;
 use eyre::Result;
-use lightdotso_simulator::types::SimulationRequest;
+use lightdotso_simulator::types::{SimulationRequest, UserOperationRequest};
 use lightdotso_tracing::tracing::info;
 
 #[derive(Clone, Debug, Parser, Default)]
@@ -51,6 +51,31 @@ impl InterpreterArgs {
         Ok(res)
     }
 
+    pub async fn run_user_operation(
+        self,
+        request: UserOperationRequest,
+    ) -> Result<InterpretationResponse> {
+        // Add info
+        info!("InterpreterArgs run, starting...");
+
+        // Print the config
+        info!("Config: {:?}", self);
+
+        // Construct the interpreter
+        let mut interpreter = Interpreter::new().await;
+
+        info!("InterpreterArgs run, starting simulate...");
+
+        // Simulate the user operation
+        let res = interpreter.run_with_simulate_user_operation(request).await?;
+
+        info!("res: {:?}", res);
+
+        info!("InterpreterArgs run, finished");
+
+        Ok(res)
+    }
+
     pub async fn run_interpretation(
         self,
         request: InterpretationRequest,
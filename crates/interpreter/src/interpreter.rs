@@ -21,11 +21,12 @@ use crate::{
     types::{AdapterResponse, CallTrace, InterpretationRequest, InterpretationResponse},
 };
 use eyre::{eyre, Result};
-use lightdotso_contracts::provider::get_provider;
+use lightdotso_contracts::{address::ENTRYPOINT_V060_ADDRESS, provider::get_provider};
 use lightdotso_simulator::{
     evm::Evm,
     simulator::{simulate, simulate_bundle},
-    types::SimulationRequest,
+    types::{SimulationRequest, UserOperationRequest},
+    user_operation::{simulate_user_operation, SIMULATION_BUNDLER},
 };
 use revm::interpreter::InstructionResult;
 
@@ -109,6 +110,7 @@ impl Interpreter<'_> {
             value: Some(request.value.try_into().unwrap_or_default()),
             traces: traces.clone(),
             logs: res.logs.clone(),
+            erc20_balances: res.erc20_balances.clone(),
         };
 
         // Run the interpreter
@@ -133,6 +135,63 @@ impl Interpreter<'_> {
         })
     }
 
+    pub async fn run_with_simulate_user_operation(
+        &mut self,
+        request: UserOperationRequest,
+    ) -> Result<InterpretationResponse> {
+        // Simulate the user operation w/ `handleOps` of the entrypoint
+        let res = simulate_user_operation(request.clone()).await?;
+
+        // Get the traces
+        let traces: Vec<CallTrace> = res
+            .clone()
+            .arena
+            .unwrap_or_default()
+            .nodes()
+            .iter()
+            .map(|node| CallTrace::from(node.clone()))
+            .collect();
+
+        // Construct the interpretation request, w/ the `handleOps` sent by the bundler
+        let req = InterpretationRequest {
+            block_number: Some(res.block_number),
+            gas_limit: u64::MAX,
+            from: SIMULATION_BUNDLER,
+            to: Some(request.entrypoint.unwrap_or(*ENTRYPOINT_V060_ADDRESS)),
+            chain_id: request.chain_id,
+            call_data: None,
+            value: Some(0),
+            traces: traces.clone(),
+            logs: res.logs.clone(),
+            erc20_balances: res.erc20_balances.clone(),
+        };
+
+        // Run the interpreter
+        let interpretation = self.interpret(req).await?;
+
+        // Flatten the actions
+        let actions = interpretation.clone().into_iter().flat_map(|res| res.actions).collect();
+
+        // Flatten the asset changes
+        let asset_changes = interpretation.into_iter().flat_map(|res| res.asset_changes).collect();
+
+        Ok(InterpretationResponse {
+            chain_id: request.chain_id,
+            gas_used: res.gas_used,
+            block_number: res.block_number,
+            success: res.validation_success && res.execution_success,
+            traces,
+            logs: res.logs,
+            exit_reason: if res.validation_success {
+                InstructionResult::Stop
+            } else {
+                InstructionResult::Revert
+            },
+            actions,
+            asset_changes,
+        })
+    }
+
     pub async fn run_with_simulate_bundle(
         &mut self,
         requests: Vec<SimulationRequest>,
@@ -170,6 +229,7 @@ impl Interpreter<'_> {
                 value: Some(req.value.try_into().unwrap_or_default()),
                 traces: traces.clone(),
                 logs: res.clone().logs,
+                erc20_balances: res.erc20_balances.clone(),
             };
 
             // Run the interpreter
@@ -14,6 +14,7 @@
 
 use crate::constants::InterpretationActionType;
 use alloy::primitives::{Address, Bytes, Log};
+use lightdotso_simulator::state_diff::Erc20BalanceDiff;
 use revm::{interpreter::InstructionResult, primitives::U256};
 use revm_inspectors::tracing::types::{CallKind, CallTraceNode};
 use serde::{Deserialize, Serialize};
@@ -41,6 +42,16 @@ pub struct InterpretationRequest {
     pub traces: Vec<CallTrace>,
     /// Logs of the transaction
     pub logs: Vec<Log>,
+    /// ERC20 balances before and after the transaction, mapped from the simulated state diff
+    #[serde(default)]
+    pub erc20_balances: Vec<Erc20BalanceDiff>,
+}
+
+impl InterpretationRequest {
+    /// Get the ERC20 balance of the owner before and after the transaction, if mapped
+    pub fn erc20_balance(&self, token: Address, owner: Address) -> Option<&Erc20BalanceDiff> {
+        self.erc20_balances.iter().find(|diff| diff.token == token && diff.owner == owner)
+    }
 }
 
 impl Default for InterpretationRequest {
@@ -55,6 +66,7 @@ impl Default for InterpretationRequest {
             value: None,
             traces: Vec::new(),
             logs: Vec::new(),
+            erc20_balances: Vec::new(),
         }
     }
 }
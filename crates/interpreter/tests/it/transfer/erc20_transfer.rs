@@ -69,6 +69,7 @@ async fn test_integration_erc20_transfer_query_overflow_bug() -> Result<()> {
         to: Some("0xe1C66210fB97C76cDAEE38950F5E9c181e9dA628".parse()?),
         call_data: Some("0x6df0e4a8000000000000000000000000000000000000000000000000000000000312181100000000000000000000000000000000000000000000000000000000000000600000000000000000000000005ff137d4b0fdcd49dca30c7cf57e578a026d278900000000000000000000000000000000000000000000000000000000000004841fad948c00000000000000000000000000000000000000000000000000000000000000400000000000000000000000002978231d983d32c5ea3e97021e6a7d636ef42bef00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000020000000000000000000000000fbd80fe5ce1ece895845fd131bd621e2b6a1345f000000000000000000000000000000000000000000000000000000000000000b00000000000000000000000000000000000000000000000000000000000001600000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000044e1c000000000000000000000000000000000000000000000000000000000001c4b4000000000000000000000000000000000000000000000000000000000001c4b400000000000000000000000000000000000000000000000000000000d320b3b350000000000000000000000000000000000000000000000000000000b323dbb3100000000000000000000000000000000000000000000000000000000000002a00000000000000000000000000000000000000000000000000000000000000360000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000e4b61d27f6000000000000000000000000c2132d05d31c914a87c6611c10748aeb04b58e8f000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000044a9059cbb000000000000000000000000e8a0e8466df96ec769a02adaa969abe67c70ec6800000000000000000000000000000000000000000000000000000000000493e000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000095000000000003193facb32d1c120719892b7ae9770000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000006588bdcd590b7625fbeeef1867e88939f27585eb38f2c8aa3c407c776b87433bc7cf91d94b7215028f3cf6351206b58acc0e00d7bcdb8b63d18db541298b5c0e1830de521b0000000000000000000000000000000000000000000000000000000000000000000000000000000000006101000100000000000104ca4889de26ace945786f5c5df485d0cdcdbb4ca19318987147eace08dcedbf047ed2eeffccf20a4f86f635b867e2b9634d33513f3b5b539952fd950bc022111b0201017f4c8bd0acc303599a1ae92414b055514ffb6f810000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000".parse()?),
         traces: vec![],
+        erc20_balances: vec![],
         logs: vec![
             Log {
                 address: "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789".parse()?,
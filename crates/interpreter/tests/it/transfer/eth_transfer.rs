@@ -34,6 +34,7 @@ async fn test_integration_eth_transfer() -> Result<()> {
         value: U256::from(1),
         gas_limit: u64::MAX,
         block_number: None,
+        state_overrides: None,
     };
 
     // Parse the command line arguments
@@ -67,6 +68,7 @@ async fn test_integration_light_eth_transfer() -> Result<()> {
         value: U256::from(1),
         gas_limit: u64::MAX,
         block_number: Some(114445705),
+        state_overrides: None,
     };
 
     // Parse the command line arguments
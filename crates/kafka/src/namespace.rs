@@ -24,6 +24,11 @@ lazy_static! {
     pub static ref BILLING_OPERATION: String = "billing-operation".to_string();
 }
 
+// The billing operation reconcile namesapce
+lazy_static! {
+    pub static ref BILLING_OPERATION_RECONCILE: String = "billing-operation-reconcile".to_string();
+}
+
 // The covalent namesapce
 lazy_static! {
     pub static ref COVALENT: String = "covalent".to_string();
@@ -39,6 +44,16 @@ lazy_static! {
     pub static ref NODE: String = "node".to_string();
 }
 
+// The node status namesapce
+lazy_static! {
+    pub static ref NODE_STATUS: String = "node-status".to_string();
+}
+
+// The node executor low balance alert namesapce
+lazy_static! {
+    pub static ref NODE_EXECUTOR_BALANCE: String = "node-executor-balance".to_string();
+}
+
 // The notification namesapce
 lazy_static! {
     pub static ref NOTIFICATION: String = "notification".to_string();
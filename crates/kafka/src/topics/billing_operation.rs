@@ -13,8 +13,10 @@
 // limitations under the License.
 
 use crate::{
-    namespace::BILLING_OPERATION, produce_message, traits::ToJson,
-    types::billing_operation::BillingOperationMessage,
+    namespace::{BILLING_OPERATION, BILLING_OPERATION_RECONCILE},
+    produce_message,
+    traits::ToJson,
+    types::billing_operation::{BillingOperationMessage, BillingOperationReconcileMessage},
 };
 use eyre::Result;
 pub use rdkafka;
@@ -35,3 +37,14 @@ pub async fn produce_billing_operation_message(
     produce_message(producer, BILLING_OPERATION.as_str(), &message, None).await?;
     Ok(())
 }
+
+/// Produce a message with BillingOperationReconcile topic.
+pub async fn produce_billing_operation_reconcile_message(
+    producer: Arc<FutureProducer>,
+    msg: &BillingOperationReconcileMessage,
+) -> Result<()> {
+    let message = msg.to_json();
+
+    produce_message(producer, BILLING_OPERATION_RECONCILE.as_str(), &message, None).await?;
+    Ok(())
+}
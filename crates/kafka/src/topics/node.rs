@@ -12,7 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{namespace::NODE, produce_message, traits::ToJson, types::node::NodeMessage};
+use crate::{
+    namespace::{NODE, NODE_EXECUTOR_BALANCE, NODE_STATUS},
+    produce_message,
+    traits::ToJson,
+    types::node::{NodeExecutorBalanceMessage, NodeMessage, NodeStatusMessage},
+};
 use eyre::Result;
 pub use rdkafka;
 use rdkafka::producer::FutureProducer;
@@ -29,3 +34,25 @@ pub async fn produce_node_message(producer: Arc<FutureProducer>, msg: &NodeMessa
     produce_message(producer, NODE.as_str(), &message, None).await?;
     Ok(())
 }
+
+/// Produce a message with NodeStatus topic.
+pub async fn produce_node_status_message(
+    producer: Arc<FutureProducer>,
+    msg: &NodeStatusMessage,
+) -> Result<()> {
+    let message = msg.to_json();
+
+    produce_message(producer, NODE_STATUS.as_str(), &message, None).await?;
+    Ok(())
+}
+
+/// Produce a message with NodeExecutorBalance topic.
+pub async fn produce_node_executor_balance_message(
+    producer: Arc<FutureProducer>,
+    msg: &NodeExecutorBalanceMessage,
+) -> Result<()> {
+    let message = msg.to_json();
+
+    produce_message(producer, NODE_EXECUTOR_BALANCE.as_str(), &message, None).await?;
+    Ok(())
+}
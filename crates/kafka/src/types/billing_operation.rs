@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::traits::ToJson;
-use alloy::primitives::Address;
+use alloy::primitives::{Address, B256, U256};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
@@ -31,6 +31,21 @@ pub struct BillingOperationMessage {
     pub call_gas_limit: u64,
 }
 
+/// The message to reconcile the billing operation w/ the executed `UserOperationEvent`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BillingOperationReconcileMessage {
+    pub chain_id: u64,
+    pub user_operation_hash: B256,
+    pub transaction_hash: Option<B256>,
+    pub block_number: u64,
+    pub actual_gas_cost: U256,
+    pub actual_gas_used: U256,
+    pub success: bool,
+    /// The number of the attempts requeued while the operation is not indexed yet
+    #[serde(default)]
+    pub attempt: u32,
+}
+
 // -----------------------------------------------------------------------------
 // Traits
 // -----------------------------------------------------------------------------
@@ -49,3 +64,20 @@ impl ToJson for BillingOperationMessage {
         msg_value.to_string()
     }
 }
+
+impl ToJson for BillingOperationReconcileMessage {
+    fn to_json(&self) -> String {
+        let msg_value: Value = json!({
+            "chain_id": self.chain_id,
+            "user_operation_hash": format!("{:?}", self.user_operation_hash),
+            "transaction_hash": self.transaction_hash.map(|hash| format!("{:?}", hash)),
+            "block_number": self.block_number,
+            "actual_gas_cost": self.actual_gas_cost,
+            "actual_gas_used": self.actual_gas_used,
+            "success": self.success,
+            "attempt": self.attempt,
+        });
+
+        msg_value.to_string()
+    }
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::traits::ToJson;
-use alloy::primitives::B256;
+use alloy::primitives::{Address, B256, U256};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
@@ -26,6 +26,26 @@ pub struct NodeMessage {
     pub hash: B256,
 }
 
+/// The message of the final status of the bundle transaction sent by the node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeStatusMessage {
+    pub chain_id: u64,
+    pub executor: Address,
+    pub nonce: u64,
+    pub transaction_hash: Option<B256>,
+    pub user_operation_hashes: Vec<B256>,
+    pub status: String,
+}
+
+/// The message of the low balance alert of the executor used by the node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeExecutorBalanceMessage {
+    pub chain_id: u64,
+    pub executor: Address,
+    pub balance: U256,
+    pub threshold: U256,
+}
+
 // -----------------------------------------------------------------------------
 // Traits
 // -----------------------------------------------------------------------------
@@ -40,6 +60,38 @@ impl ToJson for NodeMessage {
     }
 }
 
+impl ToJson for NodeStatusMessage {
+    fn to_json(&self) -> String {
+        let msg_value: Value = json!({
+            "chain_id": self.chain_id,
+            "executor": self.executor.to_checksum(None),
+            "nonce": self.nonce,
+            "transaction_hash": self.transaction_hash.map(|hash| format!("{:?}", hash)),
+            "user_operation_hashes": self
+                .user_operation_hashes
+                .iter()
+                .map(|hash| format!("{:?}", hash))
+                .collect::<Vec<_>>(),
+            "status": self.status,
+        });
+
+        msg_value.to_string()
+    }
+}
+
+impl ToJson for NodeExecutorBalanceMessage {
+    fn to_json(&self) -> String {
+        let msg_value: Value = json!({
+            "chain_id": self.chain_id,
+            "executor": self.executor.to_checksum(None),
+            "balance": self.balance.to_string(),
+            "threshold": self.threshold.to_string(),
+        });
+
+        msg_value.to_string()
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Tests
 // -----------------------------------------------------------------------------
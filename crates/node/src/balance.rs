@@ -0,0 +1,110 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, U256};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The interval of checking the balances of the executor keys of a chain
+pub const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The seconds to wait before alerting the low balance of the same executor key again
+pub const BALANCE_ALERT_TTL_SECONDS: u64 = 3600;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The monitor of the balances of the executor keys, w/ the keys under the threshold skipped
+#[derive(Clone, Debug, Default)]
+pub struct BalanceMonitor {
+    /// The min balance of an executor key to submit the bundles
+    pub threshold: U256,
+    checked_at: HashMap<u64, Instant>,
+    low: HashSet<(u64, Address)>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl BalanceMonitor {
+    pub fn new(threshold: U256) -> Self {
+        Self { threshold, ..Default::default() }
+    }
+
+    /// Whether the balances of the chain are due to check, marking them as checked if so
+    pub fn should_check(&mut self, chain_id: u64) -> bool {
+        let now = Instant::now();
+
+        match self.checked_at.get(&chain_id) {
+            Some(checked_at) if now.duration_since(*checked_at) < BALANCE_CHECK_INTERVAL => false,
+            _ => {
+                self.checked_at.insert(chain_id, now);
+                true
+            }
+        }
+    }
+
+    /// Update the balance of the executor, returns whether it is under the threshold
+    pub fn update(&mut self, chain_id: u64, executor: Address, balance: U256) -> bool {
+        let is_low = balance < self.threshold;
+
+        if is_low {
+            self.low.insert((chain_id, executor));
+        } else {
+            self.low.remove(&(chain_id, executor));
+        }
+
+        is_low
+    }
+
+    /// Get the executors of the chain under the threshold
+    pub fn low_executors(&self, chain_id: u64) -> HashSet<Address> {
+        self.low.iter().filter(|(id, _)| *id == chain_id).map(|(_, executor)| *executor).collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_monitor() {
+        let mut monitor = BalanceMonitor::new(U256::from(100));
+        let executor = Address::repeat_byte(1);
+
+        assert!(monitor.should_check(1));
+        assert!(!monitor.should_check(1));
+        assert!(monitor.should_check(10));
+
+        assert!(monitor.update(1, executor, U256::from(99)));
+        assert_eq!(monitor.low_executors(1), HashSet::from([executor]));
+        assert!(monitor.low_executors(10).is_empty());
+
+        // Recovers once funded
+        assert!(!monitor.update(1, executor, U256::from(100)));
+        assert!(monitor.low_executors(1).is_empty());
+    }
+}
@@ -15,6 +15,7 @@
 use crate::node::Node;
 use clap::Parser;
 use eyre::Result;
+use lightdotso_signer::config::SignerArgs;
 use lightdotso_tracing::tracing::info;
 
 #[derive(Clone, Debug, Parser, Default)]
@@ -25,10 +26,21 @@ pub struct NodeArgs {
     /// The AWS secret access key
     #[clap(long, env = "AWS_SECRET_ACCESS_KEY", hide = true)]
     pub aws_secret_key_id: Option<String>,
-    /// The AWS KMS key ids
-    #[arg(long, short, num_args = 1.., value_delimiter = ',')]
-    #[clap(long, env = "AWS_KMS_KEY_IDS", hide = true)]
-    pub aws_kms_key_ids: Option<Vec<String>>,
+    /// The signer of the executor keys
+    #[command(flatten)]
+    pub signer: SignerArgs,
+    /// The max gas of the operations in a bundle
+    #[arg(long, default_value_t = 10_000_000)]
+    #[clap(long, env = "NODE_MAX_BUNDLE_GAS")]
+    pub max_bundle_gas: u64,
+    /// The max number of the executor keys locked per chain
+    #[arg(long, default_value_t = 3)]
+    #[clap(long, env = "NODE_EXECUTOR_POOL_SIZE")]
+    pub executor_pool_size: usize,
+    /// The min balance in wei of an executor key to submit the bundles (0.01 ether)
+    #[arg(long, default_value_t = 10_000_000_000_000_000)]
+    #[clap(long, env = "NODE_EXECUTOR_MIN_BALANCE")]
+    pub executor_min_balance: u128,
 }
 
 impl NodeArgs {
@@ -12,5 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod balance;
 pub mod config;
+pub mod mempool;
 pub mod node;
+pub mod nonce;
+pub mod reputation;
+pub mod tracker;
@@ -0,0 +1,495 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::reputation::{Reputation, ReputationStatus, THROTTLED_ENTITY_BUNDLE_COUNT};
+use alloy::primitives::{Address, B256, U256};
+use eyre::{eyre, Result};
+use lightdotso_contracts::types::{PackedUserOperation, UserOperation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The max number of pending operations of a sender in the mempool
+pub const MAX_OPERATIONS_PER_SENDER: usize = 4;
+
+/// The min percentage bump of the fees to replace an operation w/ the same sender and nonce
+pub const REPLACEMENT_FEE_BUMP_PERCENT: u64 = 10;
+
+/// The max number of submission attempts of an operation before it is dropped
+pub const MAX_OPERATION_ATTEMPTS: u32 = 3;
+
+/// The max seconds an operation stays in the mempool before it is evicted
+pub const MAX_OPERATION_AGE_SECONDS: u64 = 1800;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The user operation in the mempool, for either of the entrypoint versions
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MempoolUserOperation {
+    /// The user operation of the entrypoint v0.6.0
+    Default(UserOperation),
+    /// The packed user operation of the entrypoint v0.7.0
+    Packed(PackedUserOperation),
+}
+
+/// The entry of the validated user operation in the mempool
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolEntry {
+    /// The user operation hash
+    pub hash: B256,
+    /// The chain id
+    pub chain_id: u64,
+    /// The entrypoint address
+    pub entry_point: Address,
+    /// The user operation
+    pub user_operation: MempoolUserOperation,
+    /// The number of the failed submission attempts
+    pub attempts: u32,
+    /// The unix timestamp of the admission to the mempool
+    #[serde(default = "now_seconds")]
+    pub added_at: u64,
+}
+
+/// The mempool of the validated user operations w/ the reputation of their entities
+#[derive(Clone, Debug, Default)]
+pub struct Mempool {
+    entries: HashMap<B256, MempoolEntry>,
+    /// The reputation of the factories and the paymasters
+    pub reputation: Reputation,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl MempoolUserOperation {
+    pub fn sender(&self) -> Address {
+        match self {
+            MempoolUserOperation::Default(op) => op.sender,
+            MempoolUserOperation::Packed(op) => op.sender,
+        }
+    }
+
+    pub fn nonce(&self) -> U256 {
+        match self {
+            MempoolUserOperation::Default(op) => op.nonce,
+            MempoolUserOperation::Packed(op) => op.nonce,
+        }
+    }
+
+    pub fn max_fee_per_gas(&self) -> U256 {
+        match self {
+            MempoolUserOperation::Default(op) => op.max_fee_per_gas,
+            MempoolUserOperation::Packed(op) => op.max_fee_per_gas,
+        }
+    }
+
+    pub fn max_priority_fee_per_gas(&self) -> U256 {
+        match self {
+            MempoolUserOperation::Default(op) => op.max_priority_fee_per_gas,
+            MempoolUserOperation::Packed(op) => op.max_priority_fee_per_gas,
+        }
+    }
+
+    /// Get the factory of the user operation, if deploying the account
+    pub fn factory(&self) -> Option<Address> {
+        match self {
+            MempoolUserOperation::Default(op) => op.init_code.get(..20).map(Address::from_slice),
+            MempoolUserOperation::Packed(op) => op.factory,
+        }
+    }
+
+    /// Get the paymaster of the user operation, if sponsored
+    pub fn paymaster(&self) -> Option<Address> {
+        match self {
+            MempoolUserOperation::Default(op) => {
+                op.paymaster_and_data.get(..20).map(Address::from_slice)
+            }
+            MempoolUserOperation::Packed(op) => op.paymaster,
+        }
+    }
+
+    /// Get the factory and the paymaster of the user operation
+    pub fn entities(&self) -> Vec<Address> {
+        self.factory().into_iter().chain(self.paymaster()).collect()
+    }
+
+    /// Get the max gas the user operation can use in the bundle
+    /// The verification gas limit of v0.6.0 also applies to the paymaster validation and post op
+    pub fn gas_limit(&self) -> U256 {
+        match self {
+            MempoolUserOperation::Default(op) => {
+                let multiplier = if op.paymaster_and_data.is_empty() { 1 } else { 3 };
+                op.call_gas_limit
+                    .saturating_add(
+                        op.verification_gas_limit.saturating_mul(U256::from(multiplier)),
+                    )
+                    .saturating_add(op.pre_verification_gas)
+            }
+            MempoolUserOperation::Packed(op) => op
+                .call_gas_limit
+                .saturating_add(op.verification_gas_limit)
+                .saturating_add(op.pre_verification_gas)
+                .saturating_add(op.paymaster_verification_gas_limit.unwrap_or_default())
+                .saturating_add(op.paymaster_post_op_gas_limit.unwrap_or_default()),
+        }
+    }
+}
+
+impl MempoolEntry {
+    pub fn new(chain_id: u64, entry_point: Address, user_operation: MempoolUserOperation) -> Self {
+        let hash = match &user_operation {
+            MempoolUserOperation::Default(op) => op.op_hash(entry_point, chain_id),
+            MempoolUserOperation::Packed(op) => op.op_hash(entry_point, chain_id),
+        };
+
+        Self { hash, chain_id, entry_point, user_operation, attempts: 0, added_at: now_seconds() }
+    }
+
+    fn is_same_bundle(&self, chain_id: u64, entry_point: Address) -> bool {
+        self.chain_id == chain_id && self.entry_point == entry_point
+    }
+}
+
+impl Mempool {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, hash: &B256) -> bool {
+        self.entries.contains_key(hash)
+    }
+
+    /// Add the validated user operation to the mempool
+    /// Returns the hash of the replaced operation w/ the same sender and nonce, if any
+    pub fn add(&mut self, entry: MempoolEntry) -> Result<Option<B256>> {
+        let op = &entry.user_operation;
+
+        // Reject the operations of the banned entities
+        if let Some(entity) = op
+            .entities()
+            .into_iter()
+            .find(|e| self.reputation.status(*e) == ReputationStatus::Banned)
+        {
+            return Err(eyre!("Entity {:?} is banned", entity));
+        }
+
+        // Replace the operation w/ the same sender and nonce only w/ the bumped fees
+        let existing = self
+            .entries
+            .values()
+            .find(|e| {
+                e.is_same_bundle(entry.chain_id, entry.entry_point) &&
+                    e.user_operation.sender() == op.sender() &&
+                    e.user_operation.nonce() == op.nonce()
+            })
+            .map(|e| (e.hash, e.user_operation.clone()));
+        if let Some((hash, existing)) = &existing {
+            if !is_fee_bumped(existing.max_fee_per_gas(), op.max_fee_per_gas()) ||
+                !is_fee_bumped(
+                    existing.max_priority_fee_per_gas(),
+                    op.max_priority_fee_per_gas(),
+                )
+            {
+                return Err(eyre!(
+                    "Replacement of {:?} must bump the fees by {}%",
+                    hash,
+                    REPLACEMENT_FEE_BUMP_PERCENT
+                ));
+            }
+        } else {
+            let pending = self
+                .entries
+                .values()
+                .filter(|e| {
+                    e.is_same_bundle(entry.chain_id, entry.entry_point) &&
+                        e.user_operation.sender() == op.sender()
+                })
+                .count();
+            if pending >= MAX_OPERATIONS_PER_SENDER {
+                return Err(eyre!("Sender {:?} has too many pending operations", op.sender()));
+            }
+        }
+
+        for entity in op.entities() {
+            self.reputation.add_seen(entity);
+        }
+
+        let replaced = existing.map(|(hash, _)| hash);
+        if let Some(hash) = replaced {
+            self.entries.remove(&hash);
+        }
+        self.entries.insert(entry.hash, entry);
+
+        Ok(replaced)
+    }
+
+    /// Put back the entry w/o the checks, e.g. after a failed submission
+    pub fn restore(&mut self, entry: MempoolEntry) {
+        self.entries.insert(entry.hash, entry);
+    }
+
+    pub fn remove(&mut self, hash: &B256) -> Option<MempoolEntry> {
+        self.entries.remove(hash)
+    }
+
+    /// Get the distinct chains and entrypoints of the pending operations
+    pub fn bundle_keys(&self) -> Vec<(u64, Address)> {
+        let mut keys =
+            self.entries.values().map(|e| (e.chain_id, e.entry_point)).collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// Evict the operations admitted more than the max age before the timestamp
+    /// Returns the hashes of the evicted operations
+    pub fn evict_expired(&mut self, now: u64) -> Vec<B256> {
+        let expired = self
+            .entries
+            .values()
+            .filter(|e| now.saturating_sub(e.added_at) > MAX_OPERATION_AGE_SECONDS)
+            .map(|e| e.hash)
+            .collect::<Vec<_>>();
+        for hash in &expired {
+            self.entries.remove(hash);
+        }
+
+        expired
+    }
+
+    /// Get all of the entries of the chain and the entrypoint
+    pub fn entries(&self, chain_id: u64, entry_point: Address) -> Vec<MempoolEntry> {
+        self.entries.values().filter(|e| e.is_same_bundle(chain_id, entry_point)).cloned().collect()
+    }
+
+    /// Select the operations of the next bundle on the chain and the entrypoint, by the priority
+    /// fee w/ the lowest nonce of each sender, under the gas limit of the bundle
+    pub fn select_bundle(
+        &self,
+        chain_id: u64,
+        entry_point: Address,
+        max_bundle_gas: U256,
+    ) -> Vec<MempoolEntry> {
+        // Only the lowest nonce of each sender is eligible, keeping the nonce order
+        let mut heads: HashMap<Address, &MempoolEntry> = HashMap::new();
+        for entry in self.entries.values().filter(|e| e.is_same_bundle(chain_id, entry_point)) {
+            let sender = entry.user_operation.sender();
+            match heads.get(&sender) {
+                Some(head) if head.user_operation.nonce() <= entry.user_operation.nonce() => {}
+                _ => {
+                    heads.insert(sender, entry);
+                }
+            }
+        }
+
+        // Order by the priority fee, w/ the hash as the tiebreaker
+        let mut heads = heads.into_values().collect::<Vec<_>>();
+        heads.sort_by(|a, b| {
+            b.user_operation
+                .max_priority_fee_per_gas()
+                .cmp(&a.user_operation.max_priority_fee_per_gas())
+                .then(a.hash.cmp(&b.hash))
+        });
+
+        let mut bundle = vec![];
+        let mut bundle_gas = U256::ZERO;
+        let mut entity_counts: HashMap<Address, usize> = HashMap::new();
+
+        for entry in heads {
+            let entities = entry.user_operation.entities();
+
+            // Skip the banned entities, and the throttled entities over the limit
+            let is_excluded = entities.iter().any(|entity| match self.reputation.status(*entity) {
+                ReputationStatus::Ok => false,
+                ReputationStatus::Throttled => {
+                    entity_counts.get(entity).copied().unwrap_or_default() >=
+                        THROTTLED_ENTITY_BUNDLE_COUNT
+                }
+                ReputationStatus::Banned => true,
+            });
+            if is_excluded {
+                continue;
+            }
+
+            // Skip the operations over the gas limit of the bundle
+            let gas_limit = entry.user_operation.gas_limit();
+            if bundle_gas.saturating_add(gas_limit) > max_bundle_gas {
+                continue;
+            }
+
+            bundle_gas = bundle_gas.saturating_add(gas_limit);
+            for entity in entities {
+                *entity_counts.entry(entity).or_default() += 1;
+            }
+            bundle.push(entry.clone());
+        }
+
+        bundle
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Check if the new fee is bumped enough from the old fee
+fn is_fee_bumped(old: U256, new: U256) -> bool {
+    new.saturating_sub(old).saturating_mul(U256::from(100)) >=
+        old.saturating_mul(U256::from(REPLACEMENT_FEE_BUMP_PERCENT))
+}
+
+/// Get the current unix timestamp in seconds
+pub fn now_seconds() -> u64 {
+    chrono::Utc::now().timestamp().try_into().unwrap_or_default()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+
+    const CHAIN_ID: u64 = 1;
+
+    fn entry(
+        sender: u8,
+        nonce: u64,
+        priority_fee: u64,
+        paymaster: Option<Address>,
+    ) -> MempoolEntry {
+        let user_operation = UserOperation {
+            sender: Address::repeat_byte(sender),
+            nonce: U256::from(nonce),
+            init_code: Bytes::default(),
+            call_data: Bytes::default(),
+            call_gas_limit: U256::from(100_000),
+            verification_gas_limit: U256::from(100_000),
+            pre_verification_gas: U256::from(50_000),
+            max_fee_per_gas: U256::from(priority_fee * 2),
+            max_priority_fee_per_gas: U256::from(priority_fee),
+            paymaster_and_data: paymaster.map(|p| Bytes::from(p.to_vec())).unwrap_or_default(),
+            signature: Bytes::default(),
+        };
+
+        MempoolEntry::new(CHAIN_ID, Address::ZERO, MempoolUserOperation::Default(user_operation))
+    }
+
+    #[test]
+    fn test_select_bundle_by_priority_fee_and_nonce() {
+        let mut mempool = Mempool::default();
+
+        let low = entry(1, 0, 1, None);
+        let high_next = entry(2, 1, 5, None);
+        let high = entry(2, 0, 3, None);
+        mempool.add(low.clone()).unwrap();
+        mempool.add(high_next.clone()).unwrap();
+        mempool.add(high.clone()).unwrap();
+
+        let bundle = mempool.select_bundle(CHAIN_ID, Address::ZERO, U256::from(10_000_000));
+        assert_eq!(bundle, vec![high, low]);
+    }
+
+    #[test]
+    fn test_select_bundle_under_gas_limit() {
+        let mut mempool = Mempool::default();
+
+        let first = entry(1, 0, 2, None);
+        mempool.add(first.clone()).unwrap();
+        mempool.add(entry(2, 0, 1, None)).unwrap();
+
+        // Each operation w/o the paymaster uses 250k gas
+        let bundle = mempool.select_bundle(CHAIN_ID, Address::ZERO, U256::from(400_000));
+        assert_eq!(bundle, vec![first]);
+    }
+
+    #[test]
+    fn test_select_bundle_skips_banned_paymaster() {
+        let mut mempool = Mempool::default();
+        let paymaster = Address::repeat_byte(9);
+
+        mempool.add(entry(1, 0, 1, Some(paymaster))).unwrap();
+        mempool.reputation.penalize(paymaster);
+
+        let bundle = mempool.select_bundle(CHAIN_ID, Address::ZERO, U256::from(10_000_000));
+        assert!(bundle.is_empty());
+
+        // New operations of the banned paymaster are rejected
+        assert!(mempool.add(entry(2, 0, 1, Some(paymaster))).is_err());
+    }
+
+    #[test]
+    fn test_add_replacement() {
+        let mut mempool = Mempool::default();
+
+        let original = entry(1, 0, 10, None);
+        mempool.add(original.clone()).unwrap();
+
+        // Same fees are rejected
+        assert!(mempool.add(entry(1, 0, 10, None)).is_err());
+
+        // Bumped fees replace the original
+        let replacement = entry(1, 0, 11, None);
+        assert_eq!(mempool.add(replacement.clone()).unwrap(), Some(original.hash));
+        assert!(!mempool.contains(&original.hash));
+        assert!(mempool.contains(&replacement.hash));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let mut mempool = Mempool::default();
+
+        let mut old = entry(1, 0, 1, None);
+        old.added_at = 1_000;
+        let mut new = entry(2, 0, 1, None);
+        new.added_at = 1_000 + MAX_OPERATION_AGE_SECONDS;
+        mempool.add(old.clone()).unwrap();
+        mempool.add(new.clone()).unwrap();
+
+        let evicted = mempool.evict_expired(1_001 + MAX_OPERATION_AGE_SECONDS);
+        assert_eq!(evicted, vec![old.hash]);
+        assert!(mempool.contains(&new.hash));
+        assert_eq!(mempool.bundle_keys(), vec![(CHAIN_ID, Address::ZERO)]);
+    }
+
+    #[test]
+    fn test_gas_limit_and_fee_bump_saturate() {
+        let mut op = entry(1, 0, 1, None);
+        if let MempoolUserOperation::Default(op) = &mut op.user_operation {
+            op.call_gas_limit = U256::MAX;
+            op.verification_gas_limit = U256::MAX;
+        }
+        assert_eq!(op.user_operation.gas_limit(), U256::MAX);
+
+        // The fees at the max never overflow, nor count as bumped
+        assert!(!is_fee_bumped(U256::MAX, U256::MAX));
+        assert!(is_fee_bumped(U256::from(10), U256::from(11)));
+        assert!(!is_fee_bumped(U256::from(11), U256::from(10)));
+    }
+}
@@ -26,17 +26,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::config::NodeArgs;
+use crate::{
+    balance::{BalanceMonitor, BALANCE_ALERT_TTL_SECONDS},
+    config::NodeArgs,
+    mempool::{now_seconds, Mempool, MempoolEntry, MempoolUserOperation, MAX_OPERATION_ATTEMPTS},
+    nonce::NonceManager,
+    tracker::{
+        bump_fees, InFlightTransaction, TransactionStatus, TransactionTracker, MAX_REPLACEMENTS,
+    },
+};
 use alloy::{
-    eips::{BlockId, BlockNumberOrTag},
+    eips::eip2718::Encodable2718,
     network::{EthereumWallet, TransactionBuilder, TxSigner},
-    primitives::{Address, Bytes, B256},
-    providers::{ext::DebugApi, Provider},
-    rpc::types::trace::geth::{
-        GethDebugTracerConfig, GethDebugTracerType, GethDebugTracingCallOptions,
-        GethDebugTracingOptions, GethDefaultTracingOptions,
-    },
-    signers::aws::AwsSigner,
+    primitives::{Address, Bytes, B256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::{Filter, TransactionReceipt, TransactionRequest},
+    sol_types::SolEvent,
+    transports::{BoxTransport, RpcError, TransportErrorKind},
 };
 use backon::{ExponentialBuilder, Retryable};
 use eyre::{eyre, ContextCompat, Result};
@@ -46,48 +52,231 @@ use lightdotso_contracts::{
     },
     entrypoint_v060::{get_entrypoint_v060, EntryPointV060},
     entrypoint_v070::{get_entrypoint_v070, EntryPointV070},
-    provider::{get_provider, get_provider_with_wallet},
-    tracer::{ExecutorTracerResult, EXECUTOR_TRACER},
+    provider::get_provider,
     types::{PackedUserOperation, UserOperation},
 };
 use lightdotso_jsonrpsee::{
     handle_response,
     types::{Request, Response},
 };
-use lightdotso_signer::connect::connect_to_kms;
-use lightdotso_tracing::tracing::info;
+use lightdotso_kafka::{
+    get_producer,
+    rdkafka::producer::FutureProducer,
+    topics::node::{produce_node_executor_balance_message, produce_node_status_message},
+    types::node::{NodeExecutorBalanceMessage, NodeStatusMessage},
+};
+use lightdotso_redis::{
+    get_redis_client,
+    query::{
+        executor::set_executor_balance_alert,
+        mempool::{get_mempool_operations, remove_mempool_operation, set_mempool_operation},
+    },
+    redis::Client,
+};
+use lightdotso_signer::{backend::BackendSigner, config::SignerArgs, pool::ExecutorPool};
+use lightdotso_simulator::validation::{validate_user_operation, ValidationRequest};
+use lightdotso_tracing::tracing::{info, warn};
 use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{Mutex, OnceCell},
+    time::sleep,
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The interval of bundling the pending operations and evicting the expired ones
+const BUNDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The max number of bundles submitted in a row, after dropping the failed operations
+const MAX_BUNDLE_ATTEMPTS: usize = 3;
+
+/// The interval of polling the receipts of the transaction in flight
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The gas limit of the plain transfer cancelling a transaction
+const TRANSFER_GAS_LIMIT: u64 = 21_000;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The bundle transaction included on-chain
+#[derive(Clone, Debug)]
+pub struct Bundle {
+    /// The hash of the `handleOps` transaction
+    pub transaction_hash: B256,
+    /// The hashes of the user operations in the bundle
+    pub user_operation_hashes: Vec<B256>,
+}
+
+/// The error of the operation which failed the bundle
+#[derive(Clone, Debug)]
+pub struct FailedOpError {
+    /// The index of the operation in the bundle
+    pub op_index: usize,
+    /// The reason of the entrypoint, e.g. `AA21 didn't pay prefund`
+    pub reason: String,
+}
+
+impl fmt::Display for FailedOpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FailedOp: {} {}", self.op_index, self.reason)
+    }
+}
+
+impl std::error::Error for FailedOpError {}
+
+// -----------------------------------------------------------------------------
+// Node
+// -----------------------------------------------------------------------------
 
 #[derive(Clone)]
-pub struct Node {}
+pub struct Node {
+    /// The max gas of the operations in a bundle
+    max_bundle_gas: u64,
+    /// The mempool of the validated user operations
+    mempool: Arc<Mutex<Mempool>>,
+    /// The signer backend of the executor keys
+    signer_args: SignerArgs,
+    /// The max number of the executor keys locked per chain
+    executor_pool_size: usize,
+    /// The pools of the executor keys per chain, submitting the bundles in parallel
+    /// Each pool is connected once, w/o holding the lock of the other chains
+    executor_pools: Arc<Mutex<HashMap<u64, Arc<OnceCell<Arc<ExecutorPool>>>>>>,
+    /// The nonce manager of the executor keys
+    nonce_manager: Arc<Mutex<NonceManager>>,
+    /// The monitor of the balances of the executor keys
+    balance_monitor: Arc<Mutex<BalanceMonitor>>,
+    /// The redis client persisting the mempool
+    redis_client: Option<Arc<Client>>,
+    /// The kafka client reporting the status of the transactions
+    kafka_client: Option<Arc<FutureProducer>>,
+    /// The tracker of the transactions in flight per executor key
+    tracker: Arc<Mutex<TransactionTracker>>,
+    /// The chains and the entrypoints w/ a bundle in flight
+    bundling: Arc<Mutex<HashSet<(u64, Address)>>>,
+}
 
 impl Node {
-    pub async fn new(_args: &NodeArgs) -> Result<Self> {
+    pub async fn new(args: &NodeArgs) -> Result<Self> {
         info!("Node new, starting");
 
-        Ok(Self {})
+        // Create the redis client
+        let redis_client: Option<Arc<Client>> =
+            get_redis_client().map_or_else(|_e| None, |client| Some(Arc::new(client)));
+
+        // Create the kafka client
+        let kafka_client: Option<Arc<FutureProducer>> =
+            get_producer().map_or_else(|_e| None, |client| Some(Arc::new(client)));
+
+        // Create the async redis connection of the nonce manager
+        let redis_con = match &redis_client {
+            Some(client) => client.get_multiplexed_async_connection().await.ok(),
+            None => None,
+        };
+
+        let node = Self {
+            max_bundle_gas: args.max_bundle_gas,
+            mempool: Arc::new(Mutex::new(Mempool::default())),
+            signer_args: args.signer.clone(),
+            executor_pool_size: args.executor_pool_size,
+            executor_pools: Arc::new(Mutex::new(HashMap::new())),
+            nonce_manager: Arc::new(Mutex::new(NonceManager::new(redis_con))),
+            balance_monitor: Arc::new(Mutex::new(BalanceMonitor::new(U256::from(
+                args.executor_min_balance,
+            )))),
+            redis_client,
+            kafka_client,
+            tracker: Arc::new(Mutex::new(TransactionTracker::default())),
+            bundling: Arc::new(Mutex::new(HashSet::new())),
+        };
+
+        // Restore the persisted mempool
+        {
+            let mut mempool = node.mempool.lock().await;
+            node.restore_mempool(&mut mempool);
+            info!("mempool: {}", mempool.len());
+        }
+
+        Ok(node)
     }
 
+    /// Start the periodic task bundling the pending operations of the mempool, w/ the bundles
+    /// resubmitted until final, and evicting the expired operations
     pub async fn run(&self) {
         info!("Node run, starting");
-    }
 
-    pub async fn get_signer(&self) -> Result<AwsSigner> {
-        // Connect to KMS
-        let signer = connect_to_kms().await?;
+        let node = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(BUNDLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                node.process_mempool().await;
+            }
+        });
+    }
 
-        // Check if the address matches one of the offchain verifier address
-        let signer_address = signer.address();
+    /// Evict the expired operations, then spawn a bundle for each of the chains and the
+    /// entrypoints w/ the pending operations and w/o a bundle in flight
+    async fn process_mempool(&self) {
+        let (evicted, keys) = {
+            let mut mempool = self.mempool.lock().await;
+            (mempool.evict_expired(now_seconds()), mempool.bundle_keys())
+        };
+        for hash in evicted {
+            warn!("Evicted the expired user operation: {:?}", hash);
+            self.unpersist_mempool_entry(hash);
+        }
 
-        // Log the address
-        info!("node signer address: {:?}", signer_address);
+        for (chain_id, entry_point) in keys {
+            if !self.bundling.lock().await.insert((chain_id, entry_point)) {
+                continue;
+            }
 
-        // Return an error if the address is not one of the offchain verifier addresses
-        if !LIGHT_OFFCHAIN_VERIFIER_ADDRESSES.contains(&signer_address) {
-            return Err(eyre!("Address is not one of the offchain verifier addresses"));
+            let node = self.clone();
+            tokio::spawn(async move {
+                match node.bundle(chain_id, entry_point).await {
+                    Ok(bundle) => info!("bundle: {:?}", bundle),
+                    Err(err) => warn!("Failed to bundle on chain {}: {:?}", chain_id, err),
+                }
+                node.bundling.lock().await.remove(&(chain_id, entry_point));
+            });
         }
+    }
+
+    /// Get the pool of the executor keys of the chain, locking the KMS keys on the first use
+    pub async fn get_executor_pool(&self, chain_id: u64) -> Result<Arc<ExecutorPool>> {
+        let cell = self.executor_pools.lock().await.entry(chain_id).or_default().clone();
+
+        // Connect outside of the lock of the pools, once per chain
+        let pool = cell
+            .get_or_try_init(|| async {
+                // The other backends have a single key, e.g. for the local development
+                let pool = if self.signer_args.is_kms() {
+                    ExecutorPool::connect(
+                        chain_id,
+                        self.signer_args.signer_kms_key_ids.clone().unwrap_or_default(),
+                        self.executor_pool_size,
+                    )
+                    .await?
+                } else {
+                    ExecutorPool::from_signers(chain_id, vec![self.signer_args.connect().await?])?
+                };
+                info!("chain_id: {}, executors: {:?}", chain_id, pool.addresses());
 
-        Ok(signer)
+                Ok::<_, eyre::Report>(Arc::new(pool))
+            })
+            .await?;
+
+        Ok(pool.clone())
     }
 
     pub async fn simulate_user_operation_with_backon(
@@ -155,75 +344,23 @@ impl Node {
         res
     }
 
-    // From: https://github.com/silius-rs/silius/blob/62cff148f386283bc44114ec9d545eae427489f2/crates/mempool/src/estimate.rs#L116-129
-    // License: Apache-2.0
-
-    /// Simulate a user operation on the node w/ `debug_traceCall`
-    /// Only for EntryPoint v0.6.0
-    /// Parses the user operation event and the user operation revert event from the trace for next.
+    /// Simulate the validation of a user operation w/ the revm tracer of the ERC-7562 rules
+    /// Returns whether the user operation is safe to bundle
     pub async fn simulate_user_operation_with_tracer(
         &self,
         chain_id: u64,
         entry_point: Address,
         user_operation: &UserOperation,
     ) -> Result<bool> {
-        // Get the entrypoint
-        let entrypoint = get_entrypoint_v060(chain_id, entry_point).await?;
-
-        // From: https://github.com/silius-rs/silius/blob/f695b54cbbabf6b3f22f7af8918a2d6d83ca8960/crates/contracts/src/entry_point.rs#L139-L175
-        // License: Apache-2.0
-
-        // Debug trace call
-        let call = entrypoint.simulateHandleOp(
-            user_operation.clone().into(),
-            Address::ZERO,
-            Bytes::default(),
-        );
-        let tx = call.into_transaction_request();
-        // tx.(Address::ZERO);
-        // tx(user_operation.clone().max_fee_per_gas);
-        // tx.set_gas(u64::MAX);
-
-        // Get provider
-        let (provider, _) = get_provider(chain_id).await?;
-
-        // Get the geth trace
-        let trace = provider
-            .debug_trace_call(
-                tx,
-                BlockId::Number(BlockNumberOrTag::Latest),
-                GethDebugTracingCallOptions::default().with_tracing_options(
-                    GethDebugTracingOptions {
-                        config: GethDefaultTracingOptions::default(),
-                        tracer: Some(GethDebugTracerType::JsTracer(EXECUTOR_TRACER.into())),
-                        tracer_config: GethDebugTracerConfig::default(),
-                        timeout: None,
-                    },
-                ),
-            )
-            .await
-            .map_err(|e| eyre!("Failed to debug trace call: {:?}", e))?;
-
-        let tracer_result: ExecutorTracerResult =
-            ExecutorTracerResult::try_from(trace).map_err(|e| eyre!(e))?;
-        info!("tracer_result: {:?}", tracer_result);
-
-        // let user_op_revert_event = tracer_result
-        //     .user_op_revert_event
-        //     .as_ref()
-        //     .and_then(|e| parse_user_op_event::<UserOperationRevertReason>(e).ok());
-        // info!("user_op_revert_event: {:?}", user_op_revert_event);
-
-        // let user_op_event = tracer_result
-        //     .user_op_event
-        //     .as_ref()
-        //     .ok_or(eyre!("Estimate trace simulate handle op user op event not found"))?;
-        // let user_op_event = parse_user_op_event::<UserOperationRevertReason>(user_op_event)?;
-        // info!("user_op_event: {:?}", user_op_event);
-
-        // Ok(user_op_event.success)
-
-        Ok(true)
+        let report = validate_user_operation(ValidationRequest::from_user_operation(
+            chain_id,
+            entry_point,
+            user_operation,
+        ))
+        .await?;
+        info!("report: {:?}", report);
+
+        Ok(report.is_valid())
     }
 
     pub async fn send_user_operation_with_backon(
@@ -337,77 +474,20 @@ impl Node {
         Ok(res)
     }
 
+    /// Send the user operation to the bundler mempool, returning the user operation hash once
+    /// admitted
     pub async fn raw_send_user_operation(
         &self,
         chain_id: u64,
         user_operation: &UserOperation,
     ) -> Result<B256> {
-        // Get the entrypoint
-        let entry_point = get_entrypoint_v060(chain_id, *ENTRYPOINT_V060_ADDRESS).await?;
-
-        // Get signer
-        let signer = self.get_signer().await?;
-
-        // Get signer address
-        let signer_address = signer.address();
-
-        // Get the wallet
-        let wallet = EthereumWallet::from(signer);
-
-        // Get the provider with the wallet
-        let (provider, _) = get_provider_with_wallet(chain_id, wallet).await?;
-
-        // Set the transaction request
-        let tx_request = entry_point
-            .handleOps(vec![user_operation.clone().into()], signer_address)
-            .into_transaction_request();
-
-        // Set the transaction
-        let tx = tx_request.with_chain_id(chain_id);
-
-        // Send the transaction
-        let builder = match provider.send_transaction(tx).await {
-            Ok(b) => b,
-            Err(e) => {
-                // Try to decode the error
-                if let Some(err_payload) = e.as_error_resp() {
-                    info!("err_payload: {:?}", err_payload);
-                    match err_payload
-                        .as_decoded_error::<EntryPointV060::EntryPointV060Errors>(false)
-                    {
-                        Some(decoded_error) => match decoded_error {
-                            EntryPointV060::EntryPointV060Errors::FailedOp(value) => {
-                                return Err(eyre!("FailedOp: {:?}", value.reason));
-                            }
-                            EntryPointV060::EntryPointV060Errors::SignatureValidationFailed(
-                                value,
-                            ) => {
-                                return Err(eyre!(
-                                    "SignatureValidationFailed: {:?}",
-                                    value.aggregator
-                                ));
-                            }
-                            _ => {
-                                return Err(eyre!("Unrecognized custom error: {:?}", err_payload));
-                            }
-                        },
-                        None => {
-                            return Err(eyre!("Unrecognized custom error: {:?}", err_payload));
-                        }
-                    }
-                } else {
-                    return Err(e.into());
-                }
-            }
-        };
-        let node_hash = *builder.tx_hash();
-
-        // Wait for the transaction to be included and get the receipt.
-        let receipt = builder.get_receipt().await?;
-
-        info!("receipt: {:?}", receipt);
+        let entry = MempoolEntry::new(
+            chain_id,
+            *ENTRYPOINT_V060_ADDRESS,
+            MempoolUserOperation::Default(user_operation.clone()),
+        );
 
-        Ok(node_hash)
+        self.send_to_mempool(entry).await
     }
 
     pub async fn send_packed_user_operation(
@@ -447,87 +527,732 @@ impl Node {
         Ok(res)
     }
 
+    /// Send the packed user operation to the bundler mempool, returning the user operation hash
+    /// once admitted
     pub async fn raw_send_packed_user_operation(
         &self,
         chain_id: u64,
         packed_user_operation: &PackedUserOperation,
     ) -> Result<B256> {
-        // Get the entrypoint
-        let entry_point = get_entrypoint_v070(chain_id, *ENTRYPOINT_V070_ADDRESS).await?;
+        let entry = MempoolEntry::new(
+            chain_id,
+            *ENTRYPOINT_V070_ADDRESS,
+            MempoolUserOperation::Packed(packed_user_operation.clone()),
+        );
+
+        self.send_to_mempool(entry).await
+    }
+
+    /// Validate and add the user operation to the mempool, bundled by the periodic task of `run`
+    async fn send_to_mempool(&self, entry: MempoolEntry) -> Result<B256> {
+        let hash = entry.hash;
+
+        // Validate and add the user operation, unless already pending
+        if !self.mempool.lock().await.contains(&hash) {
+            self.validate_mempool_entry(&entry).await?;
 
-        // Get signer
-        let signer = self.get_signer().await?;
+            let replaced = self.mempool.lock().await.add(entry.clone())?;
+            if let Some(replaced) = replaced {
+                self.unpersist_mempool_entry(replaced);
+            }
+            self.persist_mempool_entry(&entry);
+        }
+
+        Ok(hash)
+    }
+
+    /// Validate the user operation w/ the gas estimation of `handleOps` of it alone and the
+    /// tracing of the validation rules
+    async fn validate_mempool_entry(&self, entry: &MempoolEntry) -> Result<()> {
+        let (provider, _) = get_provider(entry.chain_id).await?;
+
+        let tx = self
+            .handle_ops_transaction_request(
+                entry.chain_id,
+                entry.entry_point,
+                std::slice::from_ref(entry),
+                Address::ZERO,
+            )
+            .await?;
+
+        provider.estimate_gas(&tx).await.map_err(decode_entrypoint_error)?;
+
+        // Trace the validation w/ the ERC-7562 rules
+        let request = match &entry.user_operation {
+            MempoolUserOperation::Default(op) => {
+                ValidationRequest::from_user_operation(entry.chain_id, entry.entry_point, op)
+            }
+            MempoolUserOperation::Packed(op) => {
+                ValidationRequest::from_packed_user_operation(entry.chain_id, entry.entry_point, op)
+            }
+        };
+        let report = validate_user_operation(request).await?;
+        info!("report: {:?}", report);
+
+        if !report.is_valid() {
+            let rules = report.violations.iter().map(|v| v.rule()).collect::<Vec<_>>();
+            return Err(eyre!("Validation rules {:?} violated: {:?}", rules, report.violations));
+        }
+
+        Ok(())
+    }
+
+    /// Bundle the pending operations on the chain and the entrypoint into a `handleOps`
+    /// transaction, dropping the failed operations and resubmitting the rest
+    pub async fn bundle(&self, chain_id: u64, entry_point: Address) -> Result<Option<Bundle>> {
+        // The bundles are in flight in parallel, each w/ its own executor key and nonce
+        for attempt in 0..MAX_BUNDLE_ATTEMPTS {
+            // Take the operations of the bundle out of the mempool
+            let entries = {
+                let mut mempool = self.mempool.lock().await;
+                mempool.reputation.decay_hourly();
+
+                let entries =
+                    mempool.select_bundle(chain_id, entry_point, U256::from(self.max_bundle_gas));
+                for entry in &entries {
+                    mempool.remove(&entry.hash);
+                }
+                entries
+            };
+            info!("attempt: {}, entries: {}", attempt, entries.len());
+
+            if entries.is_empty() {
+                return Ok(None);
+            }
+
+            let err = match self.submit_bundle(chain_id, entry_point, &entries).await {
+                Ok(transaction_hash) => {
+                    let mut mempool = self.mempool.lock().await;
+                    for entry in &entries {
+                        for entity in entry.user_operation.entities() {
+                            mempool.reputation.add_included(entity);
+                        }
+                        self.unpersist_mempool_entry(entry.hash);
+                    }
+
+                    return Ok(Some(Bundle {
+                        transaction_hash,
+                        user_operation_hashes: entries.iter().map(|e| e.hash).collect(),
+                    }));
+                }
+                Err(err) => err,
+            };
+            warn!("Failed to submit the bundle: {:?}", err);
+
+            let mut mempool = self.mempool.lock().await;
+            match err.downcast_ref::<FailedOpError>().cloned() {
+                // Drop the failed operation, penalizing its entity at fault, and resubmit the rest
+                Some(failed_op) => {
+                    for (index, entry) in entries.into_iter().enumerate() {
+                        if index != failed_op.op_index {
+                            mempool.restore(entry);
+                            continue;
+                        }
+
+                        let entity = if failed_op.reason.starts_with("AA1") {
+                            entry.user_operation.factory()
+                        } else if failed_op.reason.starts_with("AA3") {
+                            entry.user_operation.paymaster()
+                        } else {
+                            None
+                        };
+                        if let Some(entity) = entity {
+                            mempool.reputation.penalize(entity);
+                        }
+                        self.unpersist_mempool_entry(entry.hash);
+                    }
+                }
+                // Put back the operations for the next bundle, dropping the ones out of attempts
+                None => {
+                    for mut entry in entries {
+                        entry.attempts += 1;
+                        if entry.attempts >= MAX_OPERATION_ATTEMPTS {
+                            self.unpersist_mempool_entry(entry.hash);
+                        } else {
+                            self.persist_mempool_entry(&entry);
+                            mempool.restore(entry);
+                        }
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        Err(eyre!("Failed to submit the bundle after {} attempts", MAX_BUNDLE_ATTEMPTS))
+    }
+
+    /// Submit the `handleOps` transaction of the operations w/ the signer as the beneficiary,
+    /// tracking it until final and reporting the status
+    async fn submit_bundle(
+        &self,
+        chain_id: u64,
+        entry_point: Address,
+        entries: &[MempoolEntry],
+    ) -> Result<B256> {
+        // Get the executor pool
+        let pool = self.get_executor_pool(chain_id).await?;
+
+        // Get the provider
+        let (provider, _) = get_provider(chain_id).await?;
+
+        // Check the balances of the executors, skipping the ones under the threshold
+        self.monitor_executor_balances(&provider, &pool).await;
+        let low_executors = self.balance_monitor.lock().await.low_executors(chain_id);
+
+        // Get an executor w/ enough balance, w/ one of the offchain verifier addresses for the KMS
+        // keys, while the other backends have the keys of their own
+        let is_kms = self.signer_args.is_kms();
+        let executor = pool
+            .acquire_filtered(|address| {
+                (!is_kms || LIGHT_OFFCHAIN_VERIFIER_ADDRESSES.contains(&address)) &&
+                    !low_executors.contains(&address)
+            })
+            .await?;
 
         // Get signer address
-        let signer_address = signer.address();
+        let signer_address = executor.address();
 
         // Get the wallet
-        let wallet = EthereumWallet::from(signer);
-
-        // Get the provider with the wallet
-        let (provider, _) = get_provider_with_wallet(chain_id, wallet).await?;
+        let wallet = EthereumWallet::from(BackendSigner(executor.signer.clone()));
 
         // Set the transaction request
-        let tx_request = entry_point
-            .handleOps(vec![packed_user_operation.clone().into()], signer_address)
-            .into_transaction_request();
-
-        // Set the transaction
-        let tx = tx_request.with_chain_id(chain_id);
-
-        // Send the transaction
-        let builder = match provider.send_transaction(tx).await {
-            Ok(b) => b,
-            Err(e) => {
-                // Try to decode the error
-                if let Some(err_payload) = e.as_error_resp() {
-                    info!("err_payload: {:?}", err_payload);
-                    match err_payload
-                        .as_decoded_error::<EntryPointV070::EntryPointV070Errors>(false)
+        let tx_request = self
+            .handle_ops_transaction_request(chain_id, entry_point, entries, signer_address)
+            .await?
+            .with_from(signer_address)
+            .with_chain_id(chain_id);
+
+        // Fill the transaction
+        let gas_limit =
+            provider.estimate_gas(&tx_request).await.map_err(decode_entrypoint_error)?;
+        let fees = provider.estimate_eip1559_fees(None).await?;
+        let from_block = provider.get_block_number().await?;
+
+        // Reserve the nonce, keeping it for the replacements, after recovering from the gaps
+        let pending_nonce = provider.get_transaction_count(signer_address).pending().await?;
+        let gaps =
+            self.nonce_manager.lock().await.recover(chain_id, signer_address, pending_nonce).await;
+        if let Err(err) = self.fill_nonce_gaps(&provider, &wallet, chain_id, &gaps).await {
+            warn!("Failed to fill the nonce gaps: {:?}", err);
+        }
+        let nonce =
+            self.nonce_manager.lock().await.reserve(chain_id, signer_address, pending_nonce).await;
+
+        let mut in_flight = InFlightTransaction {
+            chain_id,
+            executor: signer_address,
+            nonce,
+            gas_limit,
+            max_fee_per_gas: fees.max_fee_per_gas,
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+            transaction_hashes: vec![],
+            raw_transaction: Bytes::default(),
+            user_operation_hashes: entries.iter().map(|entry| entry.hash).collect(),
+            from_block,
+            sent_at: Instant::now(),
+            replacements: 0,
+        };
+
+        // Send the transaction, releasing the nonce if never broadcast
+        if let Err(err) = self.sign_and_send(&provider, &wallet, &tx_request, &mut in_flight).await
+        {
+            self.nonce_manager.lock().await.release(chain_id, signer_address, nonce).await;
+            return Err(err);
+        }
+
+        // Wait for the transaction to be final
+        let res = self
+            .wait_for_transaction(&provider, &wallet, &tx_request, entry_point, &mut in_flight)
+            .await;
+        self.tracker.lock().await.remove(chain_id, signer_address);
+        self.nonce_manager.lock().await.confirm(chain_id, signer_address, nonce);
+        let (status, transaction_hash) = res?;
+        info!("status: {:?}, transaction_hash: {:?}", status, transaction_hash);
+
+        // Report the status
+        self.produce_node_status(&in_flight, status, transaction_hash).await;
+
+        match (status, transaction_hash) {
+            (TransactionStatus::Included | TransactionStatus::IncludedByOthers, Some(hash)) => {
+                Ok(hash)
+            }
+            _ => Err(eyre!("Bundle transaction of nonce {} {}", nonce, status.as_str())),
+        }
+    }
+
+    /// Sign and send the transaction at the nonce and the fees in flight
+    async fn sign_and_send(
+        &self,
+        provider: &RootProvider<BoxTransport>,
+        wallet: &EthereumWallet,
+        tx_request: &TransactionRequest,
+        in_flight: &mut InFlightTransaction,
+    ) -> Result<()> {
+        let tx = tx_request
+            .clone()
+            .with_nonce(in_flight.nonce)
+            .with_gas_limit(in_flight.gas_limit)
+            .with_max_fee_per_gas(in_flight.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(in_flight.max_priority_fee_per_gas);
+
+        let envelope = tx.build(wallet).await?;
+        let raw_transaction: Bytes = envelope.encoded_2718().into();
+
+        provider.send_raw_transaction(&raw_transaction).await.map_err(decode_entrypoint_error)?;
+        info!("sent: {:?}, nonce: {}", envelope.tx_hash(), in_flight.nonce);
+
+        in_flight.transaction_hashes.push(*envelope.tx_hash());
+        in_flight.raw_transaction = raw_transaction;
+        in_flight.sent_at = Instant::now();
+        self.tracker.lock().await.insert(in_flight.clone());
+
+        Ok(())
+    }
+
+    /// Wait for the transaction in flight to be final, rebroadcasting it if dropped and replacing
+    /// it w/ the bumped fees if stuck
+    /// The attempt reverting on the user operations landed elsewhere is cancelled w/ a plain
+    /// transfer, waiting for the cancel to be final as well
+    async fn wait_for_transaction(
+        &self,
+        provider: &RootProvider<BoxTransport>,
+        wallet: &EthereumWallet,
+        tx_request: &TransactionRequest,
+        entry_point: Address,
+        in_flight: &mut InFlightTransaction,
+    ) -> Result<(TransactionStatus, Option<B256>)> {
+        let mut tx_request = tx_request.clone();
+        // The status of the user operations landed elsewhere, once cancelled
+        let mut cancelled: Option<(TransactionStatus, Option<B256>)> = None;
+
+        loop {
+            // Poll the receipts of all of the attempts until stuck
+            while !in_flight.is_stuck() {
+                if let Some(receipt) = get_receipt_of_attempts(provider, in_flight).await? {
+                    return Ok(cancelled
+                        .unwrap_or((receipt_status(&receipt), Some(receipt.transaction_hash))));
+                }
+                sleep(RECEIPT_POLL_INTERVAL).await;
+            }
+            warn!("Stuck transaction: {:?}", in_flight);
+
+            // The nonce consumed by the cancel or one of the attempts
+            if let Some(cancelled) = cancelled {
+                let nonce = provider.get_transaction_count(in_flight.executor).latest().await?;
+                if nonce > in_flight.nonce {
+                    return Ok(cancelled);
+                }
+            }
+
+            let fees = provider.estimate_eip1559_fees(None).await?;
+
+            if cancelled.is_none() {
+                // Check if the user operations landed in the bundle of another bundler
+                let landed =
+                    get_user_operation_transactions(provider, entry_point, in_flight).await?;
+                let included_by_others = landed
+                    .iter()
+                    .find(|hash| !in_flight.transaction_hashes.contains(hash))
+                    .copied();
+
+                // The nonce consumed w/o any of the attempts
+                let nonce = provider.get_transaction_count(in_flight.executor).latest().await?;
+                if nonce > in_flight.nonce {
+                    if let Some(receipt) = get_receipt_of_attempts(provider, in_flight).await? {
+                        return Ok((receipt_status(&receipt), Some(receipt.transaction_hash)));
+                    }
+                    if landed.len() == in_flight.user_operation_hashes.len() {
+                        return Ok((TransactionStatus::IncludedByOthers, included_by_others));
+                    }
+                    if !landed.is_empty() {
+                        return Ok((TransactionStatus::PartiallyIncludedByOthers, None));
+                    }
+                    return Ok((TransactionStatus::ReplacedByOthers, None));
+                }
+
+                // Cancel the attempt reverting on the user operations landed elsewhere
+                if let Some(hash) = included_by_others {
+                    let status = if landed.len() == in_flight.user_operation_hashes.len() {
+                        TransactionStatus::IncludedByOthers
+                    } else {
+                        TransactionStatus::PartiallyIncludedByOthers
+                    };
+                    cancelled = Some((
+                        status,
+                        (status == TransactionStatus::IncludedByOthers).then_some(hash),
+                    ));
+
+                    tx_request = TransactionRequest::default()
+                        .with_from(in_flight.executor)
+                        .with_to(in_flight.executor)
+                        .with_value(U256::ZERO)
+                        .with_chain_id(in_flight.chain_id);
+                    let (max_fee_per_gas, max_priority_fee_per_gas) = bump_fees(
+                        in_flight.max_fee_per_gas,
+                        in_flight.max_priority_fee_per_gas,
+                        fees.max_fee_per_gas,
+                        fees.max_priority_fee_per_gas,
+                    );
+                    in_flight.gas_limit = TRANSFER_GAS_LIMIT;
+                    in_flight.max_fee_per_gas = max_fee_per_gas;
+                    in_flight.max_priority_fee_per_gas = max_priority_fee_per_gas;
+                    if let Err(err) =
+                        self.sign_and_send(provider, wallet, &tx_request, in_flight).await
                     {
-                        Some(decoded_error) => match decoded_error {
-                            EntryPointV070::EntryPointV070Errors::FailedOp(value) => {
-                                return Err(eyre!(
-                                    "FailedOp: {:?} {:?}",
-                                    value.opIndex,
-                                    value.reason
-                                ));
-                            }
-                            EntryPointV070::EntryPointV070Errors::FailedOpWithRevert(value) => {
-                                return Err(eyre!(
-                                    "FailedOpWithRevert: {:?} {:?} {:?}",
-                                    value.opIndex,
-                                    value.reason,
-                                    value.inner
-                                ));
-                            }
-                            EntryPointV070::EntryPointV070Errors::PostOpReverted(value) => {
-                                return Err(eyre!("PostOpReverted: {:?}", value.returnData));
-                            }
-                            _ => {
-                                return Err(eyre!("Unrecognized custom error: {:?}", err_payload));
-                            }
-                        },
-                        None => {
-                            // If it's not a recognized custom error, log the payload and return a
-                            // generic error
-                            info!("Unrecognized custom error: {:?}", err_payload);
-                            return Err(eyre!("Unrecognized custom error"));
-                        }
+                        warn!("Failed to cancel the transaction: {:?}", err);
+                        in_flight.sent_at = Instant::now();
                     }
-                } else {
-                    // If it's not a custom error, return the original error
-                    return Err(e.into());
+
+                    continue;
                 }
             }
+
+            if in_flight.replacements >= MAX_REPLACEMENTS {
+                return Ok(cancelled.unwrap_or((TransactionStatus::Dropped, None)));
+            }
+
+            // Rebroadcast the attempt dropped from the mempool, if the fees still cover the network
+            let latest_hash = in_flight.transaction_hashes.last().copied().unwrap_or_default();
+            let is_known = provider.get_transaction_by_hash(latest_hash).await?.is_some();
+            if !is_known &&
+                in_flight.covers_fees(fees.max_fee_per_gas, fees.max_priority_fee_per_gas)
+            {
+                info!("Rebroadcasting the transaction: {:?}", latest_hash);
+                if let Err(err) = provider.send_raw_transaction(&in_flight.raw_transaction).await {
+                    warn!("Failed to rebroadcast the transaction: {:?}", err);
+                }
+                in_flight.sent_at = Instant::now();
+                continue;
+            }
+
+            // Replace the attempt w/ the bumped fees
+            let (max_fee_per_gas, max_priority_fee_per_gas) = bump_fees(
+                in_flight.max_fee_per_gas,
+                in_flight.max_priority_fee_per_gas,
+                fees.max_fee_per_gas,
+                fees.max_priority_fee_per_gas,
+            );
+            in_flight.max_fee_per_gas = max_fee_per_gas;
+            in_flight.max_priority_fee_per_gas = max_priority_fee_per_gas;
+            in_flight.replacements += 1;
+            info!(
+                "Replacing the transaction w/ the fees: {} {}",
+                max_fee_per_gas, max_priority_fee_per_gas
+            );
+
+            if let Err(err) = self.sign_and_send(provider, wallet, &tx_request, in_flight).await {
+                warn!("Failed to replace the transaction: {:?}", err);
+                in_flight.sent_at = Instant::now();
+            }
+        }
+    }
+
+    /// Fill the nonce gaps of the executor w/ the plain transfers to itself, unblocking the
+    /// transactions in flight above the gaps
+    async fn fill_nonce_gaps(
+        &self,
+        provider: &RootProvider<BoxTransport>,
+        wallet: &EthereumWallet,
+        chain_id: u64,
+        gaps: &[u64],
+    ) -> Result<()> {
+        if gaps.is_empty() {
+            return Ok(());
+        }
+
+        let executor = wallet.default_signer().address();
+        let fees = provider.estimate_eip1559_fees(None).await?;
+
+        for nonce in gaps {
+            let tx = TransactionRequest::default()
+                .with_from(executor)
+                .with_to(executor)
+                .with_value(U256::ZERO)
+                .with_chain_id(chain_id)
+                .with_nonce(*nonce)
+                .with_gas_limit(TRANSFER_GAS_LIMIT)
+                .with_max_fee_per_gas(fees.max_fee_per_gas)
+                .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+            let envelope = tx.build(wallet).await?;
+            let res = provider.send_raw_transaction(&envelope.encoded_2718()).await;
+            info!("filled gap: {:?}, nonce: {}", envelope.tx_hash(), nonce);
+
+            // Retry the gap on the next recovery
+            if let Err(err) = res {
+                warn!("Failed to fill the nonce gap {}: {:?}", nonce, err);
+                self.nonce_manager.lock().await.confirm(chain_id, executor, *nonce);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the balances of the executors of the pool, alerting the ones under the threshold
+    async fn monitor_executor_balances(
+        &self,
+        provider: &RootProvider<BoxTransport>,
+        pool: &ExecutorPool,
+    ) {
+        if !self.balance_monitor.lock().await.should_check(pool.chain_id) {
+            return;
+        }
+
+        for executor in pool.addresses() {
+            let balance = match provider.get_balance(executor).await {
+                Ok(balance) => balance,
+                Err(err) => {
+                    warn!("Failed to get the balance of {:?}: {:?}", executor, err);
+                    continue;
+                }
+            };
+
+            let (is_low, threshold) = {
+                let mut balance_monitor = self.balance_monitor.lock().await;
+                (
+                    balance_monitor.update(pool.chain_id, executor, balance),
+                    balance_monitor.threshold,
+                )
+            };
+            if is_low {
+                warn!("Low balance of {:?} on chain {}: {}", executor, pool.chain_id, balance);
+                self.produce_executor_balance_alert(pool.chain_id, executor, balance, threshold)
+                    .await;
+            }
+        }
+    }
+
+    /// Alert the low balance of the executor to kafka, once per the alert ttl
+    async fn produce_executor_balance_alert(
+        &self,
+        chain_id: u64,
+        executor: Address,
+        balance: U256,
+        threshold: U256,
+    ) {
+        let Some(kafka_client) = &self.kafka_client else {
+            return;
         };
-        let node_hash = *builder.tx_hash();
 
-        // Wait for the transaction to be included and get the receipt.
-        let receipt = builder.get_receipt().await?;
+        if let Some(redis_client) = &self.redis_client {
+            let res = redis_client.get_connection().and_then(|mut con| {
+                set_executor_balance_alert(
+                    &mut con,
+                    chain_id,
+                    &executor.to_string(),
+                    BALANCE_ALERT_TTL_SECONDS,
+                )
+            });
+            if let Ok(false) = res {
+                return;
+            }
+        }
 
-        info!("receipt: {:?}", receipt);
+        let msg = NodeExecutorBalanceMessage { chain_id, executor, balance, threshold };
+        if let Err(err) = produce_node_executor_balance_message(kafka_client.clone(), &msg).await {
+            warn!("Failed to produce the executor balance alert: {:?}", err);
+        }
+    }
 
-        Ok(node_hash)
+    /// Get all of the transactions in flight of the executor keys
+    pub async fn get_in_flight_transactions(&self) -> Vec<InFlightTransaction> {
+        self.tracker.lock().await.in_flight()
     }
+
+    /// Report the final status of the transaction to kafka
+    async fn produce_node_status(
+        &self,
+        in_flight: &InFlightTransaction,
+        status: TransactionStatus,
+        transaction_hash: Option<B256>,
+    ) {
+        let Some(kafka_client) = &self.kafka_client else {
+            return;
+        };
+
+        let msg = NodeStatusMessage {
+            chain_id: in_flight.chain_id,
+            executor: in_flight.executor,
+            nonce: in_flight.nonce,
+            transaction_hash,
+            user_operation_hashes: in_flight.user_operation_hashes.clone(),
+            status: status.as_str().to_string(),
+        };
+        if let Err(err) = produce_node_status_message(kafka_client.clone(), &msg).await {
+            warn!("Failed to produce the node status: {:?}", err);
+        }
+    }
+
+    /// Get the `handleOps` transaction request of the operations for the entrypoint version
+    async fn handle_ops_transaction_request(
+        &self,
+        chain_id: u64,
+        entry_point: Address,
+        entries: &[MempoolEntry],
+        beneficiary: Address,
+    ) -> Result<TransactionRequest> {
+        if entry_point == *ENTRYPOINT_V060_ADDRESS {
+            let entry_point = get_entrypoint_v060(chain_id, entry_point).await?;
+
+            let ops = entries
+                .iter()
+                .map(|entry| match &entry.user_operation {
+                    MempoolUserOperation::Default(op) => Ok(op.clone().into()),
+                    MempoolUserOperation::Packed(_) => {
+                        Err(eyre!("Packed user operation {:?} for v0.6.0", entry.hash))
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            return Ok(entry_point.handleOps(ops, beneficiary).into_transaction_request());
+        }
+
+        let entry_point = get_entrypoint_v070(chain_id, entry_point).await?;
+
+        let ops = entries
+            .iter()
+            .map(|entry| match &entry.user_operation {
+                MempoolUserOperation::Packed(op) => Ok(op.clone().into()),
+                MempoolUserOperation::Default(_) => {
+                    Err(eyre!("User operation {:?} for v0.7.0", entry.hash))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(entry_point.handleOps(ops, beneficiary).into_transaction_request())
+    }
+
+    /// Restore the persisted user operations into the mempool
+    fn restore_mempool(&self, mempool: &mut Mempool) {
+        let Some(redis_client) = &self.redis_client else {
+            return;
+        };
+
+        let values =
+            redis_client.get_connection().and_then(|mut con| get_mempool_operations(&mut con));
+        match values {
+            Ok(values) => {
+                for value in values {
+                    match serde_json::from_str::<MempoolEntry>(&value) {
+                        Ok(entry) => mempool.restore(entry),
+                        Err(err) => warn!("Failed to parse the mempool entry: {:?}", err),
+                    }
+                }
+            }
+            Err(err) => warn!("Failed to restore the mempool: {:?}", err),
+        }
+    }
+
+    fn persist_mempool_entry(&self, entry: &MempoolEntry) {
+        let Some(redis_client) = &self.redis_client else {
+            return;
+        };
+
+        let res = serde_json::to_string(entry).map_err(|e| eyre!(e)).and_then(|value| {
+            let mut con = redis_client.get_connection()?;
+            Ok(set_mempool_operation(&mut con, &entry.hash.to_string(), &value)?)
+        });
+        if let Err(err) = res {
+            warn!("Failed to persist the mempool entry: {:?}", err);
+        }
+    }
+
+    fn unpersist_mempool_entry(&self, hash: B256) {
+        let Some(redis_client) = &self.redis_client else {
+            return;
+        };
+
+        let res = redis_client
+            .get_connection()
+            .and_then(|mut con| remove_mempool_operation(&mut con, &hash.to_string()));
+        if let Err(err) = res {
+            warn!("Failed to remove the mempool entry: {:?}", err);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the receipt of any of the attempts of the transaction in flight
+async fn get_receipt_of_attempts(
+    provider: &RootProvider<BoxTransport>,
+    in_flight: &InFlightTransaction,
+) -> Result<Option<TransactionReceipt>> {
+    for hash in in_flight.transaction_hashes.iter().rev() {
+        if let Some(receipt) = provider.get_transaction_receipt(*hash).await? {
+            return Ok(Some(receipt));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Get the status of the included attempt
+fn receipt_status(receipt: &TransactionReceipt) -> TransactionStatus {
+    if receipt.status() {
+        TransactionStatus::Included
+    } else {
+        TransactionStatus::Reverted
+    }
+}
+
+/// Get the hashes of the transactions w/ the `UserOperationEvent` of the user operations in flight
+async fn get_user_operation_transactions(
+    provider: &RootProvider<BoxTransport>,
+    entry_point: Address,
+    in_flight: &InFlightTransaction,
+) -> Result<Vec<B256>> {
+    // The event is the same in both of the versions
+    let filter = Filter::new()
+        .address(entry_point)
+        .event_signature(EntryPointV060::UserOperationEvent::SIGNATURE_HASH)
+        .topic1(in_flight.user_operation_hashes.clone())
+        .from_block(in_flight.from_block);
+
+    let logs = provider.get_logs(&filter).await?;
+
+    Ok(logs.into_iter().filter_map(|log| log.transaction_hash).collect())
+}
+
+/// Decode the error of the entrypoint, w/ the `FailedOpError` for the failed operations
+fn decode_entrypoint_error(e: RpcError<TransportErrorKind>) -> eyre::Report {
+    // Try to decode the error
+    let Some(err_payload) = e.as_error_resp() else {
+        // If it's not a custom error, return the original error
+        return e.into();
+    };
+    info!("err_payload: {:?}", err_payload);
+
+    // The `FailedOp` error is the same in both of the versions
+    if let Some(decoded_error) =
+        err_payload.as_decoded_error::<EntryPointV070::EntryPointV070Errors>(false)
+    {
+        match decoded_error {
+            EntryPointV070::EntryPointV070Errors::FailedOp(value) => {
+                return FailedOpError { op_index: value.opIndex.to(), reason: value.reason }.into();
+            }
+            EntryPointV070::EntryPointV070Errors::FailedOpWithRevert(value) => {
+                return FailedOpError {
+                    op_index: value.opIndex.to(),
+                    reason: format!("{} {:?}", value.reason, value.inner),
+                }
+                .into();
+            }
+            EntryPointV070::EntryPointV070Errors::PostOpReverted(value) => {
+                return eyre!("PostOpReverted: {:?}", value.returnData);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(EntryPointV060::EntryPointV060Errors::SignatureValidationFailed(value)) =
+        err_payload.as_decoded_error::<EntryPointV060::EntryPointV060Errors>(false)
+    {
+        return eyre!("SignatureValidationFailed: {:?}", value.aggregator);
+    }
+
+    eyre!("Unrecognized custom error: {:?}", err_payload)
 }
@@ -0,0 +1,215 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::Address;
+use lightdotso_redis::{
+    query::executor::{
+        get_executor_nonce, release_executor_nonce, reserve_executor_nonce, reset_executor_nonce,
+    },
+    redis::aio::MultiplexedConnection,
+};
+use lightdotso_tracing::tracing::warn;
+use std::collections::{BTreeSet, HashMap};
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The nonces reserved by an executor key
+#[derive(Clone, Debug, Default)]
+struct ExecutorNonces {
+    /// The next nonce to reserve
+    next: u64,
+    /// The nonces reserved and not final yet
+    outstanding: BTreeSet<u64>,
+}
+
+/// The local nonce manager of the executor keys, coordinated w/ redis across the restarts
+#[derive(Clone, Default)]
+pub struct NonceManager {
+    /// The async connection to redis, multiplexed w/o blocking the runtime
+    redis_con: Option<MultiplexedConnection>,
+    nonces: HashMap<(u64, Address), ExecutorNonces>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl NonceManager {
+    pub fn new(redis_con: Option<MultiplexedConnection>) -> Self {
+        Self { redis_con, nonces: HashMap::new() }
+    }
+
+    /// Reserve the next nonce of the executor, w/ the on-chain pending nonce as the lower bound
+    pub async fn reserve(&mut self, chain_id: u64, executor: Address, pending_nonce: u64) -> u64 {
+        let entry = self.nonces.entry((chain_id, executor)).or_default();
+        let local = entry.next.max(pending_nonce);
+
+        let nonce = match &mut self.redis_con {
+            Some(con) => reserve_executor_nonce(con, chain_id, &executor.to_string(), local)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Failed to reserve the nonce: {:?}", err);
+                    local
+                }),
+            None => local,
+        };
+
+        entry.next = nonce + 1;
+        entry.outstanding.insert(nonce);
+
+        nonce
+    }
+
+    /// Release the nonce never broadcast, reusing it if it is the latest reserved
+    pub async fn release(&mut self, chain_id: u64, executor: Address, nonce: u64) {
+        let entry = self.nonces.entry((chain_id, executor)).or_default();
+        entry.outstanding.remove(&nonce);
+
+        if entry.next != nonce + 1 {
+            return;
+        }
+        entry.next = nonce;
+
+        if let Some(con) = &mut self.redis_con {
+            let res = release_executor_nonce(con, chain_id, &executor.to_string(), nonce).await;
+            if let Err(err) = res {
+                warn!("Failed to release the nonce: {:?}", err);
+            }
+        }
+    }
+
+    /// Mark the nonce as final, w/ the transaction included, replaced or given up
+    pub fn confirm(&mut self, chain_id: u64, executor: Address, nonce: u64) {
+        if let Some(entry) = self.nonces.get_mut(&(chain_id, executor)) {
+            entry.outstanding.remove(&nonce);
+        }
+    }
+
+    /// Detect the gaps between the on-chain pending nonce and the reserved nonces, and recover
+    /// from them, returning the gaps to fill w/ the transactions
+    pub async fn recover(
+        &mut self,
+        chain_id: u64,
+        executor: Address,
+        pending_nonce: u64,
+    ) -> Vec<u64> {
+        let entry = self.nonces.entry((chain_id, executor)).or_default();
+
+        // The stored nonce may be ahead after a restart
+        if let Some(con) = &mut self.redis_con {
+            let res = get_executor_nonce(con, chain_id, &executor.to_string()).await;
+            match res {
+                Ok(Some(stored)) => entry.next = entry.next.max(stored),
+                Ok(None) => {}
+                Err(err) => warn!("Failed to get the nonce: {:?}", err),
+            }
+        }
+
+        // The nonces under the pending nonce are already known on-chain
+        entry.outstanding.retain(|nonce| *nonce >= pending_nonce);
+
+        let gaps = find_gaps(pending_nonce, entry.next, &entry.outstanding);
+        if gaps.is_empty() {
+            return gaps;
+        }
+        warn!("Nonce gaps of {:?} on chain {}: {:?}", executor, chain_id, gaps);
+
+        // Nothing in flight above the gaps, reuse them from the on-chain nonce
+        if entry.outstanding.is_empty() {
+            entry.next = pending_nonce;
+
+            if let Some(con) = &mut self.redis_con {
+                let res =
+                    reset_executor_nonce(con, chain_id, &executor.to_string(), pending_nonce).await;
+                if let Err(err) = res {
+                    warn!("Failed to reset the nonce: {:?}", err);
+                }
+            }
+
+            return vec![];
+        }
+
+        // Otherwise the gaps block the nonces in flight, and have to be filled
+        entry.outstanding.extend(gaps.iter().copied());
+
+        gaps
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the nonces from the on-chain pending nonce to the next nonce, which are not reserved
+pub fn find_gaps(pending_nonce: u64, next: u64, outstanding: &BTreeSet<u64>) -> Vec<u64> {
+    (pending_nonce..next).filter(|nonce| !outstanding.contains(nonce)).collect()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_gaps() {
+        let outstanding = BTreeSet::from([5, 7]);
+
+        assert_eq!(find_gaps(4, 8, &outstanding), vec![4, 6]);
+        assert_eq!(find_gaps(5, 6, &outstanding), Vec::<u64>::new());
+        assert_eq!(find_gaps(8, 8, &outstanding), Vec::<u64>::new());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_reserve_and_release() {
+        let mut manager = NonceManager::default();
+        let executor = Address::repeat_byte(1);
+
+        // The on-chain pending nonce is the lower bound
+        assert_eq!(manager.reserve(1, executor, 3).await, 3);
+        assert_eq!(manager.reserve(1, executor, 3).await, 4);
+
+        // The latest nonce is reused once released
+        manager.release(1, executor, 4).await;
+        assert_eq!(manager.reserve(1, executor, 3).await, 4);
+
+        // The nonces are separate per chain
+        assert_eq!(manager.reserve(10, executor, 0).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_nonce_manager_recover() {
+        let mut manager = NonceManager::default();
+        let executor = Address::repeat_byte(2);
+
+        for _ in 0..3 {
+            manager.reserve(1, executor, 0).await;
+        }
+
+        // The nonce 0 is included, the nonce 1 is never broadcast and blocks the nonce 2
+        manager.confirm(1, executor, 0);
+        manager.release(1, executor, 1).await;
+        assert_eq!(manager.recover(1, executor, 1).await, vec![1]);
+
+        // W/ nothing in flight, the gap is reused from the on-chain nonce
+        manager.confirm(1, executor, 1);
+        manager.confirm(1, executor, 2);
+        assert!(manager.recover(1, executor, 1).await.is_empty());
+        assert_eq!(manager.reserve(1, executor, 1).await, 1);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// From: https://github.com/eth-infinitism/account-abstraction/blob/develop/erc/ERCS/erc-7562.md#reputation-scoring-and-throttlingbanning-for-global-entities
+// License: CC0-1.0
+
+use alloy::primitives::Address;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The denominator of the minimum inclusion rate of the seen operations
+pub const MIN_INCLUSION_RATE_DENOMINATOR: u64 = 10;
+
+/// The slack of the included operations before the entity is throttled
+pub const THROTTLING_SLACK: u64 = 10;
+
+/// The slack of the included operations before the entity is banned
+pub const BAN_SLACK: u64 = 50;
+
+/// The max number of operations of a throttled entity in a bundle
+pub const THROTTLED_ENTITY_BUNDLE_COUNT: usize = 4;
+
+/// The seen operations set on the entity failing a bundle, banning it until decayed
+const PENALTY_OPS_SEEN: u64 = 10_000;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The reputation status of the entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReputationStatus {
+    Ok,
+    Throttled,
+    Banned,
+}
+
+/// The seen and included counts of the operations of the entity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReputationEntry {
+    pub ops_seen: u64,
+    pub ops_included: u64,
+}
+
+/// The reputation of the factories and the paymasters
+#[derive(Clone, Debug, Default)]
+pub struct Reputation {
+    entries: HashMap<Address, ReputationEntry>,
+    decayed_at: Option<Instant>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl ReputationEntry {
+    /// Get the status of the entry w/ the inclusion rate
+    pub fn status(&self) -> ReputationStatus {
+        let min_expected_included = self.ops_seen / MIN_INCLUSION_RATE_DENOMINATOR;
+
+        if min_expected_included <= self.ops_included + THROTTLING_SLACK {
+            ReputationStatus::Ok
+        } else if min_expected_included <= self.ops_included + BAN_SLACK {
+            ReputationStatus::Throttled
+        } else {
+            ReputationStatus::Banned
+        }
+    }
+}
+
+impl Reputation {
+    /// Get the entry of the entity
+    pub fn get(&self, entity: Address) -> ReputationEntry {
+        self.entries.get(&entity).copied().unwrap_or_default()
+    }
+
+    /// Get the status of the entity
+    pub fn status(&self, entity: Address) -> ReputationStatus {
+        self.get(entity).status()
+    }
+
+    /// Count an operation of the entity added to the mempool
+    pub fn add_seen(&mut self, entity: Address) {
+        self.entries.entry(entity).or_default().ops_seen += 1;
+    }
+
+    /// Count an operation of the entity included on-chain
+    pub fn add_included(&mut self, entity: Address) {
+        self.entries.entry(entity).or_default().ops_included += 1;
+    }
+
+    /// Penalize the entity which failed a bundle, banning it until decayed
+    pub fn penalize(&mut self, entity: Address) {
+        let entry = self.entries.entry(entity).or_default();
+        entry.ops_seen = PENALTY_OPS_SEEN;
+        entry.ops_included = 0;
+    }
+
+    /// Decay the counts for each hour elapsed since the last decay
+    pub fn decay_hourly(&mut self) {
+        let now = Instant::now();
+        let decayed_at = *self.decayed_at.get_or_insert(now);
+
+        let hours = now.duration_since(decayed_at).as_secs() / 3600;
+        for _ in 0..hours {
+            self.decay();
+        }
+        if hours > 0 {
+            self.decayed_at = Some(decayed_at + Duration::from_secs(hours * 3600));
+        }
+    }
+
+    /// Decay the counts of all of the entities, called hourly
+    pub fn decay(&mut self) {
+        for entry in self.entries.values_mut() {
+            entry.ops_seen -= entry.ops_seen / 24;
+            entry.ops_included -= entry.ops_included / 24;
+        }
+        self.entries.retain(|_, entry| entry.ops_seen > 0 || entry.ops_included > 0);
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reputation_status() {
+        let mut reputation = Reputation::default();
+        let entity = Address::repeat_byte(1);
+
+        assert_eq!(reputation.status(entity), ReputationStatus::Ok);
+
+        // 200 seen w/o inclusion expects 20 included, over the throttling slack
+        for _ in 0..200 {
+            reputation.add_seen(entity);
+        }
+        assert_eq!(reputation.status(entity), ReputationStatus::Throttled);
+
+        // Including operations recovers the entity
+        for _ in 0..10 {
+            reputation.add_included(entity);
+        }
+        assert_eq!(reputation.status(entity), ReputationStatus::Ok);
+
+        // 700 seen w/ 10 included expects 70 included, over the ban slack
+        for _ in 0..500 {
+            reputation.add_seen(entity);
+        }
+        assert_eq!(reputation.status(entity), ReputationStatus::Banned);
+    }
+
+    #[test]
+    fn test_reputation_penalize_and_decay() {
+        let mut reputation = Reputation::default();
+        let entity = Address::repeat_byte(2);
+
+        reputation.penalize(entity);
+        assert_eq!(reputation.status(entity), ReputationStatus::Banned);
+
+        // Decays back to ok after enough hours
+        for _ in 0..120 {
+            reputation.decay();
+        }
+        assert_eq!(reputation.status(entity), ReputationStatus::Ok);
+    }
+}
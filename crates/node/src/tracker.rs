@@ -0,0 +1,213 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, Bytes, B256};
+use lightdotso_constants::registry::get_chain_registry;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The max number of the fee-bumped replacements of a stuck transaction
+pub const MAX_REPLACEMENTS: u32 = 5;
+
+/// The min percentage bump of the priority fee for a replacement, required by the clients
+const PRIORITY_FEE_BUMP_PERCENT: u128 = 10;
+
+/// The min permille bump of the max fee for a replacement, covering the max base fee increase
+/// of a block on top of the 10% required by the clients
+const MAX_FEE_BUMP_PERMILLE: u128 = 125;
+
+/// The number of the blocks to wait for the inclusion before the transaction is stuck
+const CHAIN_WAIT_BLOCKS: u64 = 5;
+
+/// The min seconds to wait for the inclusion before the transaction is stuck
+const MIN_CHAIN_WAIT_SECONDS: u64 = 15;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The final status of the bundle transaction sent by the node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionStatus {
+    /// One of the attempts was included and succeeded
+    Included,
+    /// One of the attempts was included and reverted
+    Reverted,
+    /// All of the user operations landed in the bundle of another bundler
+    IncludedByOthers,
+    /// Some of the user operations landed in the bundle of another bundler
+    PartiallyIncludedByOthers,
+    /// The nonce was consumed by a transaction other than the attempts
+    ReplacedByOthers,
+    /// The transaction was still stuck after the max replacements
+    Dropped,
+}
+
+/// The bundle transaction in flight of an executor key
+#[derive(Clone, Debug)]
+pub struct InFlightTransaction {
+    pub chain_id: u64,
+    /// The executor key sending the transaction
+    pub executor: Address,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// The hashes of all of the attempts, w/ the latest last
+    pub transaction_hashes: Vec<B256>,
+    /// The signed latest attempt, for the rebroadcast
+    pub raw_transaction: Bytes,
+    /// The hashes of the user operations in the bundle
+    pub user_operation_hashes: Vec<B256>,
+    /// The block number before the first attempt
+    pub from_block: u64,
+    /// The time of the latest attempt
+    pub sent_at: Instant,
+    /// The number of the fee-bumped replacements
+    pub replacements: u32,
+}
+
+/// The tracker of the transactions in flight per chain and executor key
+#[derive(Clone, Debug, Default)]
+pub struct TransactionTracker {
+    in_flight: HashMap<(u64, Address), InFlightTransaction>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl TransactionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionStatus::Included => "INCLUDED",
+            TransactionStatus::Reverted => "REVERTED",
+            TransactionStatus::IncludedByOthers => "INCLUDED_BY_OTHERS",
+            TransactionStatus::PartiallyIncludedByOthers => "PARTIALLY_INCLUDED_BY_OTHERS",
+            TransactionStatus::ReplacedByOthers => "REPLACED_BY_OTHERS",
+            TransactionStatus::Dropped => "DROPPED",
+        }
+    }
+}
+
+impl InFlightTransaction {
+    /// Check if the latest attempt has waited longer than the wait of the chain
+    pub fn is_stuck(&self) -> bool {
+        self.sent_at.elapsed() >= get_chain_wait(self.chain_id)
+    }
+
+    /// Check if the fees of the network are covered by the latest attempt
+    pub fn covers_fees(&self, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> bool {
+        self.max_fee_per_gas >= max_fee_per_gas &&
+            self.max_priority_fee_per_gas >= max_priority_fee_per_gas
+    }
+}
+
+impl TransactionTracker {
+    pub fn get(&self, chain_id: u64, executor: Address) -> Option<&InFlightTransaction> {
+        self.in_flight.get(&(chain_id, executor))
+    }
+
+    pub fn insert(&mut self, transaction: InFlightTransaction) {
+        self.in_flight.insert((transaction.chain_id, transaction.executor), transaction);
+    }
+
+    pub fn remove(&mut self, chain_id: u64, executor: Address) -> Option<InFlightTransaction> {
+        self.in_flight.remove(&(chain_id, executor))
+    }
+
+    /// Get all of the transactions in flight
+    pub fn in_flight(&self) -> Vec<InFlightTransaction> {
+        self.in_flight.values().cloned().collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the wait for the inclusion on the chain before the transaction is stuck, w/ the block
+/// seconds of the registry
+pub fn get_chain_wait(chain_id: u64) -> Duration {
+    let block_seconds = get_chain_registry().block_seconds(chain_id);
+
+    Duration::from_secs(block_seconds.saturating_mul(CHAIN_WAIT_BLOCKS).max(MIN_CHAIN_WAIT_SECONDS))
+}
+
+/// Get the fees of the replacement, bumping the priority fee by 10% and the max fee by 12.5% at
+/// least, or the fees of the network if higher
+pub fn bump_fees(
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+    network_max_fee_per_gas: u128,
+    network_max_priority_fee_per_gas: u128,
+) -> (u128, u128) {
+    let min_priority_fee = max_priority_fee_per_gas +
+        (max_priority_fee_per_gas * PRIORITY_FEE_BUMP_PERCENT).div_ceil(100);
+    let min_max_fee = max_fee_per_gas + (max_fee_per_gas * MAX_FEE_BUMP_PERMILLE).div_ceil(1000);
+
+    let priority_fee = min_priority_fee.max(network_max_priority_fee_per_gas);
+    let max_fee = min_max_fee.max(network_max_fee_per_gas).max(priority_fee);
+
+    (max_fee, priority_fee)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightdotso_constants::registry::DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS;
+
+    #[test]
+    fn test_bump_fees() {
+        // Bumped by the min percentages, rounding up
+        assert_eq!(bump_fees(1_000, 100, 0, 0), (1_125, 110));
+        assert_eq!(bump_fees(1_001, 101, 0, 0), (1_127, 112));
+
+        // The fees of the network if higher
+        assert_eq!(bump_fees(1_000, 100, 2_000, 300), (2_000, 300));
+
+        // The max fee covers the priority fee
+        assert_eq!(bump_fees(100, 100, 0, 500), (500, 500));
+    }
+
+    #[test]
+    fn test_get_chain_wait() {
+        assert_eq!(get_chain_wait(1), Duration::from_secs(60));
+        assert_eq!(get_chain_wait(8453), Duration::from_secs(15));
+        assert_eq!(
+            get_chain_wait(0),
+            Duration::from_secs(DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS * CHAIN_WAIT_BLOCKS)
+        );
+    }
+
+    #[test]
+    fn test_transaction_status_as_str() {
+        assert_eq!(
+            serde_json::to_value(TransactionStatus::IncludedByOthers).unwrap(),
+            TransactionStatus::IncludedByOthers.as_str()
+        );
+    }
+}
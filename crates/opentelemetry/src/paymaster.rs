@@ -0,0 +1,37 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use lazy_static::lazy_static;
+use once_cell::sync::Lazy;
+use opentelemetry::{global, metrics::Counter, KeyValue};
+
+lazy_static! {
+    pub static ref PAYMASTER_SPONSOR_REQUEST_COUNT: Lazy<Counter<u64>> =
+        Lazy::new(|| global::meter("").u64_counter("paymaster_sponsor_request_count").init());
+    pub static ref PAYMASTER_SPONSOR_LATENCY_MS: Lazy<Counter<u64>> =
+        Lazy::new(|| global::meter("").u64_counter("paymaster_sponsor_latency_ms").init());
+}
+
+pub struct PaymasterMetrics {}
+
+impl PaymasterMetrics {
+    pub fn set_sponsor_request(provider: &str, chain_id: u64, outcome: &str, latency_ms: u64) {
+        let attributes = [
+            KeyValue::new("provider", provider.to_string()),
+            KeyValue::new("chain_id", chain_id.to_string()),
+            KeyValue::new("outcome", outcome.to_string()),
+        ];
+        PAYMASTER_SPONSOR_REQUEST_COUNT.add(1, &attributes);
+        PAYMASTER_SPONSOR_LATENCY_MS.add(latency_ms, &attributes);
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lightdotso_tracing::tracing::warn;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The max relative deviation from the median before a quote is rejected as an outlier
+pub const MAX_DEVIATION: f64 = 0.05;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The price in USD quoted by a source
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceQuote {
+    pub source: String,
+    pub price: f64,
+}
+
+// -----------------------------------------------------------------------------
+// Aggregate
+// -----------------------------------------------------------------------------
+
+/// Aggregate the quotes to the median, after rejecting the outliers from the median.
+/// There is no quorum, so a single valid quote is taken as is.
+/// Returns `None` if no quote is valid, or if no quote is within the max deviation of the median.
+pub fn aggregate_prices(quotes: &[PriceQuote]) -> Option<f64> {
+    let valid = quotes
+        .iter()
+        .filter(|quote| quote.price.is_finite() && quote.price > 0.0)
+        .collect::<Vec<_>>();
+    let center = median(valid.iter().map(|quote| quote.price).collect())?;
+
+    let (inliers, outliers): (Vec<_>, Vec<_>) =
+        valid.into_iter().partition(|quote| (quote.price - center).abs() / center <= MAX_DEVIATION);
+    for outlier in &outliers {
+        warn!(
+            "Rejected the price of {} as an outlier: {} vs {}",
+            outlier.source, outlier.price, center
+        );
+    }
+
+    median(inliers.iter().map(|quote| quote.price).collect())
+}
+
+/// The median of the prices
+fn median(mut prices: Vec<f64>) -> Option<f64> {
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort_by(|a, b| a.total_cmp(b));
+
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 0 {
+        Some((prices[mid - 1] + prices[mid]) / 2.0)
+    } else {
+        Some(prices[mid])
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotes(prices: &[f64]) -> Vec<PriceQuote> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| PriceQuote { source: i.to_string(), price: *price })
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_prices() {
+        assert_eq!(aggregate_prices(&quotes(&[3000.0])), Some(3000.0));
+        assert_eq!(aggregate_prices(&quotes(&[3000.0, 3010.0])), Some(3005.0));
+        assert_eq!(aggregate_prices(&quotes(&[])), None);
+
+        // The outlier is rejected
+        assert_eq!(aggregate_prices(&quotes(&[3000.0, 3010.0, 4500.0])), Some(3005.0));
+
+        // The invalid quotes are ignored
+        assert_eq!(aggregate_prices(&quotes(&[0.0, f64::NAN, 3000.0])), Some(3000.0));
+
+        // No quote is within the max deviation of the median
+        assert_eq!(aggregate_prices(&quotes(&[100.0, 120.0])), None);
+    }
+}
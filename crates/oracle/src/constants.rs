@@ -0,0 +1,41 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+// The defillama coins api base url
+lazy_static! {
+    pub static ref DEFILLAMA_COINS_API_URL: String = "https://coins.llama.fi".to_string();
+}
+
+// The coingecko api base url
+lazy_static! {
+    pub static ref COINGECKO_API_URL: String = "https://api.coingecko.com/api/v3".to_string();
+}
+
+// The coingecko coin ids w/ native token symbol
+lazy_static! {
+    pub static ref COINGECKO_NATIVE_IDS: HashMap<&'static str, &'static str> = [
+        ("ETH", "ethereum"),
+        ("MATIC", "matic-network"),
+        ("POL", "polygon-ecosystem-token"),
+        ("BNB", "binancecoin"),
+        ("AVAX", "avalanche-2"),
+        ("XDAI", "xdai"),
+        ("CELO", "celo"),
+    ]
+    .into_iter()
+    .collect();
+}
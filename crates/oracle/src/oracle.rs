@@ -0,0 +1,383 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    aggregate::{aggregate_prices, PriceQuote},
+    sources::{
+        coingecko::CoinGeckoSource, crypto::MiniCryptoSource, defillama::DefiLlamaSource,
+        PriceQuery, PriceSource,
+    },
+    store::{DbPriceRecords, PriceCache, PriceRecords, RedisPriceCache},
+};
+use alloy::{eips::BlockNumberOrTag, primitives::Address, providers::Provider};
+use autometrics::autometrics;
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use lightdotso_contracts::provider::get_provider;
+use lightdotso_db::db::{create_client, create_postgres_client, create_postgres_pool};
+use lightdotso_prisma::PrismaClient;
+use lightdotso_prisma_postgres::PrismaClient as PrismaPostgresClient;
+use lightdotso_redis::{get_redis_client, redis::Client};
+use lightdotso_sqlx::PostgresPool;
+use lightdotso_tracing::tracing::{info, warn};
+use lightdotso_utils::get_native_token_symbol;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The seconds of the time buckets the prices are cached in
+const PRICE_BUCKET_SECONDS: u64 = 300;
+
+/// The seconds the latest prices are cached for
+const LATEST_PRICE_TTL_SECONDS: u64 = 60;
+
+/// The seconds the historical prices are cached for
+const HISTORICAL_PRICE_TTL_SECONDS: u64 = 7 * 86_400;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// When the price is asked at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceAt {
+    Latest,
+    /// The unix timestamp
+    Timestamp(u64),
+    /// The block number on the chain
+    Block(u64),
+}
+
+// -----------------------------------------------------------------------------
+// Oracle
+// -----------------------------------------------------------------------------
+
+/// The oracle of the token prices in USD, aggregating the sources w/ the cache in redis and the
+/// records in the `TokenPrice` table
+pub struct PriceOracle {
+    sources: Vec<Arc<dyn PriceSource>>,
+    cache: Option<Arc<dyn PriceCache>>,
+    records: Option<Arc<dyn PriceRecords>>,
+}
+
+impl PriceOracle {
+    /// Constructs the oracle w/ the sources only, w/o any caches
+    pub fn new(sources: Vec<Arc<dyn PriceSource>>) -> Self {
+        Self { sources, cache: None, records: None }
+    }
+
+    /// Sets the cache of the prices
+    pub fn with_cache(mut self, cache: Arc<dyn PriceCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Sets the recorded prices
+    pub fn with_records(mut self, records: Arc<dyn PriceRecords>) -> Self {
+        self.records = Some(records);
+        self
+    }
+
+    /// Constructs the oracle w/ the default sources and the caches of the existing clients
+    pub fn with_clients(
+        redis_client: Arc<Client>,
+        db_client: Arc<PrismaClient>,
+        postgres_client: Arc<PrismaPostgresClient>,
+        postgres_pool: PostgresPool,
+    ) -> Self {
+        Self::new(default_sources())
+            .with_cache(Arc::new(RedisPriceCache { client: redis_client }))
+            .with_records(Arc::new(DbPriceRecords { db_client, postgres_client, postgres_pool }))
+    }
+
+    /// Constructs the oracle w/ the default sources and the caches available in the environment
+    pub async fn from_env() -> Self {
+        let mut oracle = Self::new(default_sources());
+
+        if let Ok(client) = get_redis_client() {
+            oracle = oracle.with_cache(Arc::new(RedisPriceCache { client: Arc::new(client) }));
+        }
+        if let (Ok(db_client), Ok(postgres_client), Ok(postgres_pool)) =
+            (create_client().await, create_postgres_client().await, create_postgres_pool().await)
+        {
+            oracle = oracle.with_records(Arc::new(DbPriceRecords {
+                db_client: Arc::new(db_client),
+                postgres_client: Arc::new(postgres_client),
+                postgres_pool,
+            }));
+        }
+
+        oracle
+    }
+
+    /// Get the price in USD of the native token of the chain
+    pub async fn get_native_price(&self, chain_id: u64, at: PriceAt) -> Result<f64> {
+        self.get_price(chain_id, Address::ZERO, at).await
+    }
+
+    /// Get the price in USD of the token on the chain, or of the native token w/ the zero address
+    #[autometrics]
+    pub async fn get_price(&self, chain_id: u64, token: Address, at: PriceAt) -> Result<f64> {
+        let timestamp = match at {
+            PriceAt::Latest => None,
+            PriceAt::Timestamp(timestamp) => Some(timestamp),
+            PriceAt::Block(block_number) => {
+                Some(get_block_timestamp(chain_id, block_number).await?)
+            }
+        };
+        let bucket = timestamp.unwrap_or_else(now) / PRICE_BUCKET_SECONDS;
+        let ttl = if timestamp.is_some() {
+            HISTORICAL_PRICE_TTL_SECONDS
+        } else {
+            LATEST_PRICE_TTL_SECONDS
+        };
+
+        // Read the cache
+        if let Some(cache) = &self.cache {
+            if let Some(price) = cache.get(chain_id, token, bucket).await {
+                return Ok(price);
+            }
+        }
+
+        // Read the recorded prices for the historical lookups
+        if let (Some(timestamp), Some(records)) = (timestamp, &self.records) {
+            if let Some(price) = records.get(chain_id, token, timestamp).await {
+                if let Some(cache) = &self.cache {
+                    cache.set(chain_id, token, bucket, price, ttl).await;
+                }
+                return Ok(price);
+            }
+        }
+
+        // Ask all of the sources
+        let query = PriceQuery {
+            chain_id,
+            token,
+            native_symbol: get_native_token_symbol(chain_id),
+            timestamp,
+        };
+        let quotes = self.get_quotes(&query).await;
+        info!("quotes: {:?}", quotes);
+
+        let price = aggregate_prices(&quotes)
+            .ok_or_else(|| eyre!("No price of {:?} on chain {} at {:?}", token, chain_id, at))?;
+
+        // Fill the cache and the recorded prices
+        if let Some(cache) = &self.cache {
+            cache.set(chain_id, token, bucket, price, ttl).await;
+        }
+        if let Some(records) = &self.records {
+            records.record(chain_id, token, price, timestamp.unwrap_or_else(now)).await;
+        }
+
+        Ok(price)
+    }
+
+    /// Get the quotes of all of the sources concurrently, skipping the failed ones
+    async fn get_quotes(&self, query: &PriceQuery) -> Vec<PriceQuote> {
+        let results =
+            join_all(self.sources.iter().map(|source| async move {
+                (source.name().to_string(), source.price(query).await)
+            }))
+            .await;
+
+        results
+            .into_iter()
+            .filter_map(|(source, result)| match result {
+                Ok(Some(price)) => Some(PriceQuote { source, price }),
+                Ok(None) => None,
+                Err(err) => {
+                    warn!("Failed to get the price from {}: {:?}", source, err);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the timestamp of the block on the chain
+async fn get_block_timestamp(chain_id: u64, block_number: u64) -> Result<u64> {
+    let (provider, _) = get_provider(chain_id).await?;
+
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(block_number), false)
+        .await?
+        .ok_or_else(|| eyre!("Block {} not found on chain {}", block_number, chain_id))?;
+
+    Ok(block.header.timestamp)
+}
+
+/// The default sources of the prices
+pub fn default_sources() -> Vec<Arc<dyn PriceSource>> {
+    vec![
+        Arc::new(MiniCryptoSource),
+        Arc::new(DefiLlamaSource),
+        Arc::new(CoinGeckoSource { api_key: std::env::var("COINGECKO_API_KEY").ok() }),
+    ]
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    const TIMESTAMP: u64 = 1_700_000_000;
+
+    struct FixedSource {
+        price: f64,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PriceSource for FixedSource {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        async fn price(&self, _query: &PriceQuery) -> Result<Option<f64>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(self.price))
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryCache {
+        prices: Mutex<HashMap<(u64, Address, u64), f64>>,
+    }
+
+    #[async_trait]
+    impl PriceCache for MemoryCache {
+        async fn get(&self, chain_id: u64, token: Address, bucket: u64) -> Option<f64> {
+            self.prices.lock().unwrap().get(&(chain_id, token, bucket)).copied()
+        }
+
+        async fn set(&self, chain_id: u64, token: Address, bucket: u64, price: f64, _ttl: u64) {
+            self.prices.lock().unwrap().insert((chain_id, token, bucket), price);
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryRecords {
+        prices: Mutex<HashMap<(u64, Address), f64>>,
+        recorded: Mutex<Vec<(u64, f64)>>,
+    }
+
+    #[async_trait]
+    impl PriceRecords for MemoryRecords {
+        async fn get(&self, chain_id: u64, token: Address, _timestamp: u64) -> Option<f64> {
+            self.prices.lock().unwrap().get(&(chain_id, token)).copied()
+        }
+
+        async fn record(&self, _chain_id: u64, _token: Address, price: f64, timestamp: u64) {
+            self.recorded.lock().unwrap().push((timestamp, price));
+        }
+    }
+
+    fn oracle(
+        source_price: f64,
+    ) -> (PriceOracle, Arc<FixedSource>, Arc<MemoryCache>, Arc<MemoryRecords>) {
+        let source = Arc::new(FixedSource { price: source_price, calls: AtomicUsize::new(0) });
+        let cache = Arc::new(MemoryCache::default());
+        let records = Arc::new(MemoryRecords::default());
+
+        let oracle = PriceOracle::new(vec![source.clone() as Arc<dyn PriceSource>])
+            .with_cache(cache.clone())
+            .with_records(records.clone());
+
+        (oracle, source, cache, records)
+    }
+
+    #[tokio::test]
+    async fn test_price_reads_the_cache_first() {
+        let (oracle, source, cache, records) = oracle(2.0);
+        cache.set(1, Address::ZERO, TIMESTAMP / PRICE_BUCKET_SECONDS, 1.0, 0).await;
+        records.prices.lock().unwrap().insert((1, Address::ZERO), 3.0);
+
+        let price = oracle.get_native_price(1, PriceAt::Timestamp(TIMESTAMP)).await.unwrap();
+
+        assert_eq!(price, 1.0);
+        assert_eq!(source.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_price_reads_the_records_before_the_sources() {
+        let (oracle, source, cache, records) = oracle(2.0);
+        records.prices.lock().unwrap().insert((1, Address::ZERO), 3.0);
+
+        let price = oracle.get_native_price(1, PriceAt::Timestamp(TIMESTAMP)).await.unwrap();
+
+        assert_eq!(price, 3.0);
+        assert_eq!(source.calls.load(Ordering::SeqCst), 0);
+        // The recorded price fills the cache
+        assert_eq!(cache.get(1, Address::ZERO, TIMESTAMP / PRICE_BUCKET_SECONDS).await, Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn test_price_asks_the_sources_last() {
+        let (oracle, source, cache, records) = oracle(2.0);
+
+        let price = oracle.get_native_price(1, PriceAt::Timestamp(TIMESTAMP)).await.unwrap();
+
+        assert_eq!(price, 2.0);
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.get(1, Address::ZERO, TIMESTAMP / PRICE_BUCKET_SECONDS).await, Some(2.0));
+        assert_eq!(*records.recorded.lock().unwrap(), vec![(TIMESTAMP, 2.0)]);
+
+        // The second lookup is served from the cache
+        oracle.get_native_price(1, PriceAt::Timestamp(TIMESTAMP)).await.unwrap();
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_latest_price_skips_the_records() {
+        let (oracle, source, _, records) = oracle(2.0);
+        records.prices.lock().unwrap().insert((1, Address::ZERO), 3.0);
+
+        let price = oracle.get_native_price(1, PriceAt::Latest).await.unwrap();
+
+        assert_eq!(price, 2.0);
+        assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_price_wo_any_source() {
+        let oracle = PriceOracle::new(vec![]);
+
+        assert!(oracle.get_native_price(1, PriceAt::Timestamp(TIMESTAMP)).await.is_err());
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    constants::{COINGECKO_API_URL, COINGECKO_NATIVE_IDS},
+    sources::{PriceQuery, PriceSource},
+};
+use async_trait::async_trait;
+use eyre::Result;
+use lightdotso_client::get_api_json_response;
+use lightdotso_constants::registry::get_chain_registry;
+use reqwest::{header::HeaderMap, Client};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The seconds around the timestamp to search the historical prices in
+const HISTORICAL_WINDOW_SECONDS: u64 = 3_600;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct CoinGeckoPrice {
+    usd: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinGeckoMarketChart {
+    /// The pairs of the timestamp in milliseconds and the price
+    prices: Vec<(f64, f64)>,
+}
+
+// -----------------------------------------------------------------------------
+// Source
+// -----------------------------------------------------------------------------
+
+/// The source of the token prices w/ the coingecko api
+pub struct CoinGeckoSource {
+    /// The demo api key, if any
+    pub api_key: Option<String>,
+}
+
+impl CoinGeckoSource {
+    fn headers(&self) -> Option<HeaderMap> {
+        let api_key = self.api_key.as_ref()?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-cg-demo-api-key", api_key.parse().ok()?);
+        Some(headers)
+    }
+
+    /// The path of the coin of the query, either the native coin id or the contract
+    fn coin_path(query: &PriceQuery) -> Option<String> {
+        if query.is_native() {
+            let id = COINGECKO_NATIVE_IDS.get(query.native_symbol.to_uppercase().as_str())?;
+            return Some(format!("coins/{}", id));
+        }
+
+        let platform = get_chain_registry().price_id(query.chain_id, "coingecko")?;
+        Some(format!("coins/{}/contract/{:?}", platform, query.token))
+    }
+
+    async fn current_price(&self, query: &PriceQuery) -> Result<Option<f64>> {
+        let client = Arc::new(Client::new());
+
+        if query.is_native() {
+            let Some(id) = COINGECKO_NATIVE_IDS.get(query.native_symbol.to_uppercase().as_str())
+            else {
+                return Ok(None);
+            };
+            let url = format!("{}/simple/price?ids={}&vs_currencies=usd", *COINGECKO_API_URL, id);
+            let res = get_api_json_response::<HashMap<String, CoinGeckoPrice>>(
+                client,
+                url,
+                self.headers(),
+            )
+            .await?;
+
+            return Ok(res.get(*id).map(|price| price.usd));
+        }
+
+        let Some(platform) = get_chain_registry().price_id(query.chain_id, "coingecko") else {
+            return Ok(None);
+        };
+        let token = format!("{:?}", query.token);
+        let url = format!(
+            "{}/simple/token_price/{}?contract_addresses={}&vs_currencies=usd",
+            *COINGECKO_API_URL, platform, token
+        );
+        let res =
+            get_api_json_response::<HashMap<String, CoinGeckoPrice>>(client, url, self.headers())
+                .await?;
+
+        Ok(res.get(&token).map(|price| price.usd))
+    }
+
+    async fn historical_price(&self, query: &PriceQuery, timestamp: u64) -> Result<Option<f64>> {
+        let Some(coin_path) = Self::coin_path(query) else {
+            return Ok(None);
+        };
+        let url = format!(
+            "{}/{}/market_chart/range?vs_currency=usd&from={}&to={}",
+            *COINGECKO_API_URL,
+            coin_path,
+            timestamp.saturating_sub(HISTORICAL_WINDOW_SECONDS),
+            timestamp + HISTORICAL_WINDOW_SECONDS
+        );
+        let res = get_api_json_response::<CoinGeckoMarketChart>(
+            Arc::new(Client::new()),
+            url,
+            self.headers(),
+        )
+        .await?;
+
+        Ok(nearest_price(&res.prices, timestamp))
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinGeckoSource {
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+
+    async fn price(&self, query: &PriceQuery) -> Result<Option<f64>> {
+        match query.timestamp {
+            Some(timestamp) => self.historical_price(query, timestamp).await,
+            None => self.current_price(query).await,
+        }
+    }
+}
+
+/// The price of the pair nearest to the timestamp in seconds
+fn nearest_price(prices: &[(f64, f64)], timestamp: u64) -> Option<f64> {
+    let timestamp_ms = timestamp as f64 * 1000.0;
+
+    prices
+        .iter()
+        .min_by(|a, b| (a.0 - timestamp_ms).abs().total_cmp(&(b.0 - timestamp_ms).abs()))
+        .map(|(_, price)| *price)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_price() {
+        let prices = vec![(1_000_000.0, 1.0), (1_300_000.0, 2.0), (1_600_000.0, 3.0)];
+
+        assert_eq!(nearest_price(&prices, 1_290), Some(2.0));
+        assert_eq!(nearest_price(&prices, 2_000), Some(3.0));
+        assert_eq!(nearest_price(&[], 1_000), None);
+    }
+}
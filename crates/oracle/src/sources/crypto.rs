@@ -0,0 +1,44 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::sources::{PriceQuery, PriceSource};
+use async_trait::async_trait;
+use eyre::Result;
+use lightdotso_client::crypto::{get_native_token_price, get_native_token_price_at};
+
+/// The source of the native token prices w/ the mini crypto api
+pub struct MiniCryptoSource;
+
+#[async_trait]
+impl PriceSource for MiniCryptoSource {
+    fn name(&self) -> &str {
+        "mini_crypto"
+    }
+
+    async fn price(&self, query: &PriceQuery) -> Result<Option<f64>> {
+        // The api only quotes by symbol, so only the native token is known
+        if !query.is_native() {
+            return Ok(None);
+        }
+
+        let price = match query.timestamp {
+            Some(timestamp) => {
+                get_native_token_price_at(query.native_symbol.clone(), timestamp).await?
+            }
+            None => get_native_token_price(query.native_symbol.clone()).await?,
+        };
+
+        Ok(Some(price))
+    }
+}
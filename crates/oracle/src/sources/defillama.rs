@@ -0,0 +1,76 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    constants::{COINGECKO_NATIVE_IDS, DEFILLAMA_COINS_API_URL},
+    sources::{PriceQuery, PriceSource},
+};
+use async_trait::async_trait;
+use eyre::Result;
+use lightdotso_client::get_api_json_response;
+use lightdotso_constants::registry::get_chain_registry;
+use reqwest::Client;
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Debug, Deserialize)]
+struct DefiLlamaCoin {
+    price: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefiLlamaPrices {
+    coins: HashMap<String, DefiLlamaCoin>,
+}
+
+/// The source of the token prices w/ the defillama coins api
+pub struct DefiLlamaSource;
+
+impl DefiLlamaSource {
+    /// The coin of the query, keyed by the chain slug or the coingecko id for the native token
+    fn coin(query: &PriceQuery) -> Option<String> {
+        if query.is_native() {
+            let id = COINGECKO_NATIVE_IDS.get(query.native_symbol.to_uppercase().as_str())?;
+            return Some(format!("coingecko:{}", id));
+        }
+
+        let chain = get_chain_registry().price_id(query.chain_id, "defillama")?;
+        Some(format!("{}:{:?}", chain, query.token))
+    }
+}
+
+#[async_trait]
+impl PriceSource for DefiLlamaSource {
+    fn name(&self) -> &str {
+        "defillama"
+    }
+
+    async fn price(&self, query: &PriceQuery) -> Result<Option<f64>> {
+        let Some(coin) = Self::coin(query) else {
+            return Ok(None);
+        };
+
+        let url = match query.timestamp {
+            Some(timestamp) => {
+                format!("{}/prices/historical/{}/{}", *DEFILLAMA_COINS_API_URL, timestamp, coin)
+            }
+            None => format!("{}/prices/current/{}", *DEFILLAMA_COINS_API_URL, coin),
+        };
+
+        let res =
+            get_api_json_response::<DefiLlamaPrices>(Arc::new(Client::new()), url, None).await?;
+
+        Ok(res.coins.get(&coin).map(|coin| coin.price))
+    }
+}
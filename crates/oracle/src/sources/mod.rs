@@ -0,0 +1,58 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod coingecko;
+pub mod crypto;
+pub mod defillama;
+
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::Result;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The query of the price of a token on a chain
+#[derive(Clone, Debug)]
+pub struct PriceQuery {
+    pub chain_id: u64,
+    /// The token, or the zero address for the native token
+    pub token: Address,
+    /// The native token symbol of the chain
+    pub native_symbol: String,
+    /// The unix timestamp, or the latest if none
+    pub timestamp: Option<u64>,
+}
+
+impl PriceQuery {
+    /// Returns `true` if the query is of the native token
+    pub fn is_native(&self) -> bool {
+        self.token == Address::ZERO
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Source
+// -----------------------------------------------------------------------------
+
+/// A source of the token prices in USD
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// The name of the source
+    fn name(&self) -> &str;
+
+    /// The price in USD, or `None` if the source does not know the token
+    async fn price(&self, query: &PriceQuery) -> Result<Option<f64>>;
+}
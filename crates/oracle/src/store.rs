@@ -0,0 +1,161 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use lightdotso_db::models::token_price::{create_token_price, get_token_price_at};
+use lightdotso_prisma::{token, PrismaClient};
+use lightdotso_prisma_postgres::PrismaClient as PrismaPostgresClient;
+use lightdotso_redis::{
+    query::price::{get_token_price, set_token_price},
+    redis::Client,
+};
+use lightdotso_sqlx::{sqlx::types::BigDecimal, PostgresPool};
+use lightdotso_tracing::tracing::{info, warn};
+use prisma_client_rust::chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The seconds around the timestamp a recorded price is accepted in
+const RECORDED_PRICE_TOLERANCE_SECONDS: i64 = 900;
+
+// -----------------------------------------------------------------------------
+// Traits
+// -----------------------------------------------------------------------------
+
+/// The cache of the prices in the time buckets
+#[async_trait]
+pub trait PriceCache: Send + Sync {
+    /// The cached price in the bucket, if any
+    async fn get(&self, chain_id: u64, token: Address, bucket: u64) -> Option<f64>;
+
+    /// Cache the price in the bucket for the ttl
+    async fn set(&self, chain_id: u64, token: Address, bucket: u64, price: f64, ttl: u64);
+}
+
+/// The recorded prices, read for the historical lookups
+#[async_trait]
+pub trait PriceRecords: Send + Sync {
+    /// The price recorded around the timestamp, if any
+    async fn get(&self, chain_id: u64, token: Address, timestamp: u64) -> Option<f64>;
+
+    /// Record the price at the timestamp
+    async fn record(&self, chain_id: u64, token: Address, price: f64, timestamp: u64);
+}
+
+// -----------------------------------------------------------------------------
+// Redis
+// -----------------------------------------------------------------------------
+
+/// The cache of the prices in redis
+pub struct RedisPriceCache {
+    pub client: Arc<Client>,
+}
+
+#[async_trait]
+impl PriceCache for RedisPriceCache {
+    async fn get(&self, chain_id: u64, token: Address, bucket: u64) -> Option<f64> {
+        let mut con = self.client.get_connection().ok()?;
+
+        get_token_price(&mut con, chain_id, &format!("{:?}", token), bucket).ok().flatten()
+    }
+
+    async fn set(&self, chain_id: u64, token: Address, bucket: u64, price: f64, ttl: u64) {
+        let res = self.client.get_connection().and_then(|mut con| {
+            set_token_price(&mut con, chain_id, &format!("{:?}", token), bucket, price, ttl)
+        });
+        if let Err(err) = res {
+            warn!("Failed to cache the token price: {:?}", err);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Db
+// -----------------------------------------------------------------------------
+
+/// The recorded prices in the `TokenPrice` table, w/ the tokens of the `Token` table
+pub struct DbPriceRecords {
+    pub db_client: Arc<PrismaClient>,
+    pub postgres_client: Arc<PrismaPostgresClient>,
+    pub postgres_pool: PostgresPool,
+}
+
+impl DbPriceRecords {
+    /// Get the id of the token in the `Token` table, if known
+    async fn get_token_id(&self, chain_id: u64, token: Address) -> Option<String> {
+        // The native token is keyed as the zero address, which has no row in the `Token` table
+        if token == Address::ZERO {
+            info!("Skipped the recorded price of the native token of chain {}", chain_id);
+            return None;
+        }
+
+        self.db_client
+            .token()
+            .find_unique(token::address_chain_id(token.to_checksum(None), chain_id as i64))
+            .exec()
+            .await
+            .ok()
+            .flatten()
+            .map(|token| token.id)
+    }
+}
+
+#[async_trait]
+impl PriceRecords for DbPriceRecords {
+    async fn get(&self, chain_id: u64, token: Address, timestamp: u64) -> Option<f64> {
+        let token_id = self.get_token_id(chain_id, token).await?;
+
+        let price = get_token_price_at(
+            &self.postgres_pool,
+            token_id,
+            to_datetime(timestamp)?,
+            Duration::seconds(RECORDED_PRICE_TOLERANCE_SECONDS),
+        )
+        .await
+        .ok()
+        .flatten()?;
+
+        to_f64(&price.price)
+    }
+
+    async fn record(&self, chain_id: u64, token: Address, price: f64, timestamp: u64) {
+        let (Some(token_id), Some(timestamp)) =
+            (self.get_token_id(chain_id, token).await, to_datetime(timestamp))
+        else {
+            return;
+        };
+
+        if let Err(err) =
+            create_token_price(&self.postgres_client, token_id, price, timestamp).await
+        {
+            warn!("Failed to record the token price: {:?}", err);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+fn to_datetime(timestamp: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp as i64, 0)
+}
+
+fn to_f64(value: &BigDecimal) -> Option<f64> {
+    value.to_string().parse().ok()
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use async_trait::async_trait;
 use eyre::Result;
 use lightdotso_contracts::types::{
     GasAndPaymasterAndData, GasAndPaymasterAndDataVariant, UserOperation,
@@ -22,6 +23,38 @@ use lightdotso_kafka::{
 };
 use std::sync::Arc;
 
+// -----------------------------------------------------------------------------
+// Biller
+// -----------------------------------------------------------------------------
+
+/// The sink of the billing operations of the final sponsorships.
+#[async_trait]
+pub trait Biller: Send + Sync {
+    /// Bills the sponsored user operation
+    async fn bill(
+        &self,
+        chain_id: u64,
+        user_operation: UserOperation,
+        gas_and_paymaster_and_data_variant: GasAndPaymasterAndDataVariant,
+    ) -> Result<()>;
+}
+
+/// The biller producing the billing operation messages to kafka.
+pub struct KafkaBiller;
+
+#[async_trait]
+impl Biller for KafkaBiller {
+    async fn bill(
+        &self,
+        chain_id: u64,
+        user_operation: UserOperation,
+        gas_and_paymaster_and_data_variant: GasAndPaymasterAndDataVariant,
+    ) -> Result<()> {
+        create_billing_operation_msg(chain_id, user_operation, gas_and_paymaster_and_data_variant)
+            .await
+    }
+}
+
 // -----------------------------------------------------------------------------
 // Producer
 // -----------------------------------------------------------------------------
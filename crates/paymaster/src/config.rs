@@ -14,32 +14,146 @@
 
 use clap::Parser;
 use eyre::{eyre, Result};
+use lightdotso_constants::registry::spawn_chain_registry_watcher;
+use lightdotso_db::db::create_client;
 use lightdotso_jsonrpsee::rpc::{JsonRpcServer, JsonRpcServerType};
-use lightdotso_tracing::tracing::{error, info};
+use lightdotso_prisma::PrismaClient;
+use lightdotso_redis::{get_redis_client, redis::Client};
+use lightdotso_tracing::tracing::{error, info, warn};
 use std::{
+    collections::HashMap,
     future::pending,
     net::{IpAddr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
 };
 
-use crate::{paymaster::PaymasterApi, paymaster_api::PaymasterApiServer};
+use crate::{
+    billing_operation::KafkaBiller,
+    constants::PIMLICO_SPONSORSHIP_POLICIES,
+    erc7677_api::Erc7677ApiServer,
+    paymaster::PaymasterApi,
+    paymaster_api::PaymasterApiServer,
+    policy::{PolicyEngine, SponsorshipPolicy},
+    services::{
+        alchemy::AlchemySponsorProvider, biconomy::BiconomySponsorProvider,
+        particle::ParticleSponsorProvider, pimlico::PimlicoSponsorProvider, SponsorProvider,
+        SponsorRouter,
+    },
+};
 
 #[derive(Clone, Debug, Parser)]
 pub struct PaymasterArgs {
     /// The alchemy API key
     #[clap(long, env = "ALCHEMY_API_KEY")]
-    pub alchemy_api_key: String,
+    pub alchemy_api_key: Option<String>,
     /// The pilmico API key
     #[clap(long, env = "PIMLICO_API_KEY")]
-    pub pimlico_api_key: String,
+    pub pimlico_api_key: Option<String>,
     /// The particle network project id
     #[clap(long, env = "PARTICLE_NETWORK_PROJECT_ID")]
-    pub particle_network_project_id: String,
+    pub particle_network_project_id: Option<String>,
     /// The particle network paymaster project key
     #[clap(long, env = "PARTICLE_NETWORK_PROJECT_KEY")]
-    pub particle_network_project_key: String,
+    pub particle_network_project_key: Option<String>,
+    /// The default order of the sponsor providers, overridden per chain by the registry
+    #[arg(long, value_delimiter = ',', default_values_t = vec!["pimlico".to_string(), "particle".to_string(), "alchemy".to_string(), "biconomy".to_string()])]
+    #[clap(long, env = "PAYMASTER_SPONSOR_PROVIDERS")]
+    pub sponsor_providers: Vec<String>,
+    /// The default timeout of a sponsor provider in milliseconds
+    #[arg(long, default_value_t = 10_000)]
+    #[clap(long, env = "PAYMASTER_SPONSOR_TIMEOUT_MS")]
+    pub sponsor_timeout_ms: u64,
+    /// The timeouts in milliseconds w/ sponsor provider name
+    /// Example: pimlico=5000,alchemy=8000
+    #[arg(long, value_delimiter = ',')]
+    #[clap(long, env = "PAYMASTER_SPONSOR_TIMEOUTS")]
+    pub sponsor_timeouts: Vec<String>,
+    /// The path to the JSON file of the sponsorship policy, sponsoring anything if not set
+    #[clap(long, env = "PAYMASTER_POLICY_PATH")]
+    pub policy_path: Option<String>,
+    /// The flag of whether the gas is estimated and cross-checked w/ a local revm fork per request
+    #[arg(long, default_value_t = false)]
+    #[clap(long, env = "PAYMASTER_SIMULATE_GAS")]
+    pub simulate_gas: bool,
+}
+
+/// Parses the timeouts w/ provider name in the format of `name=milliseconds`
+pub fn parse_provider_timeouts(values: &[String]) -> Result<HashMap<String, Duration>> {
+    values
+        .iter()
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| {
+            let (name, millis) =
+                value.trim().split_once('=').ok_or_else(|| eyre!("Invalid timeout: {}", value))?;
+            Ok((name.to_string(), Duration::from_millis(millis.parse()?)))
+        })
+        .collect()
 }
 
 impl PaymasterArgs {
+    /// Get the sponsor router w/ the providers whose credentials are set
+    pub fn router(&self) -> Result<SponsorRouter> {
+        let timeouts = parse_provider_timeouts(&self.sponsor_timeouts)?;
+        let mut router = SponsorRouter::new(
+            self.sponsor_providers.clone(),
+            Duration::from_millis(self.sponsor_timeout_ms),
+        );
+        let mut providers: Vec<Arc<dyn SponsorProvider>> = vec![];
+
+        match &self.pimlico_api_key {
+            Some(api_key) => providers.push(Arc::new(PimlicoSponsorProvider {
+                api_key: api_key.clone(),
+                policies: PIMLICO_SPONSORSHIP_POLICIES.clone(),
+            })),
+            None => warn!("PIMLICO_API_KEY not set, skipping pimlico"),
+        }
+        match (&self.particle_network_project_id, &self.particle_network_project_key) {
+            (Some(project_id), Some(project_key)) => {
+                providers.push(Arc::new(ParticleSponsorProvider {
+                    project_id: project_id.clone(),
+                    project_key: project_key.clone(),
+                }))
+            }
+            _ => warn!("PARTICLE_NETWORK_PROJECT_ID or PARTICLE_NETWORK_PROJECT_KEY not set, skipping particle"),
+        }
+        match &self.alchemy_api_key {
+            Some(api_key) => {
+                providers.push(Arc::new(AlchemySponsorProvider { api_key: api_key.clone() }))
+            }
+            None => warn!("ALCHEMY_API_KEY not set, skipping alchemy"),
+        }
+        providers.push(Arc::new(BiconomySponsorProvider));
+
+        for provider in providers {
+            let timeout = timeouts.get(provider.name()).copied();
+            router.register(provider, timeout);
+        }
+
+        Ok(router)
+    }
+
+    /// Get the policy engine w/ the policy file if set
+    pub async fn policy_engine(&self) -> Result<PolicyEngine> {
+        let policy = match &self.policy_path {
+            Some(path) => SponsorshipPolicy::from_path(path)?,
+            None => SponsorshipPolicy::default(),
+        };
+
+        // Create the redis client
+        let redis_client: Option<Arc<Client>> =
+            get_redis_client().map_or_else(|_e| None, |client| Some(Arc::new(client)));
+
+        // Create the db client w/ the billing ledger, only if the balance is checked
+        let db_client: Option<Arc<PrismaClient>> = if policy.require_billing_balance {
+            Some(Arc::new(create_client().await?))
+        } else {
+            None
+        };
+
+        Ok(PolicyEngine::new(policy, redis_client, db_client))
+    }
+
     pub async fn run(self) -> Result<()> {
         // Add info
         info!("PaymasterArgs run, starting...");
@@ -47,6 +161,18 @@ impl PaymasterArgs {
         // Print the config
         // info!("Config: {:?}", self);
 
+        // Build the sponsor router
+        let router = Arc::new(self.router()?);
+
+        // Build the sponsorship policy engine
+        let policy = Arc::new(self.policy_engine().await?);
+
+        // Keep the chain registry in sync w/ the file
+        spawn_chain_registry_watcher(Duration::from_secs(30));
+
+        // Simulate the gas w/ a local fork only if enabled, as each request forks the chain
+        let simulate_gas = self.simulate_gas;
+
         tokio::spawn({
             async move {
                 // Create the server
@@ -59,8 +185,18 @@ impl PaymasterArgs {
                     3001,
                 );
 
+                let paymaster =
+                    PaymasterApi { router, policy, biller: Arc::new(KafkaBiller), simulate_gas };
+
                 // Add the paymaster server
-                server.add_methods(PaymasterApi {}.into_rpc(), JsonRpcServerType::Http)?;
+                server.add_methods(
+                    PaymasterApiServer::into_rpc(paymaster.clone()),
+                    JsonRpcServerType::Http,
+                )?;
+
+                // Add the ERC-7677 paymaster server
+                server
+                    .add_methods(Erc7677ApiServer::into_rpc(paymaster), JsonRpcServerType::Http)?;
 
                 // Start the server
                 let handle = server.start().await.map_err(|e| eyre!("Error in handle: {:?}", e));
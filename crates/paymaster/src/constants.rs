@@ -14,7 +14,7 @@
 
 #![allow(clippy::unwrap_used)]
 
-use alloy::primitives::Address;
+use alloy::primitives::{Address, U256};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
@@ -65,56 +65,32 @@ lazy_static! {
     };
 }
 
-// The alchemy rpc policy ids
+// The sponsor name of the ERC-7677 paymaster stub data
 lazy_static! {
-    pub static ref ALCHEMY_POLICY_IDS: HashMap<u64, String> = {
-        let mut m = HashMap::new();
-
-        // Mainnet
-        m.insert(1, "dd589d48-9d24-4147-bd84-e58ae1e0c230".to_string());
-        m.insert(10, "c43f82d6-71a2-46b9-9974-4d4aeacfb6be".to_string());
-        m.insert(137, "aa975c1f-f330-42c6-ac69-fbd9a8e2acc0".to_string());
-        m.insert(252, "c4ce6a98-17aa-484a-a6ca-df91a27fe47e".to_string());
-        m.insert(8453, "1e571063-c278-47c0-9746-c338276d40e1".to_string());
-        m.insert(42161, "bda8abe2-a5ce-43f1-9382-16f3f56e9f30".to_string());
-        m.insert(7777777, "de5723e2-65d4-428c-b154-1d3e72d7ccf4".to_string());
-
-        // Testnet
-        m.insert(2522, "764f5629-df9d-40a7-a465-1056f297a595".to_string());
-        m.insert(80002, "8cc31361-3500-4220-975c-5844acc8ce9d".to_string());
-        m.insert(84532, "44bcbdce-2122-478e-ad7e-dcf82d721167".to_string());
-        m.insert(421614, "3e07561b-8b43-4cd1-8498-16c9cf04bbe8".to_string());
-        m.insert(11155111, "0264de95-c0bc-422b-86a7-70f0c372546c".to_string());
-        m.insert(11155420, "94e3559b-4b3f-427f-9d50-70c5fc02f51a".to_string());
-        m.insert(999999999, "f29dfce1-d309-443e-ba1b-405273752fc2".to_string());
-
-        m
+    #[derive(Debug)]
+    pub static ref PAYMASTER_SPONSOR_NAME: String = {
+        "Light".to_string()
     };
 }
 
-// The biconomy paymaster rpc urls
-// From: https://docs.biconomy.io/Bundler/supportedNetworks
-// Thank you to the Biconomy team for providing the service!
+// The paymaster of the ERC-7677 paymaster stub data, w/ the format of the verifying paymaster
 lazy_static! {
-    pub static ref BICONOMY_PAYMASTER_RPC_URLS: HashMap<u64, String> = {
-        let mut m = HashMap::new();
-
-        // Mainnet
-        m.insert(1329, "https://paymaster.biconomy.io/api/v1/1329/".to_string());
-
-        m
+    #[derive(Debug)]
+    pub static ref PAYMASTER_STUB_ADDRESS: Address = {
+        "0x000000000054230BA02ADD2d96fA4362A8606F97".parse().unwrap()
     };
 }
 
-// The biconomy rpc policy ids
-// From: https://docs.biconomy.io/supportedNetworks
+// The paymaster verification gas limit of the ERC-7677 paymaster stub data, as the upper bound of
+// the sponsors
 lazy_static! {
-    pub static ref BICONOMY_POLICY_IDS: HashMap<u64, String> = {
-        let mut m = HashMap::new();
-
-        // Mainnet
-        m.insert(1329, "7GR4dz_kx.9ca8789e-cbae-4a71-91d6-6fca2a96d055".to_string());
+    #[derive(Debug)]
+    pub static ref PAYMASTER_STUB_VERIFICATION_GAS_LIMIT: U256 = U256::from(100_000);
+}
 
-        m
-    };
+// The paymaster post operation gas limit of the ERC-7677 paymaster stub data, as the upper bound
+// of the sponsors
+lazy_static! {
+    #[derive(Debug)]
+    pub static ref PAYMASTER_STUB_POST_OP_GAS_LIMIT: U256 = U256::from(50_000);
 }
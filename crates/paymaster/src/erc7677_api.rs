@@ -0,0 +1,42 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, U64};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use lightdotso_contracts::types::{PaymasterData, PaymasterStubData, UserOperationRequestVariant};
+
+use crate::policy::SponsorshipContext;
+
+/// The paymaster web service methods of ERC-7677.
+/// From: https://eips.ethereum.org/EIPS/eip-7677
+#[rpc(client, server, namespace = "pm")]
+pub trait Erc7677Api {
+    #[method(name = "getPaymasterStubData")]
+    async fn get_paymaster_stub_data(
+        &self,
+        user_operation: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: U64,
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<PaymasterStubData>;
+
+    #[method(name = "getPaymasterData")]
+    async fn get_paymaster_data(
+        &self,
+        user_operation: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: U64,
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<PaymasterData>;
+}
@@ -0,0 +1,479 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::{
+    primitives::{hex, Address, Bytes, U256},
+    sol_types::SolValue,
+};
+use eyre::Result;
+use lightdotso_constants::registry::get_chain_registry;
+use lightdotso_contracts::{
+    entrypoint_v060::EntryPointV060::UserOperation as EntryPointV060UserOperation,
+    entrypoint_v070::EntryPointV070::PackedUserOperation as EntryPointV070PackedUserOperation,
+    l1_fee::{calldata_gas, get_packed_user_operation_l1_fee, get_user_operation_l1_fee},
+    types::{
+        EstimateResult, GasAndPaymasterAndDataVariant, PackedEstimateResult, PackedUserOperation,
+        UserOperation,
+    },
+};
+use lightdotso_simulator::{
+    estimate::{estimate_user_operation_gas, with_gas_margin, UserOperationGasRequest},
+    verification::{simulate_verification_gas, VerificationGasRequest},
+};
+use lightdotso_tracing::tracing::warn;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+pub use lightdotso_simulator::estimate::{
+    MIN_GAS_HEADROOM as MIN_VERIFICATION_GAS_HEADROOM, VERIFICATION_GAS_MARGIN_PERCENT,
+};
+
+/// The factor above the local estimate at which a third-party estimate is considered inflated
+pub const MAX_ESTIMATE_INFLATION: u64 = 3;
+
+/// The placeholder of the unset gas fields, so that the packed size is not underestimated
+const GAS_FIELD_PLACEHOLDER: u64 = u32::MAX as u64;
+
+/// The well-formed dummy ECDSA signature w/ a low `s` and a valid `v`, used when the signature is
+/// not set so that the signature checks run their full path w/o reverting early
+pub(crate) const DUMMY_SIGNATURE: [u8; 65] = hex!(
+    "fffffffffffffffffffffffffffffff0000000000000000000000000000000007aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1c"
+);
+
+// -----------------------------------------------------------------------------
+// Overheads
+// -----------------------------------------------------------------------------
+
+// From: https://github.com/eth-infinitism/bundler/blob/2ef42d3d2cf2ac3fb80bd2a9c7a9b6ca45b1c0d9/packages/sdk/src/calcPreVerificationGas.ts#L44-L52
+// License: MIT
+
+/// The gas overheads of a user operation in a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasOverheads {
+    /// The fixed overhead of the bundle, split between the user operations
+    pub fixed: u64,
+    /// The per user operation overhead
+    pub per_user_op: u64,
+    /// The per word overhead of the packed user operation
+    pub per_user_op_word: u64,
+    /// The expected number of user operations in a bundle
+    pub bundle_size: u64,
+}
+
+impl Default for GasOverheads {
+    fn default() -> Self {
+        Self { fixed: 21_000, per_user_op: 18_300, per_user_op_word: 4, bundle_size: 1 }
+    }
+}
+
+impl GasOverheads {
+    /// Get the overheads of the chain, w/ the overrides of the chain registry
+    pub fn for_chain(chain_id: u64) -> Self {
+        let registry = get_chain_registry();
+        let defaults = Self::default();
+        let get =
+            |name: &str, default: u64| registry.gas_overhead(chain_id, name).unwrap_or(default);
+
+        Self {
+            fixed: get("fixed", defaults.fixed),
+            per_user_op: get("per_user_op", defaults.per_user_op),
+            per_user_op_word: get("per_user_op_word", defaults.per_user_op_word),
+            bundle_size: get("bundle_size", defaults.bundle_size).max(1),
+        }
+    }
+
+    /// Get the static pre verification gas of the packed user operation
+    pub fn pre_verification_gas(&self, packed: &[u8]) -> u64 {
+        let words = (packed.len() as u64).div_ceil(32);
+
+        calldata_gas(packed) +
+            self.fixed / self.bundle_size +
+            self.per_user_op +
+            self.per_user_op_word * words
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Pre Verification Gas
+// -----------------------------------------------------------------------------
+
+/// Fills the unset gas fields and the signature w/ placeholders
+fn fill_placeholders(user_operation: &UserOperation) -> UserOperation {
+    let placeholder = |value: U256| {
+        if value.is_zero() {
+            U256::from(GAS_FIELD_PLACEHOLDER)
+        } else {
+            value
+        }
+    };
+
+    let mut user_operation = user_operation.clone();
+    user_operation.call_gas_limit = placeholder(user_operation.call_gas_limit);
+    user_operation.verification_gas_limit = placeholder(user_operation.verification_gas_limit);
+    user_operation.pre_verification_gas = placeholder(user_operation.pre_verification_gas);
+    user_operation.max_fee_per_gas = placeholder(user_operation.max_fee_per_gas);
+    user_operation.max_priority_fee_per_gas = placeholder(user_operation.max_priority_fee_per_gas);
+    if user_operation.signature.is_empty() {
+        user_operation.signature = Bytes::from(DUMMY_SIGNATURE);
+    }
+    user_operation
+}
+
+/// Fills the unset gas fields and the signature of the packed user operation w/ placeholders
+fn fill_packed_placeholders(packed_user_operation: &PackedUserOperation) -> PackedUserOperation {
+    let placeholder = |value: U256| {
+        if value.is_zero() {
+            U256::from(GAS_FIELD_PLACEHOLDER)
+        } else {
+            value
+        }
+    };
+
+    let mut packed_user_operation = packed_user_operation.clone();
+    packed_user_operation.call_gas_limit = placeholder(packed_user_operation.call_gas_limit);
+    packed_user_operation.verification_gas_limit =
+        placeholder(packed_user_operation.verification_gas_limit);
+    packed_user_operation.pre_verification_gas =
+        placeholder(packed_user_operation.pre_verification_gas);
+    packed_user_operation.max_fee_per_gas = placeholder(packed_user_operation.max_fee_per_gas);
+    packed_user_operation.max_priority_fee_per_gas =
+        placeholder(packed_user_operation.max_priority_fee_per_gas);
+    if packed_user_operation.signature.is_empty() {
+        packed_user_operation.signature = Bytes::from(DUMMY_SIGNATURE);
+    }
+    packed_user_operation
+}
+
+/// Get the L1 component of the pre verification gas, in L2 gas at the max fee per gas
+fn l1_gas_component(l1_fee: Option<U256>, max_fee_per_gas: U256) -> U256 {
+    match l1_fee {
+        Some(l1_fee) if !max_fee_per_gas.is_zero() => l1_fee.div_ceil(max_fee_per_gas),
+        _ => U256::ZERO,
+    }
+}
+
+/// Calculates the pre verification gas of the user operation (v0.6), including the L1 data fee
+/// of rollups at the max fee per gas
+pub async fn calculate_pre_verification_gas(
+    chain_id: u64,
+    user_operation: &UserOperation,
+) -> Result<U256> {
+    let filled = fill_placeholders(user_operation);
+    let packed = EntryPointV060UserOperation::from(filled.clone()).abi_encode();
+    let static_gas = GasOverheads::for_chain(chain_id).pre_verification_gas(&packed);
+
+    let l1_fee = get_user_operation_l1_fee(chain_id, &filled).await?;
+
+    Ok(U256::from(static_gas) + l1_gas_component(l1_fee, user_operation.max_fee_per_gas))
+}
+
+/// Calculates the pre verification gas of the packed user operation (v0.7), including the L1 data
+/// fee of rollups at the max fee per gas
+pub async fn calculate_packed_pre_verification_gas(
+    chain_id: u64,
+    packed_user_operation: &PackedUserOperation,
+) -> Result<U256> {
+    let filled = fill_packed_placeholders(packed_user_operation);
+    let packed = EntryPointV070PackedUserOperation::from(filled.clone()).abi_encode();
+    let static_gas = GasOverheads::for_chain(chain_id).pre_verification_gas(&packed);
+
+    let l1_fee = get_packed_user_operation_l1_fee(chain_id, &filled).await?;
+
+    Ok(U256::from(static_gas) + l1_gas_component(l1_fee, packed_user_operation.max_fee_per_gas))
+}
+
+// -----------------------------------------------------------------------------
+// Verification Gas Limit
+// -----------------------------------------------------------------------------
+
+/// Adds the safety margin to the simulated verification gas
+pub fn with_verification_gas_margin(verification_gas: u64) -> U256 {
+    U256::from(with_gas_margin(verification_gas, VERIFICATION_GAS_MARGIN_PERCENT))
+}
+
+/// Calculates the verification gas limit of the user operation (v0.6) w/ the simulator
+pub async fn calculate_verification_gas_limit(
+    chain_id: u64,
+    entry_point: Address,
+    user_operation: &UserOperation,
+) -> Result<U256> {
+    let request = VerificationGasRequest::from_user_operation(
+        chain_id,
+        entry_point,
+        &fill_placeholders(user_operation),
+    );
+    let response = simulate_verification_gas(request).await?;
+
+    Ok(with_verification_gas_margin(response.verification_gas()))
+}
+
+/// Calculates the verification gas limit of the packed user operation (v0.7) w/ the simulator
+pub async fn calculate_packed_verification_gas_limit(
+    chain_id: u64,
+    entry_point: Address,
+    packed_user_operation: &PackedUserOperation,
+) -> Result<U256> {
+    let filled = fill_packed_placeholders(packed_user_operation);
+    let request =
+        VerificationGasRequest::from_packed_user_operation(chain_id, entry_point, &filled);
+    let response = simulate_verification_gas(request).await?;
+
+    Ok(with_verification_gas_margin(response.verification_gas()))
+}
+
+// -----------------------------------------------------------------------------
+// Local Estimation
+// -----------------------------------------------------------------------------
+
+/// Estimates the gas limits of the user operation (v0.6) locally w/ the simulator, in place of the
+/// upstream `eth_estimateUserOperationGas`, failing if any of them can not be estimated
+pub async fn estimate_user_operation_gas_locally(
+    chain_id: u64,
+    entry_point: Address,
+    user_operation: &UserOperation,
+) -> Result<EstimateResult> {
+    let request = UserOperationGasRequest::from_user_operation(
+        chain_id,
+        entry_point,
+        &fill_placeholders(user_operation),
+    );
+    let response = estimate_user_operation_gas(request).await?;
+
+    // The verification gas limit also applies to the paymaster in v0.6
+    let verification_gas_limit = U256::from(with_gas_margin(
+        response.deployment_gas + response.validation_gas + response.paymaster_validation_gas,
+        VERIFICATION_GAS_MARGIN_PERCENT,
+    ));
+    let call_gas_limit = U256::from(response.call_gas_limit);
+
+    let mut user_operation = user_operation.clone();
+    user_operation.verification_gas_limit = verification_gas_limit;
+    user_operation.call_gas_limit = call_gas_limit;
+    let pre_verification_gas = calculate_pre_verification_gas(chain_id, &user_operation).await?;
+
+    Ok(EstimateResult { pre_verification_gas, verification_gas_limit, call_gas_limit })
+}
+
+/// Estimates the gas limits of the packed user operation (v0.7) locally w/ the simulator, in place
+/// of the upstream `eth_estimateUserOperationGas`, failing if any of them can not be estimated
+pub async fn estimate_packed_user_operation_gas_locally(
+    chain_id: u64,
+    entry_point: Address,
+    packed_user_operation: &PackedUserOperation,
+) -> Result<PackedEstimateResult> {
+    let request = UserOperationGasRequest::from_packed_user_operation(
+        chain_id,
+        entry_point,
+        &fill_packed_placeholders(packed_user_operation),
+    );
+    let response = estimate_user_operation_gas(request).await?;
+
+    let verification_gas_limit = U256::from(response.verification_gas_limit);
+    let call_gas_limit = U256::from(response.call_gas_limit);
+    let paymaster_verification_gas_limit = U256::from(response.paymaster_verification_gas_limit);
+
+    let mut packed_user_operation = packed_user_operation.clone();
+    packed_user_operation.verification_gas_limit = verification_gas_limit;
+    packed_user_operation.call_gas_limit = call_gas_limit;
+    if packed_user_operation.paymaster.is_some() {
+        packed_user_operation.paymaster_verification_gas_limit =
+            Some(paymaster_verification_gas_limit);
+    }
+    let pre_verification_gas =
+        calculate_packed_pre_verification_gas(chain_id, &packed_user_operation).await?;
+
+    Ok(PackedEstimateResult {
+        pre_verification_gas,
+        verification_gas_limit,
+        call_gas_limit,
+        paymaster_verification_gas_limit,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Cross Check
+// -----------------------------------------------------------------------------
+
+/// Returns `true` if the third-party estimate is inflated beyond the local estimate
+pub fn is_estimate_inflated(field: &str, local: U256, remote: U256) -> bool {
+    let inflated = !local.is_zero() && remote > local * U256::from(MAX_ESTIMATE_INFLATION);
+    if inflated {
+        warn!("Inflated {} estimate: remote {} vs local {}", field, remote, local);
+    }
+    inflated
+}
+
+/// Checks the gas estimates returned by the sponsor against the local estimates, returning `true`
+/// if any is inflated; the sponsor signs over the gas fields, so they can not be replaced
+pub fn check_sponsor_estimates(
+    pre_verification_gas: U256,
+    verification_gas_limit: U256,
+    gas_and_paymaster_and_data_variant: &GasAndPaymasterAndDataVariant,
+) -> bool {
+    let (sponsor_pre_verification_gas, sponsor_verification_gas_limit) =
+        match gas_and_paymaster_and_data_variant {
+            GasAndPaymasterAndDataVariant::Default(data) => {
+                (data.pre_verification_gas, data.verification_gas_limit)
+            }
+            GasAndPaymasterAndDataVariant::Packed(data) => {
+                (data.pre_verification_gas, data.verification_gas_limit)
+            }
+        };
+
+    let is_pre_verification_gas_inflated = is_estimate_inflated(
+        "sponsor preVerificationGas",
+        pre_verification_gas,
+        sponsor_pre_verification_gas,
+    );
+    let is_verification_gas_limit_inflated = is_estimate_inflated(
+        "sponsor verificationGasLimit",
+        verification_gas_limit,
+        sponsor_verification_gas_limit,
+    );
+
+    is_pre_verification_gas_inflated || is_verification_gas_limit_inflated
+}
+
+/// Cross-checks the third-party gas estimates of the user operation (v0.6) against the local
+/// estimates, replacing the inflated and the unset ones
+/// The pre verification gas is raised to the local one at the final fees, as the L1 component is
+/// only known once the fees are set
+/// The verification gas limit is only checked if `simulate_gas`, as it is simulated on a fork
+pub async fn cross_check_user_operation(
+    chain_id: u64,
+    entry_point: Address,
+    mut user_operation: UserOperation,
+    simulate_gas: bool,
+) -> UserOperation {
+    match calculate_pre_verification_gas(chain_id, &user_operation).await {
+        Ok(local) => {
+            if user_operation.pre_verification_gas < local ||
+                is_estimate_inflated(
+                    "preVerificationGas",
+                    local,
+                    user_operation.pre_verification_gas,
+                )
+            {
+                user_operation.pre_verification_gas = local;
+            }
+        }
+        Err(err) => warn!("Failed to calculate the preVerificationGas: {:?}", err),
+    }
+
+    if !simulate_gas {
+        return user_operation;
+    }
+
+    match calculate_verification_gas_limit(chain_id, entry_point, &user_operation).await {
+        Ok(local) => {
+            if is_estimate_inflated(
+                "verificationGasLimit",
+                local,
+                user_operation.verification_gas_limit,
+            ) {
+                user_operation.verification_gas_limit = local;
+            }
+        }
+        Err(err) => warn!("Failed to calculate the verificationGasLimit: {:?}", err),
+    }
+
+    user_operation
+}
+
+/// Cross-checks the third-party gas estimates of the packed user operation (v0.7) against the
+/// local estimates, replacing the inflated and the unset ones
+/// The pre verification gas is raised to the local one at the final fees, as the L1 component is
+/// only known once the fees are set
+/// The verification gas limit is only checked if `simulate_gas`, as it is simulated on a fork
+pub async fn cross_check_packed_user_operation(
+    chain_id: u64,
+    entry_point: Address,
+    mut packed_user_operation: PackedUserOperation,
+    simulate_gas: bool,
+) -> PackedUserOperation {
+    match calculate_packed_pre_verification_gas(chain_id, &packed_user_operation).await {
+        Ok(local) => {
+            if packed_user_operation.pre_verification_gas < local ||
+                is_estimate_inflated(
+                    "preVerificationGas",
+                    local,
+                    packed_user_operation.pre_verification_gas,
+                )
+            {
+                packed_user_operation.pre_verification_gas = local;
+            }
+        }
+        Err(err) => warn!("Failed to calculate the preVerificationGas: {:?}", err),
+    }
+
+    if !simulate_gas {
+        return packed_user_operation;
+    }
+
+    match calculate_packed_verification_gas_limit(chain_id, entry_point, &packed_user_operation)
+        .await
+    {
+        Ok(local) => {
+            if is_estimate_inflated(
+                "verificationGasLimit",
+                local,
+                packed_user_operation.verification_gas_limit,
+            ) {
+                packed_user_operation.verification_gas_limit = local;
+            }
+        }
+        Err(err) => warn!("Failed to calculate the verificationGasLimit: {:?}", err),
+    }
+
+    packed_user_operation
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_overheads_pre_verification_gas() {
+        let overheads = GasOverheads::default();
+
+        // 64 zero bytes in 2 words
+        assert_eq!(overheads.pre_verification_gas(&[0; 64]), 64 * 4 + 21_000 + 18_300 + 2 * 4);
+    }
+
+    #[test]
+    fn test_l1_gas_component() {
+        assert_eq!(l1_gas_component(None, U256::from(1)), U256::ZERO);
+        assert_eq!(l1_gas_component(Some(U256::from(10)), U256::ZERO), U256::ZERO);
+        assert_eq!(l1_gas_component(Some(U256::from(10)), U256::from(3)), U256::from(4));
+    }
+
+    #[test]
+    fn test_with_verification_gas_margin() {
+        assert_eq!(with_verification_gas_margin(10_000), U256::from(20_000));
+        assert_eq!(with_verification_gas_margin(100_000), U256::from(130_000));
+    }
+
+    #[test]
+    fn test_is_estimate_inflated() {
+        assert!(!is_estimate_inflated("test", U256::ZERO, U256::from(100)));
+        assert!(!is_estimate_inflated("test", U256::from(100), U256::from(300)));
+        assert!(is_estimate_inflated("test", U256::from(100), U256::from(301)));
+    }
+}
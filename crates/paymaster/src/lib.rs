@@ -17,8 +17,12 @@
 pub mod billing_operation;
 pub mod config;
 pub mod constants;
+pub mod erc7677_api;
+pub mod estimate;
 pub mod paymaster;
 pub mod paymaster_api;
+pub mod policy;
 pub mod server;
 pub mod services;
+pub mod token;
 pub mod utils;
@@ -13,25 +13,50 @@
 // limitations under the License.
 
 use crate::{
-    billing_operation::create_billing_operation_msg,
-    services::fetch_gas_and_paymaster_and_data_variant,
+    billing_operation::Biller,
+    constants::{
+        PAYMASTER_SPONSOR_NAME, PAYMASTER_STUB_ADDRESS, PAYMASTER_STUB_POST_OP_GAS_LIMIT,
+        PAYMASTER_STUB_VERIFICATION_GAS_LIMIT,
+    },
+    estimate::{check_sponsor_estimates, DUMMY_SIGNATURE},
+    policy::{
+        PolicyDecision, PolicyEngine, PolicyReason, SponsorshipContext, POLICY_REJECTED_CODE,
+    },
+    services::SponsorRouter,
+    token::request_token_gas_and_paymaster_and_data,
     utils::{construct_packed_user_operation, construct_user_operation},
 };
-use alloy::primitives::Address;
-use jsonrpsee::core::RpcResult;
+use alloy::primitives::{Address, Bytes, U256};
+use eyre::eyre;
+use jsonrpsee::{
+    core::RpcResult,
+    types::{ErrorObject, ErrorObjectOwned},
+};
 use lightdotso_contracts::types::{
-    GasAndPaymasterAndData, GasAndPaymasterAndDataVariant, PaymasterAndData,
+    GasAndPaymasterAndData, GasAndPaymasterAndDataResponse, GasAndPaymasterAndDataVariant,
+    PaymasterAndData, PaymasterData, PaymasterSponsor, PaymasterStubData, UserOperation,
     UserOperationRequestVariant,
 };
 use lightdotso_jsonrpsee::error::JsonRpcError;
-use lightdotso_tracing::tracing::info;
+use lightdotso_tracing::tracing::{info, warn};
+use std::sync::Arc;
 
 // -----------------------------------------------------------------------------
 // Structs
 // -----------------------------------------------------------------------------
 
 /// The paymaster api implementation.
-pub(crate) struct PaymasterApi {}
+#[derive(Clone)]
+pub(crate) struct PaymasterApi {
+    /// The router of the sponsor providers
+    pub(crate) router: Arc<SponsorRouter>,
+    /// The engine of the sponsorship policy
+    pub(crate) policy: Arc<PolicyEngine>,
+    /// The biller of the final sponsorships
+    pub(crate) biller: Arc<dyn Biller>,
+    /// Whether the gas is estimated and cross-checked w/ a local fork
+    pub(crate) simulate_gas: bool,
+}
 
 // -----------------------------------------------------------------------------
 // Implementations
@@ -43,10 +68,12 @@ impl PaymasterApi {
         user_operation: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
+        context: SponsorshipContext,
     ) -> RpcResult<PaymasterAndData> {
-        let res =
-            self.request_gas_and_paymaster_and_data(user_operation, entry_point, chain_id).await?;
-        match res {
+        let res = self
+            .request_gas_and_paymaster_and_data(user_operation, entry_point, chain_id, context)
+            .await?;
+        match res.gas_and_paymaster_and_data {
             GasAndPaymasterAndDataVariant::Default(gas_and_paymaster_and_data) => {
                 Ok(PaymasterAndData {
                     paymaster_and_data: gas_and_paymaster_and_data.paymaster_and_data,
@@ -68,65 +95,397 @@ impl PaymasterApi {
         user_operation_request: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
-    ) -> RpcResult<GasAndPaymasterAndDataVariant> {
-        match user_operation_request.clone() {
-            UserOperationRequestVariant::Default(uor) => {
-                // Construct the user operation w/ rpc.
-                let user_operation = construct_user_operation(chain_id, uor.clone(), entry_point)
-                    .await
-                    .map_err(JsonRpcError::from)?;
-                // Log the construct in hex.
-                info!("user_operation: {:?}", user_operation);
+        context: SponsorshipContext,
+    ) -> RpcResult<GasAndPaymasterAndDataResponse> {
+        self.sponsor_user_operation(user_operation_request, entry_point, chain_id, context, true)
+            .await
+    }
 
-                // Get the paymaster operation sponsor.
-                let gas_and_paymaster_and_data_variant = fetch_gas_and_paymaster_and_data_variant(
-                    user_operation_request.clone(),
-                    entry_point,
-                    chain_id,
-                )
-                .await
-                .map_err(JsonRpcError::from)?;
+    pub(crate) async fn get_paymaster_stub_data(
+        &self,
+        user_operation_request: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: u64,
+        context: SponsorshipContext,
+    ) -> RpcResult<PaymasterStubData> {
+        // The stub is sponsored if the gas is already set, w/o charging the caps nor billing, and
+        // is the unsigned dummy otherwise.
+        let stub_data = if has_gas_limits(&user_operation_request) {
+            self.sponsor_user_operation(
+                user_operation_request,
+                entry_point,
+                chain_id,
+                context,
+                false,
+            )
+            .await?
+            .gas_and_paymaster_and_data
+            .into()
+        } else {
+            stub_paymaster_data(&user_operation_request)
+        };
 
-                // Write the paymaster operation to the database.
-                create_billing_operation_msg(
-                    chain_id,
-                    user_operation,
-                    gas_and_paymaster_and_data_variant.clone(),
-                )
-                .await
-                .map_err(JsonRpcError::from)?;
+        // The stub is never final, so that the sponsorship is charged and billed w/
+        // `pm_getPaymasterData`.
+        Ok(PaymasterStubData {
+            sponsor: Some(PaymasterSponsor {
+                name: PAYMASTER_SPONSOR_NAME.to_string(),
+                icon: None,
+            }),
+            is_final: false,
+            ..stub_data
+        })
+    }
 
-                Ok(gas_and_paymaster_and_data_variant)
+    pub(crate) async fn get_paymaster_data(
+        &self,
+        user_operation_request: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: u64,
+        context: SponsorshipContext,
+    ) -> RpcResult<PaymasterData> {
+        let res = self
+            .sponsor_user_operation(user_operation_request, entry_point, chain_id, context, true)
+            .await?;
+
+        Ok(res.gas_and_paymaster_and_data.into())
+    }
+
+    /// Sponsors the user operation, charging the caps and the billing only if `is_final`.
+    async fn sponsor_user_operation(
+        &self,
+        user_operation_request: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: u64,
+        context: SponsorshipContext,
+        is_final: bool,
+    ) -> RpcResult<GasAndPaymasterAndDataResponse> {
+        // Construct the user operation w/ rpc.
+        let user_operation: UserOperation = match user_operation_request.clone() {
+            UserOperationRequestVariant::Default(uor) => {
+                construct_user_operation(chain_id, uor, entry_point, self.simulate_gas)
+                    .await
+                    .map_err(JsonRpcError::from)?
             }
             UserOperationRequestVariant::Packed(puor) => {
-                // Construct the packed user operation w/ rpc.
-                let packed_user_operation =
-                    construct_packed_user_operation(chain_id, puor.clone(), entry_point)
-                        .await
-                        .map_err(JsonRpcError::from)?;
-                // Log the packed_user_operation in hex.
-                info!("packed_user_operation: {:?}", packed_user_operation);
+                construct_packed_user_operation(chain_id, puor, entry_point, self.simulate_gas)
+                    .await
+                    .map_err(JsonRpcError::from)?
+                    .into()
+            }
+        };
+        // Log the construct in hex.
+        info!("user_operation: {:?}", user_operation);
 
-                // Get the paymaster operation sponsor.
-                let gas_and_paymaster_and_data_variant = fetch_gas_and_paymaster_and_data_variant(
-                    user_operation_request.clone(),
-                    entry_point,
+        // Evaluate the sponsorship policy before any sponsor is asked.
+        let decision = self.evaluate_policy(chain_id, &user_operation, &context).await?;
+
+        if is_final {
+            // Reserve the cost against the caps and budgets, w/ the spend since the evaluation.
+            let reason = self.policy.reserve_spend(&decision).map_err(JsonRpcError::from)?;
+            if reason != PolicyReason::Approved {
+                return Err(policy_rejection(PolicyDecision { reason, ..decision }));
+            }
+        }
+
+        let result: RpcResult<GasAndPaymasterAndDataResponse> = async {
+            // Get the paymaster operation sponsor, or the ERC-20 paymaster if paid in the token.
+            let res = match context.token {
+                Some(token) => request_token_gas_and_paymaster_and_data(
+                    &self.router,
                     chain_id,
+                    entry_point,
+                    &user_operation_request,
+                    &user_operation,
+                    token,
+                    &context,
                 )
                 .await
-                .map_err(JsonRpcError::from)?;
+                .map_err(JsonRpcError::from)?,
+                None => GasAndPaymasterAndDataResponse {
+                    gas_and_paymaster_and_data: self
+                        .router
+                        .sponsor(&user_operation_request, entry_point, chain_id, &context)
+                        .await
+                        .map_err(JsonRpcError::from)?,
+                    token_quote: None,
+                },
+            };
+
+            // Cross-check the sponsor's gas w/ the constructed user operation, rejecting the
+            // inflated sponsorship as the signed gas fields can not be replaced.
+            if check_sponsor_estimates(
+                user_operation.pre_verification_gas,
+                user_operation.verification_gas_limit,
+                &res.gas_and_paymaster_and_data,
+            ) {
+                return Err(JsonRpcError::from(eyre!("Sponsor gas estimates are inflated")).into());
+            }
 
+            if is_final {
                 // Write the paymaster operation to the database.
-                create_billing_operation_msg(
-                    chain_id,
-                    packed_user_operation.into(),
-                    gas_and_paymaster_and_data_variant.clone(),
-                )
-                .await
-                .map_err(JsonRpcError::from)?;
+                self.biller
+                    .bill(chain_id, user_operation, res.gas_and_paymaster_and_data.clone())
+                    .await
+                    .map_err(JsonRpcError::from)?;
+            }
 
-                Ok(gas_and_paymaster_and_data_variant)
+            Ok(res)
+        }
+        .await;
+
+        // Release the reserved cost if the sponsorship failed.
+        if is_final && result.is_err() {
+            if let Err(err) = self.policy.release_spend(&decision) {
+                warn!("Failed to release the sponsorship spend: {:?}", err);
             }
         }
+
+        result
+    }
+
+    /// Evaluates the sponsorship policy, rejecting the request w/ the reason code if denied.
+    async fn evaluate_policy(
+        &self,
+        chain_id: u64,
+        user_operation: &UserOperation,
+        context: &SponsorshipContext,
+    ) -> RpcResult<PolicyDecision> {
+        let decision = self.policy.evaluate(chain_id, user_operation, context).await;
+        if !decision.is_approved() {
+            return Err(policy_rejection(decision));
+        }
+
+        Ok(decision)
+    }
+}
+
+/// Get the error of the sponsorship rejected by the policy w/ its decision.
+fn policy_rejection(decision: PolicyDecision) -> ErrorObjectOwned {
+    ErrorObject::owned(
+        POLICY_REJECTED_CODE,
+        format!("Sponsorship rejected by policy: {:?}", decision.reason),
+        Some(decision),
+    )
+}
+
+/// Get the paymaster stub data w/ the dummy signature and the upper bounds of the paymaster gas.
+fn stub_paymaster_data(user_operation_request: &UserOperationRequestVariant) -> PaymasterStubData {
+    // The validity window of zero w/ the dummy signature, in the format of the verifying paymaster
+    let paymaster_data: Bytes = [[0_u8; 64].as_slice(), DUMMY_SIGNATURE.as_slice()].concat().into();
+
+    match user_operation_request {
+        UserOperationRequestVariant::Default(_) => PaymasterStubData {
+            paymaster_and_data: Some(
+                [PAYMASTER_STUB_ADDRESS.as_slice(), paymaster_data.as_ref()].concat().into(),
+            ),
+            ..Default::default()
+        },
+        UserOperationRequestVariant::Packed(_) => PaymasterStubData {
+            paymaster: Some(*PAYMASTER_STUB_ADDRESS),
+            paymaster_data: Some(paymaster_data),
+            paymaster_verification_gas_limit: Some(*PAYMASTER_STUB_VERIFICATION_GAS_LIMIT),
+            paymaster_post_op_gas_limit: Some(*PAYMASTER_STUB_POST_OP_GAS_LIMIT),
+            ..Default::default()
+        },
+    }
+}
+
+/// Returns `true` if the gas limits of the user operation are all set.
+fn has_gas_limits(user_operation_request: &UserOperationRequestVariant) -> bool {
+    let is_set = |value: Option<U256>| value.is_some_and(|value| value > U256::ZERO);
+
+    match user_operation_request {
+        UserOperationRequestVariant::Default(uor) => {
+            is_set(uor.call_gas_limit) &&
+                is_set(uor.verification_gas_limit) &&
+                is_set(uor.pre_verification_gas)
+        }
+        UserOperationRequestVariant::Packed(puor) => {
+            is_set(puor.call_gas_limit) &&
+                is_set(puor.verification_gas_limit) &&
+                is_set(puor.pre_verification_gas)
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        policy::SponsorshipPolicy,
+        services::{mock::MockSponsorProvider, SponsorRouter},
+    };
+    use alloy::primitives::Bytes;
+    use async_trait::async_trait;
+    use lightdotso_contracts::types::{GasAndPaymasterAndData, UserOperationRequest};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    /// The chain id w/o any config in the registry, so that the default order of the router is used
+    const CHAIN_ID: u64 = 999_999_999;
+
+    fn user_operation_request(gas: Option<U256>) -> UserOperationRequestVariant {
+        UserOperationRequestVariant::Default(UserOperationRequest {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            init_code: Bytes::default(),
+            call_data: Bytes::default(),
+            call_gas_limit: gas,
+            verification_gas_limit: gas,
+            pre_verification_gas: gas,
+            max_fee_per_gas: Some(U256::from(1)),
+            max_priority_fee_per_gas: Some(U256::from(1)),
+            paymaster_and_data: None,
+            signature: Bytes::default(),
+        })
+    }
+
+    /// The biller counting the billed user operations
+    #[derive(Default)]
+    struct MockBiller {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Biller for MockBiller {
+        async fn bill(
+            &self,
+            _chain_id: u64,
+            _user_operation: UserOperation,
+            _gas_and_paymaster_and_data_variant: GasAndPaymasterAndDataVariant,
+        ) -> eyre::Result<()> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn sponsored_response() -> GasAndPaymasterAndDataVariant {
+        GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+            call_gas_limit: U256::from(1),
+            verification_gas_limit: U256::from(1),
+            pre_verification_gas: U256::from(1),
+            paymaster_and_data: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+        })
+    }
+
+    fn paymaster_api(provider: Arc<MockSponsorProvider>, biller: Arc<MockBiller>) -> PaymasterApi {
+        let mut router = SponsorRouter::new(vec![provider.name.clone()], Duration::from_secs(1));
+        router.register(provider, None);
+
+        PaymasterApi {
+            router: Arc::new(router),
+            policy: Arc::new(PolicyEngine::new(SponsorshipPolicy::default(), None, None)),
+            biller,
+            simulate_gas: false,
+        }
+    }
+
+    #[test]
+    fn test_has_gas_limits() {
+        assert!(has_gas_limits(&user_operation_request(Some(U256::from(1)))));
+        assert!(!has_gas_limits(&user_operation_request(None)));
+        assert!(!has_gas_limits(&user_operation_request(Some(U256::ZERO))));
+    }
+
+    #[tokio::test]
+    async fn test_get_paymaster_stub_data() {
+        let provider = Arc::new(MockSponsorProvider::new("mock", None));
+        let paymaster = paymaster_api(provider.clone(), Arc::new(MockBiller::default()));
+
+        // The stub w/o the gas is the unsigned dummy, w/o asking any sponsor
+        let stub = paymaster
+            .get_paymaster_stub_data(
+                user_operation_request(None),
+                Address::ZERO,
+                CHAIN_ID,
+                SponsorshipContext::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!stub.is_final);
+        let paymaster_and_data = stub.paymaster_and_data.unwrap();
+        assert_eq!(&paymaster_and_data[..20], PAYMASTER_STUB_ADDRESS.as_slice());
+        assert_eq!(&paymaster_and_data[84..], DUMMY_SIGNATURE.as_slice());
+        assert_eq!(provider.calls(), 0);
+
+        // The stub w/ the gas is sponsored, failing w/ the sponsors
+        let res = paymaster
+            .get_paymaster_stub_data(
+                user_operation_request(Some(U256::from(1))),
+                Address::ZERO,
+                CHAIN_ID,
+                SponsorshipContext::default(),
+            )
+            .await;
+        assert!(res.is_err());
+        assert_eq!(provider.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_paymaster_stub_data_sponsored() {
+        let provider = Arc::new(MockSponsorProvider::new("mock", Some(sponsored_response())));
+        let biller = Arc::new(MockBiller::default());
+        let paymaster = paymaster_api(provider.clone(), biller.clone());
+
+        // The stub w/ the gas is sponsored, w/o billing it
+        let stub = paymaster
+            .get_paymaster_stub_data(
+                user_operation_request(Some(U256::from(1))),
+                Address::ZERO,
+                CHAIN_ID,
+                SponsorshipContext::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!stub.is_final);
+        assert_eq!(stub.paymaster_and_data, Some(Bytes::from(vec![0xde, 0xad, 0xbe, 0xef])));
+        assert_eq!(provider.calls(), 1);
+        assert_eq!(biller.calls.load(Ordering::Relaxed), 0);
+
+        // The paymaster data is sponsored and billed once
+        let data = paymaster
+            .get_paymaster_data(
+                user_operation_request(Some(U256::from(1))),
+                Address::ZERO,
+                CHAIN_ID,
+                SponsorshipContext::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(data.paymaster_and_data, Some(Bytes::from(vec![0xde, 0xad, 0xbe, 0xef])));
+        assert_eq!(provider.calls(), 2);
+        assert_eq!(biller.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_paymaster_data() {
+        let provider = Arc::new(MockSponsorProvider::new("mock", None));
+        let paymaster = paymaster_api(provider.clone(), Arc::new(MockBiller::default()));
+
+        // The paymaster data is always sponsored, failing w/ the sponsors
+        let res = paymaster
+            .get_paymaster_data(
+                user_operation_request(Some(U256::from(1))),
+                Address::ZERO,
+                CHAIN_ID,
+                SponsorshipContext::default(),
+            )
+            .await;
+        assert!(res.is_err());
+        assert_eq!(provider.calls(), 1);
+    }
+
+    #[test]
+    fn test_stub_paymaster_data() {
+        let stub = stub_paymaster_data(&user_operation_request(None));
+        assert_eq!(stub.paymaster_and_data.unwrap().len(), 20 + 64 + 65);
+        assert!(stub.paymaster.is_none());
     }
 }
@@ -15,9 +15,11 @@
 use alloy::primitives::Address;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use lightdotso_contracts::types::{
-    GasAndPaymasterAndDataVariant, PaymasterAndData, UserOperationRequestVariant,
+    GasAndPaymasterAndDataResponse, PaymasterAndData, UserOperationRequestVariant,
 };
 
+use crate::policy::SponsorshipContext;
+
 #[rpc(client, server, namespace = "paymaster")]
 #[cfg_attr(test, automock)]
 pub trait PaymasterApi {
@@ -27,6 +29,7 @@ pub trait PaymasterApi {
         user_operation: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
+        context: Option<SponsorshipContext>,
     ) -> RpcResult<PaymasterAndData>;
 
     #[method(name = "requestGasAndPaymasterAndData")]
@@ -35,5 +38,6 @@ pub trait PaymasterApi {
         user_operation: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
-    ) -> RpcResult<GasAndPaymasterAndDataVariant>;
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<GasAndPaymasterAndDataResponse>;
 }
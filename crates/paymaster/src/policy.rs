@@ -0,0 +1,759 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::{
+    primitives::{Address, Bytes, FixedBytes, U256},
+    sol_types::SolCall,
+};
+use eyre::{eyre, Result};
+use lightdotso_client::crypto::get_native_token_price;
+use lightdotso_contracts::{
+    light_wallet::LightWallet::{executeBatchCall, executeCall},
+    types::UserOperation,
+};
+use lightdotso_db::models::billing_ledger::has_billing_ledger_balance_with_wallet;
+use lightdotso_prisma::PrismaClient;
+use lightdotso_redis::{
+    query::sponsorship::{
+        add_sponsorship_decision, get_sponsorship_spend, release_sponsorship_spend,
+        reserve_sponsorship_spend, SponsorshipSpendScope,
+    },
+    redis::Client,
+};
+use lightdotso_tracing::tracing::{info, warn};
+use lightdotso_utils::{get_native_token_symbol, is_testnet};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The seconds of the window of the daily caps
+const DAILY_WINDOW_SECONDS: u64 = 86_400;
+
+/// The JSON-RPC error code of a sponsorship rejected by the policy
+pub const POLICY_REJECTED_CODE: i32 = -32_001;
+
+/// The max number of the recorded decisions
+const MAX_SPONSORSHIP_DECISIONS: isize = 10_000;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The context of the sponsorship request, set by the caller
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipContext {
+    /// The campaign or invite code to charge the sponsorship to
+    #[serde(default)]
+    pub campaign: Option<String>,
+    /// The ERC-20 token to pay the gas in, rather than be sponsored
+    #[serde(default)]
+    pub token: Option<Address>,
+    /// The rest of the context, passed through to the sponsor providers
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The budget of a campaign or invite code
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CampaignPolicy {
+    /// The budget in USD
+    pub budget_usd: f64,
+    /// Whether the budget resets daily, rather than over the lifetime of the campaign
+    #[serde(default)]
+    pub daily: bool,
+}
+
+/// The rules of the sponsorship, loaded from the file at `PAYMASTER_POLICY_PATH`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SponsorshipPolicy {
+    /// The daily cap in USD per wallet across all of the chains
+    #[serde(default)]
+    pub wallet_daily_usd: Option<f64>,
+    /// The daily caps in USD w/ chain id
+    #[serde(default)]
+    pub chain_daily_usd: HashMap<u64, f64>,
+    /// The allowed target contracts of the calls, or any if empty
+    #[serde(default)]
+    pub allowed_targets: Vec<Address>,
+    /// The allowed function selectors of the calls, or any if empty
+    #[serde(default)]
+    pub allowed_selectors: Vec<FixedBytes<4>>,
+    /// The max sum of the gas limits of a user operation
+    #[serde(default)]
+    pub max_gas_per_operation: Option<U256>,
+    /// The budgets w/ campaign or invite code
+    #[serde(default)]
+    pub campaigns: HashMap<String, CampaignPolicy>,
+    /// Whether the sponsorship requires a known campaign
+    #[serde(default)]
+    pub require_campaign: bool,
+    /// Whether the billing of the wallet must hold the max cost in its ledger balance
+    #[serde(default)]
+    pub require_billing_balance: bool,
+}
+
+/// The reason code of a sponsorship decision
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PolicyReason {
+    Approved,
+    GasLimitExceeded,
+    UndecodableCallData,
+    TargetNotAllowed,
+    SelectorNotAllowed,
+    CampaignRequired,
+    CampaignUnknown,
+    CampaignBudgetExceeded,
+    WalletDailyCapExceeded,
+    ChainDailyCapExceeded,
+    InsufficientBalance,
+    SpendUnavailable,
+}
+
+/// The decision of the policy engine on a user operation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyDecision {
+    pub chain_id: u64,
+    pub sender: Address,
+    pub campaign: Option<String>,
+    /// The max cost of the user operation in USD
+    pub cost_usd: f64,
+    pub reason: PolicyReason,
+    pub timestamp: u64,
+}
+
+impl PolicyDecision {
+    /// Returns `true` if the user operation may be sponsored
+    pub fn is_approved(&self) -> bool {
+        self.reason == PolicyReason::Approved
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Policy
+// -----------------------------------------------------------------------------
+
+impl SponsorshipPolicy {
+    /// Loads the policy from the JSON file
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Returns `true` if any of the rules are denominated in USD
+    fn has_usd_rules(&self) -> bool {
+        self.has_spend_rules() || self.require_billing_balance
+    }
+
+    /// Returns `true` if any of the rules track the spend in redis
+    fn has_spend_rules(&self) -> bool {
+        self.wallet_daily_usd.is_some() ||
+            !self.chain_daily_usd.is_empty() ||
+            !self.campaigns.is_empty()
+    }
+
+    /// Checks the rules which need no spend, returning the first violation
+    pub fn check_static(
+        &self,
+        user_operation: &UserOperation,
+        context: &SponsorshipContext,
+    ) -> Option<PolicyReason> {
+        if let Some(max_gas_per_operation) = self.max_gas_per_operation {
+            let gas = user_operation
+                .call_gas_limit
+                .saturating_add(user_operation.verification_gas_limit)
+                .saturating_add(user_operation.pre_verification_gas);
+            if gas > max_gas_per_operation {
+                return Some(PolicyReason::GasLimitExceeded);
+            }
+        }
+
+        if !self.allowed_targets.is_empty() || !self.allowed_selectors.is_empty() {
+            let Some(calls) = decode_calls(&user_operation.call_data) else {
+                return Some(PolicyReason::UndecodableCallData);
+            };
+            for (target, selector) in calls {
+                if !self.allowed_targets.is_empty() && !self.allowed_targets.contains(&target) {
+                    return Some(PolicyReason::TargetNotAllowed);
+                }
+                if !self.allowed_selectors.is_empty() &&
+                    !selector.is_some_and(|selector| self.allowed_selectors.contains(&selector))
+                {
+                    return Some(PolicyReason::SelectorNotAllowed);
+                }
+            }
+        }
+
+        match &context.campaign {
+            Some(campaign) if !self.campaigns.contains_key(campaign) => {
+                Some(PolicyReason::CampaignUnknown)
+            }
+            // The gas paid in the token needs no campaign to charge
+            None if self.require_campaign && context.token.is_none() => {
+                Some(PolicyReason::CampaignRequired)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the scopes of the spend charged to the sponsorship, w/ the reason code of exceeding
+    /// the cap of each
+    fn spend_scopes(
+        &self,
+        chain_id: u64,
+        sender: Address,
+        campaign: Option<&str>,
+        timestamp: u64,
+    ) -> Vec<(SponsorshipSpendScope, PolicyReason)> {
+        let day = timestamp / DAILY_WINDOW_SECONDS;
+        let ttl = Some(DAILY_WINDOW_SECONDS * 2);
+        let mut scopes = vec![];
+
+        if let Some(campaign) = campaign {
+            if let Some(campaign_policy) = self.campaigns.get(campaign) {
+                let (window, ttl) =
+                    if campaign_policy.daily { (Some(day), ttl) } else { (None, None) };
+                scopes.push((
+                    SponsorshipSpendScope {
+                        scope: campaign_scope(campaign),
+                        window,
+                        cap_usd: Some(campaign_policy.budget_usd),
+                        ttl,
+                    },
+                    PolicyReason::CampaignBudgetExceeded,
+                ));
+            }
+        }
+
+        scopes.push((
+            SponsorshipSpendScope {
+                scope: wallet_scope(sender),
+                window: Some(day),
+                cap_usd: self.wallet_daily_usd,
+                ttl,
+            },
+            PolicyReason::WalletDailyCapExceeded,
+        ));
+        scopes.push((
+            SponsorshipSpendScope {
+                scope: chain_scope(chain_id),
+                window: Some(day),
+                cap_usd: self.chain_daily_usd.get(&chain_id).copied(),
+                ttl,
+            },
+            PolicyReason::ChainDailyCapExceeded,
+        ));
+
+        scopes
+    }
+}
+
+/// Decodes the targets and calldata of the calls of `execute` or `executeBatch`
+pub fn decode_wallet_calls(call_data: &Bytes) -> Option<Vec<(Address, Bytes)>> {
+    if let Ok(decoded) = executeCall::abi_decode(call_data, true) {
+        return Some(vec![(decoded.dest, decoded.func)]);
+    }
+    if let Ok(decoded) = executeBatchCall::abi_decode(call_data, true) {
+        // A batch w/o calldata is a batch of plain transfers
+        let funcs = decoded.func.into_iter().chain(std::iter::repeat(Bytes::default()));
+        return Some(decoded.dest.into_iter().zip(funcs).collect());
+    }
+
+    None
+}
+
+/// Decodes the targets and selectors of the calls of `execute` or `executeBatch`
+pub fn decode_calls(call_data: &Bytes) -> Option<Vec<(Address, Option<FixedBytes<4>>)>> {
+    decode_wallet_calls(call_data).map(|calls| {
+        calls
+            .into_iter()
+            .map(|(dest, func)| (dest, func.get(..4).map(FixedBytes::<4>::from_slice)))
+            .collect()
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Engine
+// -----------------------------------------------------------------------------
+
+/// The engine evaluating the sponsorship policy before any sponsor is asked
+pub struct PolicyEngine {
+    policy: SponsorshipPolicy,
+    redis_client: Option<Arc<Client>>,
+    db_client: Option<Arc<PrismaClient>>,
+}
+
+impl PolicyEngine {
+    /// Constructs the engine w/ the redis client tracking the spend, and the db client w/ the
+    /// billing ledger
+    pub fn new(
+        policy: SponsorshipPolicy,
+        redis_client: Option<Arc<Client>>,
+        db_client: Option<Arc<PrismaClient>>,
+    ) -> Self {
+        Self { policy, redis_client, db_client }
+    }
+
+    /// Evaluates the policy on the user operation
+    pub async fn evaluate(
+        &self,
+        chain_id: u64,
+        user_operation: &UserOperation,
+        context: &SponsorshipContext,
+    ) -> PolicyDecision {
+        let mut decision = PolicyDecision {
+            chain_id,
+            sender: user_operation.sender,
+            campaign: context.campaign.clone(),
+            cost_usd: 0.0,
+            reason: PolicyReason::Approved,
+            timestamp: now(),
+        };
+
+        if let Some(reason) = self.policy.check_static(user_operation, context) {
+            decision.reason = reason;
+        } else if context.token.is_none() && self.policy.has_usd_rules() {
+            decision.reason = match self.check_spend(chain_id, user_operation, context).await {
+                Ok((cost_usd, reason)) => {
+                    decision.cost_usd = cost_usd;
+                    reason
+                }
+                Err(err) => {
+                    warn!("Failed to check the sponsorship spend: {:?}", err);
+                    PolicyReason::SpendUnavailable
+                }
+            };
+        }
+
+        self.record_decision(&decision);
+        decision
+    }
+
+    /// Checks the spend caps and budgets, returning the max cost of the user operation in USD
+    async fn check_spend(
+        &self,
+        chain_id: u64,
+        user_operation: &UserOperation,
+        context: &SponsorshipContext,
+    ) -> Result<(f64, PolicyReason)> {
+        let cost_usd = get_user_operation_cost_usd(chain_id, user_operation).await?;
+
+        if self.policy.require_billing_balance {
+            let db_client = self.db_client.clone().ok_or_else(|| eyre!("Db client not set"))?;
+            if !has_billing_ledger_balance_with_wallet(db_client, user_operation.sender, cost_usd)
+                .await?
+            {
+                return Ok((cost_usd, PolicyReason::InsufficientBalance));
+            }
+        }
+
+        if !self.policy.has_spend_rules() {
+            return Ok((cost_usd, PolicyReason::Approved));
+        }
+
+        let client = self.redis_client.clone().ok_or_else(|| eyre!("Redis client not set"))?;
+        let mut con = client.get_connection()?;
+
+        let scopes = self.policy.spend_scopes(
+            chain_id,
+            user_operation.sender,
+            context.campaign.as_deref(),
+            now(),
+        );
+        for (scope, reason) in scopes {
+            if scope.cap_usd.is_none() {
+                continue;
+            }
+            let spend = get_sponsorship_spend(&mut con, &scope.scope, scope.window)?;
+            if !is_within_cap(&scope, spend, cost_usd) {
+                return Ok((cost_usd, reason));
+            }
+        }
+
+        Ok((cost_usd, PolicyReason::Approved))
+    }
+
+    /// Returns `true` if the cost of the decision is charged to the caps and budgets
+    fn is_charged(&self, decision: &PolicyDecision) -> bool {
+        decision.is_approved() && decision.cost_usd > 0.0 && self.policy.has_spend_rules()
+    }
+
+    /// Reserves the cost of the approved decision against the caps and budgets atomically,
+    /// returning the reason code of the cap exceeded since the evaluation, if any
+    pub fn reserve_spend(&self, decision: &PolicyDecision) -> Result<PolicyReason> {
+        if !self.is_charged(decision) {
+            return Ok(decision.reason);
+        }
+        let client = self.redis_client.clone().ok_or_else(|| eyre!("Redis client not set"))?;
+        let mut con = client.get_connection()?;
+
+        let (scopes, reasons): (Vec<_>, Vec<_>) = self
+            .policy
+            .spend_scopes(
+                decision.chain_id,
+                decision.sender,
+                decision.campaign.as_deref(),
+                decision.timestamp,
+            )
+            .into_iter()
+            .unzip();
+        let exceeded = reserve_sponsorship_spend(&mut con, &scopes, decision.cost_usd)?;
+
+        Ok(exceeded.and_then(|index| reasons.get(index).copied()).unwrap_or(PolicyReason::Approved))
+    }
+
+    /// Releases the reserved cost of the decision, once the sponsorship failed
+    pub fn release_spend(&self, decision: &PolicyDecision) -> Result<()> {
+        if !self.is_charged(decision) {
+            return Ok(());
+        }
+        let client = self.redis_client.clone().ok_or_else(|| eyre!("Redis client not set"))?;
+        let mut con = client.get_connection()?;
+
+        let scopes: Vec<_> = self
+            .policy
+            .spend_scopes(
+                decision.chain_id,
+                decision.sender,
+                decision.campaign.as_deref(),
+                decision.timestamp,
+            )
+            .into_iter()
+            .map(|(scope, _)| scope)
+            .collect();
+
+        Ok(release_sponsorship_spend(&mut con, &scopes, decision.cost_usd)?)
+    }
+
+    /// Records the decision w/ its reason code
+    fn record_decision(&self, decision: &PolicyDecision) {
+        info!("sponsorship decision: {:?}", decision);
+
+        let Some(client) = self.redis_client.clone() else {
+            return;
+        };
+        let res = serde_json::to_string(decision).map_err(eyre::Report::from).and_then(|value| {
+            let mut con = client.get_connection()?;
+            Ok(add_sponsorship_decision(&mut con, &value, MAX_SPONSORSHIP_DECISIONS)?)
+        });
+        if let Err(err) = res {
+            warn!("Failed to record the sponsorship decision: {:?}", err);
+        }
+    }
+}
+
+/// Get the max cost of the user operation in USD, free on testnets
+pub async fn get_user_operation_cost_usd(
+    chain_id: u64,
+    user_operation: &UserOperation,
+) -> Result<f64> {
+    if is_testnet(chain_id) {
+        return Ok(0.0);
+    }
+
+    let price_usd = get_native_token_price(get_native_token_symbol(chain_id)).await?;
+    Ok(max_cost_usd(user_operation, price_usd))
+}
+
+/// Get the max cost of the user operation in USD at the native token price
+fn max_cost_usd(user_operation: &UserOperation, price_usd: f64) -> f64 {
+    let gas = user_operation
+        .call_gas_limit
+        .saturating_add(user_operation.verification_gas_limit)
+        .saturating_add(user_operation.pre_verification_gas);
+    let wei = gas.saturating_mul(user_operation.max_fee_per_gas);
+
+    u128::try_from(wei).unwrap_or(u128::MAX) as f64 / 10_f64.powi(18) * price_usd
+}
+
+/// Returns `true` if the spend of the scope w/ the cost stays within its cap, if any
+fn is_within_cap(scope: &SponsorshipSpendScope, spend_usd: f64, cost_usd: f64) -> bool {
+    scope.cap_usd.map_or(true, |cap_usd| spend_usd + cost_usd <= cap_usd)
+}
+
+fn wallet_scope(sender: Address) -> String {
+    format!("wallet:{:?}", sender)
+}
+
+fn chain_scope(chain_id: u64) -> String {
+    format!("chain:{}", chain_id)
+}
+
+fn campaign_scope(campaign: &str) -> String {
+    format!("campaign:{}", campaign)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    fn user_operation(call_data: Bytes) -> UserOperation {
+        UserOperation {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            init_code: Bytes::default(),
+            call_data,
+            call_gas_limit: U256::from(100_000),
+            verification_gas_limit: U256::from(100_000),
+            pre_verification_gas: U256::from(50_000),
+            max_fee_per_gas: U256::from(10_u64.pow(9)),
+            max_priority_fee_per_gas: U256::ZERO,
+            paymaster_and_data: Bytes::default(),
+            signature: Bytes::default(),
+        }
+    }
+
+    fn execute(dest: Address, func: &[u8]) -> Bytes {
+        executeCall { dest, value: U256::ZERO, func: Bytes::copy_from_slice(func) }
+            .abi_encode()
+            .into()
+    }
+
+    #[test]
+    fn test_decode_calls() {
+        let target = address!("0000000000000000000000000000000000000001");
+        let calls = decode_calls(&execute(target, &[0xa9, 0x05, 0x9c, 0xbb, 0x00])).unwrap();
+        assert_eq!(calls, vec![(target, Some(FixedBytes::from([0xa9, 0x05, 0x9c, 0xbb])))]);
+
+        let batch: Bytes = executeBatchCall {
+            dest: vec![target, Address::ZERO],
+            value: vec![U256::ZERO, U256::ZERO],
+            func: vec![Bytes::default(), Bytes::default()],
+        }
+        .abi_encode()
+        .into();
+        assert_eq!(decode_calls(&batch).unwrap(), vec![(target, None), (Address::ZERO, None)]);
+
+        assert!(decode_calls(&Bytes::from_static(&[0x01])).is_none());
+    }
+
+    #[test]
+    fn test_check_static() {
+        let target = address!("0000000000000000000000000000000000000001");
+        let policy = SponsorshipPolicy {
+            allowed_targets: vec![target],
+            allowed_selectors: vec![FixedBytes::from([0xa9, 0x05, 0x9c, 0xbb])],
+            max_gas_per_operation: Some(U256::from(300_000)),
+            ..Default::default()
+        };
+        let context = SponsorshipContext::default();
+
+        let allowed = user_operation(execute(target, &[0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!(policy.check_static(&allowed, &context), None);
+
+        let wrong_target = user_operation(execute(Address::ZERO, &[0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!(
+            policy.check_static(&wrong_target, &context),
+            Some(PolicyReason::TargetNotAllowed)
+        );
+
+        let wrong_selector = user_operation(execute(target, &[0x09, 0x5e, 0xa7, 0xb3]));
+        assert_eq!(
+            policy.check_static(&wrong_selector, &context),
+            Some(PolicyReason::SelectorNotAllowed)
+        );
+
+        let mut too_much_gas = allowed.clone();
+        too_much_gas.call_gas_limit = U256::from(1_000_000);
+        assert_eq!(
+            policy.check_static(&too_much_gas, &context),
+            Some(PolicyReason::GasLimitExceeded)
+        );
+
+        let unknown_campaign =
+            SponsorshipContext { campaign: Some("unknown".to_string()), ..Default::default() };
+        assert_eq!(
+            policy.check_static(&allowed, &unknown_campaign),
+            Some(PolicyReason::CampaignUnknown)
+        );
+
+        // The gas paid in the token needs no campaign
+        let policy = SponsorshipPolicy { require_campaign: true, ..policy };
+        assert_eq!(policy.check_static(&allowed, &context), Some(PolicyReason::CampaignRequired));
+        let token = SponsorshipContext { token: Some(target), ..Default::default() };
+        assert_eq!(policy.check_static(&allowed, &token), None);
+    }
+
+    #[test]
+    fn test_has_usd_rules() {
+        let policy = SponsorshipPolicy { require_billing_balance: true, ..Default::default() };
+        assert!(policy.has_usd_rules());
+        assert!(!policy.has_spend_rules());
+
+        let policy = SponsorshipPolicy { wallet_daily_usd: Some(1.0), ..Default::default() };
+        assert!(policy.has_usd_rules());
+        assert!(policy.has_spend_rules());
+
+        assert!(!SponsorshipPolicy::default().has_usd_rules());
+    }
+
+    #[test]
+    fn test_max_cost_usd() {
+        // 250k gas at 1 gwei is 0.00025 ether
+        let cost = max_cost_usd(&user_operation(Bytes::default()), 4000.0);
+        assert!((cost - 1.0).abs() < 1e-9);
+
+        // The overflowing gas saturates rather than panics
+        let mut overflow = user_operation(Bytes::default());
+        overflow.call_gas_limit = U256::MAX;
+        overflow.max_fee_per_gas = U256::MAX;
+        assert_eq!(max_cost_usd(&overflow, 1.0), u128::MAX as f64 / 10_f64.powi(18));
+    }
+
+    #[test]
+    fn test_check_static_overflow() {
+        let policy = SponsorshipPolicy {
+            max_gas_per_operation: Some(U256::from(300_000)),
+            ..Default::default()
+        };
+        let mut overflow = user_operation(Bytes::default());
+        overflow.call_gas_limit = U256::MAX;
+        overflow.verification_gas_limit = U256::MAX;
+        assert_eq!(
+            policy.check_static(&overflow, &SponsorshipContext::default()),
+            Some(PolicyReason::GasLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_spend_scopes() {
+        let timestamp = DAILY_WINDOW_SECONDS * 3 + 1;
+        let ttl = Some(DAILY_WINDOW_SECONDS * 2);
+        let policy = SponsorshipPolicy {
+            wallet_daily_usd: Some(5.0),
+            chain_daily_usd: HashMap::from([(1, 100.0)]),
+            campaigns: HashMap::from([
+                ("daily".to_string(), CampaignPolicy { budget_usd: 10.0, daily: true }),
+                ("lifetime".to_string(), CampaignPolicy { budget_usd: 50.0, daily: false }),
+            ]),
+            ..Default::default()
+        };
+
+        // The wallet and chain spends are tracked daily, w/o a cap on the other chains
+        let scopes = policy.spend_scopes(10, Address::ZERO, None, timestamp);
+        assert_eq!(
+            scopes,
+            vec![
+                (
+                    SponsorshipSpendScope {
+                        scope: wallet_scope(Address::ZERO),
+                        window: Some(3),
+                        cap_usd: Some(5.0),
+                        ttl,
+                    },
+                    PolicyReason::WalletDailyCapExceeded
+                ),
+                (
+                    SponsorshipSpendScope {
+                        scope: chain_scope(10),
+                        window: Some(3),
+                        cap_usd: None,
+                        ttl,
+                    },
+                    PolicyReason::ChainDailyCapExceeded
+                ),
+            ]
+        );
+        let scopes = policy.spend_scopes(1, Address::ZERO, None, timestamp);
+        assert_eq!(scopes[1].0.cap_usd, Some(100.0));
+
+        // The daily campaign expires w/ the window, the lifetime campaign never does
+        let scopes = policy.spend_scopes(1, Address::ZERO, Some("daily"), timestamp);
+        assert_eq!(
+            scopes[0],
+            (
+                SponsorshipSpendScope {
+                    scope: campaign_scope("daily"),
+                    window: Some(3),
+                    cap_usd: Some(10.0),
+                    ttl,
+                },
+                PolicyReason::CampaignBudgetExceeded
+            )
+        );
+        let scopes = policy.spend_scopes(1, Address::ZERO, Some("lifetime"), timestamp);
+        assert_eq!(scopes[0].0.window, None);
+        assert_eq!(scopes[0].0.ttl, None);
+        assert_eq!(scopes[0].0.cap_usd, Some(50.0));
+
+        // The unknown campaign is not charged
+        let scopes = policy.spend_scopes(1, Address::ZERO, Some("unknown"), timestamp);
+        assert_eq!(scopes.len(), 2);
+    }
+
+    #[test]
+    fn test_is_within_cap() {
+        let scope = SponsorshipSpendScope {
+            scope: wallet_scope(Address::ZERO),
+            window: Some(0),
+            cap_usd: Some(5.0),
+            ttl: None,
+        };
+        assert!(is_within_cap(&scope, 4.0, 1.0));
+        assert!(!is_within_cap(&scope, 4.5, 1.0));
+
+        let uncapped = SponsorshipSpendScope { cap_usd: None, ..scope };
+        assert!(is_within_cap(&uncapped, f64::MAX, 1.0));
+    }
+
+    #[test]
+    fn test_is_charged() {
+        let engine = PolicyEngine::new(
+            SponsorshipPolicy { wallet_daily_usd: Some(5.0), ..Default::default() },
+            None,
+            None,
+        );
+        let mut decision = PolicyDecision {
+            chain_id: 1,
+            sender: Address::ZERO,
+            campaign: None,
+            cost_usd: 1.0,
+            reason: PolicyReason::Approved,
+            timestamp: 0,
+        };
+        assert!(engine.is_charged(&decision));
+
+        // The free and the rejected decisions are neither reserved nor released w/o redis
+        decision.cost_usd = 0.0;
+        assert!(!engine.is_charged(&decision));
+        assert_eq!(engine.reserve_spend(&decision).unwrap(), PolicyReason::Approved);
+        decision.cost_usd = 1.0;
+        decision.reason = PolicyReason::WalletDailyCapExceeded;
+        assert_eq!(engine.reserve_spend(&decision).unwrap(), PolicyReason::WalletDailyCapExceeded);
+        assert!(engine.release_spend(&decision).is_ok());
+
+        // The charged decision requires redis
+        decision.reason = PolicyReason::Approved;
+        assert!(engine.reserve_spend(&decision).is_err());
+    }
+}
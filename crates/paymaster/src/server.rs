@@ -12,12 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{paymaster::PaymasterApi, paymaster_api::PaymasterApiServer};
-use alloy::primitives::Address;
+use crate::{
+    erc7677_api::Erc7677ApiServer, paymaster::PaymasterApi, paymaster_api::PaymasterApiServer,
+    policy::SponsorshipContext,
+};
+use alloy::primitives::{Address, U64};
 use async_trait::async_trait;
 use jsonrpsee::core::RpcResult;
 use lightdotso_contracts::types::{
-    GasAndPaymasterAndDataVariant, PaymasterAndData, UserOperationRequestVariant,
+    GasAndPaymasterAndDataResponse, PaymasterAndData, PaymasterData, PaymasterStubData,
+    UserOperationRequestVariant,
 };
 
 #[async_trait]
@@ -27,9 +31,16 @@ impl PaymasterApiServer for PaymasterApi {
         user_operation: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
+        context: Option<SponsorshipContext>,
     ) -> RpcResult<PaymasterAndData> {
-        Ok(PaymasterApi::request_paymaster_and_data(self, user_operation, entry_point, chain_id)
-            .await?)
+        Ok(PaymasterApi::request_paymaster_and_data(
+            self,
+            user_operation,
+            entry_point,
+            chain_id,
+            context.unwrap_or_default(),
+        )
+        .await?)
     }
 
     async fn request_gas_and_paymaster_and_data(
@@ -37,12 +48,51 @@ impl PaymasterApiServer for PaymasterApi {
         user_operation: UserOperationRequestVariant,
         entry_point: Address,
         chain_id: u64,
-    ) -> RpcResult<GasAndPaymasterAndDataVariant> {
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<GasAndPaymasterAndDataResponse> {
         Ok(PaymasterApi::request_gas_and_paymaster_and_data(
             self,
             user_operation,
             entry_point,
             chain_id,
+            context.unwrap_or_default(),
+        )
+        .await?)
+    }
+}
+
+#[async_trait]
+impl Erc7677ApiServer for PaymasterApi {
+    async fn get_paymaster_stub_data(
+        &self,
+        user_operation: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: U64,
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<PaymasterStubData> {
+        Ok(PaymasterApi::get_paymaster_stub_data(
+            self,
+            user_operation,
+            entry_point,
+            chain_id.to(),
+            context.unwrap_or_default(),
+        )
+        .await?)
+    }
+
+    async fn get_paymaster_data(
+        &self,
+        user_operation: UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: U64,
+        context: Option<SponsorshipContext>,
+    ) -> RpcResult<PaymasterData> {
+        Ok(PaymasterApi::get_paymaster_data(
+            self,
+            user_operation,
+            entry_point,
+            chain_id.to(),
+            context.unwrap_or_default(),
         )
         .await?)
     }
@@ -12,11 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::{policy::SponsorshipContext, services::SponsorProvider};
 use alloy::primitives::Address;
-use eyre::Result;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use lightdotso_constants::registry::ChainConfig;
 use lightdotso_contracts::types::{
-    AlchemyGasAndPaymasterAndData, AlchemyPackedGasAndPaymasterAndData, PackedUserOperationRequest,
-    UserOperationRequest,
+    AlchemyGasAndPaymasterAndData, AlchemyPackedGasAndPaymasterAndData, GasAndPaymasterAndData,
+    GasAndPaymasterAndDataVariant, PackedGasAndPaymasterAndData, PackedUserOperationRequest,
+    UserOperationRequest, UserOperationRequestVariant,
 };
 use lightdotso_jsonrpsee::{
     handle_response,
@@ -80,3 +84,78 @@ pub async fn get_packed_alchemy_paymaster_and_data(
     // Handle the response for the JSON-RPC API.
     handle_response(response).await
 }
+
+// -----------------------------------------------------------------------------
+// Provider
+// -----------------------------------------------------------------------------
+
+/// The sponsor provider of the alchemy gas manager.
+pub struct AlchemySponsorProvider {
+    pub api_key: String,
+}
+
+#[async_trait]
+impl SponsorProvider for AlchemySponsorProvider {
+    fn name(&self) -> &str {
+        "alchemy"
+    }
+
+    fn supports(&self, chain: &ChainConfig) -> bool {
+        chain.paymaster.alchemy_policy_id.is_some() && chain.rpc_urls.contains_key("alchemy")
+    }
+
+    async fn sponsor(
+        &self,
+        chain: &ChainConfig,
+        entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        _context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        let policy_id = chain
+            .paymaster
+            .alchemy_policy_id
+            .clone()
+            .ok_or_else(|| eyre!("Alchemy policy id not set for chain {}", chain.id))?;
+        let rpc_url = chain
+            .rpc_urls
+            .get("alchemy")
+            .ok_or_else(|| eyre!("Alchemy rpc url not set for chain {}", chain.id))?;
+        let rpc_url = format!("{}{}", rpc_url, self.api_key);
+
+        match user_operation_variant {
+            UserOperationRequestVariant::Default(user_operation) => {
+                let sponsorship =
+                    get_alchemy_paymaster_and_data(rpc_url, entry_point, user_operation, policy_id)
+                        .await?
+                        .result;
+
+                Ok(GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+                    paymaster_and_data: sponsorship.paymaster_and_data,
+                    call_gas_limit: sponsorship.call_gas_limit,
+                    verification_gas_limit: sponsorship.verification_gas_limit,
+                    pre_verification_gas: sponsorship.pre_verification_gas,
+                }))
+            }
+            UserOperationRequestVariant::Packed(packed_user_operation) => {
+                let sponsorship = get_packed_alchemy_paymaster_and_data(
+                    rpc_url,
+                    entry_point,
+                    packed_user_operation,
+                    policy_id,
+                )
+                .await?
+                .result;
+
+                Ok(GasAndPaymasterAndDataVariant::Packed(PackedGasAndPaymasterAndData {
+                    call_gas_limit: sponsorship.call_gas_limit,
+                    verification_gas_limit: sponsorship.verification_gas_limit,
+                    pre_verification_gas: sponsorship.pre_verification_gas,
+                    paymaster: sponsorship.paymaster,
+                    paymaster_verification_gas_limit: sponsorship.paymaster_verification_gas_limit,
+                    paymaster_post_op_gas_limit: sponsorship.paymaster_post_op_gas_limit,
+                    paymaster_data: sponsorship.paymaster_data,
+                }))
+            }
+        }
+    }
+}
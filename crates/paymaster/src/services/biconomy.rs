@@ -12,8 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use eyre::Result;
-use lightdotso_contracts::types::{BiconomyGasAndPaymasterAndData, UserOperationRequest};
+use crate::{policy::SponsorshipContext, services::SponsorProvider};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use lightdotso_constants::registry::ChainConfig;
+use lightdotso_contracts::types::{
+    BiconomyGasAndPaymasterAndData, GasAndPaymasterAndData, GasAndPaymasterAndDataVariant,
+    UserOperationRequest, UserOperationRequestVariant,
+};
 use lightdotso_jsonrpsee::{
     handle_response,
     types::{Request, Response},
@@ -57,3 +64,56 @@ pub async fn get_biconomy_paymaster_and_data(
     // Handle the response for the JSON-RPC API.
     handle_response(response).await
 }
+
+// -----------------------------------------------------------------------------
+// Provider
+// -----------------------------------------------------------------------------
+
+/// The sponsor provider of the biconomy paymaster, which only supports v0.6 user operations.
+pub struct BiconomySponsorProvider;
+
+#[async_trait]
+impl SponsorProvider for BiconomySponsorProvider {
+    fn name(&self) -> &str {
+        "biconomy"
+    }
+
+    fn supports(&self, chain: &ChainConfig) -> bool {
+        chain.paymaster.biconomy_policy_id.is_some() && chain.paymaster.biconomy_rpc_url.is_some()
+    }
+
+    async fn sponsor(
+        &self,
+        chain: &ChainConfig,
+        _entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        _context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        let user_operation = match user_operation_variant {
+            UserOperationRequestVariant::Default(user_operation) => user_operation,
+            UserOperationRequestVariant::Packed(_) => {
+                return Err(eyre!("Packed user operation is not supported by biconomy"));
+            }
+        };
+        let (Some(biconomy_rpc_url), Some(biconomy_policy_id)) =
+            (&chain.paymaster.biconomy_rpc_url, &chain.paymaster.biconomy_policy_id)
+        else {
+            return Err(eyre!("Biconomy paymaster not set for chain {}", chain.id));
+        };
+
+        let sponsorship = get_biconomy_paymaster_and_data(
+            format!("{}{}", biconomy_rpc_url, biconomy_policy_id),
+            user_operation,
+        )
+        .await?
+        .result;
+
+        // Biconomy only returns the paymaster and data, so the gas of the request is kept.
+        Ok(GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+            paymaster_and_data: sponsorship.paymaster_and_data,
+            call_gas_limit: user_operation.call_gas_limit.unwrap_or_default(),
+            verification_gas_limit: user_operation.verification_gas_limit.unwrap_or_default(),
+            pre_verification_gas: user_operation.pre_verification_gas.unwrap_or_default(),
+        }))
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{policy::SponsorshipContext, services::SponsorProvider, token::PaymasterTokenQuote};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use lightdotso_constants::registry::ChainConfig;
+use lightdotso_contracts::types::{GasAndPaymasterAndDataVariant, UserOperationRequestVariant};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// -----------------------------------------------------------------------------
+// Provider
+// -----------------------------------------------------------------------------
+
+/// The mock sponsor provider w/ a canned response, for tests.
+pub struct MockSponsorProvider {
+    pub name: String,
+    /// The chain ids served, or all of the chains if empty
+    pub chain_ids: Vec<u64>,
+    /// The response, or a failure if `None`
+    pub response: Option<GasAndPaymasterAndDataVariant>,
+    /// The delay before responding
+    pub delay: Duration,
+    /// The quote of the token paying the gas, or no support of tokens if `None`
+    pub token_quote: Option<PaymasterTokenQuote>,
+    calls: AtomicUsize,
+}
+
+impl MockSponsorProvider {
+    /// Constructs the mock provider, responding immediately w/ the response.
+    pub fn new(name: &str, response: Option<GasAndPaymasterAndDataVariant>) -> Self {
+        Self {
+            name: name.to_string(),
+            chain_ids: vec![],
+            response,
+            delay: Duration::ZERO,
+            token_quote: None,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the number of the sponsorship requests.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl SponsorProvider for MockSponsorProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn supports(&self, chain: &ChainConfig) -> bool {
+        self.chain_ids.is_empty() || self.chain_ids.contains(&chain.id)
+    }
+
+    async fn sponsor(
+        &self,
+        _chain: &ChainConfig,
+        _entry_point: Address,
+        _user_operation_variant: &UserOperationRequestVariant,
+        _context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+
+        self.response.clone().ok_or_else(|| eyre!("Mock sponsor {} failed", self.name))
+    }
+
+    fn supports_token(&self) -> bool {
+        self.token_quote.is_some()
+    }
+
+    async fn token_quote(
+        &self,
+        _chain: &ChainConfig,
+        _entry_point: Address,
+        token: Address,
+    ) -> Result<PaymasterTokenQuote> {
+        self.token_quote
+            .clone()
+            .filter(|quote| quote.token == token)
+            .ok_or_else(|| eyre!("Mock sponsor {} does not support token {}", self.name, token))
+    }
+}
@@ -12,287 +12,430 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-#![allow(clippy::unwrap_used)]
-
-use crate::{
-    constants::{
-        ALCHEMY_POLICY_IDS, BICONOMY_PAYMASTER_RPC_URLS, BICONOMY_POLICY_IDS,
-        PARTICLE_NETWORK_PAYMASTER_BASE_URL, PIMLICO_BASE_URL, PIMLICO_SPONSORSHIP_POLICIES,
-    },
-    services::{
-        alchemy::get_alchemy_paymaster_and_data, biconomy::get_biconomy_paymaster_and_data,
-    },
-    utils::{get_gas_and_paymaster_and_data, get_packed_gas_and_paymaster_and_data},
-};
-use alchemy::get_packed_alchemy_paymaster_and_data;
+use crate::{policy::SponsorshipContext, token::PaymasterTokenQuote};
 use alloy::primitives::Address;
+use async_trait::async_trait;
 use eyre::{eyre, Result};
-use lightdotso_contracts::types::{
-    GasAndPaymasterAndData, GasAndPaymasterAndDataVariant, PackedGasAndPaymasterAndData,
-    UserOperationRequestVariant,
-};
-
-use lightdotso_jsonrpsee::error::JsonRpcError;
-use lightdotso_rpc::constants::{ALCHEMY_RPC_URLS, PARTICLE_RPC_URLS, PIMLICO_RPC_URLS};
+use lightdotso_constants::registry::{get_chain_registry, ChainConfig};
+use lightdotso_contracts::types::{GasAndPaymasterAndDataVariant, UserOperationRequestVariant};
+use lightdotso_opentelemetry::paymaster::PaymasterMetrics;
 use lightdotso_tracing::tracing::{info, warn};
-use lightdotso_utils::is_testnet;
-use serde_json::json;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 pub mod alchemy;
 pub mod biconomy;
+#[cfg(test)]
+pub mod mock;
+pub mod particle;
+pub mod pimlico;
 
 // -----------------------------------------------------------------------------
-// Services
+// Provider
 // -----------------------------------------------------------------------------
 
-// Retryable sponsorship fetch function.
-pub async fn fetch_gas_and_paymaster_and_data_variant(
-    user_operation_variant: UserOperationRequestVariant,
-    entry_point: Address,
-    chain_id: u64,
-) -> Result<GasAndPaymasterAndDataVariant> {
-    // Get the environment variable, `PIMLICO_API_KEY`.
-    let pimlico_api_key =
-        std::env::var("PIMLICO_API_KEY").map_err(|_| eyre!("PIMLICO_API_KEY not set"))?;
-
-    // Check if the `chain_id` is one of the key of `PIMLICO_RPC_URLS`.
-    if (*PIMLICO_RPC_URLS).contains_key(&chain_id) {
-        // For each paymaster policy, attempt to fetch the user operation sponsorship.
-        for policy in PIMLICO_SPONSORSHIP_POLICIES.iter() {
-            info!("[SPONSORSHIP]: pimlico");
-            info!("pimlico policy: {:?}", policy);
-
-            match user_operation_variant.clone() {
-                UserOperationRequestVariant::Default(user_operation) => {
-                    let sponsorship = get_gas_and_paymaster_and_data(
-                        format!(
-                            "{}/{}/rpc?apikey={}",
-                            *PIMLICO_BASE_URL, chain_id, pimlico_api_key
-                        ),
-                        entry_point,
-                        &user_operation,
-                        if !is_testnet(chain_id) {
-                            Some(json!({
-                                "sponsorshipPolicyId": policy
-                            }))
-                        } else {
-                            None
-                        },
-                    )
-                    .await
-                    .map_err(JsonRpcError::from);
-
-                    // If the sponsorship is successful, return the result.
-                    if let Ok(sponsorship_data) = sponsorship {
-                        return Ok(GasAndPaymasterAndDataVariant::Default(sponsorship_data.result));
-                    } else {
-                        warn!("Failed to fetch user operation sponsorship from pimlico");
-                    }
-                }
-                UserOperationRequestVariant::Packed(packed_user_operation) => {
-                    let sponsorship = get_packed_gas_and_paymaster_and_data(
-                        format!(
-                            "{}/{}/rpc?apikey={}",
-                            *PIMLICO_BASE_URL, chain_id, pimlico_api_key
-                        ),
-                        entry_point,
-                        &packed_user_operation,
-                    )
-                    .await
-                    .map_err(JsonRpcError::from);
-
-                    // If the sponsorship is successful, return the result.
-                    if let Ok(sponsorship_data) = sponsorship {
-                        return Ok(GasAndPaymasterAndDataVariant::Packed(sponsorship_data.result));
-                    } else {
-                        warn!("Failed to fetch user operation sponsorship from pimlico");
-                    }
-                }
-            };
-        }
+/// A service sponsoring user operations w/ its paymaster.
+#[async_trait]
+pub trait SponsorProvider: Send + Sync {
+    /// The name of the provider, as referred to in the priority lists
+    fn name(&self) -> &str;
+
+    /// Returns `true` if the provider serves the chain
+    fn supports(&self, chain: &ChainConfig) -> bool;
+
+    /// Requests the sponsorship of the user operation, w/ the context of the caller
+    async fn sponsor(
+        &self,
+        chain: &ChainConfig,
+        entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant>;
+
+    /// Returns `true` if the paymaster of the provider may charge the gas in an ERC-20 token
+    fn supports_token(&self) -> bool {
+        false
     }
 
-    // Get the environment variable, `PARTICLE_NETWORK_PROJECT_ID`.
-    let particle_network_project_id = std::env::var("PARTICLE_NETWORK_PROJECT_ID")
-        .map_err(|_| eyre!("PARTICLE_NETWORK_PROJECT_ID not set"))?;
-    let particle_network_paymaster_project_key = std::env::var("PARTICLE_NETWORK_PROJECT_KEY")
-        .map_err(|_| eyre!("PARTICLE_NETWORK_PROJECT_KEY not set"))?;
-
-    // Check if the `chain_id` is one of the key of `PARTICLE_RPC_URLS`.
-    if (*PARTICLE_RPC_URLS).contains_key(&chain_id) {
-        info!("[SPONSORSHIP]: particle network");
-
-        match user_operation_variant.clone() {
-            UserOperationRequestVariant::Default(user_operation) => {
-                let sponsorship = get_gas_and_paymaster_and_data(
-                    format!(
-                        "{}?chainId={}&projectUuid={}&projectKey={}",
-                        *PARTICLE_NETWORK_PAYMASTER_BASE_URL,
-                        chain_id,
-                        particle_network_project_id,
-                        particle_network_paymaster_project_key
-                    ),
-                    entry_point,
-                    &user_operation,
-                    None,
-                )
-                .await
-                .map_err(JsonRpcError::from);
-
-                // If the sponsorship is successful, return the result.
-                if let Ok(sponsorship_data) = sponsorship {
-                    return Ok(GasAndPaymasterAndDataVariant::Default(sponsorship_data.result));
-                } else {
-                    warn!("Failed to fetch user operation sponsorship from particle network");
-                }
-            }
-            UserOperationRequestVariant::Packed(packed_user_operation) => {
-                let sponsorship = get_packed_gas_and_paymaster_and_data(
-                    format!(
-                        "{}?chainId={}&projectUuid={}&projectKey={}",
-                        *PARTICLE_NETWORK_PAYMASTER_BASE_URL,
-                        chain_id,
-                        particle_network_project_id,
-                        particle_network_paymaster_project_key
-                    ),
-                    entry_point,
-                    &packed_user_operation,
-                )
-                .await
-                .map_err(JsonRpcError::from);
-
-                // If the sponsorship is successful, return the result.
-                if let Ok(sponsorship_data) = sponsorship {
-                    return Ok(GasAndPaymasterAndDataVariant::Packed(sponsorship_data.result));
-                } else {
-                    warn!("Failed to fetch user operation sponsorship from particle network");
-                }
-            }
-        };
+    /// Get the quote of the token charged by the ERC-20 paymaster of the provider
+    async fn token_quote(
+        &self,
+        _chain: &ChainConfig,
+        _entry_point: Address,
+        _token: Address,
+    ) -> Result<PaymasterTokenQuote> {
+        Err(eyre!("Provider {} does not support paying the gas in a token", self.name()))
     }
+}
+
+// -----------------------------------------------------------------------------
+// Router
+// -----------------------------------------------------------------------------
 
-    // Get the environment variable, `ALCHEMY_API_KEY`.
-    let alchemy_api_key =
-        std::env::var("ALCHEMY_API_KEY").map_err(|_| eyre!("ALCHEMY_API_KEY not set"))?;
-
-    // Check if the `chain_id` is one of the key of `ALCHEMY_POLICY_IDS`.
-    if (*ALCHEMY_POLICY_IDS).contains_key(&chain_id) {
-        info!("[SPONSORSHIP]: alchemy");
-
-        match user_operation_variant.clone() {
-            UserOperationRequestVariant::Default(user_operation) => {
-                // Get the alchemy rpc url from the `ALCHEMY_RPC_URLS`.
-                if let Some(alchemy_rpc_url) = (*ALCHEMY_RPC_URLS).get(&chain_id) {
-                    let sponsorship = get_alchemy_paymaster_and_data(
-                        format!("{}{}", alchemy_rpc_url, alchemy_api_key),
-                        entry_point,
-                        &user_operation,
-                        (*ALCHEMY_POLICY_IDS).get(&chain_id).unwrap().to_string(),
-                    )
-                    .await
-                    .map_err(JsonRpcError::from);
-
-                    // If the sponsorship is successful, return the result.
-                    if let Ok(sponsorship_data) = sponsorship {
-                        let gas_and_paymaster_and_data = GasAndPaymasterAndData {
-                            paymaster_and_data: sponsorship_data.result.paymaster_and_data,
-                            call_gas_limit: sponsorship_data.result.call_gas_limit,
-                            verification_gas_limit: sponsorship_data.result.verification_gas_limit,
-                            pre_verification_gas: sponsorship_data.result.pre_verification_gas,
-                        };
-                        return Ok(GasAndPaymasterAndDataVariant::Default(
-                            gas_and_paymaster_and_data,
-                        ));
-                    } else {
-                        warn!("Failed to fetch user operation sponsorship from alchemy");
-                        // error!("{:?}", sponsorship.unwrap_err());
-                    }
-                }
+/// A registered provider w/ its timeout.
+struct SponsorEntry {
+    provider: Arc<dyn SponsorProvider>,
+    timeout: Duration,
+}
+
+impl SponsorEntry {
+    /// Requests the provider w/ its timeout, recording the outcome.
+    async fn request<T>(
+        &self,
+        chain_id: u64,
+        request: impl Future<Output = Result<T>>,
+    ) -> Option<T> {
+        let name = self.provider.name();
+
+        let start = Instant::now();
+        let result = tokio::time::timeout(self.timeout, request).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        match result {
+            Ok(Ok(sponsorship)) => {
+                PaymasterMetrics::set_sponsor_request(name, chain_id, "success", latency_ms);
+                Some(sponsorship)
+            }
+            Ok(Err(err)) => {
+                PaymasterMetrics::set_sponsor_request(name, chain_id, "failure", latency_ms);
+                warn!("Failed to fetch user operation sponsorship from {}: {:?}", name, err);
+                None
             }
-            UserOperationRequestVariant::Packed(packed_user_operation) => {
-                // Get the alchemy rpc url from the `ALCHEMY_RPC_URLS`.
-                if let Some(alchemy_rpc_url) = (*ALCHEMY_RPC_URLS).get(&chain_id) {
-                    let sponsorship = get_packed_alchemy_paymaster_and_data(
-                        format!("{}{}", alchemy_rpc_url, alchemy_api_key),
-                        entry_point,
-                        &packed_user_operation,
-                        (*ALCHEMY_POLICY_IDS).get(&chain_id).unwrap().to_string(),
-                    )
-                    .await
-                    .map_err(JsonRpcError::from);
-
-                    // If the sponsorship is successful, return the result.
-                    if let Ok(sponsorship_data) = sponsorship {
-                        let packed_gas_and_paymaster_and_data = PackedGasAndPaymasterAndData {
-                            call_gas_limit: sponsorship_data.result.call_gas_limit,
-                            verification_gas_limit: sponsorship_data.result.verification_gas_limit,
-                            pre_verification_gas: sponsorship_data.result.pre_verification_gas,
-                            paymaster: sponsorship_data.result.paymaster,
-                            paymaster_verification_gas_limit: sponsorship_data
-                                .result
-                                .paymaster_verification_gas_limit,
-                            paymaster_post_op_gas_limit: sponsorship_data
-                                .result
-                                .paymaster_post_op_gas_limit,
-                            paymaster_data: sponsorship_data.result.paymaster_data,
-                        };
-                        return Ok(GasAndPaymasterAndDataVariant::Packed(
-                            packed_gas_and_paymaster_and_data,
-                        ));
-                    } else {
-                        warn!("Failed to fetch user operation sponsorship from alchemy");
-                    }
-                }
+            Err(_) => {
+                PaymasterMetrics::set_sponsor_request(name, chain_id, "timeout", latency_ms);
+                warn!(
+                    "Timed out fetching user operation sponsorship from {} after {:?}",
+                    name, self.timeout
+                );
+                None
             }
+        }
+    }
+}
+
+/// The router trying the sponsor providers of a chain in the order of priority.
+pub struct SponsorRouter {
+    entries: HashMap<String, SponsorEntry>,
+    /// The order of the providers for the chains w/o their own order
+    default_order: Vec<String>,
+    default_timeout: Duration,
+}
+
+impl SponsorRouter {
+    /// Constructs the router w/o any providers.
+    pub fn new(default_order: Vec<String>, default_timeout: Duration) -> Self {
+        Self { entries: HashMap::new(), default_order, default_timeout }
+    }
+
+    /// Registers the provider, w/ the default timeout if `timeout` is `None`.
+    pub fn register(&mut self, provider: Arc<dyn SponsorProvider>, timeout: Option<Duration>) {
+        let entry = SponsorEntry { timeout: timeout.unwrap_or(self.default_timeout), provider };
+        self.entries.insert(entry.provider.name().to_string(), entry);
+    }
+
+    /// Get the names of the registered providers serving the chain, in the order of priority.
+    pub fn order(&self, chain: &ChainConfig) -> Vec<String> {
+        let order = if chain.paymaster.sponsor_providers.is_empty() {
+            &self.default_order
+        } else {
+            &chain.paymaster.sponsor_providers
         };
+
+        order
+            .iter()
+            .filter(|name| {
+                self.entries.get(name.as_str()).is_some_and(|entry| entry.provider.supports(chain))
+            })
+            .cloned()
+            .collect()
     }
 
-    // Check if the `chain_id` is one of the key of `BICONOMY_POLICY_IDS`.
-    if (*BICONOMY_POLICY_IDS).contains_key(&chain_id) {
-        info!("[SPONSORSHIP]: biconomy");
-
-        match user_operation_variant.clone() {
-            UserOperationRequestVariant::Default(user_operation) => {
-                // Get the alchemy rpc url from the `BICONOMY_PAYMASTER_RPC_URLS`.
-                if let Some(biconomy_rpc_url) = (*BICONOMY_PAYMASTER_RPC_URLS).get(&chain_id) {
-                    let sponsorship = get_biconomy_paymaster_and_data(
-                        format!(
-                            "{}{}",
-                            biconomy_rpc_url,
-                            BICONOMY_POLICY_IDS.get(&chain_id).unwrap()
-                        ),
-                        &user_operation,
-                    )
-                    .await
-                    .map_err(JsonRpcError::from);
-
-                    // If the sponsorship is successful, return the result.
-                    if let Ok(sponsorship_data) = sponsorship {
-                        let gas_and_paymaster_and_data = GasAndPaymasterAndData {
-                            paymaster_and_data: sponsorship_data.result.paymaster_and_data,
-                            call_gas_limit: user_operation.call_gas_limit.unwrap_or_default(),
-                            verification_gas_limit: user_operation
-                                .verification_gas_limit
-                                .unwrap_or_default(),
-                            pre_verification_gas: user_operation
-                                .pre_verification_gas
-                                .unwrap_or_default(),
-                        };
-                        return Ok(GasAndPaymasterAndDataVariant::Default(
-                            gas_and_paymaster_and_data,
-                        ));
-                    } else {
-                        warn!("Failed to fetch user operation sponsorship from alchemy");
-                        // error!("{:?}", sponsorship.unwrap_err());
-                    }
-                }
+    /// Requests the sponsorship from the providers of the chain, returning the first success.
+    pub async fn sponsor(
+        &self,
+        user_operation_variant: &UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: u64,
+        context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        self.sponsor_chain(
+            &get_chain_config(chain_id),
+            user_operation_variant,
+            entry_point,
+            context,
+        )
+        .await
+    }
+
+    /// Requests the sponsorship from the providers of the chain config.
+    pub async fn sponsor_chain(
+        &self,
+        chain: &ChainConfig,
+        user_operation_variant: &UserOperationRequestVariant,
+        entry_point: Address,
+        context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        for name in self.order(chain) {
+            // The providers w/o an ERC-20 paymaster would sponsor the gas paid in a token.
+            let Some(entry) = self
+                .entries
+                .get(&name)
+                .filter(|entry| context.token.is_none() || entry.provider.supports_token())
+            else {
+                continue;
+            };
+            info!("[SPONSORSHIP]: {}", name);
+
+            let request =
+                entry.provider.sponsor(chain, entry_point, user_operation_variant, context);
+            if let Some(sponsorship) = entry.request(chain.id, request).await {
+                return Ok(sponsorship);
             }
-            UserOperationRequestVariant::Packed(_) => {
-                warn!("Packed user operation is not supported by biconomy");
+        }
+
+        // If the sponsorship is not successful, return error.
+        Err(eyre!("Failed to fetch user operation sponsorship"))
+    }
+
+    /// Requests the gas paid in the ERC-20 token from the providers of the chain w/ an ERC-20
+    /// paymaster, returning the first success w/ the quote of the same provider.
+    pub async fn sponsor_token(
+        &self,
+        user_operation_variant: &UserOperationRequestVariant,
+        entry_point: Address,
+        chain_id: u64,
+        token: Address,
+        context: &SponsorshipContext,
+    ) -> Result<(PaymasterTokenQuote, GasAndPaymasterAndDataVariant)> {
+        self.sponsor_token_chain(
+            &get_chain_config(chain_id),
+            user_operation_variant,
+            entry_point,
+            token,
+            context,
+        )
+        .await
+    }
+
+    /// Requests the gas paid in the ERC-20 token from the providers of the chain config.
+    pub async fn sponsor_token_chain(
+        &self,
+        chain: &ChainConfig,
+        user_operation_variant: &UserOperationRequestVariant,
+        entry_point: Address,
+        token: Address,
+        context: &SponsorshipContext,
+    ) -> Result<(PaymasterTokenQuote, GasAndPaymasterAndDataVariant)> {
+        let context = SponsorshipContext { token: Some(token), ..context.clone() };
+
+        for name in self.order(chain) {
+            let Some(entry) =
+                self.entries.get(&name).filter(|entry| entry.provider.supports_token())
+            else {
+                continue;
+            };
+            info!("[TOKEN SPONSORSHIP]: {}", name);
+
+            let request = async {
+                let quote = entry.provider.token_quote(chain, entry_point, token).await?;
+                let sponsorship = entry
+                    .provider
+                    .sponsor(chain, entry_point, user_operation_variant, &context)
+                    .await?;
+                Ok((quote, sponsorship))
+            };
+            if let Some(res) = entry.request(chain.id, request).await {
+                return Ok(res);
             }
-        };
+        }
+
+        Err(eyre!("Failed to fetch user operation sponsorship paid in token {}", token))
+    }
+}
+
+/// Get the chain config from the registry.
+fn get_chain_config(chain_id: u64) -> ChainConfig {
+    let mut chain = get_chain_registry().get(chain_id).cloned().unwrap_or_default();
+    chain.id = chain_id;
+    chain
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock::MockSponsorProvider;
+    use alloy::primitives::{Bytes, U256};
+    use lightdotso_contracts::types::{GasAndPaymasterAndData, UserOperationRequest};
+
+    fn user_operation_variant() -> UserOperationRequestVariant {
+        UserOperationRequestVariant::Default(UserOperationRequest {
+            sender: Address::ZERO,
+            nonce: U256::ZERO,
+            init_code: Bytes::default(),
+            call_data: Bytes::default(),
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: None,
+            signature: Bytes::default(),
+        })
+    }
+
+    fn sponsorship(paymaster_and_data: &'static [u8]) -> GasAndPaymasterAndDataVariant {
+        GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+            call_gas_limit: U256::from(1),
+            verification_gas_limit: U256::from(1),
+            pre_verification_gas: U256::from(1),
+            paymaster_and_data: Bytes::from_static(paymaster_and_data),
+        })
+    }
+
+    fn paymaster_and_data(variant: GasAndPaymasterAndDataVariant) -> Bytes {
+        match variant {
+            GasAndPaymasterAndDataVariant::Default(data) => data.paymaster_and_data,
+            GasAndPaymasterAndDataVariant::Packed(_) => unreachable!(),
+        }
     }
 
-    // If the sponsorship is not successful, return error.
-    Err(eyre!("Failed to fetch user operation sponsorship"))
+    fn chain(id: u64, sponsor_providers: &[&str]) -> ChainConfig {
+        let mut chain = ChainConfig { id, ..Default::default() };
+        chain.paymaster.sponsor_providers =
+            sponsor_providers.iter().map(|name| name.to_string()).collect();
+        chain
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_router_fallback() {
+        let failing = Arc::new(MockSponsorProvider::new("a", None));
+        let succeeding = Arc::new(MockSponsorProvider::new("b", Some(sponsorship(&[0xb]))));
+
+        let mut router =
+            SponsorRouter::new(vec!["a".to_string(), "b".to_string()], Duration::from_secs(1));
+        router.register(failing.clone(), None);
+        router.register(succeeding.clone(), None);
+
+        let result = router
+            .sponsor_chain(
+                &chain(1, &[]),
+                &user_operation_variant(),
+                Address::ZERO,
+                &Default::default(),
+            )
+            .await;
+        assert_eq!(paymaster_and_data(result.unwrap()), Bytes::from_static(&[0xb]));
+        assert_eq!(failing.calls(), 1);
+        assert_eq!(succeeding.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_router_chain_order() {
+        let a = Arc::new(MockSponsorProvider::new("a", Some(sponsorship(&[0xa]))));
+        let mut b = MockSponsorProvider::new("b", Some(sponsorship(&[0xb])));
+        b.chain_ids = vec![10];
+        let b = Arc::new(b);
+
+        let mut router =
+            SponsorRouter::new(vec!["a".to_string(), "b".to_string()], Duration::from_secs(1));
+        router.register(a.clone(), None);
+        router.register(b.clone(), None);
+
+        // The chain order takes precedence over the default order
+        let result = router.sponsor_chain(
+            &chain(10, &["b", "a"]),
+            &user_operation_variant(),
+            Address::ZERO,
+            &Default::default(),
+        );
+        assert_eq!(paymaster_and_data(result.await.unwrap()), Bytes::from_static(&[0xb]));
+
+        // The unsupported and unregistered providers are skipped
+        assert_eq!(router.order(&chain(1, &["c", "b", "a"])), vec!["a".to_string()]);
+        assert_eq!(a.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_router_timeout() {
+        let mut slow = MockSponsorProvider::new("slow", Some(sponsorship(&[0x1])));
+        slow.delay = Duration::from_secs(5);
+        let slow = Arc::new(slow);
+
+        let mut router = SponsorRouter::new(vec!["slow".to_string()], Duration::from_secs(1));
+        router.register(slow.clone(), Some(Duration::from_millis(10)));
+
+        let result = router
+            .sponsor_chain(
+                &chain(1, &[]),
+                &user_operation_variant(),
+                Address::ZERO,
+                &Default::default(),
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(slow.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sponsor_router_token() {
+        let token = Address::repeat_byte(0x1);
+        let quote = PaymasterTokenQuote {
+            paymaster: Address::repeat_byte(0x2),
+            token,
+            post_op_gas: U256::from(1),
+            exchange_rate: U256::from(1),
+        };
+        let verifying = Arc::new(MockSponsorProvider::new("a", Some(sponsorship(&[0xa]))));
+        let mut erc20 = MockSponsorProvider::new("b", Some(sponsorship(&[0xb])));
+        erc20.token_quote = Some(quote);
+        let erc20 = Arc::new(erc20);
+
+        let mut router =
+            SponsorRouter::new(vec!["a".to_string(), "b".to_string()], Duration::from_secs(1));
+        router.register(verifying.clone(), None);
+        router.register(erc20.clone(), None);
+
+        // The gas paid in the token is only requested from the ERC-20 paymasters
+        let (quote, result) = router
+            .sponsor_token_chain(
+                &chain(1, &[]),
+                &user_operation_variant(),
+                Address::ZERO,
+                token,
+                &Default::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(quote.paymaster, Address::repeat_byte(0x2));
+        assert_eq!(paymaster_and_data(result), Bytes::from_static(&[0xb]));
+        assert_eq!(verifying.calls(), 0);
+
+        let context = SponsorshipContext { token: Some(token), ..Default::default() };
+        let result = router
+            .sponsor_chain(&chain(1, &[]), &user_operation_variant(), Address::ZERO, &context)
+            .await;
+        assert_eq!(paymaster_and_data(result.unwrap()), Bytes::from_static(&[0xb]));
+        assert_eq!(verifying.calls(), 0);
+
+        // The unsupported token fails w/o any sponsorship
+        let result = router
+            .sponsor_token_chain(
+                &chain(1, &[]),
+                &user_operation_variant(),
+                Address::ZERO,
+                Address::ZERO,
+                &Default::default(),
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(erc20.calls(), 2);
+    }
 }
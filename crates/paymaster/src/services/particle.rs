@@ -0,0 +1,78 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    constants::PARTICLE_NETWORK_PAYMASTER_BASE_URL,
+    policy::SponsorshipContext,
+    services::SponsorProvider,
+    utils::{get_gas_and_paymaster_and_data, get_packed_gas_and_paymaster_and_data},
+};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::Result;
+use lightdotso_constants::registry::ChainConfig;
+use lightdotso_contracts::types::{GasAndPaymasterAndDataVariant, UserOperationRequestVariant};
+
+// -----------------------------------------------------------------------------
+// Provider
+// -----------------------------------------------------------------------------
+
+/// The sponsor provider of the particle network paymaster.
+pub struct ParticleSponsorProvider {
+    pub project_id: String,
+    pub project_key: String,
+}
+
+#[async_trait]
+impl SponsorProvider for ParticleSponsorProvider {
+    fn name(&self) -> &str {
+        "particle"
+    }
+
+    fn supports(&self, chain: &ChainConfig) -> bool {
+        chain.rpc_urls.contains_key("particle")
+    }
+
+    async fn sponsor(
+        &self,
+        chain: &ChainConfig,
+        entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        _context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        let rpc_url = format!(
+            "{}?chainId={}&projectUuid={}&projectKey={}",
+            *PARTICLE_NETWORK_PAYMASTER_BASE_URL, chain.id, self.project_id, self.project_key
+        );
+
+        match user_operation_variant {
+            UserOperationRequestVariant::Default(user_operation) => {
+                let sponsorship =
+                    get_gas_and_paymaster_and_data(rpc_url, entry_point, user_operation, None)
+                        .await?;
+                Ok(GasAndPaymasterAndDataVariant::Default(sponsorship.result))
+            }
+            UserOperationRequestVariant::Packed(packed_user_operation) => {
+                let sponsorship = get_packed_gas_and_paymaster_and_data(
+                    rpc_url,
+                    entry_point,
+                    packed_user_operation,
+                    None,
+                )
+                .await?;
+                Ok(GasAndPaymasterAndDataVariant::Packed(sponsorship.result))
+            }
+        }
+    }
+}
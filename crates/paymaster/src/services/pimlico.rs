@@ -0,0 +1,221 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    constants::PIMLICO_BASE_URL,
+    policy::SponsorshipContext,
+    services::SponsorProvider,
+    token::PaymasterTokenQuote,
+    utils::{get_gas_and_paymaster_and_data, get_packed_gas_and_paymaster_and_data},
+};
+use alloy::primitives::Address;
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use lightdotso_constants::registry::ChainConfig;
+use lightdotso_contracts::types::{GasAndPaymasterAndDataVariant, UserOperationRequestVariant};
+use lightdotso_jsonrpsee::{
+    handle_response,
+    types::{Request, Response},
+};
+use lightdotso_tracing::tracing::{info, warn};
+use lightdotso_utils::is_testnet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The fields of the context of the caller forwarded to pimlico
+const PIMLICO_CONTEXT_FIELDS: [&str; 2] = ["meta", "validForSeconds"];
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The token quotes returned by `pimlico_getTokenQuotes`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PimlicoTokenQuotes {
+    pub quotes: Vec<PaymasterTokenQuote>,
+}
+
+// -----------------------------------------------------------------------------
+// Provider
+// -----------------------------------------------------------------------------
+
+/// The sponsor provider of the pimlico verifying paymaster, tried w/ each sponsorship policy.
+pub struct PimlicoSponsorProvider {
+    pub api_key: String,
+    pub policies: Vec<String>,
+}
+
+impl PimlicoSponsorProvider {
+    /// Get the pimlico rpc url of the chain.
+    fn rpc_url(&self, chain_id: u64) -> String {
+        format!("{}/{}/rpc?apikey={}", *PIMLICO_BASE_URL, chain_id, self.api_key)
+    }
+
+    /// Requests the gas and paymaster and data w/ the context.
+    async fn request(
+        &self,
+        chain_id: u64,
+        entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        context: Option<Value>,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        match user_operation_variant {
+            UserOperationRequestVariant::Default(user_operation) => {
+                let res = get_gas_and_paymaster_and_data(
+                    self.rpc_url(chain_id),
+                    entry_point,
+                    user_operation,
+                    context,
+                )
+                .await?;
+                Ok(GasAndPaymasterAndDataVariant::Default(res.result))
+            }
+            UserOperationRequestVariant::Packed(packed_user_operation) => {
+                let res = get_packed_gas_and_paymaster_and_data(
+                    self.rpc_url(chain_id),
+                    entry_point,
+                    packed_user_operation,
+                    context,
+                )
+                .await?;
+                Ok(GasAndPaymasterAndDataVariant::Packed(res.result))
+            }
+        }
+    }
+}
+
+/// Get the pimlico context w/ the server-side policy and only the allowed fields of the caller.
+fn pimlico_context(extra: &Map<String, Value>, policy: &str) -> Map<String, Value> {
+    let mut context: Map<String, Value> = extra
+        .iter()
+        .filter(|(key, _)| PIMLICO_CONTEXT_FIELDS.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    context.insert("sponsorshipPolicyId".to_string(), json!(policy));
+    context
+}
+
+#[async_trait]
+impl SponsorProvider for PimlicoSponsorProvider {
+    fn name(&self) -> &str {
+        "pimlico"
+    }
+
+    fn supports(&self, chain: &ChainConfig) -> bool {
+        chain.rpc_urls.contains_key("pimlico")
+    }
+
+    async fn sponsor(
+        &self,
+        chain: &ChainConfig,
+        entry_point: Address,
+        user_operation_variant: &UserOperationRequestVariant,
+        context: &SponsorshipContext,
+    ) -> Result<GasAndPaymasterAndDataVariant> {
+        // The gas paid in the token is charged by the ERC-20 paymaster w/o any policy.
+        if let Some(token) = context.token {
+            return self
+                .request(
+                    chain.id,
+                    entry_point,
+                    user_operation_variant,
+                    Some(json!({ "token": token })),
+                )
+                .await;
+        }
+
+        // For each paymaster policy, attempt to fetch the user operation sponsorship.
+        for policy in self.policies.iter() {
+            info!("pimlico policy: {:?}", policy);
+
+            // The testnets are sponsored w/o any policy.
+            let pimlico_context = (!is_testnet(chain.id))
+                .then(|| Value::Object(pimlico_context(&context.extra, policy)));
+            let sponsorship =
+                self.request(chain.id, entry_point, user_operation_variant, pimlico_context).await;
+
+            match sponsorship {
+                Ok(sponsorship) => return Ok(sponsorship),
+                Err(err) => {
+                    warn!("Failed to fetch sponsorship w/ pimlico policy {}: {:?}", policy, err)
+                }
+            }
+        }
+
+        Err(eyre!("No pimlico sponsorship policy sponsored the user operation"))
+    }
+
+    fn supports_token(&self) -> bool {
+        true
+    }
+
+    async fn token_quote(
+        &self,
+        chain: &ChainConfig,
+        entry_point: Address,
+        token: Address,
+    ) -> Result<PaymasterTokenQuote> {
+        let params = vec![json!({"tokens": [token]}), json!(entry_point), json!(chain.id)];
+        info!("params: {:?}", params);
+
+        let req_body = Request {
+            jsonrpc: "2.0".to_string(),
+            method: "pimlico_getTokenQuotes".to_string(),
+            params,
+            id: 1,
+        };
+
+        let client = reqwest::Client::new();
+        let response = client.post(self.rpc_url(chain.id)).json(&req_body).send().await?;
+
+        // Handle the response for the JSON-RPC API.
+        let response: Response<PimlicoTokenQuotes> = handle_response(response).await?;
+        response
+            .result
+            .quotes
+            .into_iter()
+            .find(|quote| quote.token == token)
+            .ok_or_else(|| eyre!("Token {} is not supported on chain {}", token, chain.id))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pimlico_context() {
+        let extra = json!({
+            "sponsorshipPolicyId": "sp_caller",
+            "validForSeconds": 60,
+            "token": "0x0000000000000000000000000000000000000001",
+        });
+        let Value::Object(extra) = extra else { unreachable!() };
+
+        // The policy of the caller and the fields not allowed are dropped
+        let context = pimlico_context(&extra, "sp_server");
+        assert_eq!(
+            Value::Object(context),
+            json!({ "sponsorshipPolicyId": "sp_server", "validForSeconds": 60 })
+        );
+    }
+}
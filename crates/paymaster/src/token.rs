@@ -0,0 +1,286 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    policy::{decode_wallet_calls, SponsorshipContext},
+    services::SponsorRouter,
+};
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    sol,
+    sol_types::SolCall,
+};
+use eyre::{eyre, Result};
+use lightdotso_contracts::{
+    paymaster::decode_paymaster_and_data,
+    provider::get_provider,
+    types::{
+        GasAndPaymasterAndData, GasAndPaymasterAndDataResponse, GasAndPaymasterAndDataVariant,
+        TokenQuote, UserOperation, UserOperationRequestVariant,
+    },
+};
+use lightdotso_tracing::tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -----------------------------------------------------------------------------
+// Contract
+// -----------------------------------------------------------------------------
+
+sol!(
+    #[sol(rpc)]
+    interface IERC20 {
+        function approve(address spender, uint256 amount) external returns (bool);
+        function allowance(address owner, address spender) external view returns (uint256);
+    }
+);
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The seconds of the validity of a quote, if not encoded in the paymaster and data
+const DEFAULT_QUOTE_VALIDITY_SECONDS: u64 = 300;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The quote of the token of an ERC-20 paymaster.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterTokenQuote {
+    /// The ERC-20 paymaster.
+    pub paymaster: Address,
+    /// The token.
+    pub token: Address,
+    /// The gas of the post operation of the paymaster.
+    pub post_op_gas: U256,
+    /// The token amount per ether, in the token decimals.
+    pub exchange_rate: U256,
+}
+
+// -----------------------------------------------------------------------------
+// Paymaster
+// -----------------------------------------------------------------------------
+
+/// Requests the gas and paymaster and data paid in the token from the router, w/ the quote.
+pub async fn request_token_gas_and_paymaster_and_data(
+    router: &SponsorRouter,
+    chain_id: u64,
+    entry_point: Address,
+    user_operation_request: &UserOperationRequestVariant,
+    user_operation: &UserOperation,
+    token: Address,
+    context: &SponsorshipContext,
+) -> Result<GasAndPaymasterAndDataResponse> {
+    let (quote, gas_and_paymaster_and_data) =
+        router.sponsor_token(user_operation_request, entry_point, chain_id, token, context).await?;
+    info!("token quote: {:?}", quote);
+
+    // Check that the paymaster may charge the max token amount of the sponsored gas limits.
+    let amount = required_token_amount(
+        max_cost_wei(
+            &gas_and_paymaster_and_data,
+            user_operation.max_fee_per_gas,
+            quote.post_op_gas,
+        ),
+        quote.exchange_rate,
+    );
+    if !has_approval(&user_operation.call_data, token, quote.paymaster, amount) {
+        let allowance =
+            get_allowance(chain_id, token, user_operation.sender, quote.paymaster).await?;
+        if allowance < amount {
+            return Err(eyre!(
+                "Insufficient allowance of token {} for paymaster {}: {} < {}",
+                token,
+                quote.paymaster,
+                allowance,
+                amount
+            ));
+        }
+    }
+
+    let (valid_until, valid_after) = quote_validity(&gas_and_paymaster_and_data);
+
+    Ok(GasAndPaymasterAndDataResponse {
+        gas_and_paymaster_and_data,
+        token_quote: Some(TokenQuote {
+            token,
+            paymaster: quote.paymaster,
+            exchange_rate: quote.exchange_rate,
+            amount,
+            valid_until,
+            valid_after,
+        }),
+    })
+}
+
+/// Get the max cost in wei of the sponsored gas limits at the max fee, including the post
+/// operation of the paymaster.
+pub fn max_cost_wei(
+    gas_and_paymaster_and_data: &GasAndPaymasterAndDataVariant,
+    max_fee_per_gas: U256,
+    post_op_gas: U256,
+) -> U256 {
+    let gas = match gas_and_paymaster_and_data {
+        GasAndPaymasterAndDataVariant::Default(data) => data
+            .call_gas_limit
+            .saturating_add(data.verification_gas_limit)
+            .saturating_add(data.pre_verification_gas)
+            .saturating_add(post_op_gas),
+        GasAndPaymasterAndDataVariant::Packed(data) => data
+            .call_gas_limit
+            .saturating_add(data.verification_gas_limit)
+            .saturating_add(data.pre_verification_gas)
+            .saturating_add(data.paymaster_verification_gas_limit)
+            .saturating_add(data.paymaster_post_op_gas_limit.max(post_op_gas)),
+    };
+
+    gas.saturating_mul(max_fee_per_gas)
+}
+
+/// Get the token amount of the cost in wei at the exchange rate, rounded up.
+pub fn required_token_amount(cost_wei: U256, exchange_rate: U256) -> U256 {
+    cost_wei.saturating_mul(exchange_rate).div_ceil(U256::from(10).pow(U256::from(18)))
+}
+
+/// Returns `true` if the calldata approves the paymaster to spend at least the amount of the token.
+pub fn has_approval(call_data: &Bytes, token: Address, paymaster: Address, amount: U256) -> bool {
+    decode_wallet_calls(call_data).is_some_and(|calls| {
+        calls.into_iter().any(|(dest, func)| {
+            dest == token &&
+                IERC20::approveCall::abi_decode(&func, true).is_ok_and(|approve| {
+                    approve.spender == paymaster && approve.amount >= amount
+                })
+        })
+    })
+}
+
+/// Get the allowance of the token from the owner to the spender.
+pub async fn get_allowance(
+    chain_id: u64,
+    token: Address,
+    owner: Address,
+    spender: Address,
+) -> Result<U256> {
+    let (provider, _) = get_provider(chain_id).await?;
+    let contract = IERC20::new(token, provider);
+
+    Ok(contract.allowance(owner, spender).call().await?._0)
+}
+
+/// Get the validity window of the quote from the paymaster and data, or the default validity.
+fn quote_validity(gas_and_paymaster_and_data: &GasAndPaymasterAndDataVariant) -> (u64, u64) {
+    let paymaster_and_data = match gas_and_paymaster_and_data.clone() {
+        GasAndPaymasterAndDataVariant::Default(data) => data.paymaster_and_data,
+        GasAndPaymasterAndDataVariant::Packed(data) => {
+            GasAndPaymasterAndData::from(data).paymaster_and_data
+        }
+    };
+
+    // The paymaster and data shorter than the verifying paymaster format has no validity to decode
+    let decoded = if paymaster_and_data.len() >= 84 {
+        decode_paymaster_and_data(paymaster_and_data.to_vec())
+    } else {
+        Err(eyre!("Paymaster and data too short"))
+    };
+
+    match decoded {
+        Ok((_, valid_until, valid_after, _)) if valid_until > 0 => (valid_until, valid_after),
+        res => {
+            if let Err(err) = res {
+                warn!("Failed to decode the validity of the paymaster and data: {:?}", err);
+            }
+            let now =
+                SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            (now + DEFAULT_QUOTE_VALIDITY_SECONDS, now)
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+    use lightdotso_contracts::{
+        light_wallet::LightWallet::executeBatchCall, types::PackedGasAndPaymasterAndData,
+    };
+
+    #[test]
+    fn test_max_cost_wei() {
+        let default = GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+            call_gas_limit: U256::from(100),
+            verification_gas_limit: U256::from(200),
+            pre_verification_gas: U256::from(300),
+            paymaster_and_data: Bytes::default(),
+        });
+        assert_eq!(max_cost_wei(&default, U256::from(2), U256::from(400)), U256::from(2000));
+
+        // The paymaster gas limits of the packed sponsorship are charged
+        let packed = GasAndPaymasterAndDataVariant::Packed(PackedGasAndPaymasterAndData {
+            call_gas_limit: U256::from(100),
+            verification_gas_limit: U256::from(200),
+            pre_verification_gas: U256::from(300),
+            paymaster: Address::ZERO,
+            paymaster_verification_gas_limit: U256::from(400),
+            paymaster_post_op_gas_limit: U256::from(500),
+            paymaster_data: Bytes::default(),
+        });
+        assert_eq!(max_cost_wei(&packed, U256::from(2), U256::from(1)), U256::from(3000));
+
+        // The overflowing gas saturates rather than panics
+        let overflow = GasAndPaymasterAndDataVariant::Default(GasAndPaymasterAndData {
+            call_gas_limit: U256::MAX,
+            verification_gas_limit: U256::MAX,
+            pre_verification_gas: U256::ZERO,
+            paymaster_and_data: Bytes::default(),
+        });
+        assert_eq!(max_cost_wei(&overflow, U256::from(2), U256::ZERO), U256::MAX);
+    }
+
+    #[test]
+    fn test_required_token_amount() {
+        // 0.001 ether at 3000 USDC (6 decimals) per ether is 3 USDC
+        let cost_wei = U256::from(10_u64.pow(15));
+        let exchange_rate = U256::from(3000 * 10_u64.pow(6));
+        assert_eq!(required_token_amount(cost_wei, exchange_rate), U256::from(3 * 10_u64.pow(6)));
+
+        // Rounded up
+        assert_eq!(required_token_amount(U256::from(1), U256::from(1)), U256::from(1));
+    }
+
+    #[test]
+    fn test_has_approval() {
+        let token = address!("0000000000000000000000000000000000000001");
+        let paymaster = address!("0000000000000000000000000000000000000002");
+        let approve: Bytes =
+            IERC20::approveCall { spender: paymaster, amount: U256::from(100) }.abi_encode().into();
+        let call_data: Bytes = executeBatchCall {
+            dest: vec![token, Address::ZERO],
+            value: vec![U256::ZERO, U256::ZERO],
+            func: vec![approve, Bytes::default()],
+        }
+        .abi_encode()
+        .into();
+
+        assert!(has_approval(&call_data, token, paymaster, U256::from(100)));
+        assert!(!has_approval(&call_data, token, paymaster, U256::from(101)));
+        assert!(!has_approval(&call_data, Address::ZERO, paymaster, U256::from(1)));
+    }
+}
@@ -12,6 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::estimate::{
+    cross_check_packed_user_operation, cross_check_user_operation,
+    estimate_packed_user_operation_gas_locally, estimate_user_operation_gas_locally,
+};
 use alloy::primitives::{Address, Bytes, U256};
 use eyre::Result;
 use lightdotso_contracts::types::{
@@ -63,8 +67,13 @@ pub async fn get_packed_gas_and_paymaster_and_data(
     rpc_url: String,
     entry_point: Address,
     packed_user_operation: &PackedUserOperationRequest,
+    sponsorship_policy: Option<Value>,
 ) -> Result<Response<PackedGasAndPaymasterAndData>> {
-    let params = vec![json!(packed_user_operation), json!(entry_point)];
+    let params = if let Some(policy) = sponsorship_policy {
+        vec![json!(packed_user_operation), json!(entry_point), policy]
+    } else {
+        vec![json!(packed_user_operation), json!(entry_point)]
+    };
     info!("params: {:?}", params);
 
     let req_body = Request {
@@ -161,25 +170,41 @@ pub async fn construct_user_operation(
     chain_id: u64,
     user_operation: UserOperationRequest,
     entry_point: Address,
+    simulate_gas: bool,
 ) -> Result<UserOperation> {
     // If the `preVerificationGas`, `verificationGasLimit`, and `callGasLimit` are set,
     // override the gas estimation for the user operation
-    let estimated_user_operation_gas: EstimateResult = if user_operation
+    let is_gas_overridden = user_operation
         .pre_verification_gas
         .is_some_and(|pre_verification_gas| pre_verification_gas > U256::ZERO) &&
         user_operation
             .verification_gas_limit
             .is_some_and(|verification_gas_limit| verification_gas_limit > U256::ZERO) &&
-        user_operation.call_gas_limit.is_some_and(|call_gas_limit| call_gas_limit > U256::ZERO)
-    {
+        user_operation.call_gas_limit.is_some_and(|call_gas_limit| call_gas_limit > U256::ZERO);
+    let estimated_user_operation_gas: EstimateResult = if is_gas_overridden {
         warn!("Overriding the gas estimation for the user operation");
         EstimateResult {
             pre_verification_gas: user_operation.pre_verification_gas.unwrap_or_default(),
             verification_gas_limit: user_operation.verification_gas_limit.unwrap_or_default(),
             call_gas_limit: user_operation.call_gas_limit.unwrap_or_default(),
         }
+    } else if simulate_gas {
+        // If the gas is not set, estimate the gas for the user operation locally, falling back to
+        // the upstream estimation.
+        match estimate_user_operation_gas_locally(
+            chain_id,
+            entry_point,
+            &user_operation_from_request(&user_operation),
+        )
+        .await
+        {
+            Ok(estimate) => estimate,
+            Err(err) => {
+                warn!("Failed to estimate the gas locally: {:?}", err);
+                estimate_user_operation_gas(chain_id, entry_point, &user_operation).await?.result
+            }
+        }
     } else {
-        // If the `estimate_user_operation_gas` is not set, estimate the gas for the user operation.
         estimate_user_operation_gas(chain_id, entry_point, &user_operation).await?.result
     };
     info!("estimated_user_operation_gas: {:?}", estimated_user_operation_gas);
@@ -191,7 +216,7 @@ pub async fn construct_user_operation(
             .is_some_and(|max_priority_fee_per_gas| max_priority_fee_per_gas > U256::ZERO)
     {
         warn!("Overriding the gas estimation for the user operation w/ the maxFeePerGas and maxPriorityFeePerGas");
-        return Ok(UserOperation {
+        let constructed_user_operation = UserOperation {
             call_data: user_operation.call_data,
             init_code: user_operation.init_code,
             nonce: user_operation.nonce,
@@ -203,13 +228,21 @@ pub async fn construct_user_operation(
             max_priority_fee_per_gas: user_operation.max_priority_fee_per_gas.unwrap_or_default(),
             signature: user_operation.signature,
             paymaster_and_data: Bytes::default(),
-        });
+        };
+        return Ok(cross_check_estimates(
+            chain_id,
+            entry_point,
+            constructed_user_operation,
+            is_gas_overridden,
+            simulate_gas,
+        )
+        .await);
     }
 
     // Get the estimated request gas because required gas parameters are not set.
     let estimated_request_gas = estimate_request_gas_estimation(chain_id).await?.result;
 
-    Ok(UserOperation {
+    let constructed_user_operation = UserOperation {
         call_data: user_operation.call_data,
         init_code: user_operation.init_code,
         nonce: user_operation.nonce,
@@ -221,7 +254,32 @@ pub async fn construct_user_operation(
         max_priority_fee_per_gas: estimated_request_gas.high.max_priority_fee_per_gas,
         signature: user_operation.signature,
         paymaster_and_data: Bytes::default(),
-    })
+    };
+
+    Ok(cross_check_estimates(
+        chain_id,
+        entry_point,
+        constructed_user_operation,
+        is_gas_overridden,
+        simulate_gas,
+    )
+    .await)
+}
+
+/// Cross-check the upstream gas estimates of the user operation w/ the local estimates.
+async fn cross_check_estimates(
+    chain_id: u64,
+    entry_point: Address,
+    user_operation: UserOperation,
+    is_gas_overridden: bool,
+    simulate_gas: bool,
+) -> UserOperation {
+    // The gas set by the caller is respected as is.
+    if is_gas_overridden {
+        return user_operation;
+    }
+
+    cross_check_user_operation(chain_id, entry_point, user_operation, simulate_gas).await
 }
 
 /// Construct the packed user operation.
@@ -229,10 +287,11 @@ pub async fn construct_packed_user_operation(
     chain_id: u64,
     packed_user_operation: PackedUserOperationRequest,
     entry_point: Address,
+    simulate_gas: bool,
 ) -> Result<PackedUserOperation> {
     // If the `preVerificationGas`, `verificationGasLimit`, and `callGasLimit` are set,
     // override the gas estimation for the user operation
-    let estimated_packed_user_operation_gas: PackedEstimateResult = if packed_user_operation
+    let is_gas_overridden = packed_user_operation
         .pre_verification_gas
         .is_some_and(|pre_verification_gas| pre_verification_gas > U256::ZERO) &&
         packed_user_operation
@@ -240,8 +299,8 @@ pub async fn construct_packed_user_operation(
             .is_some_and(|verification_gas_limit| verification_gas_limit > U256::ZERO) &&
         packed_user_operation
             .call_gas_limit
-            .is_some_and(|call_gas_limit| call_gas_limit > U256::ZERO)
-    {
+            .is_some_and(|call_gas_limit| call_gas_limit > U256::ZERO);
+    let estimated_packed_user_operation_gas: PackedEstimateResult = if is_gas_overridden {
         warn!("Overriding the gas estimation for the user operation");
         PackedEstimateResult {
             pre_verification_gas: packed_user_operation
@@ -258,9 +317,29 @@ pub async fn construct_packed_user_operation(
                 .paymaster_verification_gas_limit
                 .unwrap_or_default(),
         }
+    } else if simulate_gas {
+        // If the gas is not set, estimate the gas for the packed user operation locally, falling
+        // back to the upstream estimation.
+        match estimate_packed_user_operation_gas_locally(
+            chain_id,
+            entry_point,
+            &packed_user_operation_from_request(&packed_user_operation),
+        )
+        .await
+        {
+            Ok(estimate) => estimate,
+            Err(err) => {
+                warn!("Failed to estimate the gas locally: {:?}", err);
+                estimate_packed_user_operation_gas(
+                    chain_id,
+                    entry_point,
+                    packed_user_operation.clone(),
+                )
+                .await?
+                .result
+            }
+        }
     } else {
-        // If the `estimate_packed_user_operation_gas` is not set, estimate the gas for the packed
-        // user operation.
         estimate_packed_user_operation_gas(chain_id, entry_point, packed_user_operation.clone())
             .await?
             .result
@@ -278,7 +357,7 @@ pub async fn construct_packed_user_operation(
             .is_some_and(|max_priority_fee_per_gas| max_priority_fee_per_gas > U256::ZERO)
     {
         warn!("Overriding the gas estimation for the user operation w/ the maxFeePerGas and maxPriorityFeePerGas");
-        return Ok(PackedUserOperation {
+        let constructed_packed_user_operation = PackedUserOperation {
             call_data: packed_user_operation.clone().call_data,
             factory: packed_user_operation.clone().factory,
             factory_data: packed_user_operation.clone().factory_data,
@@ -299,13 +378,21 @@ pub async fn construct_packed_user_operation(
             ),
             paymaster_post_op_gas_limit: None,
             paymaster_data: None,
-        });
+        };
+        return Ok(cross_check_packed_estimates(
+            chain_id,
+            entry_point,
+            constructed_packed_user_operation,
+            is_gas_overridden,
+            simulate_gas,
+        )
+        .await);
     }
 
     // Get the estimated request gas because required gas parameters are not set.
     let estimated_request_gas = estimate_request_gas_estimation(chain_id).await?.result;
 
-    Ok(PackedUserOperation {
+    let constructed_packed_user_operation = PackedUserOperation {
         call_data: packed_user_operation.clone().call_data,
         factory: packed_user_operation.clone().factory,
         factory_data: packed_user_operation.clone().factory_data,
@@ -323,5 +410,75 @@ pub async fn construct_packed_user_operation(
         ),
         paymaster_post_op_gas_limit: None,
         paymaster_data: None,
-    })
+    };
+
+    Ok(cross_check_packed_estimates(
+        chain_id,
+        entry_point,
+        constructed_packed_user_operation,
+        is_gas_overridden,
+        simulate_gas,
+    )
+    .await)
+}
+
+/// Cross-check the upstream gas estimates of the packed user operation w/ the local estimates.
+async fn cross_check_packed_estimates(
+    chain_id: u64,
+    entry_point: Address,
+    packed_user_operation: PackedUserOperation,
+    is_gas_overridden: bool,
+    simulate_gas: bool,
+) -> PackedUserOperation {
+    // The gas set by the caller is respected as is.
+    if is_gas_overridden {
+        return packed_user_operation;
+    }
+
+    cross_check_packed_user_operation(chain_id, entry_point, packed_user_operation, simulate_gas)
+        .await
+}
+
+/// Get the user operation of the request for the local estimation, w/ the unset fields as zero and
+/// the stub paymaster and data, if any, to estimate the validation of the paymaster.
+fn user_operation_from_request(user_operation: &UserOperationRequest) -> UserOperation {
+    UserOperation {
+        sender: user_operation.sender,
+        nonce: user_operation.nonce,
+        init_code: user_operation.init_code.clone(),
+        call_data: user_operation.call_data.clone(),
+        call_gas_limit: user_operation.call_gas_limit.unwrap_or_default(),
+        verification_gas_limit: user_operation.verification_gas_limit.unwrap_or_default(),
+        pre_verification_gas: user_operation.pre_verification_gas.unwrap_or_default(),
+        max_fee_per_gas: user_operation.max_fee_per_gas.unwrap_or_default(),
+        max_priority_fee_per_gas: user_operation.max_priority_fee_per_gas.unwrap_or_default(),
+        paymaster_and_data: user_operation.paymaster_and_data.clone().unwrap_or_default(),
+        signature: user_operation.signature.clone(),
+    }
+}
+
+/// Get the packed user operation of the request for the local estimation, w/ the unset fields as
+/// zero and the stub paymaster, if any, to estimate the validation of the paymaster.
+fn packed_user_operation_from_request(
+    packed_user_operation: &PackedUserOperationRequest,
+) -> PackedUserOperation {
+    PackedUserOperation {
+        sender: packed_user_operation.sender,
+        nonce: packed_user_operation.nonce,
+        factory: packed_user_operation.factory,
+        factory_data: packed_user_operation.factory_data.clone(),
+        call_data: packed_user_operation.call_data.clone(),
+        call_gas_limit: packed_user_operation.call_gas_limit.unwrap_or_default(),
+        verification_gas_limit: packed_user_operation.verification_gas_limit.unwrap_or_default(),
+        pre_verification_gas: packed_user_operation.pre_verification_gas.unwrap_or_default(),
+        max_fee_per_gas: packed_user_operation.max_fee_per_gas.unwrap_or_default(),
+        max_priority_fee_per_gas: packed_user_operation
+            .max_priority_fee_per_gas
+            .unwrap_or_default(),
+        paymaster: packed_user_operation.paymaster,
+        paymaster_verification_gas_limit: packed_user_operation.paymaster_verification_gas_limit,
+        paymaster_post_op_gas_limit: packed_user_operation.paymaster_post_op_gas_limit,
+        paymaster_data: packed_user_operation.paymaster_data.clone(),
+        signature: packed_user_operation.signature.clone(),
+    }
 }
@@ -20,12 +20,17 @@ use crate::{
 };
 use clap::Parser;
 use eyre::Result;
-use lightdotso_constants::chains::CHAIN_BLOCK_SECONDS;
-use lightdotso_graphql::constants::{
-    SATSUMA_BASE_URL, SATSUMA_LIVE_IDS, THE_GRAPH_STUDIO_BASE_URL, THE_GRAPH_STUDIO_SERVICE_IDS,
-};
+use lightdotso_constants::registry::{get_chain_registry, spawn_chain_registry_watcher};
+use lightdotso_graphql::constants::{SATSUMA_BASE_URL, THE_GRAPH_STUDIO_BASE_URL};
 use lightdotso_tracing::tracing::{error, info};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+use tokio::task::JoinHandle;
+
+/// The interval of the reconciliation of the polling tasks w/ the chain registry
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The running polling tasks keyed by chain id, service and mode, w/ the url they poll
+type PollingTasks = HashMap<(u64, String, bool), (String, JoinHandle<Result<()>>)>;
 
 #[derive(Clone, Debug, Parser, Default)]
 pub struct PollingArgs {
@@ -77,52 +82,65 @@ impl PollingArgs {
         // Print the config
         info!("Config: {:?}", self);
 
-        // Create a mapping for chain id to polling URLs.
-        let chain_mapping = create_chain_mapping(
-            self.the_graph_studio_api_key.clone(),
-            self.the_graph_studio_enabled,
-            self.satsuma_api_key.clone(),
-            self.satsuma_enabled,
-        );
+        // Keep the chain registry in sync w/ the file
+        spawn_chain_registry_watcher(Duration::from_secs(30));
 
-        // Create a vector to store the handles to the spawned tasks.
-        let mut handles = Vec::new();
-
-        // Spawn a task for each chain id.
-        for (chain_id, chain_map) in chain_mapping.clone().into_iter() {
-            for (service, _url) in chain_map.into_iter() {
-                if self.live || self.mode == "all" {
-                    let live_handle = tokio::spawn(run_polling(
-                        self.clone(),
-                        chain_id,
-                        service.clone(),
-                        true,
-                        chain_mapping.clone(),
-                    ));
-                    handles.push(live_handle);
+        let mut tasks = PollingTasks::new();
+
+        loop {
+            // Create a mapping for chain id to polling URLs, on each cycle so that the reloads of
+            // the chain registry are applied.
+            let chain_mapping = create_chain_mapping(
+                self.the_graph_studio_api_key.clone(),
+                self.the_graph_studio_enabled,
+                self.satsuma_api_key.clone(),
+                self.satsuma_enabled,
+            );
+
+            // Stop the tasks removed or changed in the registry, and the panicked ones to respawn.
+            tasks.retain(|(chain_id, service, live), (url, handle)| {
+                if handle.is_finished() {
+                    error!("A task finished, chain_id: {} service: {}", chain_id, service);
+                    return false;
                 }
 
-                if !self.live || self.mode == "all" {
-                    let past_handle = tokio::spawn(run_polling(
-                        self.clone(),
-                        chain_id,
-                        service.clone(),
-                        false,
-                        chain_mapping.clone(),
-                    ));
-                    handles.push(past_handle);
+                let current = chain_mapping.get(chain_id).and_then(|urls| urls.get(service));
+                if current != Some(url) {
+                    info!(
+                        "Polling stopping, chain_id: {} service: {} live: {}",
+                        chain_id, service, live
+                    );
+                    handle.abort();
+                    return false;
                 }
-            }
-        }
 
-        // Wait for all tasks to finish.
-        for handle in handles {
-            if let Err(e) = handle.await {
-                error!("A task panicked: {:?}", e);
+                true
+            });
+
+            // Spawn a task for each chain id, service and mode not running yet.
+            for (chain_id, chain_map) in chain_mapping.iter() {
+                for (service, url) in chain_map.iter() {
+                    for live in [true, false] {
+                        if live != self.live && self.mode != "all" {
+                            continue;
+                        }
+
+                        tasks.entry((*chain_id, service.clone(), live)).or_insert_with(|| {
+                            let handle = tokio::spawn(run_polling(
+                                self.clone(),
+                                *chain_id,
+                                service.clone(),
+                                live,
+                                chain_mapping.clone(),
+                            ));
+                            (url.clone(), handle)
+                        });
+                    }
+                }
             }
-        }
 
-        Ok(())
+            tokio::time::sleep(RECONCILE_INTERVAL).await;
+        }
     }
 }
 
@@ -164,8 +182,10 @@ pub async fn run_polling(
 pub fn create_sleep_seconds_mapping() -> HashMap<u64, u64> {
     let mut sleep_seconds_mapping = HashMap::new();
 
-    for (chain_id, seconds) in CHAIN_BLOCK_SECONDS.clone().into_iter() {
-        sleep_seconds_mapping.insert(chain_id, seconds);
+    for chain in get_chain_registry().chains() {
+        if let Some(seconds) = chain.block_seconds {
+            sleep_seconds_mapping.insert(chain.id, seconds);
+        }
     }
 
     // Insert a default value for the sleep seconds
@@ -182,8 +202,11 @@ pub fn create_chain_mapping(
 ) -> HashMap<u64, HashMap<String, String>> {
     let mut chain_id_to_urls = HashMap::new();
 
+    // Get the chain registry
+    let registry = get_chain_registry();
+
     if the_graph_studio_api_key.is_some() && the_graph_studio_enabled {
-        for (chain_id, id) in THE_GRAPH_STUDIO_SERVICE_IDS.clone().into_iter() {
+        for (chain_id, id) in registry.subgraph_ids(&STUDIO).into_iter() {
             let url = format!(
                 "{}/{}/{}/{}",
                 THE_GRAPH_STUDIO_BASE_URL.clone(),
@@ -197,7 +220,7 @@ pub fn create_chain_mapping(
     }
 
     if satsuma_api_key.is_some() && satsuma_enabled {
-        for (chain_id, id) in SATSUMA_LIVE_IDS.clone().into_iter() {
+        for (chain_id, id) in registry.subgraph_ids(&SATSUMA).into_iter() {
             let url =
                 format!("{}/{}/{}", SATSUMA_BASE_URL.clone(), satsuma_api_key.clone().unwrap(), id);
             let child_map = chain_id_to_urls.entry(chain_id).or_insert_with(HashMap::new);
@@ -21,7 +21,7 @@ use crate::{
 use alloy::{
     consensus::{Eip658Value, ReceiptEnvelope, ReceiptWithBloom},
     eips::BlockNumberOrTag,
-    primitives::{Address, Bloom, B256},
+    primitives::{Address, Bloom, B256, U256},
     providers::{Provider, RootProvider},
     rpc::types::{Block, Log, Receipt, Transaction, TransactionReceipt},
     transports::BoxTransport,
@@ -65,10 +65,14 @@ use lightdotso_kafka::{
     get_producer,
     rdkafka::producer::FutureProducer,
     topics::{
-        activity::produce_activity_message, interpretation::produce_interpretation_message,
-        transaction::produce_transaction_message,
+        activity::produce_activity_message,
+        billing_operation::produce_billing_operation_reconcile_message,
+        interpretation::produce_interpretation_message, transaction::produce_transaction_message,
+    },
+    types::{
+        activity::ActivityMessage, billing_operation::BillingOperationReconcileMessage,
+        interpretation::InterpretationMessage,
     },
-    types::{activity::ActivityMessage, interpretation::InterpretationMessage},
 };
 use lightdotso_opentelemetry::polling::PollingMetrics;
 use lightdotso_prisma::{user_operation, ActivityEntity, ActivityOperation, PrismaClient};
@@ -364,6 +368,12 @@ impl Polling {
                             res.0.clone().hash.parse()?,
                             res.0.clone().sender.parse()?,
                             res.0.clone(),
+                            user_operation_event
+                                .actual_gas_cost
+                                .0
+                                .parse()
+                                .ok()
+                                .zip(op.block_number.0.parse().ok()),
                         )
                         .await;
                     let _ = self
@@ -375,6 +385,24 @@ impl Polling {
                 }
             }
 
+            // Reconcile the billing w/ the actual gas cost if sponsored by a paymaster.
+            let paymaster: Address = user_operation_event.paymaster.0.parse()?;
+            if self.live && self.kafka_client.is_some() && paymaster != Address::ZERO {
+                info!("send_billing_reconcile_queue");
+                let _ = self
+                    .send_billing_reconcile_queue(&BillingOperationReconcileMessage {
+                        chain_id,
+                        user_operation_hash: user_operation_event.user_op_hash.0.parse()?,
+                        transaction_hash: Some(user_operation_event.transaction_hash.0.parse()?),
+                        block_number: op.block_number.0.parse()?,
+                        actual_gas_cost: user_operation_event.actual_gas_cost.0.parse()?,
+                        actual_gas_used: user_operation_event.actual_gas_used.0.parse()?,
+                        success: user_operation_event.success,
+                        attempt: 0,
+                    })
+                    .await;
+            }
+
             // Send the tx queue on all modes.
             info!("send_tx_queue");
             if self.kafka_client.is_some() {
@@ -466,6 +494,10 @@ impl Polling {
                     receipt.clone().user_operation_hash,
                     receipt.clone().sender,
                     db_user_operation.user_operation,
+                    receipt
+                        .tx_receipt
+                        .block_number
+                        .map(|block_number| (receipt.actual_gas_cost, block_number)),
                 )
                 .await;
             let _ = self
@@ -476,6 +508,34 @@ impl Polling {
                 .await;
         }
 
+        // Reconcile the billing w/ the actual gas cost if sponsored by a paymaster.
+        if self.live &&
+            self.kafka_client.is_some() &&
+            receipt.paymaster.is_some_and(|paymaster| paymaster != Address::ZERO)
+        {
+            // The block number is required to price the actual gas cost at the execution block
+            if let Some(block_number) = receipt.tx_receipt.block_number {
+                info!("send_billing_reconcile_queue");
+                let _ = self
+                    .send_billing_reconcile_queue(&BillingOperationReconcileMessage {
+                        chain_id,
+                        user_operation_hash: receipt.user_operation_hash,
+                        transaction_hash: Some(receipt.tx_receipt.transaction_hash),
+                        block_number,
+                        actual_gas_cost: receipt.actual_gas_cost,
+                        actual_gas_used: receipt.actual_gas_used,
+                        success: receipt.success,
+                        attempt: 0,
+                    })
+                    .await;
+            } else {
+                warn!(
+                    "Block number not found for user operation: {:?}, skipping the billing reconcile",
+                    receipt.user_operation_hash
+                );
+            }
+        }
+
         // Send the tx queue on all modes.
         info!("send_tx_queue");
         if self.kafka_client.is_some() {
@@ -870,16 +930,23 @@ impl Polling {
         Err(eyre!("client not found"))
     }
 
-    /// Add a new activity in the queue
+    /// Add a new activity in the queue, w/ the actual gas cost and the block of the execution
     #[autometrics]
     pub async fn send_activity_queue(
         &self,
         user_operation_hash: B256,
         sender: Address,
         op: user_operation::Data,
+        execution: Option<(U256, u64)>,
     ) -> Result<()> {
         let client = self.kafka_client.clone().unwrap();
-        let payload = serde_json::to_value(&op).unwrap_or_else(|_| serde_json::Value::Null);
+        let mut payload = serde_json::to_value(&op).unwrap_or_else(|_| serde_json::Value::Null);
+        if let (Some((actual_gas_cost, block_number)), Some(log)) =
+            (execution, payload.as_object_mut())
+        {
+            log.insert("actualGasCost".to_string(), actual_gas_cost.to_string().into());
+            log.insert("blockNumber".to_string(), block_number.into());
+        }
         let msg = &ActivityMessage {
             operation: ActivityOperation::Update,
             log: payload.clone().to_owned(),
@@ -927,6 +994,21 @@ impl Polling {
         Ok(())
     }
 
+    /// Add a new billing reconciliation in the queue
+    #[autometrics]
+    pub async fn send_billing_reconcile_queue(
+        &self,
+        msg: &BillingOperationReconcileMessage,
+    ) -> Result<()> {
+        let client = self.kafka_client.clone().unwrap();
+
+        let _ = { || produce_billing_operation_reconcile_message(client.clone(), msg) }
+            .retry(ExponentialBuilder::default())
+            .await;
+
+        Ok(())
+    }
+
     /// Add a new tx in the queue
     #[autometrics]
     pub async fn send_tx_queue(&self, chain_id: u64, block_number: i32) -> Result<()> {
@@ -14,7 +14,8 @@
 
 use alloy::primitives::B256;
 use dotenvy::dotenv;
-use lightdotso_graphql::constants::{THE_GRAPH_STUDIO_BASE_URL, THE_GRAPH_STUDIO_SERVICE_IDS};
+use lightdotso_constants::registry::get_chain_registry;
+use lightdotso_graphql::constants::THE_GRAPH_STUDIO_BASE_URL;
 use lightdotso_polling::{config::PollingArgs, polling::Polling};
 use lightdotso_tracing::init_test_tracing;
 use std::collections::HashMap;
@@ -73,7 +74,7 @@ async fn test_polling_poll_uop() {
 
     let polling = Polling::new(&args, HashMap::new(), chain_mapping, false).await.unwrap();
 
-    let id = THE_GRAPH_STUDIO_SERVICE_IDS.get(&137).unwrap();
+    let id = get_chain_registry().subgraph_ids("studio").remove(&137).unwrap();
 
     let url = format!(
         "{}/{}/{}/{}",
@@ -19,6 +19,41 @@ lazy_static! {
     pub static ref WALLETS: String = "wallets".to_string();
 }
 
+// The gas history namespace
+lazy_static! {
+    pub static ref GAS_HISTORY: String = "gas:history".to_string();
+}
+
+// The sponsorship spend namespace
+lazy_static! {
+    pub static ref SPONSORSHIP_SPEND: String = "sponsorship:spend".to_string();
+}
+
+// The sponsorship decisions namespace
+lazy_static! {
+    pub static ref SPONSORSHIP_DECISIONS: String = "sponsorship:decisions".to_string();
+}
+
+// The token price namespace
+lazy_static! {
+    pub static ref TOKEN_PRICE: String = "token_price".to_string();
+}
+
+// The bundler mempool namespace
+lazy_static! {
+    pub static ref MEMPOOL: String = "mempool".to_string();
+}
+
+// The executor nonce namespace
+lazy_static! {
+    pub static ref EXECUTOR_NONCE: String = "executor:nonce".to_string();
+}
+
+// The executor low balance alert namespace
+lazy_static! {
+    pub static ref EXECUTOR_BALANCE_ALERT: String = "executor:balance_alert".to_string();
+}
+
 // The node queue namespace
 lazy_static! {
     pub static ref QUEUE_NODE: String = "queue:node".to_string();
@@ -0,0 +1,107 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::namespace::{EXECUTOR_BALANCE_ALERT, EXECUTOR_NONCE};
+use redis::{aio::MultiplexedConnection, AsyncCommands, Connection, RedisResult, Script};
+
+/// Reserve the next nonce w/ the max of the stored and the on-chain nonce, atomically
+const RESERVE_NONCE_SCRIPT: &str = r#"
+local current = tonumber(redis.call("GET", KEYS[1]) or "0")
+local on_chain = tonumber(ARGV[1])
+if current < on_chain then
+    current = on_chain
+end
+redis.call("SET", KEYS[1], current + 1)
+return current
+"#;
+
+/// Release the nonce only if it is the latest reserved one
+const RELEASE_NONCE_SCRIPT: &str = r#"
+local current = tonumber(redis.call("GET", KEYS[1]) or "0")
+local nonce = tonumber(ARGV[1])
+if current == nonce + 1 then
+    redis.call("SET", KEYS[1], nonce)
+    return 1
+end
+return 0
+"#;
+
+/// Get the key of the nonce of the executor on the chain
+fn executor_nonce_key(chain_id: u64, executor: &str) -> String {
+    format!("{}:{}:{}", EXECUTOR_NONCE.as_str(), chain_id, executor.to_lowercase())
+}
+
+/// Get the next nonce of the executor stored, if any
+pub async fn get_executor_nonce(
+    con: &mut MultiplexedConnection,
+    chain_id: u64,
+    executor: &str,
+) -> RedisResult<Option<u64>> {
+    con.get(executor_nonce_key(chain_id, executor)).await
+}
+
+/// Reserve the next nonce of the executor, w/ the on-chain pending nonce as the lower bound
+pub async fn reserve_executor_nonce(
+    con: &mut MultiplexedConnection,
+    chain_id: u64,
+    executor: &str,
+    on_chain_nonce: u64,
+) -> RedisResult<u64> {
+    Script::new(RESERVE_NONCE_SCRIPT)
+        .key(executor_nonce_key(chain_id, executor))
+        .arg(on_chain_nonce)
+        .invoke_async(con)
+        .await
+}
+
+/// Release the reserved nonce of the executor, returns whether the nonce was released
+pub async fn release_executor_nonce(
+    con: &mut MultiplexedConnection,
+    chain_id: u64,
+    executor: &str,
+    nonce: u64,
+) -> RedisResult<bool> {
+    Script::new(RELEASE_NONCE_SCRIPT)
+        .key(executor_nonce_key(chain_id, executor))
+        .arg(nonce)
+        .invoke_async(con)
+        .await
+}
+
+/// Reset the next nonce of the executor, e.g. to recover from a gap
+pub async fn reset_executor_nonce(
+    con: &mut MultiplexedConnection,
+    chain_id: u64,
+    executor: &str,
+    nonce: u64,
+) -> RedisResult<()> {
+    con.set(executor_nonce_key(chain_id, executor), nonce).await
+}
+
+/// Mark the low balance alert of the executor as sent for `ttl` seconds, returns whether it was
+/// not sent yet
+pub fn set_executor_balance_alert(
+    con: &mut Connection,
+    chain_id: u64,
+    executor: &str,
+    ttl: u64,
+) -> RedisResult<bool> {
+    let key =
+        format!("{}:{}:{}", EXECUTOR_BALANCE_ALERT.as_str(), chain_id, executor.to_lowercase());
+
+    let res: Option<String> =
+        redis::cmd("SET").arg(key).arg(1).arg("NX").arg("EX").arg(ttl).query(con)?;
+
+    Ok(res.is_some())
+}
@@ -0,0 +1,49 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::namespace::GAS_HISTORY;
+use redis::{Commands, Connection, RedisResult};
+
+/// Get the key of the gas history of the chain
+fn gas_history_key(chain_id: u64) -> String {
+    format!("{}:{}", GAS_HISTORY.as_str(), chain_id)
+}
+
+/// Add a gas sample to the history of the chain, keeping the latest `max_len` samples
+pub fn add_gas_history(
+    con: &mut Connection,
+    chain_id: u64,
+    timestamp: u64,
+    value: &str,
+    max_len: isize,
+) -> RedisResult<()> {
+    let key = gas_history_key(chain_id);
+
+    // Add the sample scored by the timestamp and trim the oldest samples
+    redis::pipe()
+        .zadd(&key, value, timestamp)
+        .ignore()
+        .zremrangebyrank(&key, 0, -(max_len + 1))
+        .ignore()
+        .query(con)
+}
+
+/// Get the gas samples of the chain since the timestamp, from the oldest to the latest
+pub fn get_gas_history(
+    con: &mut Connection,
+    chain_id: u64,
+    from_timestamp: u64,
+) -> RedisResult<Vec<String>> {
+    con.zrangebyscore(gas_history_key(chain_id), from_timestamp, "+inf")
+}
@@ -0,0 +1,31 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::namespace::MEMPOOL;
+use redis::{Commands, Connection, RedisResult};
+
+/// Get all of the persisted user operations in the mempool
+pub fn get_mempool_operations(con: &mut Connection) -> RedisResult<Vec<String>> {
+    con.hvals(MEMPOOL.as_str())
+}
+
+/// Persist the user operation in the mempool w/ the user operation hash
+pub fn set_mempool_operation(con: &mut Connection, hash: &str, value: &str) -> RedisResult<()> {
+    con.hset(MEMPOOL.as_str(), hash.to_lowercase(), value)
+}
+
+/// Remove the user operation w/ the user operation hash from the mempool
+pub fn remove_mempool_operation(con: &mut Connection, hash: &str) -> RedisResult<()> {
+    con.hdel(MEMPOOL.as_str(), hash.to_lowercase())
+}
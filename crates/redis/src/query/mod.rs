@@ -12,8 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod executor;
+pub mod gas;
+pub mod mempool;
 pub mod node;
 pub mod portfolio;
+pub mod price;
+pub mod sponsorship;
 pub mod token;
 pub mod transaction;
 pub mod user_operation;
@@ -0,0 +1,43 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::namespace::TOKEN_PRICE;
+use redis::{Commands, Connection, RedisResult};
+
+/// Get the key of the token price on the chain in the time bucket
+fn token_price_key(chain_id: u64, token: &str, bucket: u64) -> String {
+    format!("{}:{}:{}:{}", TOKEN_PRICE.as_str(), chain_id, token.to_lowercase(), bucket)
+}
+
+/// Get the cached token price in USD on the chain in the time bucket
+pub fn get_token_price(
+    con: &mut Connection,
+    chain_id: u64,
+    token: &str,
+    bucket: u64,
+) -> RedisResult<Option<f64>> {
+    con.get(token_price_key(chain_id, token, bucket))
+}
+
+/// Cache the token price in USD on the chain in the time bucket, expiring after `ttl` seconds
+pub fn set_token_price(
+    con: &mut Connection,
+    chain_id: u64,
+    token: &str,
+    bucket: u64,
+    price: f64,
+    ttl: u64,
+) -> RedisResult<()> {
+    con.set_ex(token_price_key(chain_id, token, bucket), price, ttl)
+}
@@ -0,0 +1,112 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::namespace::{SPONSORSHIP_DECISIONS, SPONSORSHIP_SPEND};
+use redis::{Commands, Connection, RedisResult, Script};
+
+/// Add to the spend of all of the scopes only if none of them exceeds its cap, atomically
+const RESERVE_SPEND_SCRIPT: &str = r#"
+local usd = tonumber(ARGV[1])
+for i, key in ipairs(KEYS) do
+    local cap = tonumber(ARGV[i * 2])
+    local spend = tonumber(redis.call("GET", key) or "0")
+    if cap >= 0 and spend + usd > cap then
+        return i
+    end
+end
+for i, key in ipairs(KEYS) do
+    redis.call("INCRBYFLOAT", key, ARGV[1])
+    local ttl = tonumber(ARGV[i * 2 + 1])
+    if ttl > 0 then
+        redis.call("EXPIRE", key, ttl)
+    end
+end
+return 0
+"#;
+
+/// The scope of the sponsorship spend w/ its window and cap
+#[derive(Clone, Debug, PartialEq)]
+pub struct SponsorshipSpendScope {
+    pub scope: String,
+    /// The window of the spend, or the lifetime if `None`
+    pub window: Option<u64>,
+    /// The cap of the spend in USD, or uncapped if `None`
+    pub cap_usd: Option<f64>,
+    /// The seconds until the spend expires, or never if `None`
+    pub ttl: Option<u64>,
+}
+
+/// Get the key of the sponsorship spend of the scope, w/ the window if not lifetime
+fn sponsorship_spend_key(scope: &str, window: Option<u64>) -> String {
+    match window {
+        Some(window) => format!("{}:{}:{}", SPONSORSHIP_SPEND.as_str(), scope, window),
+        None => format!("{}:{}", SPONSORSHIP_SPEND.as_str(), scope),
+    }
+}
+
+/// Get the sponsorship spend in USD of the scope in the window
+pub fn get_sponsorship_spend(
+    con: &mut Connection,
+    scope: &str,
+    window: Option<u64>,
+) -> RedisResult<f64> {
+    let spend: Option<f64> = con.get(sponsorship_spend_key(scope, window))?;
+    Ok(spend.unwrap_or_default())
+}
+
+/// Reserve the sponsorship spend in USD in all of the scopes, returns the index of the first scope
+/// whose cap would be exceeded w/o reserving any, or `None` if reserved
+pub fn reserve_sponsorship_spend(
+    con: &mut Connection,
+    scopes: &[SponsorshipSpendScope],
+    usd: f64,
+) -> RedisResult<Option<usize>> {
+    let script = Script::new(RESERVE_SPEND_SCRIPT);
+    let mut invocation = script.prepare_invoke();
+    invocation.arg(usd);
+    for scope in scopes {
+        invocation
+            .key(sponsorship_spend_key(&scope.scope, scope.window))
+            .arg(scope.cap_usd.unwrap_or(-1.0))
+            .arg(scope.ttl.unwrap_or_default());
+    }
+
+    let index: usize = invocation.invoke(con)?;
+    Ok(index.checked_sub(1))
+}
+
+/// Release the reserved sponsorship spend in USD in all of the scopes
+pub fn release_sponsorship_spend(
+    con: &mut Connection,
+    scopes: &[SponsorshipSpendScope],
+    usd: f64,
+) -> RedisResult<()> {
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    for scope in scopes {
+        pipe.incr(sponsorship_spend_key(&scope.scope, scope.window), -usd).ignore();
+    }
+    pipe.query(con)
+}
+
+/// Add a sponsorship decision, keeping the latest `max_len` decisions
+pub fn add_sponsorship_decision(
+    con: &mut Connection,
+    value: &str,
+    max_len: isize,
+) -> RedisResult<()> {
+    let key = SPONSORSHIP_DECISIONS.as_str();
+
+    redis::pipe().lpush(key, value).ignore().ltrim(key, 0, max_len - 1).ignore().query(con)
+}
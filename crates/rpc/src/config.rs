@@ -40,4 +40,12 @@ pub struct RpcArgs {
     /// The biconomy API key
     #[clap(long, env = "BICONOMY_API_KEY")]
     pub biconomy_api_key: String,
+    /// The flag of whether latency-sensitive read methods are hedged
+    #[arg(long, default_value_t = false)]
+    #[clap(long, env = "RPC_HEDGE_ENABLED")]
+    pub hedge_enabled: bool,
+    /// The maximum percentage of requests that may be hedged
+    #[arg(long, default_value_t = 10.0)]
+    #[clap(long, env = "RPC_HEDGE_BUDGET_PERCENT")]
+    pub hedge_budget_percent: f64,
 }
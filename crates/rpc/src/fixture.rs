@@ -0,0 +1,212 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use axum::{
+    body::{Body, Bytes},
+    http::Response,
+};
+use http_body_util::BodyExt;
+use lazy_static::lazy_static;
+use lightdotso_contracts::fixture::{
+    Fixture, FixtureKey, FixtureMode, FixtureStore, FIXTURE_METHODS,
+};
+use lightdotso_tracing::tracing::{info, warn};
+use serde_json::{json, Value};
+
+// The fixture store of the rpc proxy, built once from the envs
+lazy_static! {
+    pub static ref FIXTURE_STORE: FixtureStore = FixtureStore::from_env();
+}
+
+/// Get the fixture key and the id of a single JSON RPC request of a read-only method, skipping
+/// batch requests
+fn get_fixture_request(chain_id: u64, body_bytes: &Bytes) -> Option<(FixtureKey, Value, Value)> {
+    let body_json: Value = serde_json::from_slice(body_bytes).ok()?;
+    let method = body_json.get("method")?.as_str()?;
+    if !FIXTURE_METHODS.contains(&method) {
+        return None;
+    }
+
+    let params = body_json.get("params").cloned().unwrap_or(Value::Null);
+    let id = body_json.get("id").cloned().unwrap_or(Value::Null);
+
+    Some((FixtureKey::new(chain_id, method, &params), params, id))
+}
+
+/// Constructs the JSON RPC response w/ the body
+fn json_response(body: Value) -> Response<Body> {
+    Response::new(Body::from(body.to_string()))
+}
+
+/// Replays the request from the fixtures in replay mode
+/// Returns an error response on a miss if the store is strict, and `None` to proxy the request
+pub async fn replay_rpc_fixture(
+    store: &FixtureStore,
+    chain_id: u64,
+    body_bytes: &Bytes,
+) -> Option<Response<Body>> {
+    if store.mode != FixtureMode::Replay {
+        return None;
+    }
+
+    let (key, _, id) = get_fixture_request(chain_id, body_bytes)?;
+
+    match store.read(&key).await {
+        Ok(Some(fixture)) => {
+            info!("Replaying fixture: {:?}", key);
+
+            Some(json_response(match fixture.error {
+                Some(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                None => json!({ "jsonrpc": "2.0", "id": id, "result": fixture.result }),
+            }))
+        }
+        Ok(None) if store.strict => {
+            warn!("Missing fixture: {:?}", key);
+
+            Some(json_response(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32603, "message": format!("Missing fixture for {}", key.method) }
+            })))
+        }
+        Ok(None) => None,
+        Err(err) => {
+            warn!("Failed to read fixture: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Records the successful response of the request to the fixtures in record mode, and in
+/// replay mode for the misses of a lenient store
+/// A failure to record is logged and never fails the request
+pub async fn record_rpc_fixture(
+    store: &FixtureStore,
+    chain_id: u64,
+    body_bytes: &Bytes,
+    resp: Response<Body>,
+) -> Response<Body> {
+    if store.mode == FixtureMode::Off || !resp.status().is_success() {
+        return resp;
+    }
+
+    let Some((key, params, id)) = get_fixture_request(chain_id, body_bytes) else {
+        return resp;
+    };
+
+    // Read the response body and reconstruct the response afterwards
+    let (parts, body) = resp.into_parts();
+    let resp_bytes = match body.collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(err) => {
+            warn!("Failed to read the response body: {:?}", err);
+            return json_response(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32603, "message": "Internal error" }
+            }));
+        }
+    };
+
+    if let Ok(resp_json) = serde_json::from_slice::<Value>(&resp_bytes) {
+        let fixture = Fixture {
+            chain_id,
+            method: key.method.clone(),
+            params,
+            result: resp_json.get("result").cloned(),
+            error: resp_json.get("error").cloned(),
+        };
+
+        if fixture.result.is_some() || fixture.error.is_some() {
+            if let Err(err) = store.write(&key, &fixture).await {
+                warn!("Failed to write fixture: {:?}", err);
+            }
+        }
+    }
+
+    Response::from_parts(parts, Body::from(resp_bytes))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn get_store(mode: FixtureMode, strict: bool) -> FixtureStore {
+        FixtureStore {
+            mode,
+            dir: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures"),
+            strict,
+        }
+    }
+
+    async fn get_body(resp: Response<Body>) -> Value {
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay_rpc_fixture() {
+        let store = get_store(FixtureMode::Replay, true);
+        let body = Bytes::from(r#"{"jsonrpc":"2.0","id":7,"method":"eth_chainId","params":[]}"#);
+
+        let resp = replay_rpc_fixture(&store, 1, &body).await.unwrap();
+        assert_eq!(get_body(resp).await, json!({ "jsonrpc": "2.0", "id": 7, "result": "0x1" }));
+    }
+
+    #[tokio::test]
+    async fn test_replay_rpc_fixture_miss() {
+        let body =
+            Bytes::from(r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber","params":[]}"#);
+
+        // A strict store returns an error on a miss
+        let store = get_store(FixtureMode::Replay, true);
+        let resp = replay_rpc_fixture(&store, 1, &body).await.unwrap();
+        assert_eq!(get_body(resp).await["error"]["code"], json!(-32603));
+
+        // A lenient store proxies the request on a miss
+        let store = get_store(FixtureMode::Replay, false);
+        assert!(replay_rpc_fixture(&store, 1, &body).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_rpc_fixture_write_method() {
+        let store = get_store(FixtureMode::Replay, true);
+        let body = Bytes::from(
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendRawTransaction","params":["0x00"]}"#,
+        );
+
+        // The state changing methods are always proxied
+        assert!(replay_rpc_fixture(&store, 1, &body).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record_rpc_fixture_write_failure() {
+        let store = FixtureStore {
+            mode: FixtureMode::Record,
+            dir: PathBuf::from("/dev/null/fixtures"),
+            strict: true,
+        };
+        let body = Bytes::from(r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#);
+        let resp = json_response(json!({ "jsonrpc": "2.0", "id": 1, "result": "0x1" }));
+
+        // The response is returned even if the fixture could not be written
+        let resp = record_rpc_fixture(&store, 1, &body, resp).await;
+        assert_eq!(get_body(resp).await["result"], json!("0x1"));
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(clippy::unwrap_used)]
+
+use crate::config::RpcArgs;
+use lightdotso_tracing::tracing::warn;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+/// The read methods the wallet UI blocks on, which may be hedged
+pub const HEDGED_METHODS: [&str; 4] =
+    ["eth_call", "eth_estimateGas", "eth_getBalance", "eth_estimateUserOperationGas"];
+
+/// The state changing methods, which must never be hedged
+pub const NON_HEDGED_METHODS: [&str; 3] =
+    ["eth_sendRawTransaction", "eth_sendTransaction", "eth_sendUserOperation"];
+
+/// The number of latency samples kept per method
+const LATENCY_WINDOW: usize = 256;
+
+/// The number of samples required before the p95 is trusted
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// The hedge delay used until enough samples are collected
+const DEFAULT_HEDGE_DELAY: Duration = Duration::from_millis(500);
+
+/// The lower bound of the hedge delay
+const MIN_HEDGE_DELAY: Duration = Duration::from_millis(50);
+
+/// The upper bound of the hedge delay
+const MAX_HEDGE_DELAY: Duration = Duration::from_secs(2);
+
+/// The cost of a single hedge in the budget, in thousandths of a hedge
+const HEDGE_COST: u64 = 1000;
+
+/// The maximum number of hedges that can be saved up in the budget
+const MAX_HEDGE_TOKENS: u64 = 10 * HEDGE_COST;
+
+/// The default percentage of requests that may be hedged
+const DEFAULT_HEDGE_BUDGET_PERCENT: f64 = 10.0;
+
+/// The budget of hedged requests, w/ each request depositing a fraction of a hedge
+#[derive(Debug)]
+pub struct HedgeBudget {
+    deposit: u64,
+    tokens: Mutex<u64>,
+}
+
+impl HedgeBudget {
+    /// Constructs the budget allowing at most `percent` of the requests to be hedged
+    pub fn new(percent: f64) -> Self {
+        let deposit = (percent.max(0.0) / 100.0 * HEDGE_COST as f64).round() as u64;
+        Self { deposit, tokens: Mutex::new(0) }
+    }
+
+    /// Deposits the share of a hedge for a new request
+    pub fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap();
+        *tokens = (*tokens + self.deposit).min(MAX_HEDGE_TOKENS);
+    }
+
+    /// Withdraws a hedge from the budget, returning `false` if the budget is exhausted
+    pub fn try_withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap();
+        if *tokens >= HEDGE_COST {
+            *tokens -= HEDGE_COST;
+            return true;
+        }
+        false
+    }
+}
+
+/// The recent latencies of the successful requests per method
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    samples: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+impl LatencyTracker {
+    /// Records the latency of a successful request
+    pub fn record(&self, method: &str, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        let method_samples = samples.entry(method.to_string()).or_default();
+
+        if method_samples.len() >= LATENCY_WINDOW {
+            method_samples.pop_front();
+        }
+        method_samples.push_back(latency);
+    }
+
+    /// Get the p95 latency of the method, if there are enough samples
+    pub fn p95(&self, method: &str) -> Option<Duration> {
+        let samples = self.samples.lock().unwrap();
+        let method_samples = samples.get(method)?;
+
+        if method_samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+
+        let mut sorted: Vec<Duration> = method_samples.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() as f64) * 0.95).ceil() as usize - 1;
+
+        Some(sorted[index.min(sorted.len() - 1)])
+    }
+}
+
+/// The hedging policy of the rpc proxy
+#[derive(Debug)]
+pub struct Hedger {
+    enabled: bool,
+    budget: HedgeBudget,
+    latencies: LatencyTracker,
+}
+
+impl Hedger {
+    /// Constructs the hedger w/ the budget percentage
+    pub fn new(enabled: bool, budget_percent: f64) -> Self {
+        Self {
+            enabled,
+            budget: HedgeBudget::new(budget_percent),
+            latencies: LatencyTracker::default(),
+        }
+    }
+
+    /// Constructs the hedger from the rpc args
+    pub fn from_args(args: &RpcArgs) -> Self {
+        Self::new(args.hedge_enabled, args.hedge_budget_percent)
+    }
+
+    /// Returns `true` if the method should be sent w/ hedged requests
+    pub fn is_hedged_method(&self, method: &str) -> bool {
+        self.enabled && HEDGED_METHODS.contains(&method) && !NON_HEDGED_METHODS.contains(&method)
+    }
+
+    /// Get the delay before a hedged request is sent, derived from the p95 of the method
+    pub fn hedge_delay(&self, method: &str) -> Duration {
+        self.latencies
+            .p95(method)
+            .unwrap_or(DEFAULT_HEDGE_DELAY)
+            .clamp(MIN_HEDGE_DELAY, MAX_HEDGE_DELAY)
+    }
+
+    /// Records a new request for the budget
+    pub fn record_request(&self) {
+        self.budget.deposit();
+    }
+
+    /// Records the latency of a successful request, w/ samples kept only for the hedgeable methods
+    /// so that the delay is warm even while hedging is disabled or the budget is exhausted
+    pub fn record_latency(&self, method: &str, latency: Duration) {
+        if HEDGED_METHODS.contains(&method) {
+            self.latencies.record(method, latency);
+        }
+    }
+
+    /// Acquires a hedge from the budget
+    pub fn try_acquire_hedge(&self) -> bool {
+        self.budget.try_withdraw()
+    }
+}
+
+/// The hedger of the rpc proxy, set once at startup
+static HEDGER: OnceLock<Hedger> = OnceLock::new();
+
+/// Initializes the hedger of the rpc proxy from the rpc args
+pub fn init_hedger(args: &RpcArgs) {
+    if HEDGER.set(Hedger::from_args(args)).is_err() {
+        warn!("The rpc hedger is already initialized");
+    }
+}
+
+/// Get the hedger of the rpc proxy, disabled if it has not been initialized
+pub fn get_hedger() -> &'static Hedger {
+    HEDGER.get_or_init(|| Hedger::new(false, DEFAULT_HEDGE_BUDGET_PERCENT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hedge_budget() {
+        let budget = HedgeBudget::new(10.0);
+        assert!(!budget.try_withdraw());
+
+        // Ten requests deposit a single hedge
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+
+    #[test]
+    fn test_latency_tracker_p95() {
+        let tracker = LatencyTracker::default();
+        assert_eq!(tracker.p95("eth_call"), None);
+
+        for ms in 1..=100 {
+            tracker.record("eth_call", Duration::from_millis(ms));
+        }
+        assert_eq!(tracker.p95("eth_call"), Some(Duration::from_millis(95)));
+    }
+
+    #[test]
+    fn test_is_hedged_method() {
+        let hedger = Hedger::new(true, 10.0);
+        assert!(hedger.is_hedged_method("eth_call"));
+        assert!(!hedger.is_hedged_method("eth_sendUserOperation"));
+
+        let hedger = Hedger::new(false, 10.0);
+        assert!(!hedger.is_hedged_method("eth_call"));
+    }
+
+    #[test]
+    fn test_hedger_from_args() {
+        let args =
+            RpcArgs { hedge_enabled: true, hedge_budget_percent: 100.0, ..Default::default() };
+        let hedger = Hedger::from_args(&args);
+        assert!(hedger.is_hedged_method("eth_call"));
+
+        // Every request deposits a full hedge
+        hedger.record_request();
+        assert!(hedger.try_acquire_hedge());
+        assert!(!hedger.try_acquire_hedge());
+    }
+
+    #[test]
+    fn test_hedger_record_latency() {
+        let hedger = Hedger::new(false, 10.0);
+
+        // The samples are kept while hedging is disabled
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            hedger.record_latency("eth_call", Duration::from_millis(100));
+            hedger.record_latency("eth_chainId", Duration::from_millis(100));
+        }
+        assert_eq!(hedger.hedge_delay("eth_call"), Duration::from_millis(100));
+        assert_eq!(hedger.latencies.p95("eth_chainId"), None);
+    }
+}
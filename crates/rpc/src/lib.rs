@@ -17,26 +17,28 @@
 
 pub mod config;
 pub mod constants;
+pub mod fixture;
+pub mod hedge;
+pub mod subscription;
 pub mod utils;
+pub mod ws;
 
 use crate::{
-    constants::{
-        ALCHEMY_RPC_URLS, ANKR_RPC_URLS, BICONOMY_RPC_URLS, BLASTAPI_RPC_URLS, CANDIDE_RPC_URLS,
-        CHAINNODES_RPC_URLS, ETHERSPOT_RPC_URLS, GAS_RPC_URL, INFURA_RPC_URLS, LLAMANODES_RPC_URLS,
-        NODEREAL_RPC_URLS, OFFICIAL_PUBLIC_RPC_URLS, PARTICLE_RPC_URLS, PAYMASTER_RPC_URL,
-        PIMLICO_RPC_URLS, PUBLIC_NODE_RPC_URLS, SILIUS_RPC_URLS, TENDERLY_RPC_URLS,
-        THIRDWEB_RPC_URL,
-    },
+    constants::{GAS_RPC_URL, PAYMASTER_RPC_URL, THIRDWEB_RPC_URL},
+    fixture::{record_rpc_fixture, replay_rpc_fixture, FIXTURE_STORE},
+    hedge::get_hedger,
     utils::shuffle_requests,
 };
 use alloy::primitives::B256;
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     extract::{Path, State},
     http::{Request, Response},
     response::IntoResponse,
 };
+use futures::{stream::FuturesUnordered, StreamExt};
 use http_body_util::BodyExt;
+use lightdotso_constants::registry::get_chain_registry;
 use lightdotso_contracts::{
     address::{ENTRYPOINT_V060_ADDRESS, ENTRYPOINT_V070_ADDRESS},
     types::UserOperationRequestVariant,
@@ -50,7 +52,8 @@ use lightdotso_kafka::{
 use lightdotso_tracing::tracing::{error, info, trace, warn};
 use serde::ser::Error;
 use serde_json::{json, Error as SerdeError, Value};
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::VecDeque, sync::Arc, time::Instant};
+use tokio::time::sleep;
 
 /// Get the method from the body of the JSON RPC request
 pub async fn get_method(body: Body) -> Result<String, SerdeError> {
@@ -220,13 +223,12 @@ pub async fn internal_rpc_handler(
 
 /// The rpc handler for the RPC server
 async fn try_rpc_with_url(
-    rpc_urls: &HashMap<u64, String>,
+    rpc_url: Option<String>,
     api_key: Option<String>,
-    chain_id: &u64,
     client: &HyperClient,
     body: &mut Body,
 ) -> Option<Response<Body>> {
-    if let Some(rpc_url) = rpc_urls.get(chain_id) {
+    if let Some(rpc_url) = rpc_url {
         let full_url = match api_key {
             // Format the url with the api_key if it exists
             Some(key) => format!("{}{}", rpc_url, key),
@@ -248,6 +250,63 @@ async fn try_rpc_with_url(
     None
 }
 
+/// Try the rpc urls in order, failing over to the next url on error
+/// For the hedged methods, the next url is also raced once the first url exceeds the hedge delay
+async fn try_rpc_with_urls(
+    method: &str,
+    requests: Vec<(Option<String>, Option<String>)>,
+    client: &HyperClient,
+    body_bytes: &Bytes,
+) -> Option<Response<Body>> {
+    let hedger = get_hedger();
+    let mut pending: VecDeque<_> = requests.into_iter().filter(|(url, _)| url.is_some()).collect();
+    let mut hedging = hedger.is_hedged_method(method);
+    if hedging {
+        hedger.record_request();
+    }
+
+    let start_request = |(url, key): (Option<String>, Option<String>)| {
+        let client = client.clone();
+        let body_bytes = body_bytes.clone();
+        async move {
+            let start = Instant::now();
+            let result = try_rpc_with_url(url, key, &client, &mut Body::from(body_bytes)).await;
+            (result, start.elapsed())
+        }
+    };
+
+    let mut in_flight = FuturesUnordered::new();
+    in_flight.push(start_request(pending.pop_front()?));
+
+    loop {
+        let hedge_delay = hedger.hedge_delay(method);
+
+        tokio::select! {
+            Some((result, elapsed)) = in_flight.next() => {
+                if let Some(resp) = result {
+                    hedger.record_latency(method, elapsed);
+                    return Some(resp);
+                }
+
+                // Fail over to the next url if nothing else is in flight
+                if in_flight.is_empty() {
+                    in_flight.push(start_request(pending.pop_front()?));
+                }
+            }
+            _ = sleep(hedge_delay), if hedging && !pending.is_empty() => {
+                // Race the next url if the budget allows, otherwise stop hedging
+                if hedger.try_acquire_hedge() {
+                    info!("Hedging request for method: {}", method);
+                    in_flight.push(start_request(pending.pop_front()?));
+                } else {
+                    hedging = false;
+                }
+            }
+            else => return None,
+        }
+    }
+}
+
 /// The rpc proxy handler for the RPC server
 pub async fn rpc_proxy_handler(
     State(state): State<(HyperClient, Arc<FutureProducer>)>,
@@ -257,7 +316,12 @@ pub async fn rpc_proxy_handler(
 ) -> Response<Body> {
     info!("req: {:?}", req);
     let body = std::mem::take(req.body_mut());
-    let body_bytes = body.collect().await.unwrap().to_bytes();
+    let body_bytes = match body.collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => {
+            return Response::builder().status(400).body(Body::from("Bad Request")).unwrap();
+        }
+    };
 
     // Get the client from the state
     let client = state.0.clone();
@@ -280,6 +344,28 @@ pub async fn rpc_proxy_handler(
         return Response::builder().status(404).body(Body::from("Not Found")).unwrap();
     }
 
+    // Serve the request from the fixtures if the env `RPC_FIXTURE_MODE` is `replay`
+    if let Some(resp) = replay_rpc_fixture(&FIXTURE_STORE, chain_id, &body_bytes).await {
+        return resp;
+    }
+
+    let resp = proxy_rpc_request(client, producer, chain_id, &body_bytes, debug).await;
+
+    // Record the response to the fixtures if the fixture mode is enabled
+    record_rpc_fixture(&FIXTURE_STORE, chain_id, &body_bytes, resp).await
+}
+
+/// Proxies the request to the upstream rpc urls
+async fn proxy_rpc_request(
+    client: HyperClient,
+    producer: Arc<FutureProducer>,
+    chain_id: u64,
+    body_bytes: &Bytes,
+    debug: bool,
+) -> Response<Body> {
+    // Get the chain registry
+    let registry = get_chain_registry();
+
     // Get the method from the body
     let method = get_method(Body::from(body_bytes.clone())).await;
 
@@ -338,19 +424,18 @@ pub async fn rpc_proxy_handler(
                 }
 
                 let mut requests = vec![
-                    (&*CHAINNODES_RPC_URLS, Some(std::env::var("CHAINNODES_API_KEY").unwrap())),
-                    (&*BLASTAPI_RPC_URLS, Some(std::env::var("BLAST_API_KEY").unwrap())),
-                    (&*ALCHEMY_RPC_URLS, Some(std::env::var("ALCHEMY_API_KEY").unwrap())),
-                    (&*NODEREAL_RPC_URLS, Some(std::env::var("NODEREAL_API_KEY").unwrap())),
+                    ("chainnodes", Some(std::env::var("CHAINNODES_API_KEY").unwrap())),
+                    ("blastapi", Some(std::env::var("BLAST_API_KEY").unwrap())),
+                    ("alchemy", Some(std::env::var("ALCHEMY_API_KEY").unwrap())),
+                    ("nodereal", Some(std::env::var("NODEREAL_API_KEY").unwrap())),
                 ];
 
                 shuffle_requests(&mut requests);
 
-                for (url, key) in &requests {
+                for (provider, key) in &requests {
                     let result = try_rpc_with_url(
-                        url,
+                        registry.rpc_url(chain_id, provider),
                         key.clone(),
-                        &chain_id,
                         &client,
                         &mut Body::from(body_bytes.clone()),
                     )
@@ -368,7 +453,7 @@ pub async fn rpc_proxy_handler(
             "eth_getUserOperationReceipt" => {
                 // Deserialize w/ serde_json
                 let body_json_result =
-                    serde_json::from_slice::<JSONRPCRequest<Vec<Value>>>(&body_bytes.clone());
+                    serde_json::from_slice::<JSONRPCRequest<Vec<Value>>>(body_bytes);
 
                 // Provide a default case for `body_json`
                 let body_json = body_json_result.unwrap_or(JSONRPCRequest {
@@ -384,70 +469,75 @@ pub async fn rpc_proxy_handler(
                 trace!("params: {:?}", params);
 
                 let mut requests = vec![
-                    (&*CANDIDE_RPC_URLS, None),
-                    (&*PARTICLE_RPC_URLS, None),
+                    ("candide", None),
+                    ("particle", None),
                     (
-                        &*PIMLICO_RPC_URLS,
+                        "pimlico",
                         Some("?apikey=".to_owned() + &std::env::var("PIMLICO_API_KEY").unwrap()),
                     ),
-                    (&*ETHERSPOT_RPC_URLS, None),
-                    (&*BICONOMY_RPC_URLS, Some(std::env::var("BICONOMY_API_KEY").unwrap())),
-                    (&*ALCHEMY_RPC_URLS, Some(std::env::var("ALCHEMY_API_KEY").unwrap())),
-                    (&*SILIUS_RPC_URLS, None),
+                    ("etherspot", None),
+                    ("biconomy", Some(std::env::var("BICONOMY_API_KEY").unwrap())),
+                    ("alchemy", Some(std::env::var("ALCHEMY_API_KEY").unwrap())),
+                    ("silius", None),
                 ];
 
                 shuffle_requests(&mut requests);
 
-                for (url, key) in &requests {
-                    let result = try_rpc_with_url(
-                        url,
-                        key.clone(),
-                        &chain_id,
-                        &client,
-                        &mut Body::from(body_bytes.clone()),
-                    )
-                    .await;
+                let requests = requests
+                    .into_iter()
+                    .map(|(provider, key)| (registry.rpc_url(chain_id, provider), key))
+                    .collect();
 
-                    if let Some(resp) = result {
-                        // If the method is `eth_sendUserOperation` and the response is 200, get the
-                        // `result` in the response body and log it
-                        if method == "eth_sendUserOperation" && resp.status().is_success() {
-                            let body = resp.into_body().collect().await.unwrap().to_bytes();
-                            let body_json: Value = serde_json::from_slice(&body).unwrap();
-                            if let Some(result) = body_json.get("result") {
-                                info!("result: {:?}", result);
-
-                                // Convert the result to a B256
-                                let result =
-                                    serde_json::from_value::<String>(result.clone()).unwrap();
-                                let hash: Result<B256, _> = result.parse();
-                                info!("hash: {:?}", hash);
-
-                                if let Ok(hash) = hash {
-                                    info!("Successfully queueing user operation message");
-                                    let _ = produce_user_operation_message(
-                                        producer.clone(),
-                                        &UserOperationMessage {
-                                            hash,
-                                            chain_id,
-                                            is_pending_update: true,
-                                        },
-                                    )
-                                    .await;
-                                }
-                            }
+                let result = try_rpc_with_urls(&method, requests, &client, body_bytes).await;
 
-                            // Reconstruct the response and return
-                            return Response::builder().status(200).body(Body::from(body)).unwrap();
+                if let Some(resp) = result {
+                    // If the method is `eth_sendUserOperation` and the response is 200, get the
+                    // `result` in the response body and log it
+                    if method == "eth_sendUserOperation" && resp.status().is_success() {
+                        let body = resp.into_body().collect().await.unwrap().to_bytes();
+                        let body_json: Value = serde_json::from_slice(&body).unwrap();
+                        if let Some(result) = body_json.get("result") {
+                            info!("result: {:?}", result);
+
+                            // Convert the result to a B256
+                            let result = serde_json::from_value::<String>(result.clone()).unwrap();
+                            let hash: Result<B256, _> = result.parse();
+                            info!("hash: {:?}", hash);
+
+                            if let Ok(hash) = hash {
+                                info!("Successfully queueing user operation message");
+                                let _ = produce_user_operation_message(
+                                    producer.clone(),
+                                    &UserOperationMessage {
+                                        hash,
+                                        chain_id,
+                                        is_pending_update: true,
+                                    },
+                                )
+                                .await;
+                            }
                         }
 
-                        return resp;
+                        // Reconstruct the response and return
+                        return Response::builder().status(200).body(Body::from(body)).unwrap();
                     }
+
+                    return resp;
                 }
             }
-            "gas_requestGasEstimation" => {
-                // Construct the params for the rpc request
-                let params = vec![json!(chain_id)];
+            "gas_requestGasEstimation" | "gas_getHistory" => {
+                // Construct the params for the rpc request w/ the chain id prepended, forwarding
+                // the history window positionally or the L1 fee params if any
+                let mut params = vec![json!(chain_id)];
+                if let Ok(body_json) =
+                    serde_json::from_slice::<JSONRPCRequest<Vec<Value>>>(body_bytes)
+                {
+                    match method.as_str() {
+                        "gas_getHistory" => params.extend(body_json.params),
+                        _ => params
+                            .extend(body_json.params.into_iter().filter(Value::is_object).take(1)),
+                    }
+                }
                 let req_body = json!({
                     "jsonrpc": "2.0",
                     "method": method.as_str(),
@@ -466,15 +556,21 @@ pub async fn rpc_proxy_handler(
             }
             "paymaster_requestPaymasterAndData" | "paymaster_requestGasAndPaymasterAndData" => {
                 // Deserialize w/ serde_json
-                let body_json_result = serde_json::from_slice::<
-                    JSONRPCRequest<Vec<UserOperationRequestVariant>>,
-                >(&body_bytes);
+                let body_json_result =
+                    serde_json::from_slice::<JSONRPCRequest<Vec<Value>>>(body_bytes);
 
-                if let Ok(body_json) = body_json_result {
-                    // Get the user_operation from the body
-                    let user_operation = &body_json.params[0];
+                // Get the user_operation and the optional sponsorship context from the body
+                let user_operation = body_json_result.as_ref().ok().and_then(|body_json| {
+                    body_json.params.first().and_then(|param| {
+                        serde_json::from_value::<UserOperationRequestVariant>(param.clone()).ok()
+                    })
+                });
+                let context = body_json_result.as_ref().ok().and_then(|body_json| {
+                    body_json.params.iter().skip(1).find(|param| param.is_object()).cloned()
+                });
 
-                    let (entry_point, user_op_json) = match user_operation {
+                if let Some(user_operation) = user_operation {
+                    let (entry_point, user_op_json) = match &user_operation {
                         UserOperationRequestVariant::Default(uor) => {
                             (*ENTRYPOINT_V060_ADDRESS, json!(uor))
                         }
@@ -483,8 +579,11 @@ pub async fn rpc_proxy_handler(
                         }
                     };
 
-                    let params =
+                    let mut params =
                         vec![user_op_json, json!(format!("{:?}", entry_point)), json!(chain_id)];
+                    if let Some(context) = context {
+                        params.push(context);
+                    }
                     info!("params: {:?}", params);
 
                     let req_body = json!({
@@ -508,18 +607,32 @@ pub async fn rpc_proxy_handler(
                     }
                 }
             }
+            "pm_getPaymasterStubData" | "pm_getPaymasterData" => {
+                // The ERC-7677 params already carry the entry point and the chain id
+                let mut hyper_body = Body::from(body_bytes.clone());
+
+                // Get the result from the client
+                let result = get_client_result(
+                    PAYMASTER_RPC_URL.to_string(),
+                    client.clone(),
+                    &mut hyper_body,
+                )
+                .await;
+                if let Some(resp) = result {
+                    return resp;
+                }
+            }
             "pimlico_getUserOperationGasPrice" => {
                 // Construct the params for the rpc request
                 let requests = vec![(
-                    &*PIMLICO_RPC_URLS,
+                    "pimlico",
                     Some("?apikey=".to_owned() + &std::env::var("PIMLICO_API_KEY").unwrap()),
                 )];
 
-                for (url, key) in &requests {
+                for (provider, key) in &requests {
                     let result = try_rpc_with_url(
-                        url,
+                        registry.rpc_url(chain_id, provider),
                         key.clone(),
-                        &chain_id,
                         &client,
                         &mut Body::from(body_bytes.clone()),
                     )
@@ -538,41 +651,34 @@ pub async fn rpc_proxy_handler(
 
     // Construct the params for the rpc request
     let mut requests = vec![
-        (&*ANKR_RPC_URLS, None),
-        (&*LLAMANODES_RPC_URLS, None),
-        (&*TENDERLY_RPC_URLS, None),
-        (&*PUBLIC_NODE_RPC_URLS, None),
-        (&*OFFICIAL_PUBLIC_RPC_URLS, None),
-        (&*NODEREAL_RPC_URLS, Some(std::env::var("NODEREAL_API_KEY").unwrap())),
-        (&*INFURA_RPC_URLS, Some(std::env::var("INFURA_API_KEY").unwrap())),
+        ("ankr", None),
+        ("llamanodes", None),
+        ("tenderly", None),
+        ("public_node", None),
+        ("official_public", None),
+        ("nodereal", Some(std::env::var("NODEREAL_API_KEY").unwrap())),
+        ("infura", Some(std::env::var("INFURA_API_KEY").unwrap())),
     ];
 
     shuffle_requests(&mut requests);
 
-    for (url, key) in &requests {
-        let result = try_rpc_with_url(
-            url,
-            key.clone(),
-            &chain_id,
-            &client,
-            &mut Body::from(body_bytes.clone()),
-        )
-        .await;
-
-        if let Some(resp) = result {
-            return resp;
-        }
-    }
+    let requests = requests
+        .into_iter()
+        .map(|(provider, key)| (registry.rpc_url(chain_id, provider), key))
+        .collect();
 
-    // Create a temporary rpc hash map for the thirdweb rpc url
-    let mut thirdweb_rpc_urls = HashMap::new();
-    thirdweb_rpc_urls.insert(chain_id, format!("https://{}.{}", chain_id, *THIRDWEB_RPC_URL,));
+    // Get the method name for hedging, if any
+    let method_name = method.as_ref().map(|method| method.as_str()).unwrap_or_default();
+
+    let result = try_rpc_with_urls(method_name, requests, &client, body_bytes).await;
+    if let Some(resp) = result {
+        return resp;
+    }
 
     // Fallback to thirdweb rpc url
     let result = try_rpc_with_url(
-        &thirdweb_rpc_urls,
+        Some(format!("https://{}.{}", chain_id, *THIRDWEB_RPC_URL)),
         None,
-        &chain_id,
         &client,
         &mut Body::from(body_bytes.clone()),
     )
@@ -0,0 +1,315 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use eyre::{eyre, Result};
+use futures::StreamExt;
+use lightdotso_constants::registry::get_chain_registry;
+use lightdotso_tracing::tracing::{error, info, warn};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{broadcast, Mutex},
+    task::JoinHandle,
+    time::sleep,
+};
+
+/// The subscription kinds that can be multiplexed through the proxy
+pub const SUPPORTED_SUBSCRIPTION_KINDS: [&str; 3] = ["newHeads", "logs", "newPendingTransactions"];
+
+/// The capacity of the fan-out channel of each upstream subscription
+const SUBSCRIPTION_CHANNEL_CAPACITY: usize = 1024;
+
+/// The initial delay before reconnecting to an upstream
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The maximum delay before reconnecting to an upstream
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The key of an upstream subscription, unique per chain and normalized params
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SubscriptionKey {
+    pub chain_id: u64,
+    pub params: String,
+}
+
+impl SubscriptionKey {
+    /// Constructs the key from the `eth_subscribe` params
+    /// The params are normalized w/ `serde_json` so that filters w/ the same fields share a key
+    pub fn new(chain_id: u64, params: &[Value]) -> Result<Self> {
+        let kind = params
+            .first()
+            .and_then(|kind| kind.as_str())
+            .ok_or_else(|| eyre!("Missing subscription kind"))?;
+
+        if !SUPPORTED_SUBSCRIPTION_KINDS.contains(&kind) {
+            return Err(eyre!("Unsupported subscription kind: {}", kind));
+        }
+
+        Ok(Self { chain_id, params: serde_json::to_string(&normalize_params(params))? })
+    }
+}
+
+/// Normalizes the filter of the params, lower-casing the hex addresses and topics so that
+/// checksummed and lower-case subscribers share an upstream subscription
+pub fn normalize_params(params: &[Value]) -> Vec<Value> {
+    fn lowercase(value: &Value) -> Value {
+        match value {
+            Value::String(string) => Value::String(string.to_lowercase()),
+            Value::Array(values) => Value::Array(values.iter().map(lowercase).collect()),
+            Value::Object(map) => {
+                Value::Object(map.iter().map(|(k, v)| (k.clone(), lowercase(v))).collect())
+            }
+            value => value.clone(),
+        }
+    }
+
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| if index == 0 { param.clone() } else { lowercase(param) })
+        .collect()
+}
+
+/// A single upstream subscription fanned out to many clients
+struct UpstreamSubscription {
+    sender: broadcast::Sender<Value>,
+    handle: JoinHandle<()>,
+}
+
+/// The manager of the upstream subscriptions shared by all websocket clients
+#[derive(Default)]
+pub struct SubscriptionManager {
+    upstreams: Mutex<HashMap<SubscriptionKey, UpstreamSubscription>>,
+}
+
+impl SubscriptionManager {
+    /// Constructs the new SubscriptionManager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes to the upstream subscription for the key, creating it if it does not exist
+    pub async fn subscribe(
+        &self,
+        key: &SubscriptionKey,
+        params: Vec<Value>,
+    ) -> broadcast::Receiver<Value> {
+        let mut upstreams = self.upstreams.lock().await;
+
+        // Reuse the upstream subscription if it is still running
+        if let Some(upstream) = upstreams.get(key) {
+            if !upstream.handle.is_finished() {
+                return upstream.sender.subscribe();
+            }
+        }
+
+        info!("Creating upstream subscription: {:?}", key);
+
+        let (sender, receiver) = broadcast::channel(SUBSCRIPTION_CHANNEL_CAPACITY);
+        let handle = tokio::spawn(run_upstream_subscription(key.chain_id, params, sender.clone()));
+        upstreams.insert(key.clone(), UpstreamSubscription { sender, handle });
+
+        receiver
+    }
+
+    /// Releases the upstream subscription for the key if no clients are left
+    pub async fn release(&self, key: &SubscriptionKey) {
+        let mut upstreams = self.upstreams.lock().await;
+
+        if let Some(upstream) = upstreams.get(key) {
+            if upstream.sender.receiver_count() == 0 {
+                info!("Dropping upstream subscription: {:?}", key);
+                upstream.handle.abort();
+                upstreams.remove(key);
+            }
+        }
+    }
+
+    /// Get the number of active upstream subscriptions
+    pub async fn len(&self) -> usize {
+        self.upstreams.lock().await.len()
+    }
+
+    /// Returns true if there are no active upstream subscriptions
+    pub async fn is_empty(&self) -> bool {
+        self.upstreams.lock().await.is_empty()
+    }
+}
+
+/// Get the upstream websocket urls for the chain in the order of preference
+/// The env `WS_RPC_URLS` is a comma separated list w/ chain_id of websocket urls
+/// Example: 1=wss://mainnet.example.com,10=wss://optimism.example.com
+pub fn get_ws_rpc_urls(chain_id: u64) -> Vec<String> {
+    let mut urls = vec![];
+
+    if let Ok(ws_rpc_urls) = std::env::var("WS_RPC_URLS") {
+        for ws_rpc_url in ws_rpc_urls.split(',') {
+            if let Some((id, url)) = ws_rpc_url.trim().split_once('=') {
+                if id.parse::<u64>().unwrap_or(0) == chain_id {
+                    urls.push(url.to_string());
+                }
+            }
+        }
+    }
+
+    // Get the chain registry
+    let registry = get_chain_registry();
+
+    // Alchemy serves websockets on the same path as http
+    if let (Some(url), Ok(key)) =
+        (registry.rpc_url(chain_id, "alchemy"), std::env::var("ALCHEMY_API_KEY"))
+    {
+        urls.push(format!("{}{}", url.replacen("https://", "wss://", 1), key));
+    }
+
+    // Public node serves websockets on the same host as http
+    if let Some(url) = registry.rpc_url(chain_id, "public_node") {
+        urls.push(url.replacen("https://", "wss://", 1));
+    }
+
+    urls
+}
+
+/// Runs the upstream subscription, reconnecting and failing over to the next url on disconnect
+async fn run_upstream_subscription(
+    chain_id: u64,
+    params: Vec<Value>,
+    sender: broadcast::Sender<Value>,
+) {
+    let urls = get_ws_rpc_urls(chain_id);
+    if urls.is_empty() {
+        error!("Could not resolve ws rpc url for chain_id: {}", chain_id);
+        return;
+    }
+
+    let mut index = 0;
+    let mut backoff = RECONNECT_INITIAL_BACKOFF;
+
+    loop {
+        let url = &urls[index % urls.len()];
+        let connected_at = Instant::now();
+
+        match forward_upstream_subscription(url, &params, &sender).await {
+            // All of the clients have left, stop the subscription
+            Ok(()) => return,
+            Err(err) => {
+                warn!("Upstream subscription for chain_id: {} failed: {:?}", chain_id, err);
+            }
+        }
+
+        // Reset the backoff if the connection was healthy for a while
+        if connected_at.elapsed() > RECONNECT_MAX_BACKOFF {
+            backoff = RECONNECT_INITIAL_BACKOFF;
+        }
+
+        // Fail over to the next url w/ exponential backoff
+        index += 1;
+        sleep(backoff).await;
+        backoff = std::cmp::min(backoff * 2, RECONNECT_MAX_BACKOFF);
+    }
+}
+
+/// Connects to the url and forwards the subscription items to the sender
+/// Returns `Ok` only if there are no receivers left
+async fn forward_upstream_subscription(
+    url: &str,
+    params: &[Value],
+    sender: &broadcast::Sender<Value>,
+) -> Result<()> {
+    let provider = ProviderBuilder::new().on_ws(WsConnect::new(url)).await?;
+    let subscription = provider.subscribe::<_, Value>(params.to_vec()).await?;
+    info!("Subscribed upstream w/ params: {:?}", params);
+
+    let mut stream = subscription.into_stream();
+    while let Some(item) = stream.next().await {
+        if sender.send(item).is_err() {
+            return Ok(());
+        }
+    }
+
+    Err(eyre!("Upstream subscription stream closed"))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_subscription_key_kind() {
+        assert!(SubscriptionKey::new(1, &[json!("newHeads")]).is_ok());
+        assert!(SubscriptionKey::new(1, &[json!("syncing")]).is_err());
+        assert!(SubscriptionKey::new(1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_subscription_key_normalized() {
+        let checksummed = SubscriptionKey::new(
+            1,
+            &[json!("logs"), json!({ "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" })],
+        )
+        .unwrap();
+        let lowercase = SubscriptionKey::new(
+            1,
+            &[json!("logs"), json!({ "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48" })],
+        )
+        .unwrap();
+        let other_chain = SubscriptionKey::new(
+            10,
+            &[json!("logs"), json!({ "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48" })],
+        )
+        .unwrap();
+
+        assert_eq!(checksummed, lowercase);
+        assert_ne!(lowercase, other_chain);
+    }
+
+    #[test]
+    fn test_normalize_params() {
+        let params = normalize_params(&[
+            json!("logs"),
+            json!({ "address": ["0xABC"], "topics": [null, ["0xDEF"]] }),
+        ]);
+
+        assert_eq!(params[0], json!("logs"));
+        assert_eq!(params[1], json!({ "address": ["0xabc"], "topics": [null, ["0xdef"]] }));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_manager_release() {
+        let manager = SubscriptionManager::new();
+        let key = SubscriptionKey::new(u64::MAX, &[json!("newHeads")]).unwrap();
+
+        let receiver_1 = manager.subscribe(&key, vec![json!("newHeads")]).await;
+        assert_eq!(manager.len().await, 1);
+
+        // The upstream is kept while a client is still subscribed
+        let receiver_2 = manager.subscribe(&key, vec![json!("newHeads")]).await;
+        drop(receiver_1);
+        manager.release(&key).await;
+        assert_eq!(manager.len().await, 1);
+
+        drop(receiver_2);
+        manager.release(&key).await;
+        assert!(manager.is_empty().await);
+    }
+}
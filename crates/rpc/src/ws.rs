@@ -0,0 +1,240 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    rpc_proxy_handler,
+    subscription::{normalize_params, SubscriptionKey, SubscriptionManager},
+};
+use axum::{
+    body::Body,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::Request,
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use http_body_util::BodyExt;
+use lightdotso_hyper::HyperClient;
+use lightdotso_kafka::rdkafka::producer::FutureProducer;
+use lightdotso_tracing::tracing::{info, warn};
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+};
+
+/// The state of the websocket rpc handler
+pub type WsRpcState = (HyperClient, Arc<FutureProducer>, Arc<SubscriptionManager>);
+
+/// A client subscription forwarding an upstream subscription to the websocket
+struct ClientSubscription {
+    key: SubscriptionKey,
+    handle: JoinHandle<()>,
+}
+
+/// The websocket rpc handler for the RPC server
+pub async fn ws_rpc_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<WsRpcState>,
+    Path(chain_id): Path<String>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state, chain_id)).into_response()
+}
+
+/// Handles the websocket connection of a single client
+async fn handle_socket(socket: WebSocket, state: WsRpcState, chain_id: String) {
+    let (client, producer, manager) = state;
+
+    // Parse the chain_id in the same way as the http handler
+    let parsed_chain_id: u64 = if let Some(hex) = chain_id.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).unwrap_or(0)
+    } else {
+        chain_id.parse().unwrap_or(0)
+    };
+    if parsed_chain_id == 0 {
+        return;
+    }
+    info!("ws chain_id: {}", parsed_chain_id);
+
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    // Funnel all of the outgoing messages through a single channel
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let send_task = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if ws_sender.send(Message::Text(message.to_string())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut subscriptions: HashMap<String, ClientSubscription> = HashMap::new();
+
+    while let Some(Ok(message)) = ws_receiver.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let request: Value = match serde_json::from_str(&text) {
+            Ok(request) => request,
+            Err(_) => {
+                let _ = tx.send(error_response(Value::Null, -32700, "Parse error"));
+                continue;
+            }
+        };
+
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+        let params: Vec<Value> = request
+            .get("params")
+            .and_then(|p| serde_json::from_value(p.clone()).ok())
+            .unwrap_or_default();
+
+        match method {
+            "eth_subscribe" => {
+                let key = match SubscriptionKey::new(parsed_chain_id, &params) {
+                    Ok(key) => key,
+                    Err(err) => {
+                        let _ = tx.send(error_response(id, -32602, &err.to_string()));
+                        continue;
+                    }
+                };
+
+                let subscription_id = format!("0x{:032x}", rand::random::<u128>());
+                let mut receiver = manager.subscribe(&key, normalize_params(&params)).await;
+
+                // Forward the upstream items as notifications w/ the client's subscription id
+                let forward_tx = tx.clone();
+                let forward_id = subscription_id.clone();
+                let handle = tokio::spawn(async move {
+                    loop {
+                        match receiver.recv().await {
+                            Ok(result) => {
+                                let notification = json!({
+                                    "jsonrpc": "2.0",
+                                    "method": "eth_subscription",
+                                    "params": { "subscription": forward_id, "result": result },
+                                });
+                                if forward_tx.send(notification).is_err() {
+                                    break;
+                                }
+                            }
+                            Err(RecvError::Lagged(skipped)) => {
+                                warn!("Subscription {} lagged by {} items", forward_id, skipped);
+                            }
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                });
+
+                subscriptions.insert(subscription_id.clone(), ClientSubscription { key, handle });
+                let _ = tx.send(json!({ "jsonrpc": "2.0", "id": id, "result": subscription_id }));
+            }
+            "eth_unsubscribe" => {
+                let subscription_id = params.first().and_then(|p| p.as_str()).unwrap_or_default();
+
+                let removed = match subscriptions.remove(subscription_id) {
+                    Some(subscription) => {
+                        release_subscription(&manager, subscription).await;
+                        true
+                    }
+                    None => false,
+                };
+                let _ = tx.send(json!({ "jsonrpc": "2.0", "id": id, "result": removed }));
+            }
+            _ => {
+                // Proxy the other methods through the http handler
+                let req = match Request::builder().body(Body::from(text)) {
+                    Ok(req) => req,
+                    Err(_) => {
+                        let _ = tx.send(error_response(id, -32600, "Invalid request"));
+                        continue;
+                    }
+                };
+                let resp = rpc_proxy_handler(
+                    State((client.clone(), producer.clone())),
+                    Path(chain_id.clone()),
+                    req,
+                    false,
+                )
+                .await;
+
+                let body = resp.into_body().collect().await.map(|body| body.to_bytes());
+                let _ = tx.send(proxy_response(id, body.ok().as_deref()));
+            }
+        }
+    }
+
+    // Release all of the subscriptions of the client
+    for (_, subscription) in subscriptions.drain() {
+        release_subscription(&manager, subscription).await;
+    }
+    send_task.abort();
+}
+
+/// Stops forwarding the client subscription and releases the upstream if unused
+async fn release_subscription(manager: &SubscriptionManager, subscription: ClientSubscription) {
+    subscription.handle.abort();
+    // Wait for the receiver to be dropped before releasing the upstream
+    let _ = subscription.handle.await;
+    manager.release(&subscription.key).await;
+}
+
+/// Parses the proxied response body, w/ an error response if the body is missing or malformed
+fn proxy_response(id: Value, body: Option<&[u8]>) -> Value {
+    body.and_then(|body| serde_json::from_slice(body).ok())
+        .unwrap_or_else(|| error_response(id, -32603, "Internal error"))
+}
+
+/// Constructs a JSON-RPC error response
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_response() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#;
+        assert_eq!(proxy_response(json!(1), Some(body))["result"], json!("0x1"));
+
+        let response = proxy_response(json!(1), Some(b"not json"));
+        assert_eq!(response["id"], json!(1));
+        assert_eq!(response["error"]["code"], json!(-32603));
+
+        let response = proxy_response(json!(2), None);
+        assert_eq!(response["id"], json!(2));
+        assert_eq!(response["error"]["code"], json!(-32603));
+    }
+
+    #[test]
+    fn test_error_response() {
+        let response = error_response(Value::Null, -32700, "Parse error");
+        assert_eq!(
+            response,
+            json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "Parse error" } })
+        );
+    }
+}
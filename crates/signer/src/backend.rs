@@ -0,0 +1,238 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::kms::KmsSigner;
+use alloy::{
+    consensus::SignableTransaction,
+    network::TxSigner,
+    primitives::{eip191_hash_message, Address, Signature, B256},
+    signers::{aws::AwsSigner, local::PrivateKeySigner, Signer},
+};
+use async_trait::async_trait;
+use eyre::Result;
+use std::{fmt, sync::Arc};
+
+// -----------------------------------------------------------------------------
+// Traits
+// -----------------------------------------------------------------------------
+
+/// The backend of a signer, e.g. AWS KMS, a local key or a remote signer
+#[async_trait]
+pub trait SignerBackend: Send + Sync + fmt::Debug {
+    /// The address of the signer
+    fn address(&self) -> Address;
+
+    /// Sign the message w/ the EIP-191 prefix
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+
+    /// Sign the transaction, w/ the EIP-155 chain id for the legacy transactions
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> Result<Signature>;
+}
+
+/// The backend signing any hash w/ the key, unlike the remote signers only signing the data
+#[async_trait]
+pub trait HashSignerBackend: SignerBackend {
+    /// Sign the hash
+    async fn sign_hash(&self, hash: &B256) -> Result<Signature>;
+}
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The signer of the transactions w/ any of the backends, e.g. for `EthereumWallet`
+#[derive(Clone, Debug)]
+pub struct BackendSigner(pub Arc<dyn SignerBackend>);
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+#[async_trait]
+impl TxSigner<Signature> for BackendSigner {
+    fn address(&self) -> Address {
+        self.0.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        self.0.sign_transaction(tx).await.map_err(|e| alloy::signers::Error::other(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl SignerBackend for AwsSigner {
+    fn address(&self) -> Address {
+        Signer::address(self)
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        sign_message_hash(self, message).await
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> Result<Signature> {
+        sign_transaction_hash(self, tx).await
+    }
+}
+
+#[async_trait]
+impl HashSignerBackend for AwsSigner {
+    async fn sign_hash(&self, hash: &B256) -> Result<Signature> {
+        Ok(Signer::sign_hash(self, hash).await?)
+    }
+}
+
+#[async_trait]
+impl SignerBackend for KmsSigner {
+    fn address(&self) -> Address {
+        Signer::address(&self.signer)
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        sign_message_hash(self, message).await
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> Result<Signature> {
+        sign_transaction_hash(self, tx).await
+    }
+}
+
+#[async_trait]
+impl HashSignerBackend for KmsSigner {
+    async fn sign_hash(&self, hash: &B256) -> Result<Signature> {
+        Ok(Signer::sign_hash(&self.signer, hash).await?)
+    }
+}
+
+#[async_trait]
+impl SignerBackend for PrivateKeySigner {
+    fn address(&self) -> Address {
+        Signer::address(self)
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        sign_message_hash(self, message).await
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> Result<Signature> {
+        sign_transaction_hash(self, tx).await
+    }
+}
+
+#[async_trait]
+impl HashSignerBackend for PrivateKeySigner {
+    async fn sign_hash(&self, hash: &B256) -> Result<Signature> {
+        Ok(Signer::sign_hash(self, hash).await?)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Sign the message w/ the EIP-191 prefix by signing its hash
+async fn sign_message_hash<S: HashSignerBackend>(signer: &S, message: &[u8]) -> Result<Signature> {
+    signer.sign_hash(&eip191_hash_message(message)).await
+}
+
+/// Sign the transaction by signing its hash, w/ the EIP-155 chain id for the legacy transactions
+async fn sign_transaction_hash<S: HashSignerBackend>(
+    signer: &S,
+    tx: &mut dyn SignableTransaction<Signature>,
+) -> Result<Signature> {
+    let signature = signer.sign_hash(&tx.signature_hash()).await?;
+
+    Ok(with_eip155(tx, signature))
+}
+
+/// Apply the EIP-155 chain id to the signature of the legacy transaction
+pub fn with_eip155(tx: &dyn SignableTransaction<Signature>, signature: Signature) -> Signature {
+    match tx.chain_id() {
+        Some(chain_id) if tx.use_eip155() => signature.with_chain_id(chain_id),
+        _ => signature,
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        consensus::{TxEip1559, TxLegacy},
+        network::EthereumWallet,
+        primitives::{TxKind, U256},
+    };
+
+    #[tokio::test]
+    async fn test_backend_sign_message() {
+        let signer: Arc<dyn SignerBackend> = Arc::new(PrivateKeySigner::random());
+
+        let signature = signer.sign_message(b"Hello, world!").await.unwrap();
+        let recovered_address = signature.recover_address_from_msg(b"Hello, world!").unwrap();
+        assert_eq!(recovered_address, signer.address());
+    }
+
+    #[tokio::test]
+    async fn test_backend_sign_hash() {
+        let signer = PrivateKeySigner::random();
+
+        let signature = HashSignerBackend::sign_hash(&signer, &B256::ZERO).await.unwrap();
+        assert_eq!(
+            signature.recover_address_from_prehash(&B256::ZERO).unwrap(),
+            SignerBackend::address(&signer)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backend_sign_transaction() {
+        let signer = BackendSigner(Arc::new(PrivateKeySigner::random()));
+
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            to: TxKind::Call(Address::repeat_byte(1)),
+            value: U256::from(1),
+            gas_limit: 21_000,
+            ..Default::default()
+        };
+        let signature = TxSigner::sign_transaction(&signer, &mut tx).await.unwrap();
+        let signed = tx.into_signed(signature);
+        assert_eq!(signed.recover_signer().unwrap(), TxSigner::address(&signer));
+
+        // The legacy transaction is signed w/ the EIP-155 chain id
+        let mut tx = TxLegacy { chain_id: Some(10), gas_limit: 21_000, ..Default::default() };
+        let signature = TxSigner::sign_transaction(&signer, &mut tx).await.unwrap();
+        assert_eq!(signature.v().chain_id(), Some(10));
+
+        // The signer is usable as the wallet of the providers
+        let wallet = EthereumWallet::from(signer.clone());
+        assert_eq!(wallet.default_signer().address(), TxSigner::address(&signer));
+    }
+}
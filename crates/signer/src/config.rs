@@ -0,0 +1,169 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    backend::SignerBackend,
+    connect::KMS_LOCK_TTL_MILLIS,
+    kms::KmsSigner,
+    local::{connect_to_keystore, connect_to_mnemonic, DEFAULT_DERIVATION_PATH},
+    remote::RemoteSigner,
+};
+use alloy::primitives::Address;
+use clap::Parser;
+use eyre::{eyre, ContextCompat, Result};
+use std::{fmt, sync::Arc, time::Duration};
+use tokio::time::timeout;
+
+#[derive(Clone, Parser)]
+pub struct SignerArgs {
+    /// The signer backend, one of `kms`, `keystore`, `mnemonic` or `remote`
+    #[arg(long, default_value = "kms")]
+    #[clap(long, env = "SIGNER_BACKEND")]
+    pub signer_backend: String,
+    /// The AWS KMS key ids for the `kms` backend
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    #[clap(long, env = "AWS_KMS_KEY_IDS", hide = true)]
+    pub signer_kms_key_ids: Option<Vec<String>>,
+    /// The path to the encrypted JSON keystore for the `keystore` backend
+    #[clap(long, env = "SIGNER_KEYSTORE_PATH")]
+    pub signer_keystore_path: Option<String>,
+    /// The password of the keystore
+    #[clap(long, env = "SIGNER_KEYSTORE_PASSWORD", hide = true)]
+    pub signer_keystore_password: Option<String>,
+    /// The BIP-39 mnemonic for the `mnemonic` backend
+    #[clap(long, env = "SIGNER_MNEMONIC", hide = true)]
+    pub signer_mnemonic: Option<String>,
+    /// The derivation path of the mnemonic
+    #[arg(long, default_value = DEFAULT_DERIVATION_PATH)]
+    #[clap(long, env = "SIGNER_DERIVATION_PATH")]
+    pub signer_derivation_path: String,
+    /// The url of the web3signer compatible signer for the `remote` backend
+    #[clap(long, env = "SIGNER_REMOTE_URL")]
+    pub signer_remote_url: Option<String>,
+    /// The address of the key of the remote signer, the first of its keys if not set
+    #[clap(long, env = "SIGNER_REMOTE_ADDRESS")]
+    pub signer_remote_address: Option<Address>,
+}
+
+impl Default for SignerArgs {
+    fn default() -> Self {
+        Self {
+            signer_backend: "kms".to_string(),
+            signer_kms_key_ids: None,
+            signer_keystore_path: None,
+            signer_keystore_password: None,
+            signer_mnemonic: None,
+            signer_derivation_path: DEFAULT_DERIVATION_PATH.to_string(),
+            signer_remote_url: None,
+            signer_remote_address: None,
+        }
+    }
+}
+
+// The secrets are redacted from the logs of the config
+impl fmt::Debug for SignerArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignerArgs")
+            .field("signer_backend", &self.signer_backend)
+            .field("signer_kms_key_ids", &self.signer_kms_key_ids)
+            .field("signer_keystore_path", &self.signer_keystore_path)
+            .field(
+                "signer_keystore_password",
+                &self.signer_keystore_password.as_ref().map(|_| "***"),
+            )
+            .field("signer_mnemonic", &self.signer_mnemonic.as_ref().map(|_| "***"))
+            .field("signer_derivation_path", &self.signer_derivation_path)
+            .field("signer_remote_url", &self.signer_remote_url)
+            .field("signer_remote_address", &self.signer_remote_address)
+            .finish()
+    }
+}
+
+impl SignerArgs {
+    /// Whether the backend is AWS KMS, w/ the keys locked across the instances
+    pub fn is_kms(&self) -> bool {
+        self.signer_backend == "kms"
+    }
+
+    /// Connect to the signer of the backend
+    pub async fn connect(&self) -> Result<Arc<dyn SignerBackend>> {
+        let signer: Arc<dyn SignerBackend> = match self.signer_backend.as_str() {
+            "kms" => {
+                let key_ids =
+                    self.signer_kms_key_ids.clone().wrap_err("Failed to get the KMS key ids")?;
+                let signer = timeout(
+                    // 30 seconds
+                    Duration::from_secs(30),
+                    KmsSigner::connect(key_ids, KMS_LOCK_TTL_MILLIS),
+                )
+                .await
+                .map_err(|e| eyre!("Timeout Error: {}", e))??;
+                Arc::new(signer)
+            }
+            "keystore" => {
+                let path = self
+                    .signer_keystore_path
+                    .as_ref()
+                    .wrap_err("Failed to get the keystore path")?;
+                let password = self.signer_keystore_password.as_deref().unwrap_or_default();
+                Arc::new(connect_to_keystore(path, password)?)
+            }
+            "mnemonic" => {
+                let phrase =
+                    self.signer_mnemonic.as_ref().wrap_err("Failed to get the mnemonic")?;
+                Arc::new(connect_to_mnemonic(phrase, &self.signer_derivation_path)?)
+            }
+            "remote" => {
+                let url =
+                    self.signer_remote_url.as_ref().wrap_err("Failed to get the remote url")?;
+                Arc::new(RemoteSigner::connect(url, self.signer_remote_address).await?)
+            }
+            backend => return Err(eyre!("Unknown signer backend: {}", backend)),
+        };
+
+        Ok(signer)
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signer_args_connect() {
+        let args = SignerArgs {
+            signer_backend: "mnemonic".to_string(),
+            signer_mnemonic: Some(
+                "test test test test test test test test test test test junk".to_string(),
+            ),
+            ..Default::default()
+        };
+        let signer = args.connect().await.unwrap();
+        assert_eq!(
+            signer.address(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse::<Address>().unwrap()
+        );
+
+        let args = SignerArgs { signer_backend: "ledger".to_string(), ..Default::default() };
+        assert!(args.connect().await.is_err());
+
+        // The keystore backend requires the path
+        let args = SignerArgs { signer_backend: "keystore".to_string(), ..Default::default() };
+        assert!(args.connect().await.is_err());
+    }
+}
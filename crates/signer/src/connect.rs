@@ -18,6 +18,9 @@ use eyre::{eyre, Context, Result};
 use std::time::Duration;
 use tokio::time::timeout;
 
+/// The ttl of the lock of the KMS key (3 seconds)
+pub const KMS_LOCK_TTL_MILLIS: u64 = 3000;
+
 pub async fn connect_to_kms() -> Result<AwsSigner, eyre::Report> {
     let signer = timeout(
         // 30 seconds
@@ -28,8 +31,7 @@ pub async fn connect_to_kms() -> Result<AwsSigner, eyre::Report> {
                 .split(',')
                 .map(|s| s.to_string())
                 .collect(),
-            // 3 seconds for the lock
-            3000,
+            KMS_LOCK_TTL_MILLIS,
         ),
     )
     .await
@@ -48,6 +48,15 @@ pub struct KmsSigner {
 
 impl KmsSigner {
     pub async fn connect(key_ids: Vec<String>, ttl_millis: u64) -> eyre::Result<Self> {
+        Self::connect_with_namespace(key_ids, ttl_millis, None).await
+    }
+
+    /// Connect w/ the lock of the key_id under the namespace, e.g. to lock a key per chain
+    pub async fn connect_with_namespace(
+        key_ids: Vec<String>,
+        ttl_millis: u64,
+        namespace: Option<String>,
+    ) -> eyre::Result<Self> {
         let (tx, rx) = oneshot::channel::<String>();
 
         // Create the redis client
@@ -58,6 +67,7 @@ impl KmsSigner {
             vec![redis_client],
             key_ids,
             ttl_millis,
+            namespace,
             tx,
         ));
 
@@ -80,6 +90,7 @@ impl KmsSigner {
         clients: Vec<Client>,
         key_ids: Vec<String>,
         ttl_millis: u64,
+        namespace: Option<String>,
         locked_tx: oneshot::Sender<String>,
     ) -> Result<()> {
         info!("starting lock manager loop");
@@ -90,7 +101,11 @@ impl KmsSigner {
             let mut lock = None;
             let mut kid = None;
             for key_id in &key_ids {
-                match lm.lock(key_id.as_bytes(), ttl_millis as usize).await {
+                let resource = match &namespace {
+                    Some(namespace) => format!("{}:{}", namespace, key_id),
+                    None => key_id.clone(),
+                };
+                match lm.lock(resource.as_bytes(), ttl_millis as usize).await {
                     Ok(l) => {
                         info!("Locked key_id {key_id}");
                         lock = Some(l);
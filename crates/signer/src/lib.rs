@@ -12,5 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod backend;
+pub mod config;
 pub mod connect;
 pub mod kms;
+pub mod local;
+pub mod pool;
+pub mod remote;
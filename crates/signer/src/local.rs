@@ -0,0 +1,87 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::signers::local::{coins_bip39::English, MnemonicBuilder, PrivateKeySigner};
+use eyre::{eyre, Result};
+use std::path::Path;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The default derivation path of the first account
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+// -----------------------------------------------------------------------------
+// Connect
+// -----------------------------------------------------------------------------
+
+/// Connect to the signer of the encrypted JSON keystore
+pub fn connect_to_keystore(path: impl AsRef<Path>, password: &str) -> Result<PrivateKeySigner> {
+    PrivateKeySigner::decrypt_keystore(path.as_ref(), password)
+        .map_err(|e| eyre!("Keystore decryption error: {}", e))
+}
+
+/// Connect to the signer of the BIP-39 mnemonic w/ the derivation path
+pub fn connect_to_mnemonic(phrase: &str, derivation_path: &str) -> Result<PrivateKeySigner> {
+    MnemonicBuilder::<English>::default()
+        .phrase(phrase)
+        .derivation_path(derivation_path)
+        .and_then(|builder| builder.build())
+        .map_err(|e| eyre!("Mnemonic derivation error: {}", e))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::address, signers::Signer};
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_connect_to_mnemonic() {
+        let signer = connect_to_mnemonic(TEST_MNEMONIC, DEFAULT_DERIVATION_PATH).unwrap();
+        assert_eq!(signer.address(), address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+
+        let signer = connect_to_mnemonic(TEST_MNEMONIC, "m/44'/60'/0'/0/1").unwrap();
+        assert_eq!(signer.address(), address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"));
+
+        assert!(connect_to_mnemonic(TEST_MNEMONIC, "invalid").is_err());
+    }
+
+    #[test]
+    fn test_connect_to_keystore() {
+        let dir = std::env::temp_dir().join(format!("lightdotso-signer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (signer, _) = PrivateKeySigner::new_keystore(
+            &dir,
+            &mut rand::thread_rng(),
+            "password",
+            Some("keystore.json"),
+        )
+        .unwrap();
+
+        let keystore = connect_to_keystore(dir.join("keystore.json"), "password").unwrap();
+        assert_eq!(keystore.address(), signer.address());
+
+        assert!(connect_to_keystore(dir.join("keystore.json"), "wrong").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
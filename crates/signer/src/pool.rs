@@ -0,0 +1,256 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{backend::SignerBackend, kms::KmsSigner};
+use alloy::primitives::Address;
+use eyre::{eyre, Result};
+use lightdotso_tracing::tracing::{info, warn};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{sleep, timeout},
+};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The ttl of the lock of each executor key (3 seconds)
+pub const EXECUTOR_LOCK_TTL_MILLIS: u64 = 3000;
+
+/// The timeout to connect each executor key (30 seconds)
+pub const EXECUTOR_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// A pool of executor keys for a chain, e.g. locked from KMS
+#[derive(Debug)]
+pub struct ExecutorPool {
+    pub chain_id: u64,
+    signers: Vec<Arc<dyn SignerBackend>>,
+    available: Arc<Mutex<VecDeque<usize>>>,
+    semaphore: Arc<Semaphore>,
+}
+
+/// An executor key checked out of the pool, returned to the pool when dropped
+#[derive(Debug)]
+pub struct ExecutorGuard {
+    pub signer: Arc<dyn SignerBackend>,
+    index: usize,
+    available: Arc<Mutex<VecDeque<usize>>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl ExecutorPool {
+    /// Connect up to `size` KMS keys out of `key_ids`, each locked for the chain via redis
+    pub async fn connect(chain_id: u64, key_ids: Vec<String>, size: usize) -> Result<Self> {
+        let mut signers: Vec<Arc<dyn SignerBackend>> = vec![];
+
+        for _ in 0..size.min(key_ids.len()) {
+            let res = timeout(
+                EXECUTOR_CONNECT_TIMEOUT,
+                KmsSigner::connect_with_namespace(
+                    key_ids.clone(),
+                    EXECUTOR_LOCK_TTL_MILLIS,
+                    Some(format!("executor:{}", chain_id)),
+                ),
+            )
+            .await
+            .map_err(|e| eyre!("Timeout Error: {}", e))
+            .and_then(|res| res);
+
+            match res {
+                Ok(signer) => {
+                    info!("Connected executor {} for chain {}", signer.address(), chain_id);
+                    signers.push(Arc::new(signer));
+                }
+                // The remaining keys are locked by other instances
+                Err(e) => {
+                    warn!("Could not connect more executors for chain {}: {}", chain_id, e);
+                    break;
+                }
+            }
+        }
+
+        Self::from_signers(chain_id, signers)
+    }
+
+    /// Create the pool w/ the already connected signers
+    pub fn from_signers(chain_id: u64, signers: Vec<Arc<dyn SignerBackend>>) -> Result<Self> {
+        if signers.is_empty() {
+            return Err(eyre!("No executor available for chain {}", chain_id));
+        }
+
+        Ok(Self {
+            chain_id,
+            available: Arc::new(Mutex::new((0..signers.len()).collect())),
+            semaphore: Arc::new(Semaphore::new(signers.len())),
+            signers,
+        })
+    }
+
+    /// The addresses of the executors in the pool
+    pub fn addresses(&self) -> Vec<Address> {
+        self.signers.iter().map(|s| s.address()).collect()
+    }
+
+    /// The number of executors in the pool
+    pub fn len(&self) -> usize {
+        self.signers.len()
+    }
+
+    /// Whether the pool has no executors
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    /// Wait for a free executor, w/ exclusive use until the guard is dropped
+    pub async fn acquire(&self) -> Result<ExecutorGuard> {
+        self.acquire_filtered(|_| true).await
+    }
+
+    /// Wait for a free executor accepted by `filter`, e.g. to skip executors w/ a low balance
+    pub async fn acquire_filtered<F>(&self, filter: F) -> Result<ExecutorGuard>
+    where
+        F: Fn(Address) -> bool,
+    {
+        if !self.signers.iter().any(|s| filter(s.address())) {
+            return Err(eyre!("No executor accepted for chain {}", self.chain_id));
+        }
+
+        loop {
+            let permit = self.semaphore.clone().acquire_owned().await?;
+
+            let index = {
+                let mut available = self.available.lock().map_err(|e| eyre!("{}", e))?;
+                let position =
+                    available.iter().position(|index| filter(self.signers[*index].address()));
+                position.and_then(|position| available.remove(position))
+            };
+
+            match index {
+                Some(index) => {
+                    return Ok(ExecutorGuard {
+                        signer: self.signers[index].clone(),
+                        index,
+                        available: self.available.clone(),
+                        _permit: permit,
+                    })
+                }
+                // Only rejected executors are free, wait for the others to be returned
+                None => {
+                    drop(permit);
+                    sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+    }
+}
+
+impl ExecutorGuard {
+    /// The address of the executor
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+}
+
+impl Drop for ExecutorGuard {
+    fn drop(&mut self) {
+        if let Ok(mut available) = self.available.lock() {
+            available.push_back(self.index);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+
+    fn pool(size: usize) -> ExecutorPool {
+        let signers = (0..size)
+            .map(|_| Arc::new(PrivateKeySigner::random()) as Arc<dyn SignerBackend>)
+            .collect();
+
+        ExecutorPool::from_signers(1, signers).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_acquire_filtered() {
+        let pool = pool(3);
+        let addresses = pool.addresses();
+
+        // Only the accepted executor is acquired
+        let guard = pool.acquire_filtered(|address| address == addresses[1]).await.unwrap();
+        assert_eq!(guard.address(), addresses[1]);
+
+        // The other executors are still free
+        let other = pool.acquire_filtered(|address| address != addresses[1]).await.unwrap();
+        assert_ne!(other.address(), addresses[1]);
+
+        // No executor accepted at all is an error, instead of waiting forever
+        assert!(pool.acquire_filtered(|_| false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_returns_guard_on_drop() {
+        let pool = pool(1);
+        let address = pool.addresses()[0];
+
+        let guard = pool.acquire().await.unwrap();
+
+        // The executor in use is not free until the guard is dropped
+        assert!(timeout(Duration::from_millis(200), pool.acquire()).await.is_err());
+
+        drop(guard);
+        let guard = timeout(Duration::from_millis(200), pool.acquire()).await.unwrap().unwrap();
+        assert_eq!(guard.address(), address);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_filtered_waits_for_accepted_executor() {
+        let pool = Arc::new(pool(2));
+        let addresses = pool.addresses();
+
+        let guard = pool.acquire_filtered(|address| address == addresses[0]).await.unwrap();
+
+        // Waits for the accepted executor in use, even w/ the other one free
+        let waiting = {
+            let pool = pool.clone();
+            let accepted = addresses[0];
+            tokio::spawn(async move {
+                pool.acquire_filtered(|address| address == accepted).await.map(|g| g.address())
+            })
+        };
+        sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+
+        drop(guard);
+        assert_eq!(waiting.await.unwrap().unwrap(), addresses[0]);
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::backend::{with_eip155, SignerBackend};
+use alloy::{
+    consensus::SignableTransaction,
+    hex,
+    primitives::{keccak256, Address, Bytes, Signature},
+};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use serde_json::json;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The prefix of the EIP-191 signed messages
+const EIP191_PREFIX: &str = "\x19Ethereum Signed Message:\n";
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The remote signer w/ the web3signer API, which signs the keccak256 hash of the data
+/// It only signs the data, and so is not a `HashSignerBackend`
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    url: String,
+    address: Address,
+    /// The public key of the signer, identifying the key in the API
+    public_key: Bytes,
+    client: reqwest::Client,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl RemoteSigner {
+    /// Connect to the remote signer, w/ the key of the address or the first key if not set
+    pub async fn connect(url: &str, address: Option<Address>) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let client = reqwest::Client::new();
+
+        let public_keys: Vec<Bytes> = client
+            .get(format!("{}/api/v1/eth1/publicKeys", url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let (public_key, address) = public_keys
+            .into_iter()
+            .filter_map(|public_key| {
+                Some((public_key.clone(), public_key_to_address(&public_key)?))
+            })
+            .find(|(_, key_address)| address.map_or(true, |address| address == *key_address))
+            .ok_or_else(|| eyre!("No key of {:?} on the remote signer", address))?;
+
+        Ok(Self { url, address, public_key, client })
+    }
+
+    /// Sign the keccak256 hash of the data w/ the eth1 signing endpoint
+    async fn sign_data(&self, data: &[u8]) -> Result<Signature> {
+        let res = self
+            .client
+            .post(format!("{}/api/v1/eth1/sign/{}", self.url, self.public_key))
+            .json(&json!({ "data": Bytes::copy_from_slice(data) }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let bytes = hex::decode(res.trim())?;
+        let signature = Signature::try_from(bytes.as_slice())
+            .map_err(|e| eyre!("Remote signature error: {}", e))?;
+
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl SignerBackend for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        let mut data = format!("{}{}", EIP191_PREFIX, message.len()).into_bytes();
+        data.extend_from_slice(message);
+
+        self.sign_data(&data).await
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> Result<Signature> {
+        let mut data = vec![];
+        tx.encode_for_signing(&mut data);
+
+        let signature = self.sign_data(&data).await?;
+
+        Ok(with_eip155(tx, signature))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the address of the uncompressed public key, w/ or w/o the `0x04` prefix
+fn public_key_to_address(public_key: &[u8]) -> Option<Address> {
+    let public_key = match public_key {
+        [0x04, rest @ ..] if rest.len() == 64 => rest,
+        _ if public_key.len() == 64 => public_key,
+        _ => return None,
+    };
+
+    Some(Address::from_slice(&keccak256(public_key)[12..]))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        consensus::TxEip1559,
+        primitives::{keccak256, TxKind, U256},
+        signers::{local::PrivateKeySigner, Signer},
+    };
+    use axum::{
+        extract::{Path, State},
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    /// Get the uncompressed public key of the local key w/o the `0x04` prefix
+    fn public_key(signer: &PrivateKeySigner) -> Bytes {
+        let point = signer.credential().verifying_key().to_encoded_point(false);
+        Bytes::copy_from_slice(&point.as_bytes()[1..])
+    }
+
+    /// Spawn a mock of the web3signer API w/ the local key, identified by its public key
+    async fn spawn_remote_signer(signer: PrivateKeySigner) -> String {
+        async fn public_keys(State(signer): State<PrivateKeySigner>) -> Json<Value> {
+            Json(json!([public_key(&PrivateKeySigner::random()), public_key(&signer)]))
+        }
+
+        async fn sign(
+            State(signer): State<PrivateKeySigner>,
+            Path(identifier): Path<String>,
+            Json(body): Json<Value>,
+        ) -> String {
+            assert_eq!(identifier, public_key(&signer).to_string());
+
+            let data: Bytes = serde_json::from_value(body["data"].clone()).unwrap();
+            let signature = Signer::sign_hash(&signer, &keccak256(&data)).await.unwrap();
+            hex::encode_prefixed(signature.as_bytes())
+        }
+
+        let app = Router::new()
+            .route("/api/v1/eth1/publicKeys", get(public_keys))
+            .route("/api/v1/eth1/sign/:identifier", post(sign))
+            .with_state(signer);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer() {
+        let local = PrivateKeySigner::random();
+        let url = spawn_remote_signer(local.clone()).await;
+
+        // The key of the address, not the first one
+        let signer = RemoteSigner::connect(&url, Some(Signer::address(&local))).await.unwrap();
+        assert_eq!(SignerBackend::address(&signer), Signer::address(&local));
+        assert_ne!(
+            RemoteSigner::connect(&url, None).await.unwrap().address,
+            Signer::address(&local)
+        );
+
+        let signature = SignerBackend::sign_message(&signer, b"Hello, world!").await.unwrap();
+        let recovered_address = signature.recover_address_from_msg(b"Hello, world!").unwrap();
+        assert_eq!(recovered_address, Signer::address(&local));
+
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            to: TxKind::Call(Address::repeat_byte(1)),
+            value: U256::from(1),
+            gas_limit: 21_000,
+            ..Default::default()
+        };
+        let signature = SignerBackend::sign_transaction(&signer, &mut tx).await.unwrap();
+        assert_eq!(tx.into_signed(signature).recover_signer().unwrap(), Signer::address(&local));
+
+        // No key of the address
+        assert!(RemoteSigner::connect(&url, Some(Address::ZERO)).await.is_err());
+    }
+
+    #[test]
+    fn test_public_key_to_address() {
+        let signer = PrivateKeySigner::random();
+        let public_key = public_key(&signer);
+
+        assert_eq!(public_key_to_address(&public_key), Some(Signer::address(&signer)));
+        assert_eq!(
+            public_key_to_address(&[&[0x04], &public_key[..]].concat()),
+            Some(Signer::address(&signer))
+        );
+        assert_eq!(public_key_to_address(&public_key[1..]), None);
+    }
+}
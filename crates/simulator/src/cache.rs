@@ -0,0 +1,302 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, Bytes, U256};
+use eyre::Result;
+use revm::primitives::{AccountInfo, Bytecode, Env};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The state of an account as fetched from the fork, before any execution
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSnapshot {
+    pub balance: U256,
+    pub nonce: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The fork state of a chain at a block, sufficient to replay the simulations offline
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationSnapshot {
+    pub chain_id: u64,
+    pub block_number: u64,
+    /// The env of the fork, w/ the block and the cfg
+    pub env: Env,
+    pub accounts: BTreeMap<Address, AccountSnapshot>,
+}
+
+/// The cache of the fork states on disk, keyed by chain and block, unbounded and so opt-in
+#[derive(Clone, Debug)]
+pub struct ForkCache {
+    pub enabled: bool,
+    pub dir: PathBuf,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl From<AccountInfo> for AccountSnapshot {
+    fn from(info: AccountInfo) -> Self {
+        Self {
+            balance: info.balance,
+            nonce: info.nonce,
+            code: info.code.filter(|code| !code.is_empty()).map(|code| code.original_bytes()),
+            storage: BTreeMap::new(),
+        }
+    }
+}
+
+impl AccountSnapshot {
+    /// Get the account info to insert into the backend
+    pub fn info(&self) -> AccountInfo {
+        let mut info =
+            AccountInfo { balance: self.balance, nonce: self.nonce, ..Default::default() };
+        if let Some(code) = &self.code {
+            let code = Bytecode::new_raw(code.clone());
+            info.code_hash = code.hash_slow();
+            info.code = Some(code);
+        }
+
+        info
+    }
+}
+
+impl SimulationSnapshot {
+    /// Constructs an empty snapshot of the fork
+    pub fn new(env: Env) -> Self {
+        Self {
+            chain_id: env.cfg.chain_id,
+            block_number: env.block.number.to(),
+            env,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the snapshot from the file, e.g. one exported from a user-reported simulation
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the snapshot to the file, creating the parent dirs
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first, so that concurrent readers never see a partial file
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+
+    /// Merges the accounts and slots of the other snapshot, keeping the ones already present
+    pub fn merge(&mut self, other: SimulationSnapshot) {
+        for (address, account) in other.accounts {
+            let entry = self
+                .accounts
+                .entry(address)
+                .or_insert_with(|| AccountSnapshot { storage: BTreeMap::new(), ..account.clone() });
+            for (slot, value) in account.storage {
+                entry.storage.entry(slot).or_insert(value);
+            }
+        }
+    }
+}
+
+impl ForkCache {
+    /// Constructs the cache from the envs `SIMULATOR_CACHE` and `SIMULATOR_CACHE_DIR`, disabled
+    /// unless `SIMULATOR_CACHE=true`
+    pub fn from_env() -> Self {
+        Self::from_vars(
+            std::env::var("SIMULATOR_CACHE").ok().as_deref(),
+            std::env::var("SIMULATOR_CACHE_DIR").ok().as_deref(),
+        )
+    }
+
+    /// Constructs the cache from the values of `SIMULATOR_CACHE` and `SIMULATOR_CACHE_DIR`
+    pub fn from_vars(enabled: Option<&str>, dir: Option<&str>) -> Self {
+        Self {
+            enabled: enabled == Some("true"),
+            dir: PathBuf::from(dir.unwrap_or("cache/simulator")),
+        }
+    }
+
+    /// Get the path of the snapshot file
+    pub fn path(&self, chain_id: u64, block_number: u64) -> PathBuf {
+        self.dir.join(chain_id.to_string()).join(format!("{}.json", block_number))
+    }
+
+    /// Reads the snapshot on the blocking pool, returning `None` if the block was never cached
+    pub async fn read(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+    ) -> Result<Option<SimulationSnapshot>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let cache = self.clone();
+        tokio::task::spawn_blocking(move || cache.read_blocking(chain_id, block_number)).await?
+    }
+
+    /// Writes the snapshot on the blocking pool, merged w/ the state cached by other runs in the
+    /// meantime
+    pub async fn write(&self, snapshot: &SimulationSnapshot) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let cache = self.clone();
+        let snapshot = snapshot.clone();
+        tokio::task::spawn_blocking(move || cache.write_blocking(snapshot)).await?
+    }
+
+    fn read_blocking(
+        &self,
+        chain_id: u64,
+        block_number: u64,
+    ) -> Result<Option<SimulationSnapshot>> {
+        let path = self.path(chain_id, block_number);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(SimulationSnapshot::read(path)?))
+    }
+
+    fn write_blocking(&self, mut snapshot: SimulationSnapshot) -> Result<()> {
+        if let Some(cached) = self.read_blocking(snapshot.chain_id, snapshot.block_number)? {
+            snapshot.merge(cached);
+        }
+
+        snapshot.write(self.path(snapshot.chain_id, snapshot.block_number))
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(block_number: u64) -> SimulationSnapshot {
+        let mut env = Env::default();
+        env.cfg.chain_id = 10;
+        env.block.number = U256::from(block_number);
+
+        SimulationSnapshot::new(env)
+    }
+
+    #[test]
+    fn test_account_snapshot_info() {
+        let account = AccountSnapshot {
+            balance: U256::from(1),
+            nonce: 2,
+            code: Some(Bytes::from_static(&[0x60, 0x80])),
+            storage: BTreeMap::new(),
+        };
+
+        let info = account.info();
+        assert_eq!(info.balance, U256::from(1));
+        assert_eq!(info.nonce, 2);
+        assert_eq!(AccountSnapshot::from(info), account);
+    }
+
+    #[test]
+    fn test_snapshot_merge() {
+        let address = Address::repeat_byte(1);
+
+        let mut snapshot_1 = snapshot(1);
+        snapshot_1.accounts.insert(
+            address,
+            AccountSnapshot {
+                balance: U256::from(1),
+                storage: BTreeMap::from([(U256::from(1), U256::from(1))]),
+                ..Default::default()
+            },
+        );
+
+        let mut snapshot_2 = snapshot(1);
+        snapshot_2.accounts.insert(
+            address,
+            AccountSnapshot {
+                balance: U256::from(2),
+                storage: BTreeMap::from([
+                    (U256::from(1), U256::from(2)),
+                    (U256::from(2), U256::from(2)),
+                ]),
+                ..Default::default()
+            },
+        );
+        snapshot_2.accounts.insert(Address::repeat_byte(2), AccountSnapshot::default());
+
+        snapshot_1.merge(snapshot_2);
+
+        let account = snapshot_1.accounts.get(&address).unwrap();
+        assert_eq!(account.balance, U256::from(1));
+        assert_eq!(account.storage.get(&U256::from(1)), Some(&U256::from(1)));
+        assert_eq!(account.storage.get(&U256::from(2)), Some(&U256::from(2)));
+        assert_eq!(snapshot_1.accounts.len(), 2);
+    }
+
+    #[test]
+    fn test_fork_cache_disabled_by_default() {
+        let cache = ForkCache::from_vars(None, None);
+        assert!(!cache.enabled);
+        assert_eq!(cache.dir, PathBuf::from("cache/simulator"));
+
+        assert!(!ForkCache::from_vars(Some("false"), None).enabled);
+        let cache = ForkCache::from_vars(Some("true"), Some("/tmp/cache"));
+        assert!(cache.enabled);
+        assert_eq!(cache.dir, PathBuf::from("/tmp/cache"));
+    }
+
+    #[tokio::test]
+    async fn test_fork_cache_roundtrip() {
+        let cache = ForkCache {
+            enabled: true,
+            dir: std::env::temp_dir()
+                .join(format!("lightdotso-simulator-cache-test-{}", std::process::id())),
+        };
+
+        let mut snapshot = snapshot(2);
+        snapshot.accounts.insert(Address::repeat_byte(1), AccountSnapshot::default());
+        cache.write(&snapshot).await.unwrap();
+
+        let read = cache.read(10, 2).await.unwrap().unwrap();
+        assert_eq!(read.chain_id, 10);
+        assert_eq!(read.block_number, 2);
+        assert!(read.accounts.contains_key(&Address::repeat_byte(1)));
+        assert!(cache.read(10, 3).await.unwrap().is_none());
+    }
+}
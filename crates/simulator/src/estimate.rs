@@ -0,0 +1,465 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    cache::ForkCache,
+    evm::Evm,
+    simulator::persist,
+    types::{SimulationRequest, SimulationResponse},
+    validation::ValidationRequest,
+    verification::{execution_gas, VerificationGasRequest, TRANSACTION_INTRINSIC_GAS},
+};
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    providers::Provider,
+};
+use eyre::{eyre, Result};
+use lightdotso_contracts::{
+    l1_fee::calldata_gas,
+    provider::get_provider,
+    types::{PackedUserOperation, UserOperation},
+};
+use serde::{Deserialize, Serialize};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The cap of the gas limit in the search
+pub const GAS_ESTIMATION_CAP: u64 = 30_000_000;
+
+/// The tolerated error of the search, in per mille of the upper bound
+pub const GAS_ESTIMATION_ERROR_PER_MILLE: u64 = 15;
+
+/// The safety margin of the estimated call gas in percent
+pub const CALL_GAS_MARGIN_PERCENT: u64 = 110;
+
+/// The safety margin of the estimated verification gas in percent
+pub const VERIFICATION_GAS_MARGIN_PERCENT: u64 = 130;
+
+/// The minimum headroom of the estimated gas
+pub const MIN_GAS_HEADROOM: u64 = 10_000;
+
+/// The stipend of the value transfers, added to the optimistic guess
+const CALL_STIPEND: u64 = 2_300;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GasEstimationResponse {
+    /// Minimum gas limit w/ which the call succeeds
+    pub gas_limit: u64,
+    /// Estimated gas limit w/ the safety margin
+    pub estimate: u64,
+    /// Simulation of the call at the estimated gas limit
+    pub simulation: SimulationResponse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserOperationGasRequest {
+    /// Verification of the user operation, w/ the deployment of the account
+    pub verification: VerificationGasRequest,
+    /// Calldata of the user operation to the sender
+    pub call_data: Bytes,
+    /// Paymaster of the user operation, if sponsored
+    #[serde(default)]
+    pub paymaster: Option<Address>,
+    /// Calldata of `validatePaymasterUserOp` to the paymaster, if sponsored
+    #[serde(default)]
+    pub validate_paymaster_user_op_data: Option<Bytes>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct UserOperationGasResponse {
+    /// Minimum gas of the deployment of the account, excluding the intrinsic gas
+    pub deployment_gas: u64,
+    /// Minimum gas of `validateUserOp`, excluding the intrinsic gas
+    pub validation_gas: u64,
+    /// Minimum gas of the call phase, excluding the intrinsic gas
+    pub call_gas: u64,
+    /// Minimum gas of `validatePaymasterUserOp`, excluding the intrinsic gas
+    pub paymaster_validation_gas: u64,
+    /// Estimated `verificationGasLimit` of the account w/ the safety margin
+    pub verification_gas_limit: u64,
+    /// Estimated `paymasterVerificationGasLimit` w/ the safety margin, zero w/o a paymaster
+    pub paymaster_verification_gas_limit: u64,
+    /// Estimated `callGasLimit` w/ the safety margin
+    pub call_gas_limit: u64,
+    /// Simulation of the call phase at the estimated `callGasLimit`
+    pub simulation: Option<SimulationResponse>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl UserOperationGasRequest {
+    /// Constructs the request for the user operation (v0.6)
+    pub fn from_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        user_operation: &UserOperation,
+    ) -> Self {
+        let validation =
+            ValidationRequest::from_user_operation(chain_id, entry_point, user_operation);
+
+        Self {
+            verification: VerificationGasRequest::from_user_operation(
+                chain_id,
+                entry_point,
+                user_operation,
+            ),
+            call_data: user_operation.call_data.clone(),
+            paymaster: validation.paymaster,
+            validate_paymaster_user_op_data: validation.validate_paymaster_user_op_data,
+        }
+    }
+
+    /// Constructs the request for the packed user operation (v0.7)
+    pub fn from_packed_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        packed_user_operation: &PackedUserOperation,
+    ) -> Self {
+        let validation = ValidationRequest::from_packed_user_operation(
+            chain_id,
+            entry_point,
+            packed_user_operation,
+        );
+
+        Self {
+            verification: VerificationGasRequest::from_packed_user_operation(
+                chain_id,
+                entry_point,
+                packed_user_operation,
+            ),
+            call_data: packed_user_operation.call_data.clone(),
+            paymaster: validation.paymaster,
+            validate_paymaster_user_op_data: validation.validate_paymaster_user_op_data,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Estimate
+// -----------------------------------------------------------------------------
+
+/// Estimates the gas limit of the transaction by binary search, w/ the simulation at the estimate
+pub async fn estimate_gas(request: SimulationRequest) -> Result<GasEstimationResponse> {
+    // Get the provider
+    let (provider, fork_url) = get_provider(request.chain_id).await?;
+
+    // If block number is not provided, use the latest block number
+    let block_number = match request.block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    // Construct the EVM, reusing the cached fork state
+    let cache = ForkCache::from_env();
+    let cap = request.gas_limit.min(GAS_ESTIMATION_CAP);
+    let mut evm = Evm::new_cached(&cache, request.chain_id, fork_url, block_number, cap).await?;
+
+    // Apply the state overrides
+    if let Some(overrides) = &request.state_overrides {
+        evm.apply_state_override(overrides)?;
+    }
+
+    // Search the minimum gas limit
+    let gas_limit = search_call(
+        &mut evm,
+        request.from,
+        request.to,
+        Some(request.value),
+        request.data.clone(),
+        cap,
+    )?;
+    let estimate = with_gas_margin(gas_limit, CALL_GAS_MARGIN_PERCENT).min(cap);
+
+    // Simulate at the estimate for the trace
+    let simulation = evm
+        .call_raw_with_gas_limit(
+            request.from,
+            request.to,
+            Some(request.value),
+            request.data,
+            estimate,
+        )?
+        .into();
+
+    persist(&evm, &cache).await;
+
+    Ok(GasEstimationResponse { gas_limit, estimate, simulation })
+}
+
+/// Estimates the `verificationGasLimit` and the `callGasLimit` of the user operation by binary
+/// search, deploying the account and validating the user operation before the call phase
+pub async fn estimate_user_operation_gas(
+    request: UserOperationGasRequest,
+) -> Result<UserOperationGasResponse> {
+    let verification = request.verification;
+
+    // Get the provider
+    let (provider, fork_url) = get_provider(verification.chain_id).await?;
+
+    // If block number is not provided, use the latest block number
+    let block_number = match verification.block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    // Construct the EVM, reusing the cached fork state
+    let cache = ForkCache::from_env();
+    let mut evm =
+        Evm::new_cached(&cache, verification.chain_id, fork_url, block_number, GAS_ESTIMATION_CAP)
+            .await?;
+
+    // Deploy the account w/ the factory, committing the state for the validation
+    let mut deployment_gas = 0;
+    if let (Some(factory), Some(factory_data)) = (verification.factory, verification.factory_data) {
+        let gas_limit = search_call(
+            &mut evm,
+            verification.entry_point,
+            factory,
+            None,
+            Some(factory_data.clone()),
+            GAS_ESTIMATION_CAP,
+        )?;
+        deployment_gas = execution_gas(gas_limit, &factory_data);
+
+        evm.call_raw_committing(
+            verification.entry_point,
+            factory,
+            None,
+            Some(factory_data),
+            GAS_ESTIMATION_CAP,
+        )
+        .await?;
+    }
+
+    // Validate the user operation as the entrypoint, committing the state for the call phase
+    let gas_limit = search_call(
+        &mut evm,
+        verification.entry_point,
+        verification.sender,
+        None,
+        Some(verification.validate_user_op_data.clone()),
+        GAS_ESTIMATION_CAP,
+    )?;
+    let validation_gas = execution_gas(gas_limit, &verification.validate_user_op_data);
+    evm.call_raw_committing(
+        verification.entry_point,
+        verification.sender,
+        None,
+        Some(verification.validate_user_op_data.clone()),
+        GAS_ESTIMATION_CAP,
+    )
+    .await?;
+
+    // Validate the paymaster w/ its stub data as the entrypoint, committing the state as well
+    let mut paymaster_validation_gas = 0;
+    if let (Some(paymaster), Some(data)) =
+        (request.paymaster, request.validate_paymaster_user_op_data.clone())
+    {
+        let gas_limit = search_call(
+            &mut evm,
+            verification.entry_point,
+            paymaster,
+            None,
+            Some(data.clone()),
+            GAS_ESTIMATION_CAP,
+        )?;
+        paymaster_validation_gas = execution_gas(gas_limit, &data);
+        evm.call_raw_committing(
+            verification.entry_point,
+            paymaster,
+            None,
+            Some(data),
+            GAS_ESTIMATION_CAP,
+        )
+        .await?;
+    }
+
+    // The entrypoint skips the call phase w/o the calldata
+    let (call_gas, call_gas_limit, simulation) = if request.call_data.is_empty() {
+        (0, 0, None)
+    } else {
+        let gas_limit = search_call(
+            &mut evm,
+            verification.entry_point,
+            verification.sender,
+            None,
+            Some(request.call_data.clone()),
+            GAS_ESTIMATION_CAP,
+        )?;
+        let call_gas = execution_gas(gas_limit, &request.call_data);
+        let call_gas_limit = with_gas_margin(call_gas, CALL_GAS_MARGIN_PERCENT);
+
+        // Simulate the call phase at the estimate for the trace
+        let simulation = evm.call_raw_with_gas_limit(
+            verification.entry_point,
+            verification.sender,
+            None,
+            Some(request.call_data.clone()),
+            call_gas_limit + TRANSACTION_INTRINSIC_GAS + calldata_gas(&request.call_data),
+        )?;
+
+        (call_gas, call_gas_limit, Some(simulation.into()))
+    };
+
+    persist(&evm, &cache).await;
+
+    Ok(UserOperationGasResponse {
+        deployment_gas,
+        validation_gas,
+        call_gas,
+        paymaster_validation_gas,
+        verification_gas_limit: with_gas_margin(
+            deployment_gas + validation_gas,
+            VERIFICATION_GAS_MARGIN_PERCENT,
+        ),
+        paymaster_verification_gas_limit: match request.paymaster {
+            Some(_) => with_gas_margin(paymaster_validation_gas, VERIFICATION_GAS_MARGIN_PERCENT),
+            None => 0,
+        },
+        call_gas_limit,
+        simulation,
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Adds the safety margin in percent to the gas, w/ at least the minimum headroom
+pub fn with_gas_margin(gas: u64, margin_percent: u64) -> u64 {
+    (gas * margin_percent / 100).max(gas + MIN_GAS_HEADROOM)
+}
+
+/// Searches the minimum gas limit of the call, failing if it reverts at the cap
+fn search_call(
+    evm: &mut Evm,
+    from: Address,
+    to: Address,
+    value: Option<U256>,
+    data: Option<Bytes>,
+    cap: u64,
+) -> Result<u64> {
+    let res = evm.call_raw_with_gas_limit(from, to, value, data.clone(), cap)?;
+    if !res.success {
+        return Err(eyre!("Failed to execute the call w/ the gas limit cap of {}", cap));
+    }
+
+    search_gas_limit(res.gas_used, cap, |gas_limit| {
+        Ok(evm.call_raw_with_gas_limit(from, to, value, data.clone(), gas_limit)?.success)
+    })
+}
+
+/// Binary-searches the minimum gas limit w/ which the call succeeds, between the gas used and the
+/// cap, within the tolerated error
+pub fn search_gas_limit(
+    gas_used: u64,
+    cap: u64,
+    mut succeeds: impl FnMut(u64) -> Result<bool>,
+) -> Result<u64> {
+    let mut lo = gas_used.saturating_sub(1);
+    let mut hi = cap;
+
+    // Try the optimistic guess first, as a nested call only gets 63/64 of the remaining gas, w/
+    // the stipend of the value transfers
+    let optimistic = (gas_used + CALL_STIPEND) * 64 / 63;
+    if optimistic < hi {
+        if succeeds(optimistic)? {
+            hi = optimistic;
+        } else {
+            lo = optimistic;
+        }
+    }
+
+    while lo + 1 < hi {
+        if (hi - lo) * 1000 < hi * GAS_ESTIMATION_ERROR_PER_MILLE {
+            break;
+        }
+
+        // Bias the midpoint towards the lower bound, as the minimum is usually close to the gas used
+        // W/ the lower bound at zero, double one instead so that the midpoint still moves
+        let mid = ((hi + lo) / 2).min(lo.max(1) * 2);
+        if succeeds(mid)? {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    Ok(hi)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_gas_margin() {
+        assert_eq!(with_gas_margin(10_000, VERIFICATION_GAS_MARGIN_PERCENT), 20_000);
+        assert_eq!(with_gas_margin(100_000, VERIFICATION_GAS_MARGIN_PERCENT), 130_000);
+        assert_eq!(with_gas_margin(200_000, CALL_GAS_MARGIN_PERCENT), 220_000);
+    }
+
+    #[test]
+    fn test_search_gas_limit() {
+        // The call needs more than the gas used, as w/ the 63/64 of a nested call
+        let minimum = 150_000;
+        let mut calls = 0;
+        let gas_limit = search_gas_limit(100_000, GAS_ESTIMATION_CAP, |gas_limit| {
+            calls += 1;
+            Ok(gas_limit >= minimum)
+        })
+        .unwrap();
+
+        assert!(gas_limit >= minimum);
+        assert!((gas_limit - minimum) * 1000 <= gas_limit * GAS_ESTIMATION_ERROR_PER_MILLE);
+        assert!(calls < 20);
+    }
+
+    #[test]
+    fn test_search_gas_limit_optimistic() {
+        let gas_limit =
+            search_gas_limit(21_000, GAS_ESTIMATION_CAP, |gas_limit| Ok(gas_limit >= 21_000))
+                .unwrap();
+
+        assert!(gas_limit >= 21_000);
+        assert!(gas_limit <= (21_000 + CALL_STIPEND) * 64 / 63);
+    }
+
+    #[test]
+    fn test_search_gas_limit_from_zero() {
+        // The optimistic guess succeeds, leaving the lower bound at zero
+        let gas_limit =
+            search_gas_limit(0, GAS_ESTIMATION_CAP, |gas_limit| Ok(gas_limit >= 1_000)).unwrap();
+
+        assert!(gas_limit >= 1_000);
+        assert!(gas_limit <= CALL_STIPEND * 64 / 63);
+    }
+
+    #[test]
+    fn test_search_gas_limit_error() {
+        assert!(search_gas_limit(21_000, GAS_ESTIMATION_CAP, |_| Err(eyre!("error"))).is_err());
+    }
+}
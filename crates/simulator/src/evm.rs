@@ -15,8 +15,13 @@
 // From: https://github.com/EnsoFinance/transaction-simulator/blob/64fe96afd52e5ff138ea0c22ad23aa4287346e7c/src/evm.rs
 // License: MIT
 
-use crate::types::CallRawResult;
-use alloy::primitives::{Address, Bytes, Uint, U256};
+use crate::{
+    cache::{AccountSnapshot, ForkCache, SimulationSnapshot},
+    state_diff::{erc20_balance_diffs, AccountDiff, Erc20BalanceDiff},
+    state_override::StateOverride,
+    types::CallRawResult,
+};
+use alloy::primitives::{Address, Bytes, Log, Uint, U256};
 use eyre::{eyre, Result};
 use foundry_evm::{
     backend::Backend,
@@ -25,10 +30,19 @@ use foundry_evm::{
     opts::EvmOpts,
     traces::TraceMode,
 };
-use revm::primitives::Env;
+use revm::{
+    inspector_handle_register,
+    primitives::{Bytecode, Env, EvmState, ExecutionResult, ResultAndState, TxKind},
+    DatabaseCommit, DatabaseRef, Inspector,
+};
+use std::collections::BTreeMap;
 
 pub struct Evm {
     executor: Executor,
+    /// The fork state read by the executions, recorded if the evm is cached or replayed
+    snapshot: Option<SimulationSnapshot>,
+    /// The accounts w/ the storage replaced by the overrides, excluded from the recording
+    replaced_storage: Vec<Address>,
 }
 
 impl Evm {
@@ -38,19 +52,7 @@ impl Evm {
         fork_block_number: Option<u64>,
         gas_limit: u64,
     ) -> Result<Self> {
-        let evm_opts = EvmOpts {
-            fork_url: Some(fork_url.clone()),
-            fork_block_number,
-            env: foundry_evm::opts::Env {
-                chain_id: None,
-                code_size_limit: None,
-                gas_price: Some(0),
-                gas_limit: u64::MAX,
-                ..Default::default()
-            },
-            memory_limit: foundry_config::Config::default().memory_limit,
-            ..Default::default()
-        };
+        let evm_opts = evm_opts(fork_url.clone(), fork_block_number);
 
         let fork_env = evm_opts.fork_evm_env(fork_url.clone()).await?.0;
 
@@ -58,13 +60,59 @@ impl Evm {
 
         let db = Backend::spawn(Some(fork_opts.clone()));
 
-        let builder = ExecutorBuilder::default()
-            .gas_limit(gas_limit)
-            .inspectors(|stack| stack.trace_mode(TraceMode::Debug));
+        let executor = executor(env.unwrap_or(fork_opts.env.clone()), db, gas_limit);
+
+        Ok(Evm { executor, snapshot: None, replaced_storage: vec![] })
+    }
+
+    /// Constructs the evm forked at the block, reusing the fork state cached by previous runs
+    pub async fn new_cached(
+        cache: &ForkCache,
+        chain_id: u64,
+        fork_url: String,
+        fork_block_number: u64,
+        gas_limit: u64,
+    ) -> Result<Self> {
+        let evm_opts = evm_opts(fork_url.clone(), Some(fork_block_number));
+
+        // Only fetch the env of the fork if the block was never cached
+        let snapshot = match cache.read(chain_id, fork_block_number).await? {
+            Some(snapshot) => snapshot,
+            None => SimulationSnapshot::new(evm_opts.fork_evm_env(fork_url.clone()).await?.0),
+        };
+
+        let fork_opts =
+            CreateFork { url: fork_url, enable_caching: true, env: snapshot.env.clone(), evm_opts };
+
+        let mut db = Backend::spawn(Some(fork_opts));
+        seed(&mut db, &snapshot)?;
 
-        let executor = builder.build(env.unwrap_or(fork_opts.env.clone()), db);
+        let executor = executor(snapshot.env.clone(), db, gas_limit);
 
-        Ok(Evm { executor })
+        Ok(Evm { executor, snapshot: Some(snapshot), replaced_storage: vec![] })
+    }
+
+    /// Constructs the evm from the snapshot w/o a fork, replaying the simulations offline
+    pub fn from_snapshot(snapshot: SimulationSnapshot, gas_limit: u64) -> Result<Self> {
+        let mut db = Backend::spawn(None);
+        seed(&mut db, &snapshot)?;
+
+        let executor = executor(snapshot.env.clone(), db, gas_limit);
+
+        Ok(Evm { executor, snapshot: Some(snapshot), replaced_storage: vec![] })
+    }
+
+    /// Get the snapshot of the fork state read so far, to export for an offline replay
+    pub fn snapshot(&self) -> Option<&SimulationSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Persist the fork state read so far to the cache
+    pub async fn persist(&self, cache: &ForkCache) -> Result<()> {
+        match &self.snapshot {
+            Some(snapshot) => cache.write(snapshot).await,
+            None => Ok(()),
+        }
     }
 
     pub async fn call_raw(
@@ -81,6 +129,8 @@ impl Evm {
                 dbg!(&err);
                 eyre!(err)
             })?;
+        self.record(&res.state_changeset)?;
+        let (state_diff, erc20_balances) = self.state_diff(&res.state_changeset, &res.logs)?;
 
         Ok(CallRawResult {
             gas_used: res.gas_used,
@@ -90,6 +140,8 @@ impl Evm {
             logs: res.logs,
             exit_reason: res.exit_reason,
             return_data: res.result,
+            state_diff,
+            erc20_balances,
         })
     }
 
@@ -102,14 +154,49 @@ impl Evm {
         gas_limit: u64,
     ) -> Result<CallRawResult> {
         self.executor.set_gas_limit(gas_limit);
-        let res = self
+        let mut res = self
             .executor
-            .transact_raw(from, to, data.unwrap_or_default(), value.unwrap_or_default())
+            .call_raw(from, to, data.unwrap_or_default(), value.unwrap_or_default())
             .map_err(|err| {
                 dbg!(&err);
                 eyre!(err)
             })?;
 
+        // Record the state read before committing, so that the snapshot holds the fork state
+        self.record(&res.state_changeset)?;
+        let (state_diff, erc20_balances) = self.state_diff(&res.state_changeset, &res.logs)?;
+        self.executor.commit(&mut res);
+
+        Ok(CallRawResult {
+            gas_used: res.gas_used,
+            block_number: res.env.block.number.to(),
+            success: !res.reverted,
+            trace: res.traces.map(|trace| trace.arena),
+            logs: res.logs,
+            exit_reason: res.exit_reason,
+            return_data: res.result,
+            state_diff,
+            erc20_balances,
+        })
+    }
+
+    /// Run the call w/ the gas limit w/o committing the state, e.g. to search the minimum gas
+    pub fn call_raw_with_gas_limit(
+        &mut self,
+        from: Address,
+        to: Address,
+        value: Option<U256>,
+        data: Option<Bytes>,
+        gas_limit: u64,
+    ) -> Result<CallRawResult> {
+        self.executor.set_gas_limit(gas_limit);
+        let res = self
+            .executor
+            .call_raw(from, to, data.unwrap_or_default(), value.unwrap_or_default())
+            .map_err(|err| eyre!(err))?;
+        self.record(&res.state_changeset)?;
+        let (state_diff, erc20_balances) = self.state_diff(&res.state_changeset, &res.logs)?;
+
         Ok(CallRawResult {
             gas_used: res.gas_used,
             block_number: res.env.block.number.to(),
@@ -118,9 +205,221 @@ impl Evm {
             logs: res.logs,
             exit_reason: res.exit_reason,
             return_data: res.result,
+            state_diff,
+            erc20_balances,
         })
     }
 
+    /// Run the call w/ the inspector on the forked state, committing the state if `commit`
+    pub fn inspect_raw<I>(
+        &mut self,
+        from: Address,
+        to: Address,
+        data: Bytes,
+        gas_limit: u64,
+        inspector: &mut I,
+        commit: bool,
+    ) -> Result<ExecutionResult>
+    where
+        I: for<'a> Inspector<&'a mut Backend>,
+    {
+        let mut env = self.executor.env().clone();
+        env.tx.caller = from;
+        env.tx.transact_to = TxKind::Call(to);
+        env.tx.data = data;
+        env.tx.value = U256::ZERO;
+        env.tx.gas_limit = gas_limit;
+        env.tx.gas_price = U256::ZERO;
+        env.tx.gas_priority_fee = None;
+        env.tx.nonce = None;
+        env.block.basefee = U256::ZERO;
+
+        let mut evm = revm::Evm::builder()
+            .with_db(self.executor.backend_mut())
+            .with_external_context(inspector)
+            .with_env(Box::new(env))
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        let ResultAndState { result, state } = evm.transact().map_err(|err| eyre!("{:?}", err))?;
+        drop(evm);
+
+        self.record(&state)?;
+        if commit {
+            self.executor.backend_mut().commit(state);
+        }
+
+        Ok(result)
+    }
+
+    /// Run the transaction w/ the inspector at the base fee of the block, funding the caller for
+    /// the gas, w/o committing the state, returning the result w/ the state diff
+    pub fn inspect_transaction<I>(
+        &mut self,
+        from: Address,
+        to: Address,
+        data: Bytes,
+        gas_limit: u64,
+        inspector: &mut I,
+    ) -> Result<(ExecutionResult, BTreeMap<Address, AccountDiff>, Vec<Erc20BalanceDiff>)>
+    where
+        I: for<'a> Inspector<&'a mut Backend>,
+    {
+        self.record_account(from)?;
+        self.executor.set_balance(from, U256::MAX >> 1).map_err(|err| eyre!(err))?;
+
+        let mut env = self.executor.env().clone();
+        env.tx.caller = from;
+        env.tx.transact_to = TxKind::Call(to);
+        env.tx.data = data;
+        env.tx.value = U256::ZERO;
+        env.tx.gas_limit = gas_limit;
+        env.tx.gas_price = env.block.basefee;
+        env.tx.gas_priority_fee = None;
+        env.tx.nonce = None;
+
+        let mut evm = revm::Evm::builder()
+            .with_db(self.executor.backend_mut())
+            .with_external_context(inspector)
+            .with_env(Box::new(env))
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        let ResultAndState { result, state } = evm.transact().map_err(|err| eyre!("{:?}", err))?;
+        drop(evm);
+
+        self.record(&state)?;
+        let (state_diff, erc20_balances) = self.state_diff(&state, result.logs())?;
+
+        Ok((result, state_diff, erc20_balances))
+    }
+
+    /// Apply the state overrides to the forked backend before the execution
+    pub fn apply_state_override(&mut self, overrides: &StateOverride) -> Result<()> {
+        // Record the overridden fork state first, so that the overrides never reach the snapshot
+        for (address, account) in overrides {
+            account.validate(*address)?;
+
+            self.record_account(*address)?;
+            if account.state.is_some() {
+                self.replaced_storage.push(*address);
+            }
+            for (slot, _) in account.state_diff.iter().flatten() {
+                self.record_slot(*address, U256::from_be_bytes(slot.0))?;
+            }
+        }
+
+        let backend = self.executor.backend_mut();
+
+        for (address, account) in overrides {
+            let mut info =
+                backend.basic_ref(*address).map_err(|err| eyre!(err))?.unwrap_or_default();
+            if let Some(balance) = account.balance {
+                info.balance = balance;
+            }
+            if let Some(nonce) = account.nonce {
+                info.nonce = nonce.to();
+            }
+            if let Some(code) = &account.code {
+                let code = Bytecode::new_raw(code.clone());
+                info.code_hash = code.hash_slow();
+                info.code = Some(code);
+            }
+            backend.insert_account_info(*address, info);
+
+            if account.state.is_some() {
+                backend
+                    .replace_account_storage(*address, account.storage().into_iter().collect())
+                    .map_err(|err| eyre!(err))?;
+            } else {
+                for (slot, value) in account.storage() {
+                    backend
+                        .insert_account_storage(*address, slot, value)
+                        .map_err(|err| eyre!(err))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record the accounts and slots read by the execution, keeping the ones already recorded
+    fn record(&mut self, state: &EvmState) -> Result<()> {
+        for (address, account) in state {
+            self.record_account(*address)?;
+
+            if self.replaced_storage.contains(address) {
+                continue;
+            }
+
+            // The original value is the value before the execution, as the slot was first read
+            if let Some(snapshot) = self.snapshot.as_mut() {
+                let entry = snapshot.accounts.entry(*address).or_default();
+                for (slot, value) in &account.storage {
+                    entry.storage.entry(*slot).or_insert(value.original_value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the diffs of the accounts changed by the execution, from the state in the backend before
+    /// committing, w/ the ERC20 balances mapped from the storage
+    fn state_diff(
+        &self,
+        state: &EvmState,
+        logs: &[Log],
+    ) -> Result<(BTreeMap<Address, AccountDiff>, Vec<Erc20BalanceDiff>)> {
+        let mut state_diff = BTreeMap::new();
+        for (address, account) in state {
+            let before = self
+                .executor
+                .backend()
+                .basic_ref(*address)
+                .map_err(|err| eyre!(err))?
+                .unwrap_or_default();
+            if let Some(diff) = AccountDiff::new(&before, account) {
+                state_diff.insert(*address, diff);
+            }
+        }
+
+        let erc20_balances =
+            erc20_balance_diffs(self.executor.env().cfg.chain_id, &state_diff, logs);
+
+        Ok((state_diff, erc20_balances))
+    }
+
+    /// Record the account as currently in the backend, if not recorded yet
+    fn record_account(&mut self, address: Address) -> Result<()> {
+        let Some(snapshot) = self.snapshot.as_mut() else {
+            return Ok(());
+        };
+
+        if !snapshot.accounts.contains_key(&address) {
+            let info = self.executor.backend().basic_ref(address).map_err(|err| eyre!(err))?;
+            snapshot.accounts.insert(address, AccountSnapshot::from(info.unwrap_or_default()));
+        }
+
+        Ok(())
+    }
+
+    /// Record the slot as currently in the backend, if not recorded yet
+    fn record_slot(&mut self, address: Address, slot: U256) -> Result<()> {
+        let Some(snapshot) = self.snapshot.as_mut() else {
+            return Ok(());
+        };
+
+        let entry = snapshot.accounts.entry(address).or_default();
+        if !entry.storage.contains_key(&slot) {
+            let value =
+                self.executor.backend().storage_ref(address, slot).map_err(|err| eyre!(err))?;
+            entry.storage.insert(slot, value);
+        }
+
+        Ok(())
+    }
+
     pub async fn get_balance(&self, address: Address) -> Result<U256> {
         let balance = self.executor.get_balance(address).map_err(|err| {
             dbg!(&err);
@@ -152,3 +451,45 @@ impl Evm {
         U256::from(self.executor.env().cfg.chain_id)
     }
 }
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the options of the evm forked at the block
+fn evm_opts(fork_url: String, fork_block_number: Option<u64>) -> EvmOpts {
+    EvmOpts {
+        fork_url: Some(fork_url),
+        fork_block_number,
+        env: foundry_evm::opts::Env {
+            chain_id: None,
+            code_size_limit: None,
+            gas_price: Some(0),
+            gas_limit: u64::MAX,
+            ..Default::default()
+        },
+        memory_limit: foundry_config::Config::default().memory_limit,
+        ..Default::default()
+    }
+}
+
+/// Build the executor w/ the debug traces
+fn executor(env: Env, db: Backend, gas_limit: u64) -> Executor {
+    let builder = ExecutorBuilder::default()
+        .gas_limit(gas_limit)
+        .inspectors(|stack| stack.trace_mode(TraceMode::Debug));
+
+    builder.build(env, db)
+}
+
+/// Seed the backend w/ the accounts and slots of the snapshot
+fn seed(db: &mut Backend, snapshot: &SimulationSnapshot) -> Result<()> {
+    for (address, account) in &snapshot.accounts {
+        db.insert_account_info(*address, account.info());
+        for (slot, value) in &account.storage {
+            db.insert_account_storage(*address, *slot, *value).map_err(|err| eyre!(err))?;
+        }
+    }
+
+    Ok(())
+}
@@ -12,6 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod cache;
+pub mod estimate;
 pub mod evm;
 pub mod simulator;
+pub mod state_diff;
+pub mod state_override;
 pub mod types;
+pub mod user_operation;
+pub mod validation;
+pub mod validation_tracer;
+pub mod verification;
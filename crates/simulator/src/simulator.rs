@@ -16,12 +16,14 @@
 // License: MIT
 
 use crate::{
+    cache::{ForkCache, SimulationSnapshot},
     evm::Evm,
     types::{SimulationRequest, SimulationResponse},
 };
 use alloy::providers::Provider;
 use eyre::{eyre, Result};
 use lightdotso_contracts::provider::get_provider;
+use lightdotso_tracing::tracing::warn;
 
 async fn run(
     evm: &mut Evm,
@@ -50,10 +52,19 @@ async fn run(
         arena: result.trace.clone(),
         logs: result.logs,
         exit_reason: result.exit_reason,
+        state_diff: result.state_diff,
+        erc20_balances: result.erc20_balances,
     })
 }
 
 pub async fn simulate(transaction: SimulationRequest) -> Result<SimulationResponse> {
+    Ok(simulate_with_snapshot(transaction).await?.0)
+}
+
+/// Simulate the transaction, returning the snapshot of the fork state to replay it offline
+pub async fn simulate_with_snapshot(
+    transaction: SimulationRequest,
+) -> Result<(SimulationResponse, SimulationSnapshot)> {
     // Get the provider
     let (provider, fork_url) = get_provider(transaction.chain_id).await?;
 
@@ -63,21 +74,44 @@ pub async fn simulate(transaction: SimulationRequest) -> Result<SimulationRespon
     // Get the block number
     let block_number = transaction.block_number.unwrap_or(latest_block_number);
 
-    // Construct the EVM
-    let mut evm = Evm::new(None, fork_url, Some(block_number), transaction.gas_limit).await?;
+    // Construct the EVM, reusing the cached fork state
+    let cache = ForkCache::from_env();
+    let mut evm = Evm::new_cached(
+        &cache,
+        transaction.chain_id,
+        fork_url,
+        block_number,
+        transaction.gas_limit,
+    )
+    .await?;
+
+    // Apply the state overrides
+    if let Some(overrides) = &transaction.state_overrides {
+        evm.apply_state_override(overrides)?;
+    }
 
     // Run the transaction
     let response = run(&mut evm, transaction, false).await?;
 
+    // Persist the fork state, w/o failing the simulation as the cache is an optimization
+    persist(&evm, &cache).await;
+
     // Return the response
-    Ok(response)
+    Ok((response, snapshot(&evm)?))
 }
 
 pub async fn simulate_bundle(
     transactions: Vec<SimulationRequest>,
 ) -> Result<Vec<SimulationResponse>> {
+    Ok(simulate_bundle_with_snapshot(transactions).await?.0)
+}
+
+/// Simulate the transactions, returning the snapshot of the fork state to replay them offline
+pub async fn simulate_bundle_with_snapshot(
+    transactions: Vec<SimulationRequest>,
+) -> Result<(Vec<SimulationResponse>, SimulationSnapshot)> {
     // Get the first chain id and block number
-    let first_chain_id = transactions[0].chain_id;
+    let first_chain_id = transactions.first().ok_or_else(|| eyre!("No transactions"))?.chain_id;
     let first_block_number = transactions[0].block_number;
 
     // Get the provider
@@ -89,8 +123,67 @@ pub async fn simulate_bundle(
     // Get the block number
     let block_number = first_block_number.unwrap_or(latest_block_number);
 
-    // Construct the EVM
-    let mut evm = Evm::new(None, fork_url, Some(block_number), transactions[0].gas_limit).await?;
+    // Construct the EVM, reusing the cached fork state
+    let cache = ForkCache::from_env();
+    let mut evm =
+        Evm::new_cached(&cache, first_chain_id, fork_url, block_number, transactions[0].gas_limit)
+            .await?;
+
+    // Run the transactions
+    let response = run_bundle(&mut evm, transactions).await?;
+
+    // Persist the fork state, w/o failing the simulation as the cache is an optimization
+    persist(&evm, &cache).await;
+
+    // Return the response
+    Ok((response, snapshot(&evm)?))
+}
+
+/// Replay the transaction offline on the snapshot of the fork state
+pub async fn replay(
+    transaction: SimulationRequest,
+    snapshot: SimulationSnapshot,
+) -> Result<SimulationResponse> {
+    if transaction.chain_id != snapshot.chain_id {
+        return Err(eyre!("Chain id mismatch w/ the snapshot"));
+    }
+
+    // Construct the EVM w/o the fork
+    let mut evm = Evm::from_snapshot(snapshot, transaction.gas_limit)?;
+
+    // Apply the state overrides
+    if let Some(overrides) = &transaction.state_overrides {
+        evm.apply_state_override(overrides)?;
+    }
+
+    // Run the transaction
+    run(&mut evm, transaction, false).await
+}
+
+/// Replay the transactions offline on the snapshot of the fork state
+pub async fn replay_bundle(
+    transactions: Vec<SimulationRequest>,
+    snapshot: SimulationSnapshot,
+) -> Result<Vec<SimulationResponse>> {
+    let first = transactions.first().ok_or_else(|| eyre!("No transactions"))?;
+    if first.chain_id != snapshot.chain_id {
+        return Err(eyre!("Chain id mismatch w/ the snapshot"));
+    }
+
+    // Construct the EVM w/o the fork
+    let mut evm = Evm::from_snapshot(snapshot, first.gas_limit)?;
+
+    // Run the transactions
+    run_bundle(&mut evm, transactions).await
+}
+
+async fn run_bundle(
+    evm: &mut Evm,
+    transactions: Vec<SimulationRequest>,
+) -> Result<Vec<SimulationResponse>> {
+    // Get the first chain id and block number
+    let first_chain_id = transactions[0].chain_id;
+    let first_block_number = transactions[0].block_number;
 
     // Run the transactions
     let mut response = Vec::with_capacity(transactions.len());
@@ -101,9 +194,28 @@ pub async fn simulate_bundle(
         if transaction.block_number != first_block_number {
             return Err(eyre!("Multiple block numbers"));
         }
-        response.push(run(&mut evm, transaction, true).await?);
+        if let Some(overrides) = &transaction.state_overrides {
+            evm.apply_state_override(overrides)?;
+        }
+        response.push(run(evm, transaction, true).await?);
     }
 
     // Return the response
     Ok(response)
 }
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Persist the fork state read by the evm to the cache, logging the failure
+pub(crate) async fn persist(evm: &Evm, cache: &ForkCache) {
+    if let Err(err) = evm.persist(cache).await {
+        warn!("Failed to persist the fork state: {:?}", err);
+    }
+}
+
+/// Get the snapshot of the fork state read by the evm
+fn snapshot(evm: &Evm) -> Result<SimulationSnapshot> {
+    evm.snapshot().cloned().ok_or_else(|| eyre!("No snapshot of the fork state"))
+}
@@ -0,0 +1,252 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::{
+    primitives::{b256, keccak256, Address, Bytes, Log, B256, U256},
+    sol_types::SolValue,
+};
+use lightdotso_constants::registry::get_chain_registry;
+use revm::primitives::{Account, AccountInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The topic of the ERC20 `Transfer` event
+const TRANSFER_EVENT_TOPIC: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The value of a storage slot before and after the transaction
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageDiff {
+    pub before: U256,
+    pub after: U256,
+}
+
+/// The changes of an account in the transaction
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountDiff {
+    /// Native balance before the transaction
+    pub balance_before: U256,
+    /// Native balance after the transaction
+    pub balance_after: U256,
+    /// Nonce before the transaction
+    pub nonce_before: u64,
+    /// Nonce after the transaction
+    pub nonce_after: u64,
+    /// Code deployed in the transaction
+    pub code: Option<Bytes>,
+    /// Storage slots changed in the transaction
+    pub storage: BTreeMap<U256, StorageDiff>,
+}
+
+/// The ERC20 balance of an owner before and after the transaction, mapped from the storage diff
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Erc20BalanceDiff {
+    pub token: Address,
+    pub owner: Address,
+    pub before: U256,
+    pub after: U256,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl AccountDiff {
+    /// Get the diff of the account from its state before the transaction, `None` if unchanged
+    pub fn new(before: &AccountInfo, account: &Account) -> Option<Self> {
+        let after = &account.info;
+
+        let storage: BTreeMap<U256, StorageDiff> = account
+            .storage
+            .iter()
+            .filter(|(_, slot)| slot.is_changed())
+            .map(|(key, slot)| {
+                (*key, StorageDiff { before: slot.original_value, after: slot.present_value })
+            })
+            .collect();
+
+        let code = (before.code_hash != after.code_hash)
+            .then(|| after.code.as_ref().map(|code| code.original_bytes()))
+            .flatten()
+            .filter(|code| !code.is_empty());
+
+        if before.balance == after.balance &&
+            before.nonce == after.nonce &&
+            code.is_none() &&
+            storage.is_empty()
+        {
+            return None;
+        }
+
+        Some(Self {
+            balance_before: before.balance,
+            balance_after: after.balance,
+            nonce_before: before.nonce,
+            nonce_after: after.nonce,
+            code,
+            storage,
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Get the slot of the `balanceOf` mapping of the token, if the layout is known in the chain
+/// registry
+pub fn erc20_balance_slot(chain_id: u64, token: Address) -> Option<u64> {
+    get_chain_registry().erc20_balance_slot(chain_id, &token.to_string())
+}
+
+/// Get the storage key of the balance of the owner in the `balanceOf` mapping at the slot
+pub fn erc20_balance_key(owner: Address, slot: u64) -> U256 {
+    U256::from_be_bytes(keccak256((owner, U256::from(slot)).abi_encode()).0)
+}
+
+/// Maps the ERC20 balances of the owners in the `Transfer` events from the storage diff, for the
+/// tokens w/ a known layout
+pub fn erc20_balance_diffs(
+    chain_id: u64,
+    state_diff: &BTreeMap<Address, AccountDiff>,
+    logs: &[Log],
+) -> Vec<Erc20BalanceDiff> {
+    let mut diffs: Vec<Erc20BalanceDiff> = vec![];
+
+    for log in logs {
+        let topics = log.topics();
+        if topics.len() != 3 || topics[0] != TRANSFER_EVENT_TOPIC {
+            continue;
+        }
+
+        let (Some(slot), Some(account)) =
+            (erc20_balance_slot(chain_id, log.address), state_diff.get(&log.address))
+        else {
+            continue;
+        };
+
+        for topic in &topics[1..] {
+            let owner = Address::from_word(*topic);
+            if diffs.iter().any(|diff| diff.token == log.address && diff.owner == owner) {
+                continue;
+            }
+
+            if let Some(storage) = account.storage.get(&erc20_balance_key(owner, slot)) {
+                // Mask the highest bit, used as a flag by some tokens
+                diffs.push(Erc20BalanceDiff {
+                    token: log.address,
+                    owner,
+                    before: storage.before & (U256::MAX >> 1),
+                    after: storage.after & (U256::MAX >> 1),
+                });
+            }
+        }
+    }
+
+    diffs
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, LogData};
+    use revm::primitives::{AccountStatus, EvmStorageSlot};
+
+    #[test]
+    fn test_account_diff() {
+        let before = AccountInfo { balance: U256::from(2), nonce: 1, ..Default::default() };
+        let mut account = Account {
+            info: AccountInfo { balance: U256::from(1), nonce: 2, ..Default::default() },
+            storage: Default::default(),
+            status: AccountStatus::Touched,
+        };
+        account
+            .storage
+            .insert(U256::from(1), EvmStorageSlot::new_changed(U256::ZERO, U256::from(3)));
+        account.storage.insert(U256::from(2), EvmStorageSlot::new(U256::from(4)));
+
+        let diff = AccountDiff::new(&before, &account).unwrap();
+        assert_eq!(diff.balance_before, U256::from(2));
+        assert_eq!(diff.balance_after, U256::from(1));
+        assert_eq!(diff.nonce_after, 2);
+        assert_eq!(diff.code, None);
+        assert_eq!(diff.storage.len(), 1);
+        assert_eq!(
+            diff.storage.get(&U256::from(1)),
+            Some(&StorageDiff { before: U256::ZERO, after: U256::from(3) })
+        );
+
+        let unchanged = Account {
+            info: before.clone(),
+            storage: Default::default(),
+            status: AccountStatus::Touched,
+        };
+        assert!(AccountDiff::new(&before, &unchanged).is_none());
+    }
+
+    #[test]
+    fn test_erc20_balance_diffs() {
+        let token = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let mut account = AccountDiff::default();
+        account.storage.insert(
+            erc20_balance_key(from, 3),
+            StorageDiff { before: U256::from(10), after: U256::from(7) },
+        );
+        account.storage.insert(
+            erc20_balance_key(to, 3),
+            StorageDiff { before: U256::ZERO, after: U256::from(3) },
+        );
+        let state_diff = BTreeMap::from([(token, account)]);
+
+        let log = Log {
+            address: token,
+            data: LogData::new_unchecked(
+                vec![TRANSFER_EVENT_TOPIC, from.into_word(), to.into_word()],
+                U256::from(3).abi_encode().into(),
+            ),
+        };
+
+        let diffs = erc20_balance_diffs(1, &state_diff, &[log.clone()]);
+        assert_eq!(
+            diffs,
+            vec![
+                Erc20BalanceDiff {
+                    token,
+                    owner: from,
+                    before: U256::from(10),
+                    after: U256::from(7)
+                },
+                Erc20BalanceDiff { token, owner: to, before: U256::ZERO, after: U256::from(3) },
+            ]
+        );
+
+        // The layout is only known on the chain of the token
+        assert!(erc20_balance_diffs(10, &state_diff, &[log]).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, Bytes, B256, U256, U64};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The overrides of the accounts in the Geth `eth_call` format
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+/// The override of an account in the Geth `eth_call` format
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    /// Balance of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// Nonce of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    /// Code of the account, e.g. a new implementation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Storage of the account, replacing the whole storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<HashMap<B256, B256>>,
+    /// Storage slots of the account, patching the storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<HashMap<B256, B256>>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl AccountOverride {
+    /// Validate the override, as the full state and the state diff are exclusive
+    pub fn validate(&self, address: Address) -> Result<()> {
+        if self.state.is_some() && self.state_diff.is_some() {
+            return Err(eyre!("Both state and stateDiff overrides of {}", address));
+        }
+
+        Ok(())
+    }
+
+    /// Get the storage slots of the full state or the state diff as words
+    pub fn storage(&self) -> Vec<(U256, U256)> {
+        self.state
+            .iter()
+            .chain(self.state_diff.iter())
+            .flatten()
+            .map(|(slot, value)| (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0)))
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_state_override_deserialize() {
+        let overrides: StateOverride = serde_json::from_value(json!({
+            "0x4fd9D0eE6D6564E80A9Ee00c0163fC952d0A45Ed": {
+                "balance": "0xde0b6b3a7640000",
+                "nonce": "0x1",
+                "code": "0x6080",
+                "stateDiff": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001":
+                        "0x00000000000000000000000000000000000000000000000000000000000000ff"
+                }
+            }
+        }))
+        .unwrap();
+
+        let address: Address = "0x4fd9D0eE6D6564E80A9Ee00c0163fC952d0A45Ed".parse().unwrap();
+        let account = overrides.get(&address).unwrap();
+        assert_eq!(account.balance, Some(U256::from(10).pow(U256::from(18))));
+        assert_eq!(account.nonce, Some(U64::from(1)));
+        assert_eq!(account.code, Some(Bytes::from_static(&[0x60, 0x80])));
+        assert_eq!(account.storage(), vec![(U256::from(1), U256::from(0xff))]);
+        assert!(account.validate(address).is_ok());
+    }
+
+    #[test]
+    fn test_state_override_validate() {
+        let account = AccountOverride {
+            state: Some(HashMap::new()),
+            state_diff: Some(HashMap::new()),
+            ..Default::default()
+        };
+        assert!(account.validate(Address::ZERO).is_err());
+    }
+}
@@ -12,28 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloy::{
-    primitives::{Address, Bytes, Log, U256},
-    sol,
-    sol_types::{Error, SolCall},
+use crate::{
+    state_diff::{AccountDiff, Erc20BalanceDiff},
+    state_override::StateOverride,
 };
-use eyre::{eyre, Result};
+use alloy::primitives::{Address, Bytes, Log, U256};
 use foundry_evm::traces::CallTraceArena;
 use revm::interpreter::InstructionResult;
 use serde::{Deserialize, Serialize};
-
-sol!(
-    interface LightWalletFactory {
-        function createAccount(bytes32 hash, bytes32 salt) external returns (address);
-    }
-);
-
-sol!(
-    interface LightWallet {
-        function execute(address dest, uint256 value, bytes calldata func) external returns (bytes);
-        function executeBatch(address[] calldata dest, uint256[] calldata value, bytes[] calldata func) external returns (bytes[]);
-    }
-);
+use std::collections::BTreeMap;
 
 // Entire file is derived from https://github.com/EnsoFinance/transaction-simulator/blob/42bc679fb171de760838457820d5c6622e53ab15/src/simulation.rs
 // License: MIT
@@ -54,6 +41,9 @@ pub struct SimulationRequest {
     pub value: U256,
     /// Block number of the request
     pub block_number: Option<u64>,
+    /// State overrides applied before the transaction
+    #[serde(default)]
+    pub state_overrides: Option<StateOverride>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -68,6 +58,9 @@ pub struct SimulationUserOperationRequest {
     pub init_code: Option<Bytes>,
     /// Calldata of the transaction
     pub call_data: Option<Bytes>,
+    /// State overrides applied before the user operation
+    #[serde(default)]
+    pub state_overrides: Option<StateOverride>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -84,6 +77,12 @@ pub struct SimulationResponse {
     pub logs: Vec<Log>,
     /// Exit reason of the transaction
     pub exit_reason: InstructionResult,
+    /// Changes of the accounts in the transaction
+    #[serde(default)]
+    pub state_diff: BTreeMap<Address, AccountDiff>,
+    /// Changes of the ERC20 balances of the tokens w/ a known layout
+    #[serde(default)]
+    pub erc20_balances: Vec<Erc20BalanceDiff>,
 }
 
 #[derive(Debug, Clone)]
@@ -95,6 +94,8 @@ pub struct CallRawResult {
     pub logs: Vec<Log>,
     pub exit_reason: InstructionResult,
     pub return_data: Bytes,
+    pub state_diff: BTreeMap<Address, AccountDiff>,
+    pub erc20_balances: Vec<Erc20BalanceDiff>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -128,106 +129,48 @@ pub struct UserOperationRequest {
     pub paymaster_and_data: Option<Bytes>,
     /// Signature of the transaction
     pub signature: Option<Bytes>,
+    /// State overrides applied before the user operation
+    #[serde(default)]
+    pub state_overrides: Option<StateOverride>,
 }
 
 // -----------------------------------------------------------------------------
-// Try From
+// From
 // -----------------------------------------------------------------------------
 
-impl TryFrom<SimulationUserOperationRequest> for Vec<SimulationRequest> {
-    type Error = eyre::Report;
-
-    fn try_from(params: SimulationUserOperationRequest) -> Result<Self, Self::Error> {
-        let mut requests = vec![];
-
-        if let Some(init_code) = params.clone().init_code {
-            // Check if the length is more than 20 bytes
-            if init_code.0.len() > 20 {
-                // Try decoding for `createAccount`
-                // Omit the first 20 bytes (the address of the contract)
-                let factory_addr = Address::from_slice(&init_code.0[..20]);
-                let res: Result<LightWalletFactory::createAccountCall, Error> =
-                    LightWalletFactory::createAccountCall::abi_decode(
-                        &init_code.0.slice(20..),
-                        true,
-                    );
-
-                if res.is_ok() {
-                    requests.push(SimulationRequest {
-                        chain_id: params.chain_id,
-                        from: params.sender,
-                        to: factory_addr,
-                        data: Some(init_code),
-                        gas_limit: u64::MAX,
-                        value: U256::ZERO,
-                        block_number: None,
-                    });
-                }
-            }
-        }
-
-        if let Some(call_data) = params.clone().call_data {
-            // Try decoding for `execute`
-            let res: Result<LightWallet::executeCall, Error> =
-                LightWallet::executeCall::abi_decode(&call_data, true);
-
-            if let Ok(decoded) = res {
-                requests.push(SimulationRequest {
-                    chain_id: params.chain_id,
-                    from: params.sender,
-                    to: decoded.dest,
-                    data: Some(decoded.func.0.into()),
-                    gas_limit: u64::MAX,
-                    value: decoded.value,
-                    block_number: None,
-                });
-            }
-        }
-
-        if let Some(call_data) = params.clone().call_data {
-            // Try decoding for `executeBatch`
-            let res: Result<LightWallet::executeBatchCall, Error> =
-                LightWallet::executeBatchCall::abi_decode(&call_data, true);
-
-            if let Ok(decoded) = res {
-                for ((dest, value), func) in
-                    decoded.dest.into_iter().zip(decoded.value).zip(decoded.func)
-                {
-                    requests.push(SimulationRequest {
-                        chain_id: params.chain_id,
-                        from: params.sender,
-                        to: dest,
-                        data: Some(func.0.into()),
-                        gas_limit: u64::MAX,
-                        value,
-                        block_number: None,
-                    });
-                }
-            }
-        }
-
-        if requests.is_empty() {
-            Err(eyre!("Invalid transaction"))
-        } else {
-            Ok(requests)
+impl From<SimulationUserOperationRequest> for UserOperationRequest {
+    fn from(params: SimulationUserOperationRequest) -> Self {
+        UserOperationRequest {
+            chain_id: params.chain_id,
+            block_number: None,
+            sender: params.sender,
+            entrypoint: None,
+            nonce: Some(U256::from(params.nonce)),
+            init_code: params.init_code,
+            call_data: params.call_data,
+            call_gas_limit: None,
+            verification_gas_limit: None,
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: None,
+            signature: None,
+            state_overrides: params.state_overrides,
         }
     }
 }
 
-// -----------------------------------------------------------------------------
-// From
-// -----------------------------------------------------------------------------
-
-impl From<UserOperationRequest> for SimulationRequest {
-    fn from(uo: UserOperationRequest) -> Self {
-        SimulationRequest {
-            chain_id: uo.chain_id,
-            from: uo.sender,
-            to: uo.entrypoint.unwrap_or_default(),
-            data: uo.call_data,
-            gas_limit: uo.call_gas_limit.unwrap_or_default().try_into().unwrap_or_default(),
-            value: U256::ZERO,
-            block_number: uo.block_number,
+impl From<CallRawResult> for SimulationResponse {
+    fn from(res: CallRawResult) -> Self {
+        SimulationResponse {
+            gas_used: res.gas_used,
+            block_number: res.block_number,
+            success: res.success,
+            arena: res.trace,
+            logs: res.logs,
+            exit_reason: res.exit_reason,
+            state_diff: res.state_diff,
+            erc20_balances: res.erc20_balances,
         }
     }
 }
@@ -0,0 +1,599 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    evm::Evm,
+    state_diff::{AccountDiff, Erc20BalanceDiff},
+    types::UserOperationRequest,
+    validation::{
+        VALIDATE_PAYMASTER_USER_OP_V060_SELECTOR, VALIDATE_PAYMASTER_USER_OP_V070_SELECTOR,
+    },
+    verification::{VALIDATE_USER_OP_V060_SELECTOR, VALIDATE_USER_OP_V070_SELECTOR},
+};
+use alloy::{
+    primitives::{address, aliases::U192, Address, Bytes, Log, B256, U256},
+    providers::Provider,
+    sol_types::{SolCall, SolError, SolEvent},
+};
+use eyre::Result;
+use foundry_evm::traces::CallTraceArena;
+use lightdotso_contracts::{
+    address::{ENTRYPOINT_V060_ADDRESS, ENTRYPOINT_V070_ADDRESS},
+    entrypoint_v060::EntryPointV060,
+    entrypoint_v070::{
+        EntryPointV070, EntryPointV070::PackedUserOperation as EntryPointV070PackedUserOperation,
+    },
+    provider::get_provider,
+    types::{PackedUserOperation, UserOperation},
+};
+use revm::{
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, InterpreterResult,
+    },
+    primitives::ExecutionResult,
+    Database, EvmContext, Inspector,
+};
+use revm_inspectors::tracing::{TracingInspector, TracingInspectorConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The bundler sending the simulated `handleOps` and receiving the compensation
+pub const SIMULATION_BUNDLER: Address = address!("000000000000000000000000000000000000b0b0");
+
+/// The gas limit of the simulated `handleOps` transaction
+const SIMULATION_GAS_LIMIT: u64 = 30_000_000;
+
+/// The call gas limit of the user operation if not set
+const DEFAULT_CALL_GAS_LIMIT: u64 = 10_000_000;
+
+/// The verification gas limit of the user operation if not set
+const DEFAULT_VERIFICATION_GAS_LIMIT: u64 = 1_000_000;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct UserOperationSimulationResponse {
+    /// Block number of the simulation
+    pub block_number: u64,
+    /// Hash of the user operation emitted by the entrypoint
+    pub user_operation_hash: Option<B256>,
+    /// Whether the user operation passed the validation of the entrypoint
+    pub validation_success: bool,
+    /// Reason of the failed validation, e.g. `AA21 didn't pay prefund`
+    pub validation_error: Option<String>,
+    /// Whether the signature of the account and the paymaster was valid, bypassed if not
+    pub signature_valid: bool,
+    /// Whether the validation of the account or the paymaster reverted, e.g. on an invalid
+    /// signature, which is not bypassed as only `SIG_VALIDATION_FAILED` is supported
+    #[serde(default)]
+    pub validation_reverted: bool,
+    /// Whether the account was deployed w/ the init code
+    pub account_deployed: bool,
+    /// Whether the execution of the call data succeeded
+    pub execution_success: bool,
+    /// Revert reason of the execution, if reverted
+    pub execution_revert_reason: Option<Bytes>,
+    /// Revert reason of the `postOp` of the paymaster, if reverted (v0.7)
+    pub post_op_revert_reason: Option<Bytes>,
+    /// Gas cost charged by the entrypoint
+    pub actual_gas_cost: U256,
+    /// Gas used charged by the entrypoint
+    pub actual_gas_used: U256,
+    /// Gas used by the `handleOps` transaction
+    pub gas_used: u64,
+    /// Logs of the transaction
+    pub logs: Vec<Log>,
+    /// Trace of the transaction in the form of a CallTraceArena
+    pub arena: Option<CallTraceArena>,
+    /// Changes of the accounts in the transaction
+    #[serde(default)]
+    pub state_diff: BTreeMap<Address, AccountDiff>,
+    /// Changes of the ERC20 balances of the tokens w/ a known layout
+    #[serde(default)]
+    pub erc20_balances: Vec<Erc20BalanceDiff>,
+}
+
+/// The inspector of the `handleOps` transaction, tracing the calls and bypassing the signature
+/// check of the account and the paymaster
+#[derive(Debug)]
+pub struct UserOperationInspector {
+    entry_point: Address,
+    /// Whether the signature was valid, cleared once bypassed
+    pub signature_valid: bool,
+    /// Whether the validation reverted, w/o any validation data to bypass
+    pub validation_reverted: bool,
+    tracer: TracingInspector,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl UserOperationSimulationResponse {
+    /// Decode the result of the `handleOps` transaction w/ a single user operation, w/ the events
+    /// emitted by the entrypoint only
+    pub fn from_execution_result(
+        block_number: u64,
+        entry_point: Address,
+        res: &ExecutionResult,
+        signature_valid: bool,
+        arena: Option<CallTraceArena>,
+    ) -> Self {
+        let mut response = Self {
+            block_number,
+            user_operation_hash: None,
+            validation_success: res.is_success(),
+            validation_error: None,
+            signature_valid,
+            validation_reverted: false,
+            account_deployed: false,
+            execution_success: false,
+            execution_revert_reason: None,
+            post_op_revert_reason: None,
+            actual_gas_cost: U256::ZERO,
+            actual_gas_used: U256::ZERO,
+            gas_used: res.gas_used(),
+            logs: res.logs().to_vec(),
+            arena,
+            state_diff: BTreeMap::new(),
+            erc20_balances: vec![],
+        };
+
+        // The validation failures revert the whole `handleOps`
+        if !res.is_success() {
+            response.validation_error = Some(match res.output() {
+                Some(output) => decode_failed_op(output),
+                None => format!("Halted: {:?}", res),
+            });
+            return response;
+        }
+
+        // Any contract called by the user operation can emit the events w/ the same signature
+        for log in res.logs().iter().filter(|log| log.address == entry_point) {
+            if let Ok(event) = EntryPointV060::UserOperationEvent::decode_log_data(&log.data, true)
+            {
+                response.user_operation_hash = Some(event.userOpHash);
+                response.execution_success = event.success;
+                response.actual_gas_cost = event.actualGasCost;
+                response.actual_gas_used = event.actualGasUsed;
+            } else if let Ok(event) =
+                EntryPointV060::UserOperationRevertReason::decode_log_data(&log.data, true)
+            {
+                response.execution_revert_reason = Some(event.revertReason);
+            } else if let Ok(event) =
+                EntryPointV070::PostOpRevertReason::decode_log_data(&log.data, true)
+            {
+                response.post_op_revert_reason = Some(event.revertReason);
+            } else if EntryPointV060::AccountDeployed::decode_log_data(&log.data, true).is_ok() {
+                response.account_deployed = true;
+            }
+        }
+
+        response
+    }
+}
+
+impl UserOperationInspector {
+    pub fn new(entry_point: Address) -> Self {
+        Self {
+            entry_point,
+            signature_valid: true,
+            validation_reverted: false,
+            tracer: TracingInspector::new(TracingInspectorConfig::default_parity()),
+        }
+    }
+
+    /// Get the traces of the transaction
+    pub fn into_traces(self) -> CallTraceArena {
+        self.tracer.into_traces()
+    }
+
+    /// Bypass the failed signature in the validation data returned to the entrypoint
+    fn bypass_signature(&mut self, caller: Address, input: &[u8], result: &mut InterpreterResult) {
+        if caller != self.entry_point {
+            return;
+        }
+
+        // The offset of the validation data in the returned data
+        let offset = match input.get(..4) {
+            Some(selector)
+                if selector == VALIDATE_USER_OP_V060_SELECTOR ||
+                    selector == VALIDATE_USER_OP_V070_SELECTOR =>
+            {
+                0
+            }
+            // The validation data is after the offset of the context
+            Some(selector)
+                if selector == VALIDATE_PAYMASTER_USER_OP_V060_SELECTOR ||
+                    selector == VALIDATE_PAYMASTER_USER_OP_V070_SELECTOR =>
+            {
+                32
+            }
+            _ => return,
+        };
+
+        // The accounts and the paymasters reverting on an invalid signature can not be bypassed
+        if !result.result.is_ok() {
+            self.validation_reverted = true;
+            return;
+        }
+
+        let mut output = result.output.to_vec();
+        if let Some(word) = output.get_mut(offset..offset + 32) {
+            if clear_signature_failure(word) {
+                self.signature_valid = false;
+                result.output = output.into();
+            }
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for UserOperationInspector {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.tracer.initialize_interp(interp, context);
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.tracer.step(interp, context);
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.tracer.step_end(interp, context);
+    }
+
+    fn log(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>, log: &Log) {
+        self.tracer.log(interp, context, log);
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.tracer.call(context, inputs)
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        mut outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.bypass_signature(inputs.caller, &inputs.input, &mut outcome.result);
+        self.tracer.call_end(context, inputs, outcome)
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.tracer.create(context, inputs)
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.tracer.create_end(context, inputs, outcome)
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        <TracingInspector as Inspector<DB>>::selfdestruct(
+            &mut self.tracer,
+            contract,
+            target,
+            value,
+        );
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Clear the `SIG_VALIDATION_FAILED` aggregator of the validation data, keeping the time range,
+/// returns whether cleared
+fn clear_signature_failure(word: &mut [u8]) -> bool {
+    // The aggregator is the lowest 20 bytes, w/ `address(1)` for the failed signature
+    if word.len() != 32 || word[12..31].iter().any(|b| *b != 0) || word[31] != 1 {
+        return false;
+    }
+
+    word[31] = 0;
+    true
+}
+
+/// Decode the revert of `handleOps`, e.g. `FailedOp` w/ the reason of the entrypoint
+fn decode_failed_op(output: &Bytes) -> String {
+    if let Ok(err) = EntryPointV070::FailedOpWithRevert::abi_decode(output, true) {
+        return format!("{} {}", err.reason, err.inner);
+    }
+    if let Ok(err) = EntryPointV060::FailedOp::abi_decode(output, true) {
+        return err.reason;
+    }
+
+    format!("Reverted: {}", output)
+}
+
+/// Build the user operation of the request, w/ the defaults for the simulation
+fn build_user_operation(request: &UserOperationRequest, nonce: U256) -> UserOperation {
+    UserOperation {
+        sender: request.sender,
+        nonce,
+        init_code: request.init_code.clone().unwrap_or_default(),
+        call_data: request.call_data.clone().unwrap_or_default(),
+        call_gas_limit: request.call_gas_limit.unwrap_or(U256::from(DEFAULT_CALL_GAS_LIMIT)),
+        verification_gas_limit: request
+            .verification_gas_limit
+            .unwrap_or(U256::from(DEFAULT_VERIFICATION_GAS_LIMIT)),
+        pre_verification_gas: request.pre_verification_gas.unwrap_or_default(),
+        // W/o the fees, the user operation is simulated w/o any prefund
+        max_fee_per_gas: request.max_fee_per_gas.unwrap_or_default(),
+        max_priority_fee_per_gas: request.max_priority_fee_per_gas.unwrap_or_default(),
+        paymaster_and_data: request.paymaster_and_data.clone().unwrap_or_default(),
+        signature: request.signature.clone().unwrap_or_default(),
+    }
+}
+
+/// Simulates the user operation w/ `handleOps` of the entrypoint, bypassing the signature check,
+/// and returns the validation and the execution results as on-chain
+pub async fn simulate_user_operation(
+    request: UserOperationRequest,
+) -> Result<UserOperationSimulationResponse> {
+    // Get the provider
+    let (provider, fork_url) = get_provider(request.chain_id).await?;
+
+    // If block number is not provided, use the latest block number
+    let block_number = match request.block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    // Construct the EVM
+    let mut evm = Evm::new(None, fork_url, Some(block_number), SIMULATION_GAS_LIMIT).await?;
+
+    let entry_point = request.entrypoint.unwrap_or(*ENTRYPOINT_V060_ADDRESS);
+
+    // Get the nonce of the sender w/ the default key if not set
+    let nonce = match request.nonce {
+        Some(nonce) => nonce,
+        None => {
+            let data = EntryPointV060::getNonceCall { sender: request.sender, key: U192::ZERO }
+                .abi_encode();
+            let res =
+                evm.call_raw(SIMULATION_BUNDLER, entry_point, None, Some(data.into())).await?;
+            EntryPointV060::getNonceCall::abi_decode_returns(&res.return_data, true)?.nonce
+        }
+    };
+
+    // Encode `handleOps` w/ the user operation for the entrypoint version
+    let user_operation = build_user_operation(&request, nonce);
+    let data = if entry_point == *ENTRYPOINT_V070_ADDRESS {
+        let op: EntryPointV070PackedUserOperation =
+            PackedUserOperation::from(user_operation).into();
+        EntryPointV070::handleOpsCall { ops: vec![op], beneficiary: SIMULATION_BUNDLER }
+            .abi_encode()
+    } else {
+        EntryPointV060::handleOpsCall {
+            ops: vec![user_operation.into()],
+            beneficiary: SIMULATION_BUNDLER,
+        }
+        .abi_encode()
+    };
+
+    // Apply the state overrides before the user operation
+    if let Some(overrides) = &request.state_overrides {
+        evm.apply_state_override(overrides)?;
+    }
+
+    // Run `handleOps` as the bundler
+    let mut inspector = UserOperationInspector::new(entry_point);
+    let (res, state_diff, erc20_balances) = evm.inspect_transaction(
+        SIMULATION_BUNDLER,
+        entry_point,
+        data.into(),
+        SIMULATION_GAS_LIMIT,
+        &mut inspector,
+    )?;
+
+    let signature_valid = inspector.signature_valid;
+    let validation_reverted = inspector.validation_reverted;
+    Ok(UserOperationSimulationResponse {
+        validation_reverted,
+        state_diff,
+        erc20_balances,
+        ..UserOperationSimulationResponse::from_execution_result(
+            block_number,
+            entry_point,
+            &res,
+            signature_valid,
+            Some(inspector.into_traces()),
+        )
+    })
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::LogData;
+    use revm::{
+        interpreter::{Gas, InstructionResult},
+        primitives::{Output, SuccessReason},
+    };
+
+    #[test]
+    fn test_clear_signature_failure() {
+        // `SIG_VALIDATION_FAILED` w/ the time range kept
+        let mut word = [0u8; 32];
+        word[0] = 0x01;
+        word[31] = 1;
+        assert!(clear_signature_failure(&mut word));
+        assert_eq!(word[0], 0x01);
+        assert_eq!(word[31], 0);
+
+        // The valid signature and the aggregators are kept
+        let mut word = [0u8; 32];
+        assert!(!clear_signature_failure(&mut word));
+        let mut word = [0u8; 32];
+        word[12] = 0xaa;
+        word[31] = 1;
+        assert!(!clear_signature_failure(&mut word));
+    }
+
+    #[test]
+    fn test_bypass_signature() {
+        let entry_point = *ENTRYPOINT_V060_ADDRESS;
+        let input = VALIDATE_USER_OP_V060_SELECTOR.to_vec();
+        let mut validation_data = [0u8; 32];
+        validation_data[31] = 1;
+
+        // `SIG_VALIDATION_FAILED` is bypassed
+        let mut inspector = UserOperationInspector::new(entry_point);
+        let mut result = InterpreterResult::new(
+            InstructionResult::Return,
+            Bytes::copy_from_slice(&validation_data),
+            Gas::new(0),
+        );
+        inspector.bypass_signature(entry_point, &input, &mut result);
+        assert!(!inspector.signature_valid);
+        assert!(!inspector.validation_reverted);
+        assert_eq!(result.output, Bytes::copy_from_slice(&[0u8; 32]));
+
+        // The account reverting on the invalid signature is not bypassed
+        let mut inspector = UserOperationInspector::new(entry_point);
+        let mut result = InterpreterResult::new(
+            InstructionResult::Revert,
+            Bytes::from_static(b"invalid signature"),
+            Gas::new(0),
+        );
+        inspector.bypass_signature(entry_point, &input, &mut result);
+        assert!(inspector.signature_valid);
+        assert!(inspector.validation_reverted);
+        assert_eq!(result.output, Bytes::from_static(b"invalid signature"));
+
+        // The calls not made by the entrypoint are ignored
+        let mut inspector = UserOperationInspector::new(entry_point);
+        let mut result =
+            InterpreterResult::new(InstructionResult::Revert, Bytes::default(), Gas::new(0));
+        inspector.bypass_signature(Address::ZERO, &input, &mut result);
+        assert!(!inspector.validation_reverted);
+    }
+
+    #[test]
+    fn test_from_execution_result_reverted() {
+        let output: Bytes = EntryPointV060::FailedOp {
+            opIndex: U256::ZERO,
+            reason: "AA21 didn't pay prefund".to_string(),
+        }
+        .abi_encode()
+        .into();
+        let res = ExecutionResult::Revert { gas_used: 100_000, output };
+
+        let response = UserOperationSimulationResponse::from_execution_result(
+            1,
+            *ENTRYPOINT_V060_ADDRESS,
+            &res,
+            true,
+            None,
+        );
+        assert!(!response.validation_success);
+        assert_eq!(response.validation_error.as_deref(), Some("AA21 didn't pay prefund"));
+        assert_eq!(response.gas_used, 100_000);
+    }
+
+    #[test]
+    fn test_from_execution_result_success() {
+        let user_op_hash = B256::repeat_byte(1);
+        let sender = Address::repeat_byte(2);
+        let log = |data: LogData| Log { address: *ENTRYPOINT_V060_ADDRESS, data };
+        let spoofed = |data: LogData| Log { address: Address::repeat_byte(4), data };
+
+        let logs = vec![
+            log(EntryPointV060::AccountDeployed {
+                userOpHash: user_op_hash,
+                sender,
+                factory: Address::repeat_byte(3),
+                paymaster: Address::ZERO,
+            }
+            .encode_log_data()),
+            log(EntryPointV060::UserOperationRevertReason {
+                userOpHash: user_op_hash,
+                sender,
+                nonce: U256::ZERO,
+                revertReason: Bytes::from_static(b"revert"),
+            }
+            .encode_log_data()),
+            log(EntryPointV060::UserOperationEvent {
+                userOpHash: user_op_hash,
+                sender,
+                paymaster: Address::ZERO,
+                nonce: U256::ZERO,
+                success: false,
+                actualGasCost: U256::from(1_000),
+                actualGasUsed: U256::from(100),
+            }
+            .encode_log_data()),
+            // The events emitted by other contracts are ignored
+            spoofed(
+                EntryPointV060::UserOperationEvent {
+                    userOpHash: B256::repeat_byte(5),
+                    sender,
+                    paymaster: Address::ZERO,
+                    nonce: U256::ZERO,
+                    success: true,
+                    actualGasCost: U256::ZERO,
+                    actualGasUsed: U256::ZERO,
+                }
+                .encode_log_data(),
+            ),
+        ];
+        let res = ExecutionResult::Success {
+            reason: SuccessReason::Stop,
+            gas_used: 200_000,
+            gas_refunded: 0,
+            logs,
+            output: Output::Call(Bytes::default()),
+        };
+
+        let response = UserOperationSimulationResponse::from_execution_result(
+            1,
+            *ENTRYPOINT_V060_ADDRESS,
+            &res,
+            false,
+            None,
+        );
+        assert!(response.validation_success);
+        assert!(!response.signature_valid);
+        assert!(response.account_deployed);
+        assert!(!response.execution_success);
+        assert_eq!(response.user_operation_hash, Some(user_op_hash));
+        assert_eq!(response.execution_revert_reason, Some(Bytes::from_static(b"revert")));
+        assert_eq!(response.actual_gas_cost, U256::from(1_000));
+        assert_eq!(response.actual_gas_used, U256::from(100));
+        assert_eq!(response.logs.len(), 4);
+    }
+}
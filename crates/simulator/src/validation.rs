@@ -0,0 +1,471 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    evm::Evm,
+    validation_tracer::{ValidationPhase, ValidationTracer, ValidationViolation},
+    verification::{encode_validate_user_op, execution_gas, VerificationGasRequest},
+};
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes, U256},
+    providers::{Provider, RootProvider},
+    sol_types::SolValue,
+    transports::BoxTransport,
+};
+use eyre::Result;
+use lightdotso_contracts::{
+    address::ENTRYPOINT_V060_ADDRESS,
+    entrypoint_v060::{
+        EntryPointV060, EntryPointV060::UserOperation as EntryPointV060UserOperation,
+    },
+    entrypoint_v070::{
+        EntryPointV070, EntryPointV070::PackedUserOperation as EntryPointV070PackedUserOperation,
+    },
+    provider::get_provider,
+    types::{PackedUserOperation, UserOperation},
+};
+use serde::{Deserialize, Serialize};
+
+/// The selector of `validatePaymasterUserOp` of the v0.6 paymaster interface
+pub(crate) const VALIDATE_PAYMASTER_USER_OP_V060_SELECTOR: [u8; 4] = [0xf4, 0x65, 0xc7, 0x7e];
+
+/// The selector of `validatePaymasterUserOp` of the v0.7 paymaster interface
+pub(crate) const VALIDATE_PAYMASTER_USER_OP_V070_SELECTOR: [u8; 4] = [0x52, 0xb7, 0x51, 0x2c];
+
+/// The gas limit of each traced validation call
+const VALIDATION_GAS_LIMIT: u64 = 30_000_000;
+
+/// The min stake in the entrypoint of a staked entity (1 ether)
+const MIN_STAKE: u128 = 1_000_000_000_000_000_000;
+
+/// The min unstake delay in seconds of a staked entity (1 day)
+const MIN_UNSTAKE_DELAY: u32 = 86_400;
+
+/// The min seconds the user operation stays valid after the validation, to be bundled in time
+const VALID_UNTIL_MARGIN_SECONDS: u64 = 30;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidationRequest {
+    /// Chain ID of the network
+    pub chain_id: u64,
+    /// Entrypoint address calling the entities
+    pub entry_point: Address,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Factory of the account, if not yet deployed
+    pub factory: Option<Address>,
+    /// Factory data of the account, if not yet deployed
+    pub factory_data: Option<Bytes>,
+    /// Calldata of `validateUserOp` to the sender
+    pub validate_user_op_data: Bytes,
+    /// Gas limit of the deployment and `validateUserOp`
+    pub verification_gas_limit: u64,
+    /// Paymaster of the user operation, if sponsored
+    pub paymaster: Option<Address>,
+    /// Calldata of `validatePaymasterUserOp` to the paymaster, if sponsored
+    pub validate_paymaster_user_op_data: Option<Bytes>,
+    /// Gas limit of `validatePaymasterUserOp`
+    pub paymaster_verification_gas_limit: u64,
+    /// Entities staked in the entrypoint, relaxing the storage and opcode rules
+    pub staked_entities: Vec<Address>,
+    /// Block number of the request
+    pub block_number: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Violations of the validation rules of ERC-7562
+    pub violations: Vec<ValidationViolation>,
+    /// Gas used by the deployment of the account, excluding the intrinsic gas
+    pub factory_gas: u64,
+    /// Gas used by `validateUserOp`, excluding the intrinsic gas
+    pub account_gas: u64,
+    /// Gas used by `validatePaymasterUserOp`, excluding the intrinsic gas
+    pub paymaster_gas: u64,
+}
+
+impl ValidationReport {
+    /// Whether the user operation is safe to include in the mempool
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl ValidationRequest {
+    /// Constructs the request for the user operation (v0.6)
+    pub fn from_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        user_operation: &UserOperation,
+    ) -> Self {
+        let verification =
+            VerificationGasRequest::from_user_operation(chain_id, entry_point, user_operation);
+
+        // The verification gas limit also applies to the paymaster in v0.6
+        let paymaster = user_operation.paymaster_and_data.get(..20).map(Address::from_slice);
+        let validate_paymaster_user_op_data = paymaster.map(|_| {
+            let user_op_hash = user_operation.op_hash(entry_point, chain_id);
+            let op: EntryPointV060UserOperation = user_operation.clone().into();
+            encode_validate_user_op(
+                VALIDATE_PAYMASTER_USER_OP_V060_SELECTOR,
+                (op, user_op_hash, U256::ZERO).abi_encode_params(),
+            )
+        });
+        let verification_gas_limit = user_operation.verification_gas_limit.saturating_to();
+
+        Self {
+            chain_id,
+            entry_point,
+            sender: user_operation.sender,
+            factory: verification.factory,
+            factory_data: verification.factory_data,
+            validate_user_op_data: verification.validate_user_op_data,
+            verification_gas_limit,
+            paymaster,
+            validate_paymaster_user_op_data,
+            paymaster_verification_gas_limit: verification_gas_limit,
+            staked_entities: vec![],
+            block_number: None,
+        }
+    }
+
+    /// Constructs the request for the packed user operation (v0.7)
+    pub fn from_packed_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        packed_user_operation: &PackedUserOperation,
+    ) -> Self {
+        let verification = VerificationGasRequest::from_packed_user_operation(
+            chain_id,
+            entry_point,
+            packed_user_operation,
+        );
+
+        let paymaster = packed_user_operation.paymaster;
+        let validate_paymaster_user_op_data = paymaster.map(|_| {
+            let user_op_hash = packed_user_operation.op_hash(entry_point, chain_id);
+            let op: EntryPointV070PackedUserOperation = packed_user_operation.clone().into();
+            encode_validate_user_op(
+                VALIDATE_PAYMASTER_USER_OP_V070_SELECTOR,
+                (op, user_op_hash, U256::ZERO).abi_encode_params(),
+            )
+        });
+
+        Self {
+            chain_id,
+            entry_point,
+            sender: packed_user_operation.sender,
+            factory: verification.factory,
+            factory_data: verification.factory_data,
+            validate_user_op_data: verification.validate_user_op_data,
+            verification_gas_limit: packed_user_operation.verification_gas_limit.saturating_to(),
+            paymaster,
+            validate_paymaster_user_op_data,
+            paymaster_verification_gas_limit: packed_user_operation
+                .paymaster_verification_gas_limit
+                .unwrap_or_default()
+                .saturating_to(),
+            staked_entities: vec![],
+            block_number: None,
+        }
+    }
+
+    fn tracer(&self, phase: ValidationPhase, entity: Address) -> ValidationTracer {
+        ValidationTracer::new(
+            phase,
+            self.sender,
+            self.entry_point,
+            entity,
+            self.staked_entities.contains(&entity),
+        )
+    }
+}
+
+/// Traces the validation phases of the user operation, deploying the account if needed, and
+/// reports the violations of the validation rules of ERC-7562
+pub async fn validate_user_operation(mut request: ValidationRequest) -> Result<ValidationReport> {
+    // Get the provider
+    let (provider, fork_url) = get_provider(request.chain_id).await?;
+
+    // If block number is not provided, use the latest block number
+    let block_number = match request.block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    // Get the entities staked in the entrypoint
+    let entities = [Some(request.sender), request.factory, request.paymaster]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let staked_entities =
+        get_staked_entities(&provider, request.entry_point, &entities, block_number).await?;
+    request.staked_entities.extend(staked_entities);
+
+    // Construct the EVM
+    let mut evm = Evm::new(None, fork_url, Some(block_number), VALIDATION_GAS_LIMIT).await?;
+    let now = evm.get_block_timestamp().saturating_to::<u64>();
+
+    let mut report = ValidationReport::default();
+
+    // Deploy the account w/ the factory, committing the state for the validation
+    if let (Some(factory), Some(factory_data)) = (request.factory, request.factory_data.clone()) {
+        let mut tracer = request.tracer(ValidationPhase::Factory, factory);
+        let res = evm.inspect_raw(
+            request.entry_point,
+            factory,
+            factory_data.clone(),
+            VALIDATION_GAS_LIMIT,
+            &mut tracer,
+            true,
+        )?;
+        report.violations.extend(tracer.violations);
+        report.factory_gas = execution_gas(res.gas_used(), &factory_data);
+
+        if !res.is_success() {
+            report.violations.push(ValidationViolation::Reverted {
+                phase: ValidationPhase::Factory,
+                output: res.output().cloned().unwrap_or_default(),
+            });
+            return Ok(report);
+        }
+    }
+
+    // Validate the user operation as the entrypoint, committing the state for the paymaster
+    let mut tracer = request.tracer(ValidationPhase::Account, request.sender);
+    let res = evm.inspect_raw(
+        request.entry_point,
+        request.sender,
+        request.validate_user_op_data.clone(),
+        VALIDATION_GAS_LIMIT,
+        &mut tracer,
+        true,
+    )?;
+    report.violations.extend(tracer.violations);
+    report.account_gas = execution_gas(res.gas_used(), &request.validate_user_op_data);
+
+    if !res.is_success() {
+        report.violations.push(ValidationViolation::Reverted {
+            phase: ValidationPhase::Account,
+            output: res.output().cloned().unwrap_or_default(),
+        });
+        return Ok(report);
+    }
+
+    // The signature and the time range of the account
+    let output = res.output().cloned().unwrap_or_default();
+    let violation = match U256::abi_decode(&output, false) {
+        Ok(validation_data) => {
+            check_validation_data(ValidationPhase::Account, validation_data, now)
+        }
+        Err(_) => {
+            Some(ValidationViolation::InvalidReturn { phase: ValidationPhase::Account, output })
+        }
+    };
+    report.violations.extend(violation);
+
+    // [GAS-010] The deployment and the validation within the verification gas limit
+    if report.factory_gas + report.account_gas > request.verification_gas_limit {
+        report.violations.push(ValidationViolation::GasLimitExceeded {
+            phase: ValidationPhase::Account,
+            gas_used: report.factory_gas + report.account_gas,
+            gas_limit: request.verification_gas_limit,
+        });
+    }
+
+    // Validate the paymaster as the entrypoint
+    if let (Some(paymaster), Some(data)) =
+        (request.paymaster, request.validate_paymaster_user_op_data.clone())
+    {
+        let mut tracer = request.tracer(ValidationPhase::Paymaster, paymaster);
+        let res = evm.inspect_raw(
+            request.entry_point,
+            paymaster,
+            data.clone(),
+            VALIDATION_GAS_LIMIT,
+            &mut tracer,
+            false,
+        )?;
+        report.violations.extend(tracer.violations);
+        report.paymaster_gas = execution_gas(res.gas_used(), &data);
+
+        let output = res.output().cloned().unwrap_or_default();
+        if !res.is_success() {
+            report
+                .violations
+                .push(ValidationViolation::Reverted { phase: ValidationPhase::Paymaster, output });
+            return Ok(report);
+        }
+
+        // The signature and the time range of the paymaster, after the context
+        let violation = match <(Bytes, U256)>::abi_decode_params(&output, false) {
+            Ok((_, validation_data)) => {
+                check_validation_data(ValidationPhase::Paymaster, validation_data, now)
+            }
+            Err(_) => Some(ValidationViolation::InvalidReturn {
+                phase: ValidationPhase::Paymaster,
+                output,
+            }),
+        };
+        report.violations.extend(violation);
+
+        if report.paymaster_gas > request.paymaster_verification_gas_limit {
+            report.violations.push(ValidationViolation::GasLimitExceeded {
+                phase: ValidationPhase::Paymaster,
+                gas_used: report.paymaster_gas,
+                gas_limit: request.paymaster_verification_gas_limit,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Get the entities w/ at least the min stake and the min unstake delay in the entrypoint
+async fn get_staked_entities(
+    provider: &RootProvider<BoxTransport>,
+    entry_point: Address,
+    entities: &[Address],
+    block_number: u64,
+) -> Result<Vec<Address>> {
+    let mut staked_entities = vec![];
+
+    for entity in entities {
+        // The deposit info is the same in both of the versions, except the width of the deposit
+        let (staked, stake, unstake_delay) = if entry_point == *ENTRYPOINT_V060_ADDRESS {
+            let info = EntryPointV060::new(entry_point, provider.clone())
+                .getDepositInfo(*entity)
+                .block(BlockId::number(block_number))
+                .call()
+                .await?
+                .info;
+            (info.staked, info.stake.to::<u128>(), info.unstakeDelaySec)
+        } else {
+            let info = EntryPointV070::new(entry_point, provider.clone())
+                .getDepositInfo(*entity)
+                .block(BlockId::number(block_number))
+                .call()
+                .await?
+                .info;
+            (info.staked, info.stake.to::<u128>(), info.unstakeDelaySec)
+        };
+
+        if is_staked(staked, stake, unstake_delay) {
+            staked_entities.push(*entity);
+        }
+    }
+
+    Ok(staked_entities)
+}
+
+/// Whether the deposit info is of a staked entity
+fn is_staked(staked: bool, stake: u128, unstake_delay: u32) -> bool {
+    staked && stake >= MIN_STAKE && unstake_delay >= MIN_UNSTAKE_DELAY
+}
+
+/// Check the validation data returned by the validation of the phase at the timestamp
+/// The lowest 20 bytes are the aggregator (1 for the failed signature), followed by the 6 bytes of
+/// `validUntil` (0 for no expiry) and the 6 bytes of `validAfter`
+fn check_validation_data(
+    phase: ValidationPhase,
+    validation_data: U256,
+    now: u64,
+) -> Option<ValidationViolation> {
+    let bytes = validation_data.to_be_bytes::<32>();
+    let aggregator = Address::from_slice(&bytes[12..]);
+    let valid_until = read_u48(&bytes[6..12]);
+    let valid_after = read_u48(&bytes[..6]);
+
+    // The failed signature, or an aggregator which is not supported
+    if aggregator != Address::ZERO {
+        return Some(ValidationViolation::SignatureFailed { phase, aggregator });
+    }
+
+    let is_expired =
+        valid_until != 0 && valid_until < now.saturating_add(VALID_UNTIL_MARGIN_SECONDS);
+    if valid_after > now || is_expired {
+        return Some(ValidationViolation::OutOfTimeRange { phase, valid_after, valid_until });
+    }
+
+    None
+}
+
+/// Read the big endian bytes of a `uint48`
+fn read_u48(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, byte| (acc << 8) | u64::from(*byte))
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validation_data(aggregator: Address, valid_until: u64, valid_after: u64) -> U256 {
+        U256::from_be_slice(aggregator.as_slice()) |
+            (U256::from(valid_until) << 160) |
+            (U256::from(valid_after) << 208)
+    }
+
+    #[test]
+    fn test_check_validation_data() {
+        let now = 1_000_000;
+        let phase = ValidationPhase::Account;
+
+        // No signature failure and no time range
+        assert_eq!(check_validation_data(phase, U256::ZERO, now), None);
+
+        // The failed signature
+        let violation =
+            check_validation_data(phase, validation_data(Address::with_last_byte(1), 0, 0), now);
+        assert_eq!(violation.map(|v| v.rule()), Some("AA24"));
+
+        // Valid w/in the time range
+        assert_eq!(
+            check_validation_data(phase, validation_data(Address::ZERO, now + 60, now - 60), now),
+            None
+        );
+
+        // Not valid yet
+        let violation =
+            check_validation_data(phase, validation_data(Address::ZERO, 0, now + 60), now);
+        assert_eq!(
+            violation,
+            Some(ValidationViolation::OutOfTimeRange {
+                phase,
+                valid_after: now + 60,
+                valid_until: 0
+            })
+        );
+
+        // Expiring before it can be bundled
+        let violation = check_validation_data(
+            ValidationPhase::Paymaster,
+            validation_data(Address::ZERO, now + 10, 0),
+            now,
+        );
+        assert_eq!(violation.map(|v| v.rule()), Some("AA32"));
+    }
+
+    #[test]
+    fn test_is_staked() {
+        assert!(is_staked(true, MIN_STAKE, MIN_UNSTAKE_DELAY));
+        assert!(!is_staked(false, MIN_STAKE, MIN_UNSTAKE_DELAY));
+        assert!(!is_staked(true, MIN_STAKE - 1, MIN_UNSTAKE_DELAY));
+        assert!(!is_staked(true, MIN_STAKE, MIN_UNSTAKE_DELAY - 1));
+    }
+}
@@ -0,0 +1,463 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// From: https://github.com/eth-infinitism/account-abstraction/blob/develop/erc/ERCS/erc-7562.md
+// License: CC0-1.0
+
+use alloy::primitives::{keccak256, Address, Bytes, U256};
+use revm::{
+    interpreter::{
+        opcode::{self, OpCode},
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, InstructionResult, Interpreter,
+    },
+    primitives::CreateScheme,
+    Database, EvmContext, Inspector,
+};
+use serde::{Deserialize, Serialize};
+
+// -----------------------------------------------------------------------------
+// Constants
+// -----------------------------------------------------------------------------
+
+/// The selector of `depositTo` of the entrypoint, the only method callable in the validation
+const DEPOSIT_TO_SELECTOR: [u8; 4] = [0xb7, 0x60, 0xfa, 0xf9];
+
+/// The number of the slots after a keccak of the sender counted as the associated storage
+const ASSOCIATED_SLOTS: u64 = 128;
+
+/// The highest address of the precompiles, incl. the `P256VERIFY` of RIP-7212
+const MAX_PRECOMPILE_ADDRESS: u64 = 0x100;
+
+// -----------------------------------------------------------------------------
+// Types
+// -----------------------------------------------------------------------------
+
+/// The phase of the validation, w/ the entity responsible for it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationPhase {
+    /// The deployment of the sender w/ the factory
+    Factory,
+    /// The `validateUserOp` of the sender
+    #[default]
+    Account,
+    /// The `validatePaymasterUserOp` of the paymaster
+    Paymaster,
+}
+
+/// The violation of the validation rules of ERC-7562
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ValidationViolation {
+    /// The banned opcode was executed
+    BannedOpcode { phase: ValidationPhase, contract: Address, opcode: String },
+    /// The `GAS` opcode was not followed by a call
+    GasNotFollowedByCall { phase: ValidationPhase, contract: Address },
+    /// The storage not associated w/ the sender was accessed
+    StorageAccess { phase: ValidationPhase, contract: Address, slot: U256 },
+    /// The contract was created outside of the deployment of the sender
+    Create { phase: ValidationPhase, contract: Address },
+    /// The entrypoint was called w/ a method other than `depositTo`
+    CallToEntryPoint { phase: ValidationPhase, contract: Address, input: Bytes },
+    /// The value was sent to a contract other than the entrypoint
+    CallWithValue { phase: ValidationPhase, contract: Address, target: Address },
+    /// The inner call ran out of gas
+    OutOfGas { phase: ValidationPhase, contract: Address },
+    /// The address w/o the deployed code was accessed
+    NoCode { phase: ValidationPhase, contract: Address, target: Address },
+    /// The phase used more gas than its limit
+    GasLimitExceeded { phase: ValidationPhase, gas_used: u64, gas_limit: u64 },
+    /// The phase reverted
+    Reverted { phase: ValidationPhase, output: Bytes },
+    /// The phase returned the failed signature, or an aggregator which is not supported
+    SignatureFailed { phase: ValidationPhase, aggregator: Address },
+    /// The phase returned a time range which is not valid now, or expiring before the bundle
+    OutOfTimeRange { phase: ValidationPhase, valid_after: u64, valid_until: u64 },
+    /// The phase returned the data which is not the validation data
+    InvalidReturn { phase: ValidationPhase, output: Bytes },
+}
+
+/// The inspector of a validation phase, collecting the violations of ERC-7562
+#[derive(Clone, Debug, Default)]
+pub struct ValidationTracer {
+    phase: ValidationPhase,
+    sender: Address,
+    entry_point: Address,
+    /// The entity of the phase, i.e. the factory, the sender or the paymaster
+    entity: Address,
+    /// Whether the entity is staked in the entrypoint
+    is_staked: bool,
+    /// The keccaks of the preimages starting w/ the sender
+    associated_slots: Vec<U256>,
+    /// The contract of the last `GAS` opcode, expecting a call next
+    pending_gas: Option<Address>,
+    creates: usize,
+    /// The violations found
+    pub violations: Vec<ValidationViolation>,
+}
+
+// -----------------------------------------------------------------------------
+// Implementation
+// -----------------------------------------------------------------------------
+
+impl ValidationViolation {
+    /// Get the id of the rule of ERC-7562 violated
+    pub fn rule(&self) -> &'static str {
+        match self {
+            ValidationViolation::BannedOpcode { .. } => "OP-011",
+            ValidationViolation::GasNotFollowedByCall { .. } => "OP-012",
+            ValidationViolation::OutOfGas { .. } => "OP-020",
+            ValidationViolation::NoCode { .. } => "OP-041",
+            ValidationViolation::Create { .. } => "OP-031",
+            ValidationViolation::CallToEntryPoint { .. } => "OP-052",
+            ValidationViolation::CallWithValue { .. } => "OP-061",
+            ValidationViolation::StorageAccess { .. } => "STO-021",
+            ValidationViolation::GasLimitExceeded { .. } => "GAS-010",
+            ValidationViolation::Reverted { .. } => "REVERT",
+            // The entrypoint errors of the account and the paymaster
+            ValidationViolation::SignatureFailed { phase: ValidationPhase::Paymaster, .. } => {
+                "AA34"
+            }
+            ValidationViolation::SignatureFailed { .. } => "AA24",
+            ValidationViolation::OutOfTimeRange { phase: ValidationPhase::Paymaster, .. } => "AA32",
+            ValidationViolation::OutOfTimeRange { .. } => "AA22",
+            ValidationViolation::InvalidReturn { phase: ValidationPhase::Paymaster, .. } => "AA33",
+            ValidationViolation::InvalidReturn { .. } => "AA23",
+        }
+    }
+}
+
+impl ValidationTracer {
+    pub fn new(
+        phase: ValidationPhase,
+        sender: Address,
+        entry_point: Address,
+        entity: Address,
+        is_staked: bool,
+    ) -> Self {
+        Self { phase, sender, entry_point, entity, is_staked, ..Default::default() }
+    }
+
+    /// Check the storage access of the contract at the slot
+    fn check_storage(&mut self, contract: Address, slot: U256) {
+        // [STO-010] The storage of the sender
+        if contract == self.sender {
+            return;
+        }
+
+        // [STO-021] The storage associated w/ the sender in any contract
+        if self.is_associated(slot) {
+            return;
+        }
+
+        // [STO-031] The storage of the staked entity itself
+        if contract == self.entity && self.is_staked {
+            return;
+        }
+
+        self.violations.push(ValidationViolation::StorageAccess {
+            phase: self.phase,
+            contract,
+            slot,
+        });
+    }
+
+    /// Check if the slot is the sender or within the slots after a keccak of the sender
+    fn is_associated(&self, slot: U256) -> bool {
+        if slot == U256::from_be_bytes(self.sender.into_word().0) {
+            return true;
+        }
+
+        self.associated_slots
+            .iter()
+            .any(|base| slot >= *base && slot - *base < U256::from(ASSOCIATED_SLOTS))
+    }
+
+    /// Check the access of the contract to the target w/ the `EXTCODE*` or the `*CALL` opcodes
+    fn check_code_access(&mut self, contract: Address, target: Address, has_code: bool) {
+        // [OP-042] The sender, and the precompiles w/o any code
+        if has_code || target == self.sender || is_precompile(target) {
+            return;
+        }
+
+        // [OP-041] The address w/o the deployed code
+        self.violations.push(ValidationViolation::NoCode { phase: self.phase, contract, target });
+    }
+
+    /// Check if the opcode is banned in the validation for the entity
+    fn is_banned_opcode(&self, op: u8) -> bool {
+        match op {
+            // [OP-011] The opcodes of the environment varying between the validation and the
+            // execution
+            opcode::GASPRICE
+            | opcode::GASLIMIT
+            | opcode::DIFFICULTY
+            | opcode::TIMESTAMP
+            | opcode::BASEFEE
+            | opcode::BLOCKHASH
+            | opcode::NUMBER
+            | opcode::ORIGIN
+            | opcode::COINBASE
+            | opcode::SELFDESTRUCT
+            | opcode::BLOBHASH
+            | opcode::BLOBBASEFEE
+            | opcode::INVALID => true,
+            // [OP-080] The balances only for the staked entities
+            opcode::BALANCE | opcode::SELFBALANCE => !self.is_staked,
+            _ => false,
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for ValidationTracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let contract = interp.contract.target_address;
+
+        // [OP-012] The `GAS` opcode only followed by a call
+        if let Some(gas_contract) = self.pending_gas.take() {
+            if !matches!(
+                op,
+                opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL
+            ) {
+                self.violations.push(ValidationViolation::GasNotFollowedByCall {
+                    phase: self.phase,
+                    contract: gas_contract,
+                });
+            }
+        }
+
+        // The entrypoint is trusted
+        if contract == self.entry_point {
+            return;
+        }
+
+        match op {
+            opcode::GAS => self.pending_gas = Some(contract),
+            opcode::EXTCODESIZE | opcode::EXTCODEHASH | opcode::EXTCODECOPY => {
+                if let Ok(target) = interp.stack.peek(0) {
+                    let target = Address::from_word(target.into());
+                    let has_code = has_code(context, target);
+                    self.check_code_access(contract, target, has_code);
+                }
+            }
+            opcode::SLOAD | opcode::SSTORE => {
+                if let Ok(slot) = interp.stack.peek(0) {
+                    self.check_storage(contract, slot);
+                }
+            }
+            opcode::KECCAK256 => {
+                // Record the keccaks of the preimages starting w/ the sender
+                if let (Ok(offset), Ok(size)) = (interp.stack.peek(0), interp.stack.peek(1)) {
+                    let offset = offset.saturating_to::<usize>();
+                    let size = size.saturating_to::<usize>();
+                    if size >= 32 && offset.saturating_add(size) <= interp.shared_memory.len() {
+                        let data = interp.shared_memory.slice(offset, size);
+                        if data[..32] == self.sender.into_word()[..] {
+                            self.associated_slots.push(U256::from_be_bytes(keccak256(data).0));
+                        }
+                    }
+                }
+            }
+            op if self.is_banned_opcode(op) => {
+                self.violations.push(ValidationViolation::BannedOpcode {
+                    phase: self.phase,
+                    contract,
+                    opcode: OpCode::name_by_op(op).to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        // The top level call from the entrypoint
+        if inputs.caller == self.entry_point {
+            return None;
+        }
+
+        let has_code = has_code(context, inputs.bytecode_address);
+        self.check_code_access(inputs.caller, inputs.bytecode_address, has_code);
+
+        if inputs.bytecode_address == self.entry_point {
+            // [OP-052] The entrypoint only w/ `depositTo` or the fallback
+            let is_allowed =
+                inputs.input.is_empty() || inputs.input.get(..4) == Some(&DEPOSIT_TO_SELECTOR[..]);
+            if !is_allowed {
+                self.violations.push(ValidationViolation::CallToEntryPoint {
+                    phase: self.phase,
+                    contract: inputs.caller,
+                    input: inputs.input.clone(),
+                });
+            }
+        } else if inputs.transfers_value() {
+            // [OP-061] The value only to the entrypoint
+            self.violations.push(ValidationViolation::CallWithValue {
+                phase: self.phase,
+                contract: inputs.caller,
+                target: inputs.target_address,
+            });
+        }
+
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        // [OP-020] The inner calls out of gas
+        if matches!(
+            outcome.result.result,
+            InstructionResult::OutOfGas
+                | InstructionResult::MemoryOOG
+                | InstructionResult::MemoryLimitOOG
+                | InstructionResult::PrecompileOOG
+                | InstructionResult::InvalidOperandOOG
+        ) {
+            self.violations.push(ValidationViolation::OutOfGas {
+                phase: self.phase,
+                contract: inputs.target_address,
+            });
+        }
+
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.creates += 1;
+
+        // [OP-031] Only the factory deploying the sender w/ a single `CREATE2`
+        let is_allowed = self.phase == ValidationPhase::Factory &&
+            self.creates == 1 &&
+            matches!(inputs.scheme, CreateScheme::Create2 { .. });
+        if !is_allowed {
+            self.violations
+                .push(ValidationViolation::Create { phase: self.phase, contract: inputs.caller });
+        }
+
+        None
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Utils
+// -----------------------------------------------------------------------------
+
+/// Check if the address has the deployed code, in the state of the validation or the database
+fn has_code<DB: Database>(context: &mut EvmContext<DB>, address: Address) -> bool {
+    if let Some(account) = context.journaled_state.state.get(&address) {
+        return !account.info.is_empty_code_hash();
+    }
+
+    context.db.basic(address).ok().flatten().map_or(false, |info| !info.is_empty_code_hash())
+}
+
+/// Check if the address is of a precompile
+fn is_precompile(address: Address) -> bool {
+    U256::from_be_slice(address.as_slice()) <= U256::from(MAX_PRECOMPILE_ADDRESS)
+}
+
+// -----------------------------------------------------------------------------
+// Tests
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_associated() {
+        let sender = Address::repeat_byte(1);
+        let mut tracer =
+            ValidationTracer::new(ValidationPhase::Account, sender, Address::ZERO, sender, false);
+
+        let base = U256::from(1_000);
+        tracer.associated_slots.push(base);
+
+        assert!(tracer.is_associated(U256::from_be_bytes(sender.into_word().0)));
+        assert!(tracer.is_associated(base));
+        assert!(tracer.is_associated(base + U256::from(127)));
+        assert!(!tracer.is_associated(base + U256::from(128)));
+        assert!(!tracer.is_associated(base - U256::from(1)));
+    }
+
+    #[test]
+    fn test_check_storage() {
+        let sender = Address::repeat_byte(1);
+        let paymaster = Address::repeat_byte(2);
+        let mut tracer = ValidationTracer::new(
+            ValidationPhase::Paymaster,
+            sender,
+            Address::ZERO,
+            paymaster,
+            false,
+        );
+
+        // The storage of the sender is allowed
+        tracer.check_storage(sender, U256::from(1));
+        assert!(tracer.violations.is_empty());
+
+        // The own storage of the unstaked paymaster is not allowed
+        tracer.check_storage(paymaster, U256::from(1));
+        assert_eq!(tracer.violations.len(), 1);
+        assert_eq!(tracer.violations[0].rule(), "STO-021");
+
+        // The own storage of the staked paymaster is allowed
+        tracer.is_staked = true;
+        tracer.check_storage(paymaster, U256::from(1));
+        assert_eq!(tracer.violations.len(), 1);
+    }
+
+    #[test]
+    fn test_check_code_access() {
+        let sender = Address::repeat_byte(1);
+        let contract = Address::repeat_byte(2);
+        let mut tracer =
+            ValidationTracer::new(ValidationPhase::Account, sender, Address::ZERO, sender, false);
+
+        // The deployed contracts, the sender and the precompiles are allowed
+        tracer.check_code_access(sender, contract, true);
+        tracer.check_code_access(contract, sender, false);
+        tracer.check_code_access(sender, Address::with_last_byte(1), false);
+        assert!(tracer.violations.is_empty());
+
+        // The address w/o the deployed code is not allowed
+        tracer.check_code_access(sender, Address::repeat_byte(3), false);
+        assert_eq!(tracer.violations.len(), 1);
+        assert_eq!(tracer.violations[0].rule(), "OP-041");
+    }
+
+    #[test]
+    fn test_is_banned_opcode() {
+        let mut tracer = ValidationTracer::default();
+
+        assert!(tracer.is_banned_opcode(opcode::TIMESTAMP));
+        assert!(tracer.is_banned_opcode(opcode::BALANCE));
+        assert!(!tracer.is_banned_opcode(opcode::SLOAD));
+
+        tracer.is_staked = true;
+        assert!(!tracer.is_banned_opcode(opcode::BALANCE));
+    }
+}
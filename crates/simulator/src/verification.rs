@@ -0,0 +1,200 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::evm::Evm;
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    providers::Provider,
+    sol_types::SolValue,
+};
+use eyre::{eyre, Result};
+use lightdotso_contracts::{
+    entrypoint_v060::EntryPointV060::UserOperation as EntryPointV060UserOperation,
+    entrypoint_v070::EntryPointV070::PackedUserOperation as EntryPointV070PackedUserOperation,
+    l1_fee::calldata_gas,
+    provider::get_provider,
+    types::{PackedUserOperation, UserOperation},
+};
+use serde::{Deserialize, Serialize};
+
+/// The selector of `validateUserOp` of the v0.6 account interface
+pub(crate) const VALIDATE_USER_OP_V060_SELECTOR: [u8; 4] = [0x3a, 0x87, 0x1c, 0xdd];
+
+/// The selector of `validateUserOp` of the v0.7 account interface
+pub(crate) const VALIDATE_USER_OP_V070_SELECTOR: [u8; 4] = [0x19, 0x82, 0x2f, 0x7c];
+
+/// The intrinsic gas of a transaction, excluded from the simulated verification gas
+pub(crate) const TRANSACTION_INTRINSIC_GAS: u64 = 21_000;
+
+/// The gas limit of each simulated verification call
+const VERIFICATION_GAS_LIMIT: u64 = 30_000_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerificationGasRequest {
+    /// Chain ID of the network
+    pub chain_id: u64,
+    /// Entrypoint address calling the account
+    pub entry_point: Address,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Factory of the account, if not yet deployed
+    pub factory: Option<Address>,
+    /// Factory data of the account, if not yet deployed
+    pub factory_data: Option<Bytes>,
+    /// Calldata of `validateUserOp` to the sender
+    pub validate_user_op_data: Bytes,
+    /// Block number of the request
+    pub block_number: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerificationGasResponse {
+    /// Gas used by the deployment of the account, excluding the intrinsic gas
+    pub deployment_gas: u64,
+    /// Gas used by `validateUserOp`, excluding the intrinsic gas
+    pub validation_gas: u64,
+}
+
+impl VerificationGasResponse {
+    /// Get the total verification gas of the account
+    pub fn verification_gas(&self) -> u64 {
+        self.deployment_gas + self.validation_gas
+    }
+}
+
+impl VerificationGasRequest {
+    /// Constructs the request for the user operation (v0.6)
+    pub fn from_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        user_operation: &UserOperation,
+    ) -> Self {
+        let user_op_hash = user_operation.op_hash(entry_point, chain_id);
+        let op: EntryPointV060UserOperation = user_operation.clone().into();
+
+        let (factory, factory_data) = split_init_code(&user_operation.init_code);
+
+        Self {
+            chain_id,
+            entry_point,
+            sender: user_operation.sender,
+            factory,
+            factory_data,
+            validate_user_op_data: encode_validate_user_op(
+                VALIDATE_USER_OP_V060_SELECTOR,
+                (op, user_op_hash, U256::ZERO).abi_encode_params(),
+            ),
+            block_number: None,
+        }
+    }
+
+    /// Constructs the request for the packed user operation (v0.7)
+    pub fn from_packed_user_operation(
+        chain_id: u64,
+        entry_point: Address,
+        packed_user_operation: &PackedUserOperation,
+    ) -> Self {
+        let user_op_hash = packed_user_operation.op_hash(entry_point, chain_id);
+        let op: EntryPointV070PackedUserOperation = packed_user_operation.clone().into();
+
+        Self {
+            chain_id,
+            entry_point,
+            sender: packed_user_operation.sender,
+            factory: packed_user_operation.factory,
+            factory_data: packed_user_operation.factory_data.clone(),
+            validate_user_op_data: encode_validate_user_op(
+                VALIDATE_USER_OP_V070_SELECTOR,
+                (op, user_op_hash, U256::ZERO).abi_encode_params(),
+            ),
+            block_number: None,
+        }
+    }
+}
+
+/// Splits the init code into the factory and the factory data
+fn split_init_code(init_code: &Bytes) -> (Option<Address>, Option<Bytes>) {
+    if init_code.len() < 20 {
+        return (None, None);
+    }
+    (Some(Address::from_slice(&init_code[..20])), Some(init_code.slice(20..)))
+}
+
+/// Prepends the selector to the encoded params
+pub(crate) fn encode_validate_user_op(selector: [u8; 4], params: Vec<u8>) -> Bytes {
+    let mut data = Vec::with_capacity(4 + params.len());
+    data.extend_from_slice(&selector);
+    data.extend_from_slice(&params);
+    data.into()
+}
+
+/// Get the execution gas of a simulated call, excluding the intrinsic gas
+pub(crate) fn execution_gas(gas_used: u64, data: &[u8]) -> u64 {
+    gas_used.saturating_sub(TRANSACTION_INTRINSIC_GAS + calldata_gas(data))
+}
+
+/// Simulates the verification of the user operation, deploying the account if needed and calling
+/// `validateUserOp` from the entrypoint w/o any missing account funds
+pub async fn simulate_verification_gas(
+    request: VerificationGasRequest,
+) -> Result<VerificationGasResponse> {
+    // Get the provider
+    let (provider, fork_url) = get_provider(request.chain_id).await?;
+
+    // If block number is not provided, use the latest block number
+    let block_number = match request.block_number {
+        Some(block_number) => block_number,
+        None => provider.get_block_number().await?,
+    };
+
+    // Construct the EVM
+    let mut evm = Evm::new(None, fork_url, Some(block_number), VERIFICATION_GAS_LIMIT).await?;
+
+    // Deploy the account w/ the factory, committing the state for the validation
+    let mut deployment_gas = 0;
+    if let (Some(factory), Some(factory_data)) = (request.factory, request.factory_data) {
+        let res = evm
+            .call_raw_committing(
+                request.entry_point,
+                factory,
+                None,
+                Some(factory_data.clone()),
+                VERIFICATION_GAS_LIMIT,
+            )
+            .await?;
+        if !res.success {
+            return Err(eyre!("Failed to deploy the account w/ the factory"));
+        }
+        deployment_gas = execution_gas(res.gas_used, &factory_data);
+    }
+
+    // Validate the user operation as the entrypoint
+    let res = evm
+        .call_raw(
+            request.entry_point,
+            request.sender,
+            None,
+            Some(request.validate_user_op_data.clone()),
+        )
+        .await?;
+    if !res.success {
+        return Err(eyre!("Failed to validate the user operation"));
+    }
+
+    // The returned validation data is ignored, as the signature is a dummy in estimations
+    Ok(VerificationGasResponse {
+        deployment_gas,
+        validation_gas: execution_gas(res.gas_used, &request.validate_user_op_data),
+    })
+}
@@ -28,6 +28,7 @@ async fn test_integration_eth_transfer() -> Result<()> {
         value: U256::from(1),
         gas_limit: u64::MAX,
         block_number: None,
+        state_overrides: None,
     };
 
     let res = simulate(request).await?;
@@ -13,3 +13,4 @@
 // limitations under the License.
 
 mod eth_transfer;
+mod replay;
@@ -0,0 +1,111 @@
+// Copyright 2023-2024 LightDotSo.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::primitives::{Address, U256};
+use eyre::Result;
+use lightdotso_simulator::{
+    cache::{AccountSnapshot, SimulationSnapshot},
+    simulator::{replay, replay_bundle, simulate_with_snapshot},
+    types::SimulationRequest,
+};
+use revm::primitives::Env;
+
+fn snapshot(from: Address) -> SimulationSnapshot {
+    let mut env = Env::default();
+    env.cfg.chain_id = 1;
+    env.block.number = U256::from(19_000_000);
+
+    let mut snapshot = SimulationSnapshot::new(env);
+    snapshot.accounts.insert(
+        from,
+        AccountSnapshot { balance: U256::from(10).pow(U256::from(18)), ..Default::default() },
+    );
+
+    snapshot
+}
+
+fn request(from: Address, to: Address) -> SimulationRequest {
+    SimulationRequest {
+        chain_id: 1,
+        from,
+        to,
+        data: None,
+        value: U256::from(1),
+        gas_limit: 100_000,
+        block_number: Some(19_000_000),
+        state_overrides: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_integration_replay() -> Result<()> {
+    let from = Address::repeat_byte(1);
+    let to = Address::repeat_byte(2);
+
+    let res_1 = replay(request(from, to), snapshot(from)).await?;
+    let res_2 = replay(request(from, to), snapshot(from)).await?;
+
+    assert!(res_1.success);
+    assert_eq!(res_1, res_2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_integration_replay_snapshot_file() -> Result<()> {
+    let from = Address::repeat_byte(1);
+    let to = Address::repeat_byte(2);
+
+    let path = std::env::temp_dir()
+        .join(format!("lightdotso-simulator-replay-test-{}.json", std::process::id()));
+    snapshot(from).write(&path)?;
+
+    let res =
+        replay_bundle(vec![request(from, to), request(from, to)], SimulationSnapshot::read(&path)?)
+            .await?;
+
+    assert_eq!(res.len(), 2);
+    assert!(res.iter().all(|res| res.success));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_integration_record_export_replay() -> Result<()> {
+    let request = SimulationRequest {
+        chain_id: 1,
+        // kaki.eth
+        from: "0x4fd9D0eE6D6564E80A9Ee00c0163fC952d0A45Ed".parse()?,
+        // fiveoutofnine.eth
+        to: "0xA85572Cd96f1643458f17340b6f0D6549Af482F5".parse()?,
+        data: None,
+        value: U256::from(1),
+        gas_limit: 100_000,
+        block_number: Some(19_000_000),
+        state_overrides: None,
+    };
+
+    // Record the fork state read by the simulation
+    let (res, snapshot) = simulate_with_snapshot(request.clone()).await?;
+
+    // Export the snapshot and replay it offline
+    let path = std::env::temp_dir()
+        .join(format!("lightdotso-simulator-record-test-{}.json", std::process::id()));
+    snapshot.write(&path)?;
+    let replayed = replay(request, SimulationSnapshot::read(&path)?).await?;
+
+    assert_eq!(res, replayed);
+
+    Ok(())
+}
@@ -14,10 +14,7 @@
 
 use alloy::primitives::{Address, U256};
 /// Entire file is copied from https://github.com/Vid201/silius/blob/bc8b7b0039c9a2b02256fefc7eed3b2efc94bf96/bin/silius/src/utils.rs
-use lightdotso_constants::chains::{
-    CHAIN_BLOCK_SECONDS, DEFAULT_CHAIN_BLOCK_SECONDS, DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS,
-    MAINNET_CHAIN_IDS, NATIVE_TOKEN_SYMBOLS, TESTNET_CHAIN_IDS,
-};
+use lightdotso_constants::registry::get_chain_registry;
 /// License: MIT or Apache-2.0
 use std::str::FromStr;
 
@@ -33,22 +30,18 @@ pub fn parse_u256(s: &str) -> Result<U256, String> {
 
 /// Utility function to get the native token symbol for a given chain id.
 /// Returns a fallback message for chains that use ETH or are not listed.
-pub fn get_native_token_symbol(chain_id: u64) -> &'static str {
-    NATIVE_TOKEN_SYMBOLS.get(&chain_id).unwrap_or(&"ETH")
+pub fn get_native_token_symbol(chain_id: u64) -> String {
+    get_chain_registry().native_token_symbol(chain_id)
 }
 
 /// Returns `true` if the chain ID is a testnet chain id.
 /// Falls back to `true` if the chain ID is not a mainnet chain id.
 pub fn is_testnet(chain_id: u64) -> bool {
-    TESTNET_CHAIN_IDS.contains_key(&chain_id) || !MAINNET_CHAIN_IDS.contains_key(&chain_id)
+    get_chain_registry().is_testnet(chain_id)
 }
 
 /// Get the chain seconds to sleep for a given chain id.
 /// Returns a fallback value for chains that are not listed.
 pub fn get_chain_block_seconds(chain_id: u64) -> u64 {
-    *CHAIN_BLOCK_SECONDS.get(&chain_id).unwrap_or(if !is_testnet(chain_id) {
-        &*DEFAULT_CHAIN_BLOCK_SECONDS
-    } else {
-        &*DEFAULT_TESTNET_CHAIN_BLOCK_SECONDS
-    })
+    get_chain_registry().block_seconds(chain_id)
 }